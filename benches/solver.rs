@@ -0,0 +1,173 @@
+//! Criterion benchmarks for the subset solver and the worker messages.
+//!
+//! Run with `cargo bench --target x86_64-unknown-linux-gnu` (or any native
+//! target); the default wasm target has no benchmark runner.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use criterion::{BenchmarkId, Criterion};
+    use random_karma::generators::PoolSpec;
+    use random_karma::worker_agent::{
+        KarmaSuccess, RequestMetadata, WorkerCodec, WorkerInput, WorkerOutput,
+    };
+    use random_karma::{
+        dataset_hash, find_approximate_subset, perform_multiple_runs, Car, RunReport,
+        SolverStrategy, SubsetCalculationConfig,
+    };
+    use serde::{de::DeserializeOwned, Serialize};
+    use std::collections::HashSet;
+    use std::hint::black_box;
+
+    const POOL_SIZES: [usize; 3] = [50, 200, 1_000];
+    const LAP_COUNTS: [usize; 2] = [3, 8];
+    const TOLERANCES: [f64; 3] = [0.1, 0.5, 2.0];
+    const PLAYER_COUNT: usize = 4;
+
+    /// Uniform lap times between 1:30 and 2:00, identical for every run.
+    fn pool(size: usize) -> Vec<Car> {
+        PoolSpec::uniform(size, 90_000, 120_000).generate_seeded(size as u64)
+    }
+
+    /// A target at the pool's mean lap time, which every size can reach.
+    fn target(cars: &[Car], lap_count: usize) -> u32 {
+        let mean = cars.iter().map(|car| u64::from(car.lap_time)).sum::<u64>() / cars.len() as u64;
+        (mean * lap_count as u64) as u32
+    }
+
+    fn single_subset(c: &mut Criterion) {
+        let mut group = c.benchmark_group("find_approximate_subset");
+        for size in POOL_SIZES {
+            let cars = pool(size);
+            for lap_count in LAP_COUNTS {
+                for tolerance in TOLERANCES {
+                    let target = target(&cars, lap_count);
+                    group.bench_with_input(
+                        BenchmarkId::new(format!("{lap_count}laps_{tolerance}pct"), size),
+                        &cars,
+                        |b, cars| {
+                            b.iter(|| {
+                                find_approximate_subset(
+                                    black_box(cars),
+                                    target,
+                                    lap_count,
+                                    &HashSet::new(),
+                                    tolerance,
+                                )
+                            })
+                        },
+                    );
+                }
+            }
+        }
+        group.finish();
+    }
+
+    fn multiple_runs(c: &mut Criterion) {
+        let mut group = c.benchmark_group("perform_multiple_runs");
+        group.sample_size(20);
+        for strategy in [SolverStrategy::Bounded, SolverStrategy::Legacy] {
+            for size in POOL_SIZES {
+                let cars = pool(size);
+                for lap_count in LAP_COUNTS {
+                    for tolerance in TOLERANCES {
+                        let config = SubsetCalculationConfig::builder(
+                            target(&cars, lap_count),
+                            lap_count,
+                            PLAYER_COUNT,
+                        )
+                        .tolerance_percent(tolerance)
+                        .strategy(strategy)
+                        .build()
+                        .expect("benchmark configurations are valid");
+                        group.bench_with_input(
+                            BenchmarkId::new(
+                                format!("{strategy:?}_{lap_count}laps_{tolerance}pct"),
+                                size,
+                            ),
+                            &cars,
+                            |b, cars| b.iter(|| perform_multiple_runs(black_box(cars), &config)),
+                        );
+                    }
+                }
+            }
+        }
+        group.finish();
+    }
+
+    /// Encodes and decodes `message` with the worker codec and with JSON,
+    /// the text the serde-wasm-bindgen objects it replaced resemble most.
+    fn round_trips<M: Serialize + DeserializeOwned>(c: &mut Criterion, name: &str, message: &M) {
+        let mut group = c.benchmark_group(format!("worker_codec/{name}"));
+        group.bench_function("postcard", |b| {
+            b.iter(|| {
+                let bytes = WorkerCodec::to_bytes(black_box(message));
+                WorkerCodec::from_bytes::<M>(&bytes).unwrap()
+            })
+        });
+        group.bench_function("json", |b| {
+            b.iter(|| {
+                let json = serde_json::to_vec(black_box(message)).unwrap();
+                serde_json::from_slice::<M>(&json).unwrap()
+            })
+        });
+        group.finish();
+    }
+
+    fn worker_codec(c: &mut Criterion) {
+        let cars = pool(1_000);
+        let register = WorkerInput::RegisterDataset { cars: cars.clone() };
+        round_trips(c, "register_1000_cars", &register);
+
+        let lap_count = 8;
+        let config = SubsetCalculationConfig::builder(target(&cars, lap_count), lap_count, 50)
+            .tolerance_percent(2.0)
+            .build()
+            .expect("benchmark configurations are valid");
+        let sets = perform_multiple_runs(&cars, &config).expect("the pool reaches the target");
+        let success = WorkerOutput::Finished(Ok(KarmaSuccess {
+            metadata: RequestMetadata {
+                request_id: 1,
+                dataset_generation: 1,
+                dataset_hash: dataset_hash(&cars),
+                target: config.target,
+                lap_count,
+                player_count: 50,
+                timeout_ms: config.timeout_ms,
+                tolerance_percent: config.tolerance_percent,
+                strategy: config.strategy,
+                min_lap_time: None,
+                max_lap_time: None,
+            },
+            runs: (1..=sets.len())
+                .map(|run| RunReport {
+                    run,
+                    attempts: 3,
+                    ..RunReport::default()
+                })
+                .collect(),
+            sets,
+            similarity: 0.2,
+            weighted_similarity: 0.2,
+            calculated_target: config.target,
+            exact_hits: 4,
+            quality: 0.9,
+        }));
+        round_trips(c, "result_50_players", &success);
+    }
+
+    pub fn main() {
+        let mut criterion = Criterion::default().configure_from_args();
+        single_subset(&mut criterion);
+        multiple_runs(&mut criterion);
+        worker_codec(&mut criterion);
+        criterion.final_summary();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    native::main();
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,513 @@
+//! Car usage across the subsets of a calculation.
+//!
+//! [`analyze_multiple_runs`] summarizes which cars were picked and how
+//! often, and how the picked lap times are distributed, as a serializable
+//! [`AnalysisReport`] for the results view and exports.
+//! [`lap_time_histogram`] sets the picked lap times against the pool's.
+//! [`summarize_results`] and [`result_overlap`] set two calculations side
+//! by side, and [`diff_results`] tells what changed in every row.
+//! [`subset_stats`] describes the spread of lap times within one subset.
+
+use crate::{accuracy_percent, calculate_subset_sum, compute_jaccard_similarity, Car, CarIndex};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Width of the [`LapTimeBucket`]s of an [`AnalysisReport`].
+pub const LAP_TIME_BUCKET_MS: u32 = 10_000;
+
+/// Bins of a [`lap_time_histogram`].
+pub const HISTOGRAM_BIN_COUNT: usize = 12;
+
+/// How often one car was selected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CarFrequency {
+    pub car: CarIndex,
+    pub id: String,
+    pub lap_time: u32,
+    /// Subsets the car appears in.
+    pub count: usize,
+    /// `count` as a fraction of all subsets.
+    pub share: f64,
+}
+
+/// Selections with a lap time in `start..end` milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LapTimeBucket {
+    pub start: u32,
+    pub end: u32,
+    pub count: usize,
+}
+
+/// Pool cars and selections with a lap time in `start..end` milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistogramBin {
+    pub start: u32,
+    pub end: u32,
+    pub pool: usize,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisReport {
+    pub subset_count: usize,
+    /// Cars selected over all subsets, counting repeats.
+    pub selection_count: usize,
+    /// Every selected car, most frequent first and then by id.
+    pub car_frequencies: Vec<CarFrequency>,
+    /// Lap-time buckets from the fastest to the slowest selection, empty
+    /// ones included.
+    pub lap_time_buckets: Vec<LapTimeBucket>,
+    /// `reuse_counts[n]` cars were selected in exactly `n` subsets; the
+    /// first entry counts the cars never selected.
+    pub reuse_counts: Vec<usize>,
+}
+
+impl AnalysisReport {
+    pub fn fastest_selected(&self) -> Option<u32> {
+        self.car_frequencies.iter().map(|car| car.lap_time).min()
+    }
+
+    pub fn slowest_selected(&self) -> Option<u32> {
+        self.car_frequencies.iter().map(|car| car.lap_time).max()
+    }
+}
+
+/// Totals and deviations of one calculation's subsets.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResultSummary {
+    pub subset_count: usize,
+    pub mean_total: u32,
+    pub fastest_total: u32,
+    pub slowest_total: u32,
+    /// Mean distance of the subsets' totals from the target, in percent.
+    pub mean_deviation_percent: f64,
+    /// Distance of the total furthest from the target, in percent.
+    pub worst_deviation_percent: f64,
+    /// Subsets within the tolerance of the target.
+    pub within_tolerance: usize,
+    /// Jaccard similarity of the subsets, 0 for fewer than two.
+    pub similarity: f64,
+}
+
+/// What two calculations' subsets share. Cars are told apart by id, so
+/// results on different pools compare too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResultOverlap {
+    /// Subsets in both, as the same cars.
+    pub shared_subsets: usize,
+    /// Cars picked in both.
+    pub shared_cars: usize,
+    /// Cars picked in either.
+    pub picked_cars: usize,
+}
+
+impl ResultOverlap {
+    /// Shared cars as a share of the cars picked, from 0 to 1.
+    pub fn car_similarity(&self) -> f64 {
+        if self.picked_cars == 0 {
+            return 0.0;
+        }
+        self.shared_cars as f64 / self.picked_cars as f64
+    }
+}
+
+/// Lap times within one subset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubsetStats {
+    /// The car with the shortest lap time, none for an empty subset.
+    pub fastest: Option<CarIndex>,
+    /// The car with the longest lap time.
+    pub slowest: Option<CarIndex>,
+    pub mean_lap_time: u32,
+    /// Milliseconds between the slowest and the fastest lap time.
+    pub spread: u32,
+}
+
+/// How one row of a calculation changed in a later one on the same pool.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubsetDiff {
+    /// Cars of the later row the earlier one lacks, by index.
+    pub added: Vec<CarIndex>,
+    /// Cars of the earlier row the later one dropped, by index.
+    pub removed: Vec<CarIndex>,
+    /// Cars in both rows.
+    pub kept: usize,
+    pub old_total: u32,
+    pub new_total: u32,
+}
+
+impl SubsetDiff {
+    pub fn is_unchanged(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// How many milliseconds the row's total moved, negative when faster.
+    pub fn total_change(&self) -> i64 {
+        i64::from(self.new_total) - i64::from(self.old_total)
+    }
+}
+
+/// Summarizes `subsets` of `cars` against `target` and its
+/// `tolerance_percent`.
+pub fn summarize_results(
+    cars: &[Car],
+    subsets: &[Vec<CarIndex>],
+    target: u32,
+    tolerance_percent: f64,
+) -> ResultSummary {
+    let totals = subsets
+        .iter()
+        .map(|subset| calculate_subset_sum(cars, subset))
+        .collect::<Vec<_>>();
+    let deviations = totals
+        .iter()
+        .map(|&total| (accuracy_percent(total, target) - 100.0).abs())
+        .collect::<Vec<_>>();
+    let count = subsets.len().max(1);
+    ResultSummary {
+        subset_count: subsets.len(),
+        mean_total: (totals.iter().map(|&total| u64::from(total)).sum::<u64>() / count as u64)
+            as u32,
+        fastest_total: totals.iter().copied().min().unwrap_or(0),
+        slowest_total: totals.iter().copied().max().unwrap_or(0),
+        mean_deviation_percent: deviations.iter().sum::<f64>() / count as f64,
+        worst_deviation_percent: deviations.iter().copied().fold(0.0, f64::max),
+        within_tolerance: deviations
+            .iter()
+            .filter(|&&deviation| deviation <= tolerance_percent)
+            .count(),
+        similarity: compute_jaccard_similarity(subsets).unwrap_or(0.0),
+    }
+}
+
+/// Subsets and cars `first`, subsets of `first_cars`, has in common with
+/// `second`, subsets of `second_cars`. A subset repeated in one counts as
+/// shared as often as it appears in the other too.
+pub fn result_overlap(
+    first_cars: &[Car],
+    first: &[Vec<CarIndex>],
+    second_cars: &[Car],
+    second: &[Vec<CarIndex>],
+) -> ResultOverlap {
+    let ids = |cars: &[Car], subset: &[CarIndex]| {
+        subset
+            .iter()
+            .filter_map(|&index| cars.get(index).map(|car| car.id.clone()))
+            .collect::<BTreeSet<_>>()
+    };
+    let mut unmatched = BTreeMap::<BTreeSet<String>, usize>::new();
+    for subset in first {
+        *unmatched.entry(ids(first_cars, subset)).or_default() += 1;
+    }
+    let mut shared_subsets = 0;
+    for subset in second {
+        if let Some(count) = unmatched
+            .get_mut(&ids(second_cars, subset))
+            .filter(|count| **count > 0)
+        {
+            *count -= 1;
+            shared_subsets += 1;
+        }
+    }
+    let picked = |cars: &[Car], subsets: &[Vec<CarIndex>]| {
+        subsets
+            .iter()
+            .flat_map(|subset| ids(cars, subset))
+            .collect::<BTreeSet<_>>()
+    };
+    let (first_picked, second_picked) = (picked(first_cars, first), picked(second_cars, second));
+    ResultOverlap {
+        shared_subsets,
+        shared_cars: first_picked.intersection(&second_picked).count(),
+        picked_cars: first_picked.union(&second_picked).count(),
+    }
+}
+
+/// Describes `subset` of `cars`. Indexes outside `cars` are ignored.
+pub fn subset_stats(cars: &[Car], subset: &[CarIndex]) -> SubsetStats {
+    let picked = subset
+        .iter()
+        .filter_map(|&index| cars.get(index).map(|car| (index, car.lap_time)))
+        .collect::<Vec<_>>();
+    let fastest = picked.iter().min_by_key(|(_, lap_time)| *lap_time).copied();
+    let slowest = picked.iter().max_by_key(|(_, lap_time)| *lap_time).copied();
+    let total = picked
+        .iter()
+        .map(|&(_, lap_time)| u64::from(lap_time))
+        .sum::<u64>();
+    SubsetStats {
+        fastest: fastest.map(|(index, _)| index),
+        slowest: slowest.map(|(index, _)| index),
+        mean_lap_time: (total / picked.len().max(1) as u64) as u32,
+        spread: match (fastest, slowest) {
+            (Some((_, fastest)), Some((_, slowest))) => slowest - fastest,
+            _ => 0,
+        },
+    }
+}
+
+/// Compares the rows of two calculations on `cars` in order, as the players
+/// holding them see it. A row only `to` has counts every car as added, and
+/// one only `from` has as removed.
+pub fn diff_results(cars: &[Car], from: &[Vec<CarIndex>], to: &[Vec<CarIndex>]) -> Vec<SubsetDiff> {
+    let empty = Vec::new();
+    (0..from.len().max(to.len()))
+        .map(|row| {
+            let old = from.get(row).unwrap_or(&empty);
+            let new = to.get(row).unwrap_or(&empty);
+            let old_cars = old.iter().copied().collect::<BTreeSet<_>>();
+            let new_cars = new.iter().copied().collect::<BTreeSet<_>>();
+            SubsetDiff {
+                added: new_cars.difference(&old_cars).copied().collect(),
+                removed: old_cars.difference(&new_cars).copied().collect(),
+                kept: old_cars.intersection(&new_cars).count(),
+                old_total: calculate_subset_sum(cars, old),
+                new_total: calculate_subset_sum(cars, new),
+            }
+        })
+        .collect()
+}
+
+/// Summarizes `all_results`, subsets of `global_cars`. Indexes outside
+/// `global_cars` are ignored.
+pub fn analyze_multiple_runs(global_cars: &[Car], all_results: &[Vec<CarIndex>]) -> AnalysisReport {
+    let mut counts = vec![0_usize; global_cars.len()];
+    for &index in all_results.iter().flatten() {
+        if let Some(count) = counts.get_mut(index) {
+            *count += 1;
+        }
+    }
+    let subset_count = all_results.len();
+    let selection_count = counts.iter().sum();
+
+    let mut car_frequencies = counts
+        .iter()
+        .enumerate()
+        .filter(|&(_, &count)| count > 0)
+        .map(|(car, &count)| CarFrequency {
+            car,
+            id: global_cars[car].id.clone(),
+            lap_time: global_cars[car].lap_time,
+            count,
+            share: count as f64 / subset_count as f64,
+        })
+        .collect::<Vec<_>>();
+    car_frequencies.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.id.cmp(&b.id)));
+
+    let mut reuse_counts = vec![0; counts.iter().max().map_or(1, |&max| max + 1)];
+    for &count in &counts {
+        reuse_counts[count] += 1;
+    }
+
+    let mut report = AnalysisReport {
+        subset_count,
+        selection_count,
+        car_frequencies,
+        lap_time_buckets: Vec::new(),
+        reuse_counts,
+    };
+    if let (Some(fastest), Some(slowest)) = (report.fastest_selected(), report.slowest_selected()) {
+        let first = fastest / LAP_TIME_BUCKET_MS;
+        report.lap_time_buckets = (first..=slowest / LAP_TIME_BUCKET_MS)
+            .map(|bucket| LapTimeBucket {
+                start: bucket * LAP_TIME_BUCKET_MS,
+                end: bucket.saturating_add(1).saturating_mul(LAP_TIME_BUCKET_MS),
+                count: 0,
+            })
+            .collect();
+        for car in &report.car_frequencies {
+            report.lap_time_buckets[(car.lap_time / LAP_TIME_BUCKET_MS - first) as usize].count +=
+                car.count;
+        }
+    }
+
+    report
+}
+
+/// Lap times of the enabled cars in `cars` against those of the selections
+/// in `all_results`, which count a car once for every subset it is in, in
+/// [`HISTOGRAM_BIN_COUNT`] equal bins from the fastest to the slowest of
+/// them. Empty without enabled or selected cars.
+pub fn lap_time_histogram(cars: &[Car], all_results: &[Vec<CarIndex>]) -> Vec<HistogramBin> {
+    let pool = cars
+        .iter()
+        .filter(|car| car.enabled)
+        .map(|car| car.lap_time);
+    let selected = all_results
+        .iter()
+        .flatten()
+        .filter_map(|&index| cars.get(index))
+        .map(|car| car.lap_time);
+    let (Some(fastest), Some(slowest)) = (
+        pool.clone().chain(selected.clone()).min(),
+        pool.clone().chain(selected.clone()).max(),
+    ) else {
+        return Vec::new();
+    };
+
+    let width = (slowest - fastest) / HISTOGRAM_BIN_COUNT as u32 + 1;
+    let mut bins = (0..HISTOGRAM_BIN_COUNT as u32)
+        .map(|bin| HistogramBin {
+            start: fastest + bin * width,
+            end: fastest + (bin + 1) * width,
+            pool: 0,
+            selected: 0,
+        })
+        .collect::<Vec<_>>();
+    let bin = |lap_time: u32| ((lap_time - fastest) / width) as usize;
+    for lap_time in pool {
+        bins[bin(lap_time)].pool += 1;
+    }
+    for lap_time in selected {
+        bins[bin(lap_time)].selected += 1;
+    }
+    bins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cars() -> Vec<Car> {
+        [("a", 58_000), ("b", 61_000), ("c", 79_999), ("d", 62_000)]
+            .into_iter()
+            .map(|(id, lap_time)| Car::new(id, lap_time))
+            .collect()
+    }
+
+    #[test]
+    fn reports_count_cars_buckets_and_reuse() {
+        let report = analyze_multiple_runs(&cars(), &[vec![0, 2], vec![2, 1], vec![2, 9]]);
+
+        assert_eq!(report.subset_count, 3);
+        assert_eq!(report.selection_count, 5);
+        let frequencies = report
+            .car_frequencies
+            .iter()
+            .map(|car| (car.id.as_str(), car.count))
+            .collect::<Vec<_>>();
+        assert_eq!(frequencies, [("c", 3), ("a", 1), ("b", 1)]);
+        assert_eq!(report.car_frequencies[0].share, 1.0);
+        assert_eq!(
+            report.lap_time_buckets,
+            [
+                LapTimeBucket {
+                    start: 50_000,
+                    end: 60_000,
+                    count: 1
+                },
+                LapTimeBucket {
+                    start: 60_000,
+                    end: 70_000,
+                    count: 1
+                },
+                LapTimeBucket {
+                    start: 70_000,
+                    end: 80_000,
+                    count: 3
+                },
+            ]
+        );
+        assert_eq!(report.reuse_counts, [1, 2, 0, 1]);
+        assert_eq!(report.fastest_selected(), Some(58_000));
+
+        let empty = analyze_multiple_runs(&cars(), &[]);
+        assert!(empty.lap_time_buckets.is_empty());
+        assert_eq!(empty.reuse_counts, [4]);
+    }
+
+    #[test]
+    fn summaries_and_overlaps_compare_two_calculations() {
+        let cars = cars();
+        let summary = summarize_results(&cars, &[vec![0, 1], vec![1, 3], vec![0, 3]], 120_000, 1.0);
+        assert_eq!(summary.subset_count, 3);
+        assert_eq!(
+            (summary.fastest_total, summary.slowest_total),
+            (119_000, 123_000)
+        );
+        assert_eq!(summary.mean_total, 120_666);
+        assert!((summary.worst_deviation_percent - 2.5).abs() < 1e-9);
+        assert_eq!(summary.within_tolerance, 2);
+        assert!((summary.mean_deviation_percent - (2.5 + 1.0 / 1.2) / 3.0).abs() < 1e-9);
+        assert!(summary.similarity > 0.0);
+        assert_eq!(
+            summarize_results(&cars, &[], 120_000, 1.0),
+            ResultSummary::default()
+        );
+
+        let mut reordered = cars.clone();
+        reordered.reverse();
+        let overlap = result_overlap(
+            &cars,
+            &[vec![0, 1], vec![0, 1], vec![2, 3]],
+            &reordered,
+            &[vec![3, 2], vec![1, 2]],
+        );
+        assert_eq!(
+            overlap,
+            ResultOverlap {
+                shared_subsets: 1,
+                shared_cars: 3,
+                picked_cars: 4,
+            }
+        );
+        assert_eq!(overlap.car_similarity(), 0.75);
+    }
+
+    #[test]
+    fn subset_stats_find_the_extremes() {
+        let cars = cars();
+        assert_eq!(
+            subset_stats(&cars, &[1, 2, 0, 9]),
+            SubsetStats {
+                fastest: Some(0),
+                slowest: Some(2),
+                mean_lap_time: 66_333,
+                spread: 21_999,
+            }
+        );
+        assert_eq!(subset_stats(&cars, &[]), SubsetStats::default());
+    }
+
+    #[test]
+    fn diffs_pair_rows_in_order() {
+        let cars = cars();
+        let diffs = diff_results(
+            &cars,
+            &[vec![0, 1], vec![1, 3]],
+            &[vec![1, 0], vec![1, 2], vec![3]],
+        );
+        assert!(diffs[0].is_unchanged());
+        assert_eq!(diffs[0].kept, 2);
+        assert_eq!(
+            diffs[1],
+            SubsetDiff {
+                added: vec![2],
+                removed: vec![3],
+                kept: 1,
+                old_total: 123_000,
+                new_total: 140_999,
+            }
+        );
+        assert_eq!(diffs[1].total_change(), 17_999);
+        assert_eq!((diffs[2].added.clone(), diffs[2].old_total), (vec![3], 0));
+    }
+
+    #[test]
+    fn histograms_bin_the_pool_and_the_selections() {
+        let mut cars = cars();
+        cars[3].enabled = false;
+        let bins = lap_time_histogram(&cars, &[vec![0, 2], vec![2, 3]]);
+
+        assert_eq!(bins.len(), HISTOGRAM_BIN_COUNT);
+        assert_eq!((bins[0].start, bins[11].end), (58_000, 80_008));
+        assert_eq!(bins.iter().map(|bin| bin.pool).sum::<usize>(), 3);
+        assert_eq!(bins.iter().map(|bin| bin.selected).sum::<usize>(), 4);
+        assert_eq!((bins[0].pool, bins[0].selected), (1, 1));
+        assert_eq!((bins[1].pool, bins[2].selected), (1, 1));
+        assert_eq!((bins[11].pool, bins[11].selected), (1, 2));
+
+        assert_eq!(lap_time_histogram(&cars, &[]).len(), HISTOGRAM_BIN_COUNT);
+        assert!(lap_time_histogram(&[], &[vec![0]]).is_empty());
+    }
+}
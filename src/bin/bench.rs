@@ -0,0 +1,83 @@
+//! Quick native comparison of solver configurations.
+//!
+//! `cargo run --release --target x86_64-unknown-linux-gnu --bin bench -- [repeats]`
+//! prints one row per configuration with its success rate, run time, and
+//! accuracy. Use `cargo bench` for statistically rigorous timings.
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    use random_karma::generators::PoolSpec;
+    use random_karma::{measure_multiple_runs, SolverStrategy, SubsetCalculationConfig};
+
+    const POOL_SIZES: [usize; 3] = [50, 200, 1_000];
+    const LAP_COUNTS: [usize; 2] = [3, 8];
+    const TOLERANCES: [f64; 3] = [0.1, 0.5, 2.0];
+    const PLAYER_COUNT: usize = 4;
+
+    let repeats = match std::env::args().nth(1).map(|arg| arg.parse::<usize>()) {
+        None => 10,
+        Some(Ok(repeats)) if repeats > 0 => repeats,
+        Some(_) => {
+            eprintln!("usage: bench [repeats]");
+            std::process::exit(2);
+        }
+    };
+
+    println!(
+        "{:<8} {:>6} {:>5} {:>6} {:>8} {:>10} {:>10}",
+        "strategy", "pool", "laps", "tol%", "success", "mean ms", "accuracy"
+    );
+    for strategy in [SolverStrategy::Bounded, SolverStrategy::Legacy] {
+        for size in POOL_SIZES {
+            let cars = PoolSpec::uniform(size, 90_000, 120_000).generate_seeded(size as u64);
+            let mean_lap =
+                cars.iter().map(|car| u64::from(car.lap_time)).sum::<u64>() / size as u64;
+
+            for lap_count in LAP_COUNTS {
+                for tolerance in TOLERANCES {
+                    let config = SubsetCalculationConfig::builder(
+                        (mean_lap * lap_count as u64) as u32,
+                        lap_count,
+                        PLAYER_COUNT,
+                    )
+                    .tolerance_percent(tolerance)
+                    .strategy(strategy)
+                    .build()
+                    .expect("benchmark configurations are valid");
+
+                    let mut successes = 0;
+                    let mut total_ms = 0.0;
+                    let mut accuracies = Vec::new();
+                    for _ in 0..repeats {
+                        let started = std::time::Instant::now();
+                        let (result, metrics) = measure_multiple_runs(&cars, &config);
+                        total_ms += started.elapsed().as_secs_f64() * 1_000.0;
+                        successes += usize::from(result.is_ok());
+                        accuracies.extend(metrics.accuracy_percent);
+                    }
+                    let accuracy = if accuracies.is_empty() {
+                        "-".to_string()
+                    } else {
+                        format!(
+                            "{:.3}%",
+                            accuracies.iter().sum::<f64>() / accuracies.len() as f64
+                        )
+                    };
+                    println!(
+                        "{:<8} {:>6} {:>5} {:>6} {:>7.0}% {:>10.2} {:>10}",
+                        format!("{strategy:?}"),
+                        size,
+                        lap_count,
+                        tolerance,
+                        successes as f64 / repeats as f64 * 100.0,
+                        total_ms / repeats as f64,
+                        accuracy
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
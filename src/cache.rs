@@ -1,34 +1,38 @@
 //! Thread-local cache for calculation results.
 //!
-//! Entries are scoped to the loaded dataset generation and every solver setting
-//! that can affect a result. The cache is deliberately bounded so exploring
+//! Entries are scoped to the hash of the loaded cars and every solver setting
+//! that can affect a result, so loading a list again finds its old results. The cache is deliberately bounded so exploring
 //! many combinations cannot grow browser memory without limit.
 
 use random_karma::SolverStrategy;
-use std::cell::RefCell;
-use std::collections::{hash_map::Entry, HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::collections::{hash_map::Entry, HashMap};
 
-/// Maximum number of full calculation results retained in memory.
+/// Default number of full calculation results retained in memory.
 ///
 /// A result can contain many subsets, so keeping this modest is preferable to
 /// retaining every pre-cache run for the entire browser session.
 pub const MAX_CACHE_ENTRIES: usize = 256;
 
 /// Identity of a cached solver request.
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct CacheKey {
-    pub dataset_generation: u64,
+    pub dataset_hash: u64,
     pub target_ms: u32,
     pub lap_count: usize,
     pub player_count: usize,
     pub tolerance_percent_bits: u64,
     pub timeout_ms_bits: u64,
     pub strategy: SolverStrategy,
+    pub min_lap_time: Option<u32>,
+    pub max_lap_time: Option<u32>,
 }
 
 impl CacheKey {
     pub fn new(
-        dataset_generation: u64,
+        dataset_hash: u64,
         target_ms: u32,
         lap_count: usize,
         player_count: usize,
@@ -37,95 +41,329 @@ impl CacheKey {
         strategy: SolverStrategy,
     ) -> Self {
         Self {
-            dataset_generation,
+            dataset_hash,
             target_ms,
             lap_count,
             player_count,
             tolerance_percent_bits: tolerance_percent.to_bits(),
             timeout_ms_bits: timeout_ms.to_bits(),
             strategy,
+            min_lap_time: None,
+            max_lap_time: None,
         }
     }
+
+    /// Scopes the key to a lap-time filter on the car pool.
+    pub fn with_lap_time_filter(
+        mut self,
+        min_lap_time: Option<u32>,
+        max_lap_time: Option<u32>,
+    ) -> Self {
+        self.min_lap_time = min_lap_time;
+        self.max_lap_time = max_lap_time;
+        self
+    }
 }
 
-/// Cache value: (subsets, similarity, calculated_target).
-pub type CacheValue = (Vec<Vec<usize>>, f64, u32);
+/// Cache value: (subsets, similarity, calculated_target, weighted_similarity).
+pub type CacheValue = (Vec<Vec<usize>>, f64, u32, f64);
 
-/// Lookup abstraction that keeps legacy cache-counting code source-compatible.
-pub trait CacheLookup {
-    fn matches(&self, key: &CacheKey) -> bool;
+/// Subsets stored as one array of `u32` car indexes. The indexes point into
+/// the dataset named by the [`CacheKey::dataset_hash`] they are stored under.
+///
+/// A `Vec<Vec<usize>>` spends a separate allocation and 24 bytes of header
+/// on every subset on top of 8-byte indexes; packing keeps 4 bytes per index
+/// and per subset, which also serializes without any nesting.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PackedSubsets {
+    /// End of each subset in `indices`.
+    ends: Box<[u32]>,
+    indices: Box<[u32]>,
 }
 
-impl CacheLookup for CacheKey {
-    fn matches(&self, key: &CacheKey) -> bool {
-        self == key
+impl PackedSubsets {
+    pub fn pack(subsets: &[Vec<usize>]) -> Self {
+        let to_u32 = |n: usize| u32::try_from(n).expect("car indexes fit in u32");
+        let indices: Box<[u32]> = subsets.iter().flatten().map(|&i| to_u32(i)).collect();
+        let ends = subsets
+            .iter()
+            .scan(0, |end, subset| {
+                *end += subset.len();
+                Some(to_u32(*end))
+            })
+            .collect();
+        Self { ends, indices }
     }
+
+    pub fn unpack(&self) -> Vec<Vec<usize>> {
+        let mut start = 0;
+        self.ends
+            .iter()
+            .map(|&end| {
+                let subset = self.indices[start..end as usize]
+                    .iter()
+                    .map(|&i| i as usize)
+                    .collect();
+                start = end as usize;
+                subset
+            })
+            .collect()
+    }
+}
+
+/// A cached result as stored; [`CompactValue::expand`] turns it back into a
+/// [`CacheValue`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CompactValue {
+    pub subsets: PackedSubsets,
+    pub similarity: f64,
+    pub calculated_target: u32,
+    pub weighted_similarity: f64,
 }
 
-impl CacheLookup for (u32, usize, usize) {
-    fn matches(&self, key: &CacheKey) -> bool {
-        (key.target_ms, key.lap_count, key.player_count) == *self
+impl CompactValue {
+    pub fn new(value: &CacheValue) -> Self {
+        let (subsets, similarity, calculated_target, weighted_similarity) = value;
+        Self {
+            subsets: PackedSubsets::pack(subsets),
+            similarity: *similarity,
+            calculated_target: *calculated_target,
+            weighted_similarity: *weighted_similarity,
+        }
+    }
+
+    pub fn expand(&self) -> CacheValue {
+        (
+            self.subsets.unpack(),
+            self.similarity,
+            self.calculated_target,
+            self.weighted_similarity,
+        )
     }
 }
 
-/// A bounded insertion-ordered cache.
+/// Version of the [`CacheSnapshot`] layout. Raise it whenever a stored field
+/// changes and add the step from the previous version to [`migrate`].
+pub const CACHE_FORMAT_VERSION: u32 = 2;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SnapshotEntry {
+    pub key: CacheKey,
+    pub value: CompactValue,
+}
+
+/// Cache contents for carrying warmed-up results to another session, least
+/// recently used first.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CacheSnapshot {
+    pub format_version: u32,
+    pub entries: Vec<SnapshotEntry>,
+    /// Entries of the read data that could not be understood.
+    #[serde(skip)]
+    pub skipped: usize,
+}
+
+impl CacheSnapshot {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("cache entries contain only serializable values")
+    }
+
+    /// Reads a snapshot of any version up to [`CACHE_FORMAT_VERSION`],
+    /// migrating older layouts. Entries that still cannot be read are
+    /// counted in `skipped` rather than failing the whole snapshot.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let mut snapshot: Value =
+            serde_json::from_str(json).map_err(|error| format!("Invalid cache data: {error}"))?;
+        let version = snapshot
+            .get("format_version")
+            .and_then(Value::as_u64)
+            .ok_or("Cache data has no format version")?;
+        if version == 0 || version > u64::from(CACHE_FORMAT_VERSION) {
+            return Err(format!(
+                "Cache data version {version} is not supported (expected 1 to {CACHE_FORMAT_VERSION})"
+            ));
+        }
+        let Some(Value::Array(raw_entries)) = snapshot.get_mut("entries").map(Value::take) else {
+            return Err("Cache data has no entries".to_string());
+        };
+
+        let mut entries = Vec::with_capacity(raw_entries.len());
+        let mut skipped = 0;
+        for entry in raw_entries {
+            match migrate(entry, version as u32)
+                .and_then(|entry| serde_json::from_value(entry).ok())
+            {
+                Some(entry) => entries.push(entry),
+                None => skipped += 1,
+            }
+        }
+        Ok(Self {
+            format_version: CACHE_FORMAT_VERSION,
+            entries,
+            skipped,
+        })
+    }
+}
+
+/// Brings an entry written as `version` up to [`CACHE_FORMAT_VERSION`].
+fn migrate(mut entry: Value, version: u32) -> Option<Value> {
+    for from in version..CACHE_FORMAT_VERSION {
+        match from {
+            // Version 1 stored the value as an unpacked `CacheValue` tuple.
+            1 => {
+                let value: CacheValue =
+                    serde_json::from_value(entry.get_mut("value")?.take()).ok()?;
+                entry["value"] = serde_json::to_value(CompactValue::new(&value)).ok()?;
+            }
+            _ => return None,
+        }
+    }
+    Some(entry)
+}
+
+/// Called with every entry the cache evicts to make room.
+pub type EvictionHook = Box<dyn FnMut(&CacheKey, &CompactValue)>;
+
+struct CachedEntry {
+    value: CompactValue,
+    /// Tick of the last insert or [`CacheStore::get`] hit.
+    last_used: Cell<u64>,
+}
+
+/// A bounded cache that evicts the least recently used entry.
 ///
-/// FIFO eviction is intentional: pre-cache jobs visit targets in a spread-out
-/// order, and this avoids the mutation/borrowing overhead of a full LRU while
-/// providing a hard memory bound.
+/// Recency is ticked through a [`Cell`] so lookups keep taking `&self`, and
+/// eviction scans for the oldest tick: with a few hundred entries that costs
+/// less than maintaining an ordered list on every hit.
 pub struct CacheStore {
-    entries: HashMap<CacheKey, CacheValue>,
-    insertion_order: VecDeque<CacheKey>,
+    entries: HashMap<CacheKey, CachedEntry>,
+    capacity: usize,
+    clock: Cell<u64>,
+    on_evict: Option<EvictionHook>,
 }
 
 impl CacheStore {
     pub fn new() -> Self {
+        Self::with_capacity(MAX_CACHE_ENTRIES)
+    }
+
+    /// A cache holding at most `capacity` entries, and at least one.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
         Self {
-            entries: HashMap::with_capacity(MAX_CACHE_ENTRIES),
-            insertion_order: VecDeque::with_capacity(MAX_CACHE_ENTRIES),
+            entries: HashMap::with_capacity(capacity),
+            capacity,
+            clock: Cell::new(0),
+            on_evict: None,
         }
     }
 
-    pub fn contains_key<K: CacheLookup + ?Sized>(&self, key: &K) -> bool {
-        self.entries.keys().any(|cache_key| key.matches(cache_key))
+    pub fn capacity(&self) -> usize {
+        self.capacity
     }
 
-    pub fn get<K: CacheLookup + ?Sized>(&self, key: &K) -> Option<&CacheValue> {
-        self.entries
-            .iter()
-            .find_map(|(cache_key, value)| key.matches(cache_key).then_some(value))
+    /// Changes the capacity, evicting the least recently used entries that
+    /// no longer fit.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        self.evict_to(self.capacity);
+    }
+
+    pub fn set_eviction_hook(&mut self, hook: impl FnMut(&CacheKey, &CompactValue) + 'static) {
+        self.on_evict = Some(Box::new(hook));
+    }
+
+    fn tick(&self) -> u64 {
+        let tick = self.clock.get() + 1;
+        self.clock.set(tick);
+        tick
+    }
+
+    fn evict_to(&mut self, len: usize) {
+        while self.entries.len() > len {
+            let Some(oldest_key) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used.get())
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest_key) {
+                if let Some(hook) = self.on_evict.as_mut() {
+                    hook(&oldest_key, &entry.value);
+                }
+            }
+        }
+    }
+
+    pub fn contains_key(&self, key: &CacheKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Looks up an entry, unpacked, and marks it as recently used.
+    pub fn get(&self, key: &CacheKey) -> Option<CacheValue> {
+        let entry = self.entries.get(key)?;
+        entry.last_used.set(self.tick());
+        Some(entry.value.expand())
+    }
+
+    /// Stores `value` packed, returning the replaced entry.
+    pub fn insert(&mut self, key: CacheKey, value: &CacheValue) -> Option<CompactValue> {
+        self.insert_compact(key, CompactValue::new(value))
     }
 
-    pub fn insert(&mut self, key: CacheKey, value: CacheValue) -> Option<CacheValue> {
+    fn insert_compact(&mut self, key: CacheKey, value: CompactValue) -> Option<CompactValue> {
+        let last_used = Cell::new(self.tick());
         match self.entries.entry(key) {
-            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Occupied(mut entry) => {
+                let previous = entry.insert(CachedEntry { value, last_used });
+                Some(previous.value)
+            }
             Entry::Vacant(entry) => {
                 let key = entry.into_key();
-                while self.entries.len() >= MAX_CACHE_ENTRIES {
-                    let Some(oldest_key) = self.insertion_order.pop_front() else {
-                        break;
-                    };
-                    self.entries.remove(&oldest_key);
-                }
-
-                self.insertion_order.push_back(key.clone());
-                self.entries.insert(key, value);
+                self.evict_to(self.capacity - 1);
+                self.entries.insert(key, CachedEntry { value, last_used });
                 None
             }
         }
     }
 
+    /// Every entry, least recently used first.
+    pub fn snapshot(&self) -> CacheSnapshot {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(_, entry)| entry.last_used.get());
+        CacheSnapshot {
+            format_version: CACHE_FORMAT_VERSION,
+            entries: entries
+                .into_iter()
+                .map(|(key, entry)| SnapshotEntry {
+                    key: key.clone(),
+                    value: entry.value.clone(),
+                })
+                .collect(),
+            skipped: 0,
+        }
+    }
+
+    /// Adds the entries of `snapshot` in order, so that its most recently
+    /// used entries are the last to be evicted.
+    pub fn restore(&mut self, snapshot: CacheSnapshot) {
+        for entry in snapshot.entries {
+            self.insert_compact(entry.key, entry.value);
+        }
+    }
+
     pub fn clear(&mut self) {
         self.entries.clear();
-        self.insertion_order.clear();
     }
 
     pub fn len(&self) -> usize {
         self.entries.len()
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&CacheKey, &CacheValue)> {
-        self.entries.iter()
+    pub fn iter(&self) -> impl Iterator<Item = (&CacheKey, &CompactValue)> {
+        self.entries.iter().map(|(key, entry)| (key, &entry.value))
     }
 }
 
@@ -144,19 +382,42 @@ mod tests {
     }
 
     fn value(target_ms: u32) -> CacheValue {
-        (vec![vec![target_ms as usize]], 0.0, target_ms)
+        (vec![vec![target_ms as usize]], 0.0, target_ms, 0.0)
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_entry_at_capacity() {
+        let evicted = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let mut cache = CacheStore::with_capacity(3);
+        cache.set_eviction_hook({
+            let evicted = evicted.clone();
+            move |key, _| evicted.borrow_mut().push(key.target_ms)
+        });
+        for target in 1..=3 {
+            cache.insert(key(target), &value(target));
+        }
+
+        assert!(cache.get(&key(1)).is_some());
+        cache.insert(key(4), &value(4));
+        assert!(cache.contains_key(&key(1)));
+        assert!(!cache.contains_key(&key(2)));
+
+        cache.set_capacity(1);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains_key(&key(4)));
+        assert_eq!(*evicted.borrow(), [2, 3, 1]);
     }
 
     #[test]
     fn evicts_the_oldest_entry_at_capacity() {
         let mut cache = CacheStore::new();
         for target in 0..MAX_CACHE_ENTRIES as u32 {
-            cache.insert(key(target), value(target));
+            cache.insert(key(target), &value(target));
         }
 
         cache.insert(
             key(MAX_CACHE_ENTRIES as u32),
-            value(MAX_CACHE_ENTRIES as u32),
+            &value(MAX_CACHE_ENTRIES as u32),
         );
 
         assert_eq!(cache.len(), MAX_CACHE_ENTRIES);
@@ -171,19 +432,103 @@ mod tests {
         let mut legacy = bounded.clone();
         legacy.strategy = SolverStrategy::Legacy;
 
-        cache.insert(bounded.clone(), value(1));
-        cache.insert(legacy.clone(), value(2));
+        cache.insert(bounded.clone(), &value(1));
+        cache.insert(legacy.clone(), &value(2));
 
         assert_eq!(cache.len(), 2);
         assert_eq!(cache.get(&bounded).unwrap().2, 1);
         assert_eq!(cache.get(&legacy).unwrap().2, 2);
     }
 
+    #[test]
+    fn lap_time_filters_use_distinct_entries() {
+        let mut cache = CacheStore::new();
+        let filtered = key(1).with_lap_time_filter(Some(10), None);
+
+        cache.insert(key(1), &value(1));
+        cache.insert(filtered.clone(), &value(2));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&filtered).unwrap().2, 2);
+    }
+
+    #[test]
+    fn datasets_tolerances_and_timeouts_use_distinct_entries() {
+        let mut cache = CacheStore::new();
+        let other_dataset = CacheKey::new(7, 1, 1, 1, 0.5, 1_000.0, SolverStrategy::Bounded);
+        let wider = CacheKey::new(0, 1, 1, 1, 1.0, 1_000.0, SolverStrategy::Bounded);
+        let longer = CacheKey::new(0, 1, 1, 1, 0.5, 2_000.0, SolverStrategy::Bounded);
+
+        cache.insert(key(1), &value(1));
+        for (i, key) in [&other_dataset, &wider, &longer].into_iter().enumerate() {
+            assert!(!cache.contains_key(key));
+            cache.insert(key.clone(), &value(i as u32 + 2));
+        }
+
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.get(&key(1)).unwrap().2, 1);
+        assert_eq!(cache.get(&wider).unwrap().2, 3);
+    }
+
+    #[test]
+    fn packed_subsets_unpack_unchanged() {
+        for subsets in [
+            vec![vec![3, 0, 7], vec![1, 2, 4], vec![6, 5, 8]],
+            vec![vec![9], vec![], vec![2, 70_000]],
+            Vec::new(),
+        ] {
+            assert_eq!(PackedSubsets::pack(&subsets).unpack(), subsets);
+        }
+    }
+
+    #[test]
+    fn snapshots_restore_entries_in_recency_order() {
+        let mut cache = CacheStore::new();
+        for target in 1..=3 {
+            cache.insert(key(target), &value(target));
+        }
+        cache.get(&key(1));
+
+        let snapshot = CacheSnapshot::from_json(&cache.snapshot().to_json()).unwrap();
+        assert_eq!(snapshot.skipped, 0);
+        let mut restored = CacheStore::with_capacity(2);
+        restored.restore(snapshot);
+
+        assert_eq!(restored.len(), 2);
+        assert!(!restored.contains_key(&key(2)));
+        assert_eq!(restored.get(&key(1)), Some(value(1)));
+    }
+
+    #[test]
+    fn older_snapshots_are_migrated() {
+        let key_json = serde_json::to_string(&key(5)).unwrap();
+        let json = format!(
+            r#"{{"format_version": 1, "entries": [
+                {{"key": {key_json}, "value": [[[0, 4], [2, 3]], 0.25, 5, 0.5]}},
+                {{"key": {key_json}, "value": "unreadable"}}
+            ]}}"#
+        );
+        let snapshot = CacheSnapshot::from_json(&json).unwrap();
+        assert_eq!(snapshot.skipped, 1);
+        assert_eq!(snapshot.entries[0].key, key(5));
+        assert_eq!(
+            snapshot.entries[0].value.expand(),
+            (vec![vec![0, 4], vec![2, 3]], 0.25, 5, 0.5)
+        );
+
+        let future = format!(
+            r#"{{"format_version": {}, "entries": []}}"#,
+            CACHE_FORMAT_VERSION + 1
+        );
+        assert!(CacheSnapshot::from_json(&future).is_err());
+        assert!(CacheSnapshot::from_json(r#"{"entries": []}"#).is_err());
+    }
+
     #[test]
     fn replacing_an_entry_does_not_evict_another_entry() {
         let mut cache = CacheStore::new();
-        cache.insert(key(1), value(1));
-        cache.insert(key(1), value(2));
+        cache.insert(key(1), &value(1));
+        cache.insert(key(1), &value(2));
 
         assert_eq!(cache.len(), 1);
         assert_eq!(cache.get(&key(1)).expect("entry exists").2, 2);
@@ -0,0 +1,55 @@
+//! Stopping a calculation that is no longer wanted.
+//!
+//! A [`CancellationToken`] in [`SubsetCalculationConfig::cancellation`] is
+//! checked wherever the solver checks its timeout. Once cancelled, the
+//! calculation stops at the next such check and fails with
+//! [`SubsetError::Cancelled`], whatever it had found so far.
+//!
+//! [`SubsetCalculationConfig::cancellation`]: crate::SubsetCalculationConfig::cancellation
+//! [`SubsetError::Cancelled`]: crate::SubsetError::Cancelled
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A shared flag that cancels every calculation holding a clone of it.
+///
+/// Clones compare equal; separately created tokens do not.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_flag() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert_eq!(token, clone);
+        assert_ne!(token, CancellationToken::new());
+
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+    }
+}
@@ -1,22 +1,774 @@
-//! JavaScript interop for Chart.js visualization.
-//! Provides Rust bindings to chart helper functions defined in chart_helpers.js.
+//! The similarity chart, drawn on its canvas from a [`ChartState`].
+//!
+//! Results are added as they arrive and drawn on the next animation frame.
+//! The wheel zooms around the pointer, dragging pans, a double click shows
+//! the full range again, and a click moves the target slider to the nearest
+//! point. Colors come from the page's CSS variables, so the chart follows
+//! the light and dark themes.
 
-use wasm_bindgen::prelude::*;
+use crate::config::MAX_TOLERANCE_PCT;
+use crate::state::chart::{
+    pin_label, target_tick_label, target_title, ChartHit, ChartPoint, ChartState, PlotArea,
+    MAX_PINNED_CURVES, VALUE_TICKS,
+};
+use gloo_events::{EventListener, EventListenerOptions};
+use gloo_render::{request_animation_frame, AnimationFrame};
+use gloo_timers::callback::Timeout;
+use random_karma::difficulty::{DifficultyProfile, LIKELY_PROBABILITY};
+use random_karma::worker_agent::{RequestMetadata, WorkerError};
+use random_karma::SubsetError;
+use std::cell::RefCell;
+use std::f64::consts::{FRAC_1_SQRT_2, TAU};
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    CanvasRenderingContext2d, Event, EventInit, HtmlCanvasElement, HtmlInputElement, MouseEvent,
+    PointerEvent, WheelEvent,
+};
 
-#[wasm_bindgen(module = "/chart_helpers.js")]
-extern "C" {
-    #[wasm_bindgen(js_name = initSimilarityChart)]
-    pub fn init_similarity_chart(min: u32, max: u32, lap_count: u32, player_count: u32);
+const CANVAS_ID: &str = "similarityChart";
+const SLIDER_SELECTOR: &str = r#".target-slider-container input[type="range"]"#;
+/// Wait after the last zoom or pan before reporting the view.
+const VIEW_REPORT_DELAY_MS: u32 = 300;
+/// Pointer travel, in pixels, from which a press pans instead of clicking.
+const DRAG_THRESHOLD_PX: f64 = 4.0;
+/// Zoom per pixel of wheel scroll.
+const WHEEL_ZOOM_RATE: f64 = 0.002;
+/// How far each point's neighbours bend the curve through it.
+const CURVE_TENSION: f64 = 0.32;
+const FONT_FAMILY: &str =
+    r#"Inter, ui-sans-serif, system-ui, -apple-system, "Segoe UI", sans-serif"#;
+const SIMILARITY_LABEL: &str = "Jaccard similarity";
+const WEIGHTED_LABEL: &str = "Weighted by lap time";
+/// Pinned curve colors when the page does not set `--pin-N`.
+const PIN_FALLBACK_COLORS: [&str; MAX_PINNED_CURVES] = ["#d89113", "#e0569b", "#3b8ee8", "#18a66a"];
+/// Dashes of the weighted line, in pixels drawn and skipped.
+const WEIGHTED_DASH: [f64; 2] = [6.0, 4.0];
 
-    #[wasm_bindgen(js_name = addSimilarityData)]
-    pub fn add_similarity_data(target: u32, similarity_pct: f64, lap_count: u32, player_count: u32);
+type ViewListener = Rc<dyn Fn(u32, u32)>;
 
-    #[wasm_bindgen(js_name = chartAddFailedTargetMarker)]
-    fn chart_add_failed_target_marker(target: u32, lap_count: u32, player_count: u32);
+thread_local! {
+    static CHART: RefCell<Option<Chart>> = const { RefCell::new(None) };
+    static VIEW_LISTENER: RefCell<Option<ViewListener>> = const { RefCell::new(None) };
 }
 
-/// Plot a red “✖” marker at the given target position so users
-/// can immediately spot targets that could not be calculated.
-pub fn add_failed_target_marker(target: u32, lap_count: u32, player_count: u32) {
-    chart_add_failed_target_marker(target, lap_count, player_count);
+struct Chart {
+    canvas: HtmlCanvasElement,
+    state: ChartState,
+    /// Pointer position over the canvas, in CSS pixels.
+    hover: Option<(f64, f64)>,
+    drag: Option<Drag>,
+    frame: Option<AnimationFrame>,
+    view_report: Option<Timeout>,
+    _listeners: Vec<EventListener>,
+}
+
+/// A press on the chart, panning once it travels far enough.
+struct Drag {
+    x: f64,
+    view: (f64, f64),
+    moved: bool,
+}
+
+/// How a series' line and points are drawn.
+struct SeriesStyle<'a> {
+    color: &'a str,
+    dash: &'a [f64],
+    line_width: f64,
+    radius: f64,
+}
+
+/// Colors of the current theme.
+struct Theme {
+    primary: String,
+    secondary: String,
+    danger: String,
+    grid: String,
+    text: String,
+    surface: String,
+    tooltip: String,
+    /// One per pinned curve, in pinning order.
+    pins: Vec<String>,
+}
+
+/// Shows `min..max` for `lap_count` laps and `player_count` players,
+/// dropping the points of the previous range. The view is kept when they
+/// are unchanged, so re-initialising a zoomed chart stays zoomed.
+pub fn init_similarity_chart(min: u32, max: u32, lap_count: u32, player_count: u32) {
+    let Some(canvas) = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id(CANVAS_ID))
+        .and_then(|element| element.dyn_into::<HtmlCanvasElement>().ok())
+    else {
+        return;
+    };
+    CHART.with(|chart| {
+        let mut slot = chart.borrow_mut();
+        let reset = match slot.as_mut() {
+            Some(chart) if chart.canvas == canvas => {
+                chart.hover = None;
+                chart.state.reset(min, max, lap_count, player_count)
+            }
+            _ => {
+                let listeners = bind(&canvas);
+                *slot = Some(Chart {
+                    canvas,
+                    state: ChartState::new(min, max, lap_count, player_count),
+                    hover: None,
+                    drag: None,
+                    frame: None,
+                    view_report: None,
+                    _listeners: listeners,
+                });
+                true
+            }
+        };
+        let chart = slot.as_mut().expect("the chart was just set");
+        if reset {
+            chart.schedule_view_report();
+        }
+        chart.schedule_render();
+    });
+}
+
+pub fn add_similarity_data(
+    target: u32,
+    similarity_pct: f64,
+    weighted_similarity_pct: f64,
+    lap_count: u32,
+    player_count: u32,
+) {
+    with_chart(|chart| {
+        if chart.state.shows(lap_count, player_count) {
+            chart
+                .state
+                .add_similarity(target, similarity_pct, weighted_similarity_pct);
+            chart.schedule_render();
+        }
+    });
+}
+
+/// Calls `listener(min, max)` with the visible range after the user zooms
+/// or pans the chart, and with the full range when a new range resets it.
+pub fn set_chart_view_listener(listener: impl Fn(u32, u32) + 'static) {
+    VIEW_LISTENER.with(|slot| *slot.borrow_mut() = Some(Rc::new(listener)));
+}
+
+pub fn clear_chart_view_listener() {
+    VIEW_LISTENER.with(|slot| *slot.borrow_mut() = None);
+    with_chart(|chart| chart.view_report = None);
+}
+
+/// Pins the curve shown for comparison, replacing an earlier pin of the
+/// same lap and player count and keeping it drawn after they change.
+/// Returns the lap and player counts pinned, in the order of their
+/// `--pin-N` colors; nothing is added once [`MAX_PINNED_CURVES`] are.
+pub fn pin_similarity_curve() -> Vec<(u32, u32)> {
+    with_chart(|chart| {
+        chart.state.pin();
+        chart.schedule_render();
+        chart.pinned()
+    })
+    .unwrap_or_default()
+}
+
+/// Drops the curve pinned for `lap_count` and `player_count`, returning
+/// the lap and player counts still pinned.
+pub fn unpin_similarity_curve(lap_count: u32, player_count: u32) -> Vec<(u32, u32)> {
+    with_chart(|chart| {
+        chart.state.unpin(lap_count, player_count);
+        chart.schedule_render();
+        chart.pinned()
+    })
+    .unwrap_or_default()
+}
+
+/// Plot a red “✖” marker at the failed request's target so users can
+/// immediately spot targets that could not be calculated. Its tooltip says
+/// why, and which tolerance `profile` expects to succeed.
+pub fn add_failed_target_marker(
+    metadata: &RequestMetadata,
+    error: &WorkerError,
+    profile: &DifficultyProfile,
+) {
+    let reason = failure_reason(metadata, error, profile);
+    with_chart(|chart| {
+        if chart
+            .state
+            .shows(metadata.lap_count as u32, metadata.player_count as u32)
+        {
+            chart.state.add_failed(metadata.target, reason);
+            chart.schedule_render();
+        }
+    });
+}
+
+/// Tooltip lines of a failed target's marker.
+fn failure_reason(
+    metadata: &RequestMetadata,
+    error: &WorkerError,
+    profile: &DifficultyProfile,
+) -> Vec<String> {
+    let kind = match error {
+        WorkerError::Timeout(_) => format!("Timed out after {} s", metadata.timeout_ms / 1000.0),
+        WorkerError::Infeasible(SubsetError::TargetUnreachable { .. }) => "Unreachable".to_string(),
+        WorkerError::Infeasible(_) => "Infeasible".to_string(),
+        _ => "Failed".to_string(),
+    };
+    let hint = match profile.tolerance_for(metadata.target, LIKELY_PROBABILITY, MAX_TOLERANCE_PCT) {
+        Some(tolerance) if tolerance > metadata.tolerance_percent => {
+            format!("Likely to succeed at ±{tolerance}% tolerance")
+        }
+        Some(_) => "Likely to succeed at this tolerance with a longer timeout".to_string(),
+        None => format!("Unlikely to succeed at any tolerance up to ±{MAX_TOLERANCE_PCT}%"),
+    };
+    vec![kind, error.to_string(), hint]
+}
+
+/// Runs `f` on the chart, if one is shown. Listeners and slider events are
+/// left to the caller, after the chart is released, since they may
+/// re-initialise it.
+fn with_chart<R>(f: impl FnOnce(&mut Chart) -> R) -> Option<R> {
+    CHART.with(|chart| chart.borrow_mut().as_mut().map(f))
+}
+
+fn report_view() {
+    let Some((min, max)) = with_chart(|chart| chart.state.view) else {
+        return;
+    };
+    let listener = VIEW_LISTENER.with(|slot| slot.borrow().clone());
+    if let Some(listener) = listener {
+        listener(min.round() as u32, max.round() as u32);
+    }
+}
+
+/// Moves the target slider to `target` as if it were dragged there.
+fn select_target(target: u32) {
+    let Some(slider) = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.query_selector(SLIDER_SELECTOR).ok().flatten())
+        .and_then(|element| element.dyn_into::<HtmlInputElement>().ok())
+    else {
+        return;
+    };
+    slider.set_value(&target.to_string());
+    let init = EventInit::new();
+    init.set_bubbles(true);
+    if let Ok(event) = Event::new_with_event_init_dict("input", &init) {
+        let _ = slider.dispatch_event(&event);
+    }
+}
+
+/// Keeps the target slider as wide as the chart, so its thumb sits under
+/// the point for its target.
+fn sync_slider_width(width: f64) {
+    let slider = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.query_selector(SLIDER_SELECTOR).ok().flatten())
+        .and_then(|element| element.dyn_into::<HtmlInputElement>().ok());
+    if let Some(slider) = slider {
+        let _ = slider
+            .style()
+            .set_property("width", &format!("{}px", width.max(0.0)));
+    }
+}
+
+fn bind(canvas: &HtmlCanvasElement) -> Vec<EventListener> {
+    let mut listeners = vec![
+        EventListener::new_with_options(
+            canvas,
+            "wheel",
+            EventListenerOptions::enable_prevent_default(),
+            |event| {
+                let Some(event) = event.dyn_ref::<WheelEvent>() else {
+                    return;
+                };
+                event.prevent_default();
+                let (x, delta) = (f64::from(event.offset_x()), event.delta_y());
+                with_chart(|chart| {
+                    let anchor = chart.state.target_at(&chart.plot_area(), x);
+                    chart.state.zoom_at(anchor, (delta * WHEEL_ZOOM_RATE).exp());
+                    chart.view_changed();
+                });
+            },
+        ),
+        EventListener::new(canvas, "pointerdown", |event| {
+            let Some(event) = event.dyn_ref::<PointerEvent>() else {
+                return;
+            };
+            let (x, pointer) = (f64::from(event.client_x()), event.pointer_id());
+            with_chart(|chart| {
+                chart.drag = Some(Drag {
+                    x,
+                    view: chart.state.view,
+                    moved: false,
+                });
+                let _ = chart.canvas.set_pointer_capture(pointer);
+            });
+        }),
+        EventListener::new(canvas, "pointermove", |event| {
+            let Some(event) = event.dyn_ref::<PointerEvent>() else {
+                return;
+            };
+            let hover = (f64::from(event.offset_x()), f64::from(event.offset_y()));
+            let (x, pressed) = (f64::from(event.client_x()), event.buttons() & 1 != 0);
+            with_chart(|chart| {
+                chart.hover = Some(hover);
+                let plot_width = chart.plot_area().width.max(1.0);
+                let mut panned = false;
+                if let Some(drag) = chart.drag.as_mut().filter(|_| pressed) {
+                    let dx = x - drag.x;
+                    if drag.moved || dx.abs() >= DRAG_THRESHOLD_PX {
+                        drag.moved = true;
+                        let shift = dx * (drag.view.1 - drag.view.0) / plot_width;
+                        let view = (drag.view.0 - shift, drag.view.1 - shift);
+                        chart.state.set_view(view.0, view.1);
+                        panned = true;
+                    }
+                }
+                if panned {
+                    chart.view_changed();
+                } else {
+                    chart.schedule_render();
+                }
+            });
+        }),
+        EventListener::new(canvas, "pointerleave", |_| {
+            with_chart(|chart| {
+                chart.hover = None;
+                chart.schedule_render();
+            });
+        }),
+        EventListener::new(canvas, "click", |event| {
+            let Some(event) = event.dyn_ref::<MouseEvent>() else {
+                return;
+            };
+            let x = f64::from(event.offset_x());
+            let target = with_chart(|chart| {
+                // The click that ends a pan does not pick a target.
+                if chart.drag.take().is_some_and(|drag| drag.moved) {
+                    return None;
+                }
+                chart.state.hit(&chart.plot_area(), x).map(|hit| hit.target)
+            });
+            if let Some(target) = target.flatten() {
+                select_target(target);
+            }
+        }),
+        EventListener::new(canvas, "dblclick", |_| {
+            with_chart(|chart| {
+                let (min, max) = chart.state.full;
+                chart.state.set_view(f64::from(min), f64::from(max));
+                chart.view_changed();
+            });
+        }),
+    ];
+    if let Some(window) = web_sys::window() {
+        listeners.push(EventListener::new(&window, "resize", |_| {
+            with_chart(Chart::schedule_render);
+        }));
+        if let Ok(Some(scheme)) = window.match_media("(prefers-color-scheme: dark)") {
+            listeners.push(EventListener::new(&scheme, "change", |_| {
+                with_chart(Chart::schedule_render);
+            }));
+        }
+    }
+    listeners
+}
+
+impl Chart {
+    fn css_size(&self) -> (f64, f64) {
+        (
+            f64::from(self.canvas.client_width()),
+            f64::from(self.canvas.client_height()),
+        )
+    }
+
+    fn plot_area(&self) -> PlotArea {
+        let (width, height) = self.css_size();
+        PlotArea::for_canvas(width, height)
+    }
+
+    fn pinned(&self) -> Vec<(u32, u32)> {
+        self.state
+            .pinned
+            .iter()
+            .map(|pinned| (pinned.lap_count, pinned.player_count))
+            .collect()
+    }
+
+    fn schedule_render(&mut self) {
+        if self.frame.is_none() {
+            self.frame = Some(request_animation_frame(|_| {
+                with_chart(|chart| {
+                    chart.frame = None;
+                    chart.render();
+                });
+            }));
+        }
+    }
+
+    fn schedule_view_report(&mut self) {
+        self.view_report = Some(Timeout::new(VIEW_REPORT_DELAY_MS, report_view));
+    }
+
+    fn view_changed(&mut self) {
+        self.schedule_render();
+        self.schedule_view_report();
+    }
+
+    fn render(&mut self) {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let Some(context) = self
+            .canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .and_then(|context| context.dyn_into::<CanvasRenderingContext2d>().ok())
+        else {
+            return;
+        };
+        let (width, height) = self.css_size();
+        let ratio = window.device_pixel_ratio();
+        let (pixel_width, pixel_height) = (
+            (width * ratio).round() as u32,
+            (height * ratio).round() as u32,
+        );
+        if (self.canvas.width(), self.canvas.height()) != (pixel_width, pixel_height) {
+            self.canvas.set_width(pixel_width);
+            self.canvas.set_height(pixel_height);
+        }
+        sync_slider_width(width);
+        let _ = context.set_transform(ratio, 0.0, 0.0, ratio, 0.0, 0.0);
+        context.clear_rect(0.0, 0.0, width, height);
+
+        let theme = Theme::read();
+        let plot = PlotArea::for_canvas(width, height);
+        let state = &self.state;
+        let hit = self
+            .hover
+            .filter(|&(x, y)| plot.contains(x, y))
+            .and_then(|(x, _)| state.hit(&plot, x));
+        draw_axes(&context, state, &plot, &theme);
+        draw_legend(&context, width, &plot, &theme);
+
+        context.save();
+        context.begin_path();
+        // Failed markers sit on the bottom edge, so they may overflow it.
+        context.rect(plot.left, plot.top - 10.0, plot.width, plot.height + 20.0);
+        context.clip();
+        let hovered = hit.as_ref().map(|hit| hit.target);
+        for (pinned, color) in state.pinned.iter().zip(&theme.pins) {
+            let style = SeriesStyle {
+                color,
+                dash: &[],
+                line_width: 1.75,
+                radius: 0.0,
+            };
+            draw_series(&context, state, &plot, &pinned.points, style, color, None);
+        }
+        let similarity = SeriesStyle {
+            color: &theme.primary,
+            dash: &[],
+            line_width: 2.5,
+            radius: 3.0,
+        };
+        let weighted = SeriesStyle {
+            color: &theme.secondary,
+            dash: &WEIGHTED_DASH,
+            line_width: 2.0,
+            radius: 2.0,
+        };
+        let series = [(&state.similarity, similarity), (&state.weighted, weighted)];
+        for (points, style) in series {
+            draw_series(
+                &context,
+                state,
+                &plot,
+                points,
+                style,
+                &theme.surface,
+                hovered,
+            );
+        }
+        for failed in &state.failed {
+            let radius = if hovered == Some(failed.target) {
+                9.0
+            } else {
+                7.0
+            };
+            let x = state.x_px(&plot, f64::from(failed.target));
+            draw_cross(&context, (x, plot.y_px(0.0)), radius, &theme.danger);
+        }
+        context.restore();
+
+        if let Some(hit) = hit {
+            draw_tooltip(&context, state, &hit, (width, &plot), &theme);
+        }
+    }
+}
+
+impl Theme {
+    fn read() -> Self {
+        let style = web_sys::window().and_then(|window| {
+            let root = window.document()?.document_element()?;
+            window.get_computed_style(&root).ok().flatten()
+        });
+        let color = |name: &str, fallback: &str| {
+            style
+                .as_ref()
+                .and_then(|style| style.get_property_value(name).ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| fallback.to_string())
+        };
+        Self {
+            primary: color("--primary", "#6d5dfc"),
+            secondary: color("--accent", "#18b6a4"),
+            danger: color("--danger", "#dc4c64"),
+            grid: color("--grid", "rgba(80,94,121,.13)"),
+            text: color("--text-muted", "#667085"),
+            surface: color("--surface-solid", "#fff"),
+            tooltip: color("--text", "#172033"),
+            pins: (1..=MAX_PINNED_CURVES)
+                .zip(PIN_FALLBACK_COLORS)
+                .map(|(index, fallback)| color(&format!("--pin-{index}"), fallback))
+                .collect(),
+        }
+    }
+}
+
+fn font(size: u32, weight: u32) -> String {
+    format!("{weight} {size}px {FONT_FAMILY}")
+}
+
+fn set_line_dash(context: &CanvasRenderingContext2d, dash: &[f64]) {
+    let segments = dash
+        .iter()
+        .map(|&length| JsValue::from_f64(length))
+        .collect::<js_sys::Array>();
+    let _ = context.set_line_dash(&segments);
+}
+
+fn draw_axes(
+    context: &CanvasRenderingContext2d,
+    state: &ChartState,
+    plot: &PlotArea,
+    theme: &Theme,
+) {
+    context.set_line_width(1.0);
+    context.set_stroke_style_str(&theme.grid);
+    context.set_fill_style_str(&theme.text);
+    context.begin_path();
+    context.set_font(&font(11, 400));
+    context.set_text_align("center");
+    context.set_text_baseline("top");
+    for tick in state.target_ticks() {
+        let x = state.x_px(plot, f64::from(tick)).round() + 0.5;
+        context.move_to(x, plot.top);
+        context.line_to(x, plot.bottom());
+        let _ = context.fill_text(&target_tick_label(tick), x, plot.bottom() + 10.0);
+    }
+    context.set_font(&font(10, 400));
+    context.set_text_align("right");
+    context.set_text_baseline("middle");
+    for value in VALUE_TICKS {
+        let y = plot.y_px(value).round() + 0.5;
+        context.move_to(plot.left, y);
+        context.line_to(plot.right(), y);
+        let _ = context.fill_text(&format!("{value}%"), plot.left - 8.0, y);
+    }
+    context.stroke();
+}
+
+fn draw_legend(context: &CanvasRenderingContext2d, width: f64, plot: &PlotArea, theme: &Theme) {
+    const SWATCH: f64 = 14.0;
+    const GAP: f64 = 6.0;
+    const SPACING: f64 = 16.0;
+    let items = [
+        (SIMILARITY_LABEL, &theme.primary, &[][..]),
+        (WEIGHTED_LABEL, &theme.secondary, &WEIGHTED_DASH[..]),
+    ];
+    context.set_font(&font(11, 400));
+    context.set_text_align("left");
+    context.set_text_baseline("middle");
+    let text_width = |label: &str| {
+        context
+            .measure_text(label)
+            .map_or(0.0, |metrics| metrics.width())
+    };
+    let total = items
+        .iter()
+        .map(|(label, ..)| SWATCH + GAP + text_width(label))
+        .sum::<f64>()
+        + SPACING * (items.len() - 1) as f64;
+    let y = plot.top - 16.0;
+    let mut x = width - 10.0 - total;
+    for (label, color, dash) in items {
+        context.set_line_width(2.0);
+        context.set_stroke_style_str(color);
+        set_line_dash(context, dash);
+        context.begin_path();
+        context.move_to(x, y);
+        context.line_to(x + SWATCH, y);
+        context.stroke();
+        set_line_dash(context, &[]);
+        context.set_fill_style_str(&theme.text);
+        let _ = context.fill_text(label, x + SWATCH + GAP, y);
+        x += SWATCH + GAP + text_width(label) + SPACING;
+    }
+}
+
+/// Draws one series as a smoothed line with its points, filled with
+/// `fill`; the `hovered` target's point is drawn larger.
+fn draw_series(
+    context: &CanvasRenderingContext2d,
+    state: &ChartState,
+    plot: &PlotArea,
+    points: &[ChartPoint],
+    style: SeriesStyle,
+    fill: &str,
+    hovered: Option<u32>,
+) {
+    let pixels = points
+        .iter()
+        .map(|point| {
+            (
+                state.x_px(plot, f64::from(point.target)),
+                plot.y_px(point.value),
+            )
+        })
+        .collect::<Vec<_>>();
+    let Some(&(first_x, first_y)) = pixels.first() else {
+        return;
+    };
+    context.set_line_width(style.line_width);
+    context.set_stroke_style_str(style.color);
+    set_line_dash(context, style.dash);
+    context.begin_path();
+    context.move_to(first_x, first_y);
+    for index in 1..pixels.len() {
+        let previous = pixels[index.saturating_sub(2)];
+        let (start, end) = (pixels[index - 1], pixels[index]);
+        let next = pixels[(index + 1).min(pixels.len() - 1)];
+        let half = CURVE_TENSION / 2.0;
+        context.bezier_curve_to(
+            start.0 + (end.0 - previous.0) * half,
+            start.1 + (end.1 - previous.1) * half,
+            end.0 - (next.0 - start.0) * half,
+            end.1 - (next.1 - start.1) * half,
+            end.0,
+            end.1,
+        );
+    }
+    context.stroke();
+    set_line_dash(context, &[]);
+
+    if style.radius == 0.0 {
+        return;
+    }
+    context.set_line_width(2.0);
+    context.set_fill_style_str(fill);
+    for (point, &(x, y)) in points.iter().zip(&pixels) {
+        let radius = if hovered == Some(point.target) {
+            style.radius + 3.0
+        } else {
+            style.radius
+        };
+        context.begin_path();
+        let _ = context.arc(x, y, radius, 0.0, TAU);
+        context.fill();
+        context.stroke();
+    }
+}
+
+fn draw_cross(context: &CanvasRenderingContext2d, (x, y): (f64, f64), radius: f64, color: &str) {
+    let reach = radius * FRAC_1_SQRT_2;
+    context.set_line_width(2.0);
+    context.set_stroke_style_str(color);
+    context.begin_path();
+    context.move_to(x - reach, y - reach);
+    context.line_to(x + reach, y + reach);
+    context.move_to(x + reach, y - reach);
+    context.line_to(x - reach, y + reach);
+    context.stroke();
+}
+
+/// Draws the tooltip of `hit` beside its point, flipped left near the
+/// canvas's right edge `width`.
+fn draw_tooltip(
+    context: &CanvasRenderingContext2d,
+    state: &ChartState,
+    hit: &ChartHit,
+    (width, plot): (f64, &PlotArea),
+    theme: &Theme,
+) {
+    const PADDING: f64 = 12.0;
+    const LINE_HEIGHT: f64 = 18.0;
+    const RADIUS: f64 = 10.0;
+    let mut lines = match &hit.failure {
+        Some(reason) => reason.clone(),
+        None => [
+            (SIMILARITY_LABEL, hit.similarity),
+            (WEIGHTED_LABEL, hit.weighted),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| value.map(|value| format!("{label}  {value:.1}%")))
+        .collect::<Vec<_>>(),
+    };
+    lines.extend(state.pinned.iter().filter_map(|pinned| {
+        let value = pinned.value_at(hit.target)?;
+        let label = pin_label(pinned.lap_count, pinned.player_count);
+        Some(format!("{label}  {value:.1}%"))
+    }));
+    let title = target_title(hit.target);
+    let measure = |text: &str| context.measure_text(text).map_or(0.0, |m| m.width());
+    context.set_font(&font(12, 600));
+    let mut text_width = measure(&title);
+    context.set_font(&font(12, 400));
+    for line in &lines {
+        text_width = text_width.max(measure(line));
+    }
+    let box_width = text_width + PADDING * 2.0;
+    let box_height = LINE_HEIGHT * (lines.len() + 1) as f64 + PADDING * 2.0 - 4.0;
+
+    let anchor_x = state.x_px(plot, f64::from(hit.target));
+    let anchor_y = match (&hit.failure, hit.similarity) {
+        (None, Some(similarity)) => plot.y_px(similarity),
+        _ => plot.y_px(0.0),
+    };
+    let mut x = anchor_x + PADDING;
+    if x + box_width > width - 4.0 {
+        x = anchor_x - PADDING - box_width;
+    }
+    let x = x.max(4.0);
+    let y = (anchor_y - box_height / 2.0).clamp(4.0, (plot.bottom() - box_height).max(4.0));
+
+    context.set_fill_style_str(&theme.tooltip);
+    context.begin_path();
+    context.move_to(x + RADIUS, y);
+    let corners = [
+        (x + box_width, y, x + box_width, y + box_height),
+        (x + box_width, y + box_height, x, y + box_height),
+        (x, y + box_height, x, y),
+        (x, y, x + box_width, y),
+    ];
+    for (corner_x, corner_y, toward_x, toward_y) in corners {
+        let _ = context.arc_to(corner_x, corner_y, toward_x, toward_y, RADIUS);
+    }
+    context.close_path();
+    context.fill();
+
+    context.set_fill_style_str(&theme.surface);
+    context.set_text_align("left");
+    context.set_text_baseline("top");
+    context.set_font(&font(12, 600));
+    let _ = context.fill_text(&title, x + PADDING, y + PADDING);
+    context.set_font(&font(12, 400));
+    for (index, line) in lines.iter().enumerate() {
+        let line_y = y + PADDING + LINE_HEIGHT * (index + 1) as f64;
+        let _ = context.fill_text(line, x + PADDING, line_y);
+    }
 }
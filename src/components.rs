@@ -1,6 +1,13 @@
 //! Pure Yew view components for the Random Karma UI.
 
-use crate::{format_ms_to_minsecms, Car};
+use crate::state::car_pool::CarEdit;
+use crate::state::roster::subset_label;
+use crate::{format_ms_to_minsecms, tolerance_probability, Car};
+use random_karma::{
+    analyze_multiple_runs, diff_results, lap_time_histogram, result_overlap, subset_stats,
+    summarize_results, ResultSummary, RunReport, SubsetStats,
+};
+use std::collections::{BTreeSet, HashMap};
 use std::rc::Rc;
 use yew::prelude::*;
 
@@ -19,20 +26,220 @@ fn calculate_percentage_diff(actual: u32, target: u32) -> f64 {
     (actual as i64 - target as i64) as f64 / target as f64 * 100.0
 }
 
-fn render_result_row(cars: &[Car], set: &[usize], index: usize, target: u32) -> Html {
+/// Tooltip with the id and whichever of class and track the car has.
+fn car_details(car: &Car) -> String {
+    [
+        Some(car.id.as_str()),
+        car.class.as_deref(),
+        car.track.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" · ")
+}
+
+/// Whether the car's id or name contains `query`, which is lowercase and
+/// not empty.
+fn car_matches(car: &Car, query: &str) -> bool {
+    car.id.to_lowercase().contains(query) || car.display_name().to_lowercase().contains(query)
+}
+
+/// Most other selections a shared car's tooltip names.
+const MAX_LISTED_SHARES: usize = 10;
+
+/// The rows each car appears in, once per row, in order.
+fn rows_by_car(results: &[Vec<usize>]) -> HashMap<usize, Vec<usize>> {
+    let mut rows = HashMap::<usize, Vec<usize>>::new();
+    for (row, set) in results.iter().enumerate() {
+        for &car in set {
+            let car_rows = rows.entry(car).or_default();
+            if car_rows.last() != Some(&row) {
+                car_rows.push(row);
+            }
+        }
+    }
+    rows
+}
+
+/// Tooltip line naming the rows other than `row` that `rows` holds.
+fn shared_with(players: &[String], rows: &[usize], row: usize) -> String {
+    let others = rows
+        .iter()
+        .filter(|&&other| other != row)
+        .collect::<Vec<_>>();
+    let mut listed = others
+        .iter()
+        .take(MAX_LISTED_SHARES)
+        .map(|&&other| subset_label(players, other))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if others.len() > MAX_LISTED_SHARES {
+        listed.push_str(&format!(" and {} more", others.len() - MAX_LISTED_SHARES));
+    }
+    format!("Also in {}: {listed}", others.len())
+}
+
+/// Optional columns of the results table describing the lap times within
+/// each selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StatColumn {
+    Fastest,
+    Slowest,
+    Mean,
+    Spread,
+}
+
+impl StatColumn {
+    pub const ALL: [StatColumn; 4] = [
+        StatColumn::Fastest,
+        StatColumn::Slowest,
+        StatColumn::Mean,
+        StatColumn::Spread,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StatColumn::Fastest => "Fastest Car",
+            StatColumn::Slowest => "Slowest Car",
+            StatColumn::Mean => "Mean Lap",
+            StatColumn::Spread => "Spread",
+        }
+    }
+
+    fn render(self, cars: &[Car], stats: &SubsetStats) -> Html {
+        let car = |index: Option<usize>| match index.and_then(|index| cars.get(index)) {
+            Some(car) => html! {
+                <td title={car_details(car)}>
+                    { format!("{} ({})", car.display_name(), format_ms_to_minsecms(car.lap_time)) }
+                </td>
+            },
+            None => html! { <td>{ "—" }</td> },
+        };
+        match self {
+            StatColumn::Fastest => car(stats.fastest),
+            StatColumn::Slowest => car(stats.slowest),
+            StatColumn::Mean => html! { <td>{ format_ms_to_minsecms(stats.mean_lap_time) }</td> },
+            StatColumn::Spread => html! {
+                <td title="Slowest minus fastest lap time">{ format!("{:.3}s", f64::from(stats.spread) / 1000.0) }</td>
+            },
+        }
+    }
+}
+
+/// The row under an expanded result row, spanning `columns`.
+fn render_run_report(report: &RunReport, player_count: usize, columns: usize) -> Html {
+    html! {
+        <tr class="diagnostics-row">
+            <td colspan={columns.to_string()}>
+                { format!(
+                    "Run {} of {player_count} · {} {} · {} {} · {:.1} ms · {:.3}% of target",
+                    report.run,
+                    report.attempts,
+                    if report.attempts == 1 { "attempt" } else { "attempts" },
+                    report.backtracks,
+                    if report.backtracks == 1 { "backtrack" } else { "backtracks" },
+                    report.elapsed_ms,
+                    report.accuracy,
+                ) }
+                if report.exact {
+                    <span class="exact-badge">{ "exact" }</span>
+                }
+            </td>
+        </tr>
+    }
+}
+
+fn render_result_row(
+    props: &ResultsWrapperProps,
+    set: &[usize],
+    index: usize,
+    tolerance_percent: Option<f64>,
+    query: &str,
+    car_rows: &HashMap<usize, Vec<usize>>,
+    diagnostics: Option<(bool, &Callback<usize>)>,
+) -> Html {
+    let cars = &props.cars;
+    let target = props.calculated_target;
     let total = calculate_total_time(cars, set);
     let percentage = calculate_percentage_diff(total, target);
+    let stats = subset_stats(cars, set);
+    let label = subset_label(&props.players, index);
+    let locked = props.locked.contains(&index);
 
     html! {
-        <tr>
-            <td class="sticky-col">{ index + 1 }</td>
+        <tr class={classes!(locked.then_some("locked-row"))}>
+            <td class="sticky-col">{ &label }</td>
+            <td class="row-actions">
+                <button
+                    class="row-action"
+                    title="Copy this selection as text"
+                    aria-label={format!("Copy selection {label}")}
+                    onclick={props.on_copy.reform(move |_| index)}
+                >
+                    { "⧉" }
+                </button>
+                <button
+                    class="row-action"
+                    title="Replace this selection, keeping the others"
+                    aria-label={format!("Re-roll selection {label}")}
+                    disabled={locked}
+                    onclick={props.on_reroll.reform(move |_| index)}
+                >
+                    { "↻" }
+                </button>
+                <button
+                    class={classes!("row-action", locked.then_some("active"))}
+                    title="Keep this selection when recomputing the others"
+                    aria-label={format!("Lock selection {label}")}
+                    aria-pressed={locked.to_string()}
+                    onclick={props.on_toggle_lock.reform(move |_| index)}
+                >
+                    { if locked { "🔒" } else { "🔓" } }
+                </button>
+                if let Some((expanded, on_toggle)) = diagnostics {
+                    <button
+                        class={classes!("row-action", expanded.then_some("active"))}
+                        title="Show how the solver found this selection"
+                        aria-label={format!("Solver diagnostics for selection {label}")}
+                        aria-expanded={expanded.to_string()}
+                        onclick={on_toggle.reform(move |_| index)}
+                    >
+                        { "ⓘ" }
+                    </button>
+                }
+            </td>
             <td>{ format_ms_to_minsecms(total) }</td>
             <td>{ format!("{percentage:.2}%") }</td>
+            if let Some(tolerance_percent) = tolerance_percent {
+                <td>{ format!("{:.0}%", tolerance_probability(cars, set, target, tolerance_percent) * 100.0) }</td>
+            }
+            { for props.stat_columns.iter().map(|column| column.render(cars, &stats)) }
             { for set.iter().map(|&car_index| {
                 match cars.get(car_index) {
-                    Some(car) => html! {
-                        <td>{ format!("{} ({})", car.id, format_ms_to_minsecms(car.lap_time)) }</td>
-                    },
+                    Some(car) => {
+                        let rows = car_rows.get(&car_index).map_or(&[][..], Vec::as_slice);
+                        let shared = rows.len() > 1;
+                        let title = if shared {
+                            format!("{}\n{}", car_details(car), shared_with(&props.players, rows, index))
+                        } else {
+                            car_details(car)
+                        };
+                        html! {
+                            <td
+                                class={classes!(
+                                    (!query.is_empty() && car_matches(car, query)).then_some("car-match"),
+                                    shared.then_some("shared-car"),
+                                )}
+                                {title}
+                            >
+                                { format!("{} ({})", car.display_name(), format_ms_to_minsecms(car.lap_time)) }
+                                if shared {
+                                    <span class="share-badge">{ format!("×{}", rows.len()) }</span>
+                                }
+                            </td>
+                        }
+                    }
                     None => html! { <td class="invalid-result">{ "Invalid car index" }</td> },
                 }
             }) }
@@ -45,7 +252,28 @@ pub struct ResultsWrapperProps {
     pub cars: Rc<Vec<Car>>,
     pub all_results: Rc<Vec<Vec<usize>>>,
     pub similarity: f64,
+    /// Similarity weighted by lap time; see
+    /// [`compute_weighted_jaccard_similarity`](random_karma::compute_weighted_jaccard_similarity).
+    pub weighted_similarity: f64,
+    /// Composite score from [`quality_score`](random_karma::quality_score).
+    pub quality: f64,
     pub calculated_target: u32,
+    pub tolerance_percent: f64,
+    /// Names the rows in order; without a roster they are numbered.
+    pub players: Rc<Vec<String>>,
+    /// Copies the row at an index.
+    pub on_copy: Callback<usize>,
+    /// Re-solves the row at an index, keeping the others.
+    pub on_reroll: Callback<usize>,
+    /// Rows kept when the others are recomputed.
+    pub locked: Rc<BTreeSet<usize>>,
+    pub on_toggle_lock: Callback<usize>,
+    /// Statistics columns shown after the totals, chosen above the table.
+    pub stat_columns: Rc<BTreeSet<StatColumn>>,
+    pub on_toggle_stat_column: Callback<StatColumn>,
+    /// How the solver found each row, where known; rows with a report can
+    /// be expanded to show it.
+    pub run_reports: Rc<Vec<Option<RunReport>>>,
 }
 
 /// Virtualizes rows while retaining a native, horizontally scrollable table.
@@ -77,13 +305,79 @@ pub fn results_wrapper(props: &ResultsWrapperProps) -> Html {
         })
     };
 
-    let total_rows = props.all_results.len();
+    // Cars to find, and whether rows without them are hidden.
+    let filter_text = use_state(String::new);
+    let only_matching = use_state(|| false);
+    let on_filter_input = {
+        let filter_text = filter_text.clone();
+        let scroll_top = scroll_top.clone();
+        Callback::from(move |event: InputEvent| {
+            let input: web_sys::HtmlInputElement = event.target_unchecked_into();
+            filter_text.set(input.value());
+            scroll_top.set(0.0);
+        })
+    };
+    let on_only_matching = {
+        let only_matching = only_matching.clone();
+        let scroll_top = scroll_top.clone();
+        Callback::from(move |event: Event| {
+            let input: web_sys::HtmlInputElement = event.target_unchecked_into();
+            only_matching.set(input.checked());
+            scroll_top.set(0.0);
+        })
+    };
+    let query = filter_text.trim().to_lowercase();
+    let matching_rows = (!query.is_empty()).then(|| {
+        props
+            .all_results
+            .iter()
+            .enumerate()
+            .filter(|(_, set)| {
+                set.iter().any(|&index| {
+                    props
+                        .cars
+                        .get(index)
+                        .is_some_and(|car| car_matches(car, &query))
+                })
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>()
+    });
+    let shown_rows = match &matching_rows {
+        Some(matching) if *only_matching => matching.clone(),
+        _ => (0..props.all_results.len()).collect(),
+    };
+
+    let car_rows = use_memo(props.all_results.clone(), |results| rows_by_car(results));
+
+    // Rows whose solver diagnostics are expanded.
+    let expanded_reports = use_state(BTreeSet::<usize>::new);
+    let on_toggle_report = {
+        let expanded_reports = expanded_reports.clone();
+        Callback::from(move |index: usize| {
+            let mut expanded = (*expanded_reports).clone();
+            if !expanded.remove(&index) {
+                expanded.insert(index);
+            }
+            expanded_reports.set(expanded);
+        })
+    };
+
+    let total_rows = shown_rows.len();
     let subset_size = props.all_results.first().map_or(0, Vec::len);
-    let total_columns = 3 + subset_size;
+    // The confidence column is shown only for data with lap-time
+    // uncertainties.
+    let confidence_tolerance = props
+        .cars
+        .iter()
+        .any(|car| car.uncertainty_ms.is_some())
+        .then_some(props.tolerance_percent);
+    let total_columns =
+        4 + usize::from(confidence_tolerance.is_some()) + props.stat_columns.len() + subset_size;
     let visible_rows = (VIEWPORT_HEIGHT / ROW_HEIGHT).ceil() as usize;
     let first_visible_row = (*scroll_top / ROW_HEIGHT).floor() as usize;
-    let start_row = first_visible_row.saturating_sub(OVERSCAN_ROWS);
     let end_row = (first_visible_row + visible_rows + OVERSCAN_ROWS).min(total_rows);
+    let start_row = first_visible_row.saturating_sub(OVERSCAN_ROWS).min(end_row);
     let leading_spacer_height = start_row as f64 * ROW_HEIGHT;
     let trailing_spacer_height = (total_rows - end_row) as f64 * ROW_HEIGHT;
 
@@ -92,21 +386,63 @@ pub fn results_wrapper(props: &ResultsWrapperProps) -> Html {
             <div class="results-overview">
                 <div class="similarity-status">
                     { format!("Jaccard similarity · {:.2}%", props.similarity * 100.0) }
+                    <span class="weighted-similarity" title="Shared cars weighted by lap time">
+                        { format!("Weighted by lap time · {:.2}%", props.weighted_similarity * 100.0) }
+                    </span>
                 </div>
-                <span class="results-count">{ format!("{} selections", total_rows) }</span>
+                <span class="results-quality" title="Combines mean accuracy, worst accuracy, and diversity">
+                    { format!("Quality · {:.0}/100", props.quality * 100.0) }
+                </span>
+                <span class="results-count">{ format!("{} selections", props.all_results.len()) }</span>
             </div>
             <div class="result-sets">
                 <div class="result-sets-header">
                     <h3>{ "Car selections" }</h3>
                     <span>{ format!("Target · {}", format_ms_to_minsecms(props.calculated_target)) }</span>
                 </div>
+                <div class="results-filter">
+                    <input
+                        type="search"
+                        value={(*filter_text).clone()}
+                        placeholder="Find a car"
+                        aria-label="Find selections with a car"
+                        oninput={on_filter_input}
+                    />
+                    <label>
+                        <input type="checkbox" checked={*only_matching} onchange={on_only_matching} />
+                        { "Only matching rows" }
+                    </label>
+                    if let Some(matching) = &matching_rows {
+                        <span class="filter-count">
+                            { format!("{} of {} selections", matching.len(), props.all_results.len()) }
+                        </span>
+                    }
+                    <details class="column-chooser">
+                        <summary>{ "Columns" }</summary>
+                        { for StatColumn::ALL.into_iter().map(|column| html! {
+                            <label>
+                                <input
+                                    type="checkbox"
+                                    checked={props.stat_columns.contains(&column)}
+                                    onchange={props.on_toggle_stat_column.reform(move |_| column)}
+                                />
+                                { column.label() }
+                            </label>
+                        }) }
+                    </details>
+                </div>
                 <div class="big-car-table-container" onscroll={on_scroll} tabindex="0" aria-label="Car selection results">
                     <table class="big-car-table">
                         <thead>
                             <tr>
-                                <th class="sticky-col">{ "Set #" }</th>
+                                <th class="sticky-col">{ if props.players.is_empty() { "Set #" } else { "Player" } }</th>
+                                <th><span class="visually-hidden">{ "Actions" }</span></th>
                                 <th>{ "Total Time" }</th>
                                 <th>{ "% Off Target" }</th>
+                                if confidence_tolerance.is_some() {
+                                    <th title="Chance that the real total lands within tolerance">{ "In Tolerance" }</th>
+                                }
+                                { for props.stat_columns.iter().map(|column| html! { <th>{ column.label() }</th> }) }
                                 { for (0..subset_size).map(|index| html! { <th>{ format!("Car {}", index + 1) }</th> }) }
                             </tr>
                         </thead>
@@ -116,8 +452,18 @@ pub fn results_wrapper(props: &ResultsWrapperProps) -> Html {
                                     <td colspan={total_columns.to_string()} style={format!("height: {leading_spacer_height}px")}></td>
                                 </tr>
                             }
-                            { for props.all_results.iter().enumerate().skip(start_row).take(end_row - start_row).map(|(index, set)| {
-                                render_result_row(&props.cars, set, index, props.calculated_target)
+                            { for shown_rows[start_row..end_row].iter().map(|&index| {
+                                let report = props.run_reports.get(index).copied().flatten();
+                                let expanded = report.is_some() && expanded_reports.contains(&index);
+                                let diagnostics = report.map(|_| (expanded, &on_toggle_report));
+                                html! {
+                                    <>
+                                        { render_result_row(props, &props.all_results[index], index, confidence_tolerance, &query, &car_rows, diagnostics) }
+                                        if let Some(report) = report.filter(|_| expanded) {
+                                            { render_run_report(&report, props.all_results.len(), total_columns) }
+                                        }
+                                    </>
+                                }
                             }) }
                             if trailing_spacer_height > 0.0 {
                                 <tr class="table-spacer">
@@ -128,6 +474,522 @@ pub fn results_wrapper(props: &ResultsWrapperProps) -> Html {
                     </table>
                 </div>
             </div>
+            <AccuracyChart
+                cars={props.cars.clone()}
+                results={props.all_results.clone()}
+                target={props.calculated_target}
+                tolerance_percent={props.tolerance_percent}
+                players={props.players.clone()}
+            />
+            <CarUsage cars={props.cars.clone()} results={props.all_results.clone()} />
+            <LapTimeHistogram cars={props.cars.clone()} results={props.all_results.clone()} />
         </div>
     }
 }
+
+/// A calculation's selections and the parameters they were found for.
+#[derive(Clone, PartialEq)]
+pub struct ComparedResult {
+    pub cars: Rc<Vec<Car>>,
+    pub results: Rc<Vec<Vec<usize>>>,
+    pub target: u32,
+    pub lap_count: usize,
+    pub player_count: usize,
+    pub tolerance_percent: f64,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ResultComparisonProps {
+    pub pinned: Rc<ComparedResult>,
+    pub current: Rc<ComparedResult>,
+    pub on_unpin: Callback<()>,
+}
+
+/// The pinned result beside the current one: their totals, deviations and
+/// similarity in two columns, and the selections and cars they share.
+#[function_component(ResultComparison)]
+pub fn result_comparison(props: &ResultComparisonProps) -> Html {
+    let summaries = use_memo(
+        (props.pinned.clone(), props.current.clone()),
+        |(pinned, current)| {
+            let summary = |result: &ComparedResult| {
+                summarize_results(
+                    &result.cars,
+                    &result.results,
+                    result.target,
+                    result.tolerance_percent,
+                )
+            };
+            let overlap = result_overlap(
+                &pinned.cars,
+                &pinned.results,
+                &current.cars,
+                &current.results,
+            );
+            (summary(pinned), summary(current), overlap)
+        },
+    );
+    let (pinned, current, overlap) = &*summaries;
+    let sides = [(&*props.pinned, pinned), (&*props.current, current)];
+    let row = |label: &str, value: &dyn Fn(&ComparedResult, &ResultSummary) -> String| {
+        html! {
+            <tr>
+                <th scope="row">{ label }</th>
+                { for sides.iter().map(|(result, summary)| html! { <td>{ value(result, summary) }</td> }) }
+            </tr>
+        }
+    };
+
+    html! {
+        <section class="result-comparison" aria-label="Pinned and current results">
+            <div class="result-comparison-header">
+                <h3>{ "Pinned vs current" }</h3>
+                <button class="btn-secondary small" onclick={props.on_unpin.reform(|_| ())}>
+                    { "Unpin" }
+                </button>
+            </div>
+            <table class="comparison-table">
+                <thead>
+                    <tr>
+                        <th><span class="visually-hidden">{ "Measure" }</span></th>
+                        <th scope="col">{ "Pinned" }</th>
+                        <th scope="col">{ "Current" }</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    { row("Target", &|result, _| format_ms_to_minsecms(result.target)) }
+                    { row("Laps · players", &|result, _| format!("{} · {}", result.lap_count, result.player_count)) }
+                    { row("Mean total", &|_, summary| format_ms_to_minsecms(summary.mean_total)) }
+                    { row("Fastest – slowest", &|_, summary| format!(
+                        "{} – {}",
+                        format_ms_to_minsecms(summary.fastest_total),
+                        format_ms_to_minsecms(summary.slowest_total),
+                    )) }
+                    { row("Mean deviation", &|_, summary| format!("{:.2}%", summary.mean_deviation_percent)) }
+                    { row("Worst deviation", &|_, summary| format!("{:.2}%", summary.worst_deviation_percent)) }
+                    { row("In tolerance", &|result, summary| format!(
+                        "{} of {} (±{}%)",
+                        summary.within_tolerance, summary.subset_count, result.tolerance_percent,
+                    )) }
+                    { row("Jaccard similarity", &|_, summary| format!("{:.2}%", summary.similarity * 100.0)) }
+                </tbody>
+            </table>
+            <p class="comparison-overlap">
+                { format!(
+                    "{} identical selections · {} of {} cars picked in both ({:.0}%)",
+                    overlap.shared_subsets,
+                    overlap.shared_cars,
+                    overlap.picked_cars,
+                    overlap.car_similarity() * 100.0,
+                ) }
+            </p>
+        </section>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ResultDiffProps {
+    pub cars: Rc<Vec<Car>>,
+    /// The cached result compared against, for `from_target`.
+    pub from: Rc<Vec<Vec<usize>>>,
+    pub from_target: u32,
+    /// The result shown, for `to_target`.
+    pub to: Rc<Vec<Vec<usize>>>,
+    pub to_target: u32,
+    pub players: Rc<Vec<String>>,
+    pub on_close: Callback<()>,
+}
+
+/// What changed for every row between the result for another cached target
+/// and the one shown: the cars swapped out and in, and how far the total
+/// moved.
+#[function_component(ResultDiff)]
+pub fn result_diff(props: &ResultDiffProps) -> Html {
+    let diffs = use_memo(
+        (props.cars.clone(), props.from.clone(), props.to.clone()),
+        |(cars, from, to)| diff_results(cars, from, to),
+    );
+    let unchanged = diffs.iter().filter(|diff| diff.is_unchanged()).count();
+    let swapped: usize = diffs.iter().map(|diff| diff.added.len()).sum();
+    let car_names = |indices: &[usize], sign: char, class: &'static str| {
+        if indices.is_empty() {
+            return html! { { "—" } };
+        }
+        html! {
+            { for indices.iter().filter_map(|&index| props.cars.get(index)).map(|car| html! {
+                <span class={class} title={car_details(car)}>{ format!("{sign}{}", car.display_name()) }</span>
+            }) }
+        }
+    };
+
+    html! {
+        <section class="result-diff" aria-label="Changes between two targets">
+            <div class="result-comparison-header">
+                <h3>{ format!(
+                    "Changes from {} to {}",
+                    format_ms_to_minsecms(props.from_target),
+                    format_ms_to_minsecms(props.to_target),
+                ) }</h3>
+                <button class="btn-secondary small" onclick={props.on_close.reform(|_| ())}>
+                    { "Close" }
+                </button>
+            </div>
+            <table class="comparison-table diff-table">
+                <thead>
+                    <tr>
+                        <th scope="col">{ if props.players.is_empty() { "Set #" } else { "Player" } }</th>
+                        <th scope="col">{ "Removed" }</th>
+                        <th scope="col">{ "Added" }</th>
+                        <th scope="col">{ "Total" }</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    { for diffs.iter().enumerate().map(|(index, diff)| html! {
+                        <tr class={classes!(diff.is_unchanged().then_some("unchanged-row"))}>
+                            <th scope="row">{ subset_label(&props.players, index) }</th>
+                            <td>{ car_names(&diff.removed, '−', "diff-removed") }</td>
+                            <td>{ car_names(&diff.added, '+', "diff-added") }</td>
+                            <td>
+                                { format!(
+                                    "{} → {} ({:+.3}s)",
+                                    format_ms_to_minsecms(diff.old_total),
+                                    format_ms_to_minsecms(diff.new_total),
+                                    diff.total_change() as f64 / 1000.0,
+                                ) }
+                            </td>
+                        </tr>
+                    }) }
+                </tbody>
+            </table>
+            <p class="comparison-overlap">
+                { format!(
+                    "{unchanged} of {} selections unchanged · {swapped} cars swapped",
+                    diffs.len(),
+                ) }
+            </p>
+        </section>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct AccuracyChartProps {
+    pub cars: Rc<Vec<Car>>,
+    pub results: Rc<Vec<Vec<usize>>>,
+    pub target: u32,
+    pub tolerance_percent: f64,
+    pub players: Rc<Vec<String>>,
+}
+
+/// A bar for every selection's deviation from the target, above or below
+/// a shaded tolerance band, and the selection furthest off.
+#[function_component(AccuracyChart)]
+pub fn accuracy_chart(props: &AccuracyChartProps) -> Html {
+    let open = use_state(|| true);
+    let on_toggle = {
+        let open = open.clone();
+        Callback::from(move |e: Event| {
+            let details: web_sys::HtmlDetailsElement = e.target_unchecked_into();
+            open.set(details.open());
+        })
+    };
+    let deviations = use_memo(
+        (props.cars.clone(), props.results.clone(), props.target),
+        |(cars, results, target)| {
+            results
+                .iter()
+                .map(|set| calculate_percentage_diff(calculate_total_time(cars, set), *target))
+                .collect::<Vec<_>>()
+        },
+    );
+    let worst = deviations
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()));
+    // Half the chart's height, in percent off the target.
+    let scale = deviations
+        .iter()
+        .map(|deviation| deviation.abs())
+        .fold(props.tolerance_percent, f64::max)
+        * 1.1;
+    let outside = deviations
+        .iter()
+        .filter(|deviation| deviation.abs() > props.tolerance_percent)
+        .count();
+
+    html! {
+        <details class="accuracy-chart" open={*open} ontoggle={on_toggle}>
+            <summary>
+                { "Accuracy per selection" }
+                if let Some((index, deviation)) = worst {
+                    <span class="accuracy-worst">
+                        { format!(" · furthest off: {} ({deviation:+.2}%)", subset_label(&props.players, index)) }
+                    </span>
+                }
+            </summary>
+            if *open {
+                <div
+                    class="accuracy-bars"
+                    role="list"
+                    style={format!("--band: {:.3}", props.tolerance_percent / scale)}
+                >
+                    { for deviations.iter().enumerate().map(|(index, &deviation)| {
+                        let class = classes!(
+                            "accuracy-bar",
+                            (deviation < 0.0).then_some("below"),
+                            (deviation.abs() > props.tolerance_percent).then_some("out-of-tolerance"),
+                            (worst.map(|(worst, _)| worst) == Some(index)).then_some("worst"),
+                        );
+                        html! {
+                            <div
+                                {class}
+                                role="listitem"
+                                style={format!("--deviation: {:.3}", deviation.abs() / scale)}
+                                title={format!("{} · {deviation:+.2}%", subset_label(&props.players, index))}
+                            ></div>
+                        }
+                    }) }
+                </div>
+                <p class="accuracy-note">
+                    { format!("±{}% tolerance shaded · {outside} of {} outside", props.tolerance_percent, deviations.len()) }
+                </p>
+            }
+        </details>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CarUsageProps {
+    pub cars: Rc<Vec<Car>>,
+    pub results: Rc<Vec<Vec<usize>>>,
+}
+
+/// Heatmap of how many selections every car is in, from the
+/// [`AnalysisReport`](random_karma::AnalysisReport) of the results, most
+/// used first. Rendered only while open, like [`CarPoolEditor`].
+#[function_component(CarUsage)]
+pub fn car_usage(props: &CarUsageProps) -> Html {
+    let open = use_state(|| false);
+    let on_toggle = {
+        let open = open.clone();
+        Callback::from(move |e: Event| {
+            let details: web_sys::HtmlDetailsElement = e.target_unchecked_into();
+            open.set(details.open());
+        })
+    };
+    let report = use_memo(
+        (props.cars.clone(), props.results.clone()),
+        |(cars, results)| analyze_multiple_runs(cars, results),
+    );
+    let most_used = report.car_frequencies.first().map_or(1, |car| car.count);
+    let unused = report.reuse_counts.first().copied().unwrap_or(0);
+
+    html! {
+        <details class="car-usage" open={*open} ontoggle={on_toggle}>
+            <summary>
+                { format!("Car usage · {} of {} cars selected", report.car_frequencies.len(), props.cars.len()) }
+            </summary>
+            if *open {
+                <div class="usage-heatmap" role="list">
+                    { for report.car_frequencies.iter().map(|car| html! {
+                        <div
+                            class="usage-cell"
+                            role="listitem"
+                            style={format!("--usage: {:.3}", car.count as f64 / most_used as f64)}
+                            title={format!(
+                                "{} · {} · in {} of {} selections ({:.0}%)",
+                                car.id,
+                                format_ms_to_minsecms(car.lap_time),
+                                car.count,
+                                report.subset_count,
+                                car.share * 100.0
+                            )}
+                        >
+                            <span class="usage-id">{ &car.id }</span>
+                            <span class="usage-count">{ car.count }</span>
+                        </div>
+                    }) }
+                </div>
+                if unused > 0 {
+                    <p class="usage-unused">{ format!("{unused} cars are in no selection.") }</p>
+                }
+            }
+        </details>
+    }
+}
+
+/// Shares of the enabled pool and of the selections in every lap-time bin,
+/// side by side, to show which paces the solver favors. Rendered only
+/// while open, like [`CarUsage`].
+#[function_component(LapTimeHistogram)]
+pub fn lap_time_histogram_panel(props: &CarUsageProps) -> Html {
+    let open = use_state(|| false);
+    let on_toggle = {
+        let open = open.clone();
+        Callback::from(move |e: Event| {
+            let details: web_sys::HtmlDetailsElement = e.target_unchecked_into();
+            open.set(details.open());
+        })
+    };
+    let bins = use_memo(
+        (props.cars.clone(), props.results.clone()),
+        |(cars, results)| lap_time_histogram(cars, results),
+    );
+    let share = |count: usize, total: usize| count as f64 / total.max(1) as f64;
+    let pool_total = bins.iter().map(|bin| bin.pool).sum::<usize>();
+    let selected_total = bins.iter().map(|bin| bin.selected).sum::<usize>();
+    let tallest = bins
+        .iter()
+        .map(|bin| share(bin.pool, pool_total).max(share(bin.selected, selected_total)))
+        .fold(0.0, f64::max)
+        .max(f64::EPSILON);
+
+    html! {
+        <details class="lap-histogram" open={*open} ontoggle={on_toggle}>
+            <summary>{ "Lap times · pool vs selected" }</summary>
+            if *open {
+                <div class="histogram-legend">
+                    <span class="legend-pool">{ format!("Pool ({pool_total} cars)") }</span>
+                    <span class="legend-selected">{ format!("Selected ({selected_total} picks)") }</span>
+                </div>
+                <div class="histogram" role="list">
+                    { for bins.iter().map(|bin| {
+                        let pool = share(bin.pool, pool_total);
+                        let selected = share(bin.selected, selected_total);
+                        html! {
+                            <div
+                                class="histogram-bin"
+                                role="listitem"
+                                title={format!(
+                                    "{}–{} · {:.0}% of pool ({}) · {:.0}% of selected ({})",
+                                    format_ms_to_minsecms(bin.start),
+                                    format_ms_to_minsecms(bin.end),
+                                    pool * 100.0,
+                                    bin.pool,
+                                    selected * 100.0,
+                                    bin.selected
+                                )}
+                            >
+                                <span class="histogram-bar bar-pool" style={format!("--share: {:.3}", pool / tallest)}></span>
+                                <span class="histogram-bar bar-selected" style={format!("--share: {:.3}", selected / tallest)}></span>
+                            </div>
+                        }
+                    }) }
+                </div>
+                if let (Some(first), Some(last)) = (bins.first(), bins.last()) {
+                    <div class="histogram-axis">
+                        <span>{ format_ms_to_minsecms(first.start) }</span>
+                        <span>{ format_ms_to_minsecms(last.end) }</span>
+                    </div>
+                }
+            }
+        </details>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CarPoolEditorProps {
+    pub cars: Rc<Vec<Car>>,
+    pub on_edit: Callback<CarEdit>,
+    /// Why the last edit was rejected.
+    pub error: Option<String>,
+}
+
+/// Lists the loaded cars for editing in place. Every committed change is
+/// sent to `on_edit`, which replaces the dataset the calculations use.
+///
+/// The rows are rendered only while the editor is open, as a full pool has
+/// hundreds of them.
+#[function_component(CarPoolEditor)]
+pub fn car_pool_editor(props: &CarPoolEditorProps) -> Html {
+    let open = use_state(|| false);
+    let on_toggle = {
+        let open = open.clone();
+        Callback::from(move |e: Event| {
+            let details: web_sys::HtmlDetailsElement = e.target_unchecked_into();
+            open.set(details.open());
+        })
+    };
+    let enabled_count = props.cars.iter().filter(|car| car.enabled).count();
+    let emit = |edit: CarEdit| {
+        let on_edit = props.on_edit.clone();
+        Callback::from(move |_| on_edit.emit(edit.clone()))
+    };
+    let row = |(index, car): (usize, &Car)| {
+        let on_id = {
+            let on_edit = props.on_edit.clone();
+            Callback::from(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                on_edit.emit(CarEdit::SetId(index, input.value()));
+            })
+        };
+        let on_lap_time = {
+            let on_edit = props.on_edit.clone();
+            Callback::from(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                on_edit.emit(CarEdit::SetLapTime(index, input.value()));
+            })
+        };
+        let on_enabled = {
+            let on_edit = props.on_edit.clone();
+            Callback::from(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                on_edit.emit(CarEdit::SetEnabled(index, input.checked()));
+            })
+        };
+        html! {
+            <tr key={index} class={classes!((!car.enabled).then_some("disabled-car"))}>
+                <td>
+                    <input type="checkbox" checked={car.enabled} onchange={on_enabled}
+                        aria-label={format!("Use {}", car.id)} />
+                </td>
+                <td>
+                    <input type="text" value={car.id.clone()} onchange={on_id}
+                        aria-label="Car ID" title={car_details(car)} />
+                </td>
+                <td>
+                    <input type="text" value={format_ms_to_minsecms(car.lap_time)} onchange={on_lap_time}
+                        aria-label={format!("Lap time of {}", car.id)} />
+                </td>
+                <td>
+                    <button class="btn-secondary small" onclick={emit(CarEdit::Remove(index))}
+                        aria-label={format!("Remove {}", car.id)}>
+                        { "Remove" }
+                    </button>
+                </td>
+            </tr>
+        }
+    };
+
+    html! {
+        <details class="car-pool-editor" open={*open} ontoggle={on_toggle}>
+            <summary>{ format!("Car pool · {enabled_count} of {} cars in use", props.cars.len()) }</summary>
+            if *open {
+                <div class="car-pool-actions">
+                    <button class="btn-secondary small" onclick={emit(CarEdit::Add)}>{ "Add Car" }</button>
+                    <button class="btn-secondary small" onclick={emit(CarEdit::SetAllEnabled(true))}>{ "Enable All" }</button>
+                    <button class="btn-secondary small" onclick={emit(CarEdit::SetAllEnabled(false))}>{ "Disable All" }</button>
+                    <button class="btn-secondary small" onclick={emit(CarEdit::RemoveDisabled)}>{ "Remove Disabled" }</button>
+                    <button class="btn-secondary small" onclick={emit(CarEdit::SortByLapTime)}>{ "Sort by Lap Time" }</button>
+                </div>
+                if let Some(error) = &props.error {
+                    <div class="input-error">{ error }</div>
+                }
+                <div class="car-pool-table-container">
+                    <table class="car-pool-table">
+                        <thead>
+                            <tr>
+                                <th>{ "Use" }</th>
+                                <th>{ "ID" }</th>
+                                <th>{ "Lap Time" }</th>
+                                <th></th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            { for props.cars.iter().enumerate().map(row) }
+                        </tbody>
+                    </table>
+                </div>
+            }
+        </details>
+    }
+}
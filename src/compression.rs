@@ -0,0 +1,80 @@
+//! Gzip support for datasets and exports.
+//!
+//! Full-season lap databases are usually shared compressed. The readers
+//! accept gzipped and plain bytes alike, telling them apart by the gzip magic
+//! number, and [`gzip`] compresses exports for download. Both use flate2's
+//! pure-Rust backend, which builds for the browser as well.
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// Largest decompressed size accepted, so a small malicious file cannot
+/// exhaust memory.
+pub const MAX_DECOMPRESSED_BYTES: u64 = 256 * 1024 * 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+pub fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(bytes)
+        .expect("compressing to memory cannot fail");
+    encoder.finish().expect("compressing to memory cannot fail")
+}
+
+/// Decompresses gzip data, including files of several concatenated members.
+pub fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decompressed = Vec::new();
+    MultiGzDecoder::new(bytes)
+        .take(MAX_DECOMPRESSED_BYTES + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|error| format!("Invalid gzip data: {error}"))?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_BYTES {
+        return Err(format!(
+            "Decompressed data exceeds {} MiB",
+            MAX_DECOMPRESSED_BYTES / (1024 * 1024)
+        ));
+    }
+    Ok(decompressed)
+}
+
+/// Reads UTF-8 text, decompressing it first when it is gzipped.
+pub fn decode_text(bytes: &[u8]) -> Result<String, String> {
+    let bytes = if is_gzip(bytes) {
+        gunzip(bytes)?
+    } else {
+        bytes.to_vec()
+    };
+    String::from_utf8(bytes).map_err(|_| "Data is not UTF-8 text".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trips_compressed_or_not() {
+        let text = "GT3-01,1:42.000\n".repeat(1_000);
+        let compressed = gzip(text.as_bytes());
+        assert!(is_gzip(&compressed));
+        assert!(compressed.len() < text.len() / 10);
+        assert_eq!(decode_text(&compressed).unwrap(), text);
+        assert_eq!(decode_text(text.as_bytes()).unwrap(), text);
+
+        let mut concatenated = gzip(b"a,1:00.000\n");
+        concatenated.extend(gzip(b"b,1:01.000\n"));
+        assert_eq!(
+            decode_text(&concatenated).unwrap(),
+            "a,1:00.000\nb,1:01.000\n"
+        );
+
+        assert!(decode_text(&compressed[..compressed.len() / 2]).is_err());
+        assert!(decode_text(&[0xff, 0xfe]).is_err());
+    }
+}
@@ -2,7 +2,18 @@
 
 // UI Behavior
 pub const DEBOUNCE_MS: u32 = 300;
+/// Parameter states kept for undo.
+pub const PARAMETER_HISTORY_LIMIT: usize = 50;
+/// Parameter changes closer together than this are undone as one.
+pub const PARAMETER_HISTORY_COALESCE_MS: f64 = 600.0;
+#[cfg(target_arch = "wasm32")]
 pub const WORKER_SCRIPT: &str = "worker.js";
+/// Idle workers kept for later calculations, each with the results it
+/// already solved.
+pub const MAX_IDLE_WORKERS: usize = 4;
+/// Most pre-cache workers, however many cores the processor has.
+pub const MAX_WORKER_COUNT: usize = 16;
+pub const WORKER_COUNT_STORAGE_KEY: &str = "random-karma.worker-count";
 
 // Default values for input fields
 pub const DEFAULT_LAP_COUNT: usize = 25;
@@ -17,6 +28,20 @@ pub const MAX_TIMEOUT_SEC: f64 = 30.0;
 pub const MIN_TOLERANCE_PCT: f64 = 0.1;
 pub const MAX_TOLERANCE_PCT: f64 = 5.0;
 pub const MAX_PLAYER_COUNT: usize = 250;
+/// Enough to hold every slider position of one parameter combination.
+pub const MIN_CACHE_CAPACITY: usize = SLIDER_MAX_INDEX + 1;
+pub const MAX_CACHE_CAPACITY: usize = 4096;
 
 // UI constants
 pub const SLIDER_MAX_INDEX: usize = 99;
+/// Targets pre-cached across the visible range of a zoomed-in chart, on
+/// top of the slider's.
+pub const ZOOM_TARGET_COUNT: usize = 25;
+
+/// Time allowed for deriving one neighboring target's result by swaps, which
+/// runs on the main thread.
+pub const NEIGHBOR_SEED_TIMEOUT_MS: f64 = 100.0;
+
+/// Time allowed for re-rolling one result row, which also runs on the main
+/// thread.
+pub const REROLL_TIMEOUT_MS: f64 = 500.0;
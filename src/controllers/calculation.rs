@@ -1,13 +1,31 @@
 use crate::cache::{CacheKey, CacheValue, CACHE_STORE};
 use crate::chart::{add_failed_target_marker, add_similarity_data};
+use crate::config::{NEIGHBOR_SEED_TIMEOUT_MS, REROLL_TIMEOUT_MS, SLIDER_MAX_INDEX};
+use crate::utils::{base_target_range, calc_target_from_idx};
 use futures::future::{AbortRegistration, Abortable};
 use futures::{SinkExt, StreamExt};
-use random_karma::worker_agent::{KarmaArgs, KarmaResult, KarmaTask, RequestMetadata};
+use gloo_timers::future::TimeoutFuture;
+use random_karma::worker_agent::{
+    KarmaArgs, KarmaFailure, RequestMetadata, SolverOptions, WorkerError, WorkerInput,
+    WorkerOutput, WorkerResult,
+};
+#[cfg(target_arch = "wasm32")]
+use random_karma::worker_agent::{KarmaTask, WorkerCodec};
+use random_karma::{
+    compute_jaccard_similarity, compute_weighted_jaccard_similarity, dataset_hash,
+    derive_neighbor_result, resolve_single_subset, Car, DifficultyProfile, RunReport,
+    SubsetCalculationConfig,
+};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashSet};
+#[cfg(target_arch = "wasm32")]
+use yew_agent::reactor::ReactorBridge;
+#[cfg(target_arch = "wasm32")]
 use yew_agent::Spawnable;
 
 pub fn cache_key(metadata: &RequestMetadata) -> CacheKey {
     CacheKey::new(
-        metadata.dataset_generation,
+        metadata.dataset_hash,
         metadata.target,
         metadata.lap_count,
         metadata.player_count,
@@ -15,61 +33,575 @@ pub fn cache_key(metadata: &RequestMetadata) -> CacheKey {
         metadata.timeout_ms,
         metadata.strategy,
     )
+    .with_lap_time_filter(metadata.min_lap_time, metadata.max_lap_time)
 }
 
 pub fn cached_result(metadata: &RequestMetadata) -> Option<CacheValue> {
-    CACHE_STORE.with(|cache| cache.borrow().get(&cache_key(metadata)).cloned())
+    CACHE_STORE.with(|cache| cache.borrow().get(&cache_key(metadata)))
+}
+
+/// Targets with a cached result for the parameters of `metadata` but its
+/// target, in order.
+pub fn cached_targets(metadata: &RequestMetadata) -> Vec<u32> {
+    let key = cache_key(metadata);
+    let mut targets: Vec<u32> = CACHE_STORE.with(|cache| {
+        cache
+            .borrow()
+            .iter()
+            .filter(|(cached, _)| {
+                CacheKey {
+                    target_ms: key.target_ms,
+                    ..(*cached).clone()
+                } == key
+            })
+            .map(|(cached, _)| cached.target_ms)
+            .collect()
+    });
+    targets.sort_unstable();
+    targets
+}
+
+/// Derives results for the slider targets on either side of the solved
+/// `metadata` with [`derive_neighbor_result`] and caches those not cached
+/// yet, so the chart fills without solving every target. Returns how many
+/// were added.
+pub fn seed_neighbors(cars: &[Car], metadata: &RequestMetadata, sets: &[Vec<usize>]) -> usize {
+    let (min, max) = base_target_range(
+        cars,
+        metadata.lap_count,
+        metadata.player_count,
+        metadata.strategy,
+        (metadata.min_lap_time, metadata.max_lap_time),
+    );
+    let targets = (0..=SLIDER_MAX_INDEX).map(|idx| calc_target_from_idx(min, max, idx));
+    let below = targets.clone().filter(|&t| t < metadata.target).max();
+    let above = targets.filter(|&t| t > metadata.target).min();
+
+    let mut seeded = 0;
+    for target in below.into_iter().chain(above) {
+        let neighbor = RequestMetadata {
+            target,
+            ..metadata.clone()
+        };
+        let key = cache_key(&neighbor);
+        if CACHE_STORE.with(|cache| cache.borrow().contains_key(&key)) {
+            continue;
+        }
+        let config = SubsetCalculationConfig {
+            timeout_ms: NEIGHBOR_SEED_TIMEOUT_MS,
+            ..neighbor.config()
+        };
+        let Some(derived) = derive_neighbor_result(cars, sets, &config) else {
+            continue;
+        };
+        let similarity = compute_jaccard_similarity(&derived).unwrap_or(0.0);
+        let weighted_similarity =
+            compute_weighted_jaccard_similarity(cars, &derived).unwrap_or(0.0);
+        add_similarity_data(
+            target,
+            similarity * 100.0,
+            weighted_similarity * 100.0,
+            metadata.lap_count as u32,
+            metadata.player_count as u32,
+        );
+        CACHE_STORE.with(|cache| {
+            cache
+                .borrow_mut()
+                .insert(key, &(derived, similarity, target, weighted_similarity));
+        });
+        seeded += 1;
+    }
+    seeded
+}
+
+/// Replaces the subset at `index` of the shown results `value` with one
+/// from [`resolve_single_subset`], keeping the others, and recomputes the
+/// similarities. `config` describes the shown results; the re-roll gets at
+/// most [`REROLL_TIMEOUT_MS`] of it.
+pub fn reroll_subset(
+    cars: &[Car],
+    value: &CacheValue,
+    index: usize,
+    config: &SubsetCalculationConfig,
+) -> Result<CacheValue, String> {
+    let (sets, _, target, _) = value;
+    let config = SubsetCalculationConfig {
+        timeout_ms: config.timeout_ms.min(REROLL_TIMEOUT_MS),
+        ..config.clone()
+    };
+    let subset =
+        resolve_single_subset(cars, sets, index, &config).map_err(|error| error.to_string())?;
+    let mut sets = sets.clone();
+    sets[index] = subset;
+    let similarity = compute_jaccard_similarity(&sets).unwrap_or(0.0);
+    let weighted_similarity = compute_weighted_jaccard_similarity(cars, &sets).unwrap_or(0.0);
+    Ok((sets, similarity, *target, weighted_similarity))
+}
+
+/// Re-rolls every row of `value` outside `locked` in order with
+/// [`reroll_subset`], so the locked rows and those re-rolled before constrain
+/// each next one. Yields to the page before each row, reporting the rows
+/// done and the total. Fails with the index and error of the first row that
+/// cannot be re-rolled.
+pub async fn reroll_unlocked(
+    cars: &[Car],
+    mut value: CacheValue,
+    locked: &BTreeSet<usize>,
+    config: &SubsetCalculationConfig,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<CacheValue, (usize, String)> {
+    let unlocked = (0..value.0.len())
+        .filter(|index| !locked.contains(index))
+        .collect::<Vec<_>>();
+    for (done, &index) in unlocked.iter().enumerate() {
+        on_progress(done, unlocked.len());
+        TimeoutFuture::new(0).await;
+        value = reroll_subset(cars, &value, index, config).map_err(|error| (index, error))?;
+    }
+    Ok(value)
 }
 
 /// UI-neutral result of applying a correlated worker response.
 pub enum CalculationOutcome {
-    Success(CacheValue),
+    /// The result, and how each of its runs went.
+    Success(CacheValue, Vec<RunReport>),
     Failure(String),
 }
 
-/// Runs one calculation on an exclusively owned worker bridge.
+thread_local! {
+    static IDLE_BRIDGES: RefCell<Vec<TaskBridge>> = const { RefCell::new(Vec::new()) };
+    static INTERACTIVE_CALCULATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Whether a calculation the user waits for is running, during which
+/// pre-caching holds back so it gets the processor to itself.
+pub fn interactive_calculation_running() -> bool {
+    INTERACTIVE_CALCULATIONS.with(Cell::get) > 0
+}
+
+/// Counts as an interactive calculation until dropped.
+struct InteractiveCalculation;
+
+impl InteractiveCalculation {
+    fn start() -> Self {
+        INTERACTIVE_CALCULATIONS.with(|count| count.set(count.get() + 1));
+        Self
+    }
+}
+
+impl Drop for InteractiveCalculation {
+    fn drop(&mut self) {
+        INTERACTIVE_CALCULATIONS.with(|count| count.set(count.get() - 1));
+    }
+}
+
+/// The karma task a [`TaskBridge`] talks to: a Web Worker in the browser.
+#[cfg(target_arch = "wasm32")]
+type TaskBackend = ReactorBridge<KarmaTask>;
+/// The karma task a [`TaskBridge`] talks to: native threads elsewhere.
+#[cfg(not(target_arch = "wasm32"))]
+type TaskBackend = NativeTask;
+
+/// [`spawn_task`](random_karma::native_worker::spawn_task) behind the
+/// stream and sink of a reactor bridge.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct NativeTask {
+    input: std::sync::mpsc::Sender<WorkerInput>,
+    outputs: futures::channel::mpsc::UnboundedReceiver<WorkerOutput>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl NativeTask {
+    fn spawn() -> Self {
+        let (outputs, received) = futures::channel::mpsc::unbounded();
+        Self {
+            input: random_karma::native_worker::spawn_task(move |output| {
+                outputs.unbounded_send(output).is_ok()
+            }),
+            outputs: received,
+        }
+    }
+
+    fn send_input(&self, input: WorkerInput) {
+        let _ = self.input.send(input);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl futures::Stream for NativeTask {
+    type Item = WorkerOutput;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<WorkerOutput>> {
+        self.outputs.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl futures::Sink<WorkerInput> for NativeTask {
+    type Error = std::sync::mpsc::SendError<WorkerInput>;
+
+    fn poll_ready(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: std::pin::Pin<&mut Self>, input: WorkerInput) -> Result<(), Self::Error> {
+        self.input.send(input)
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+/// A bridge to a karma task that cancels its unanswered request when
+/// dropped, as happens when the future owning it is aborted. In the
+/// browser the task runs in a Web Worker, in native builds on threads of
+/// its own.
+pub struct TaskBridge {
+    bridge: TaskBackend,
+    /// Hashes of the datasets registered with the worker.
+    registered: HashSet<u64>,
+    /// Whether the worker reported its thread pool running.
+    threaded: bool,
+    pending: Option<u64>,
+}
+
+impl TaskBridge {
+    pub fn spawn() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let bridge = <KarmaTask as Spawnable>::spawner()
+            .encoding::<WorkerCodec>()
+            .spawn(crate::config::WORKER_SCRIPT);
+        #[cfg(not(target_arch = "wasm32"))]
+        let bridge = NativeTask::spawn();
+        Self {
+            bridge,
+            registered: HashSet::new(),
+            threaded: false,
+            pending: None,
+        }
+    }
+
+    /// A bridge [`TaskBridge::release`]d earlier, or a new one. Reusing a
+    /// worker keeps its datasets and the results it solved, so it answers
+    /// a repeated request at once.
+    pub fn acquire() -> Self {
+        IDLE_BRIDGES
+            .with(|idle| idle.borrow_mut().pop())
+            .unwrap_or_else(Self::spawn)
+    }
+
+    /// Keeps an idle bridge for [`TaskBridge::acquire`], or closes it when
+    /// [`MAX_IDLE_WORKERS`](crate::config::MAX_IDLE_WORKERS) are kept
+    /// already. Only release a bridge whose worker answered all it was
+    /// sent, or the next calculation waits behind the rest.
+    pub fn release(self) {
+        IDLE_BRIDGES.with(|idle| {
+            let mut idle = idle.borrow_mut();
+            if self.pending.is_none() && idle.len() < crate::config::MAX_IDLE_WORKERS {
+                idle.push(self);
+            }
+        });
+    }
+
+    /// Sends `args` and waits for their result, passing the finished and
+    /// total runs to `on_progress` as they complete. `cars` are registered
+    /// with the worker unless they already are. Returns `None` when the
+    /// worker closes without answering.
+    pub async fn calculate(
+        &mut self,
+        cars: &[Car],
+        args: KarmaArgs,
+        on_progress: impl Fn(usize, usize),
+    ) -> Option<WorkerResult> {
+        let mut result = None;
+        self.calculate_batch(cars, args, on_progress, |response| {
+            result = Some(response);
+            true
+        })
+        .await?;
+        result
+    }
+
+    /// Registers `cars` with the worker unless they already are, and
+    /// returns whether it solves the targets of a request together on its
+    /// thread pool, as it answers. Returns `None` when the worker closes
+    /// first.
+    pub async fn register(&mut self, cars: &[Car]) -> Option<bool> {
+        let dataset_hash = dataset_hash(cars);
+        if self.registered.contains(&dataset_hash) {
+            return Some(self.threaded);
+        }
+        let cars = cars.to_vec();
+        self.bridge
+            .send(WorkerInput::RegisterDataset { cars })
+            .await
+            .ok()?;
+        loop {
+            // Answers to a batch stopped early may still arrive.
+            if let WorkerOutput::Registered {
+                dataset_hash: registered,
+                threaded,
+            } = self.bridge.next().await?
+            {
+                if registered == dataset_hash {
+                    self.registered.insert(dataset_hash);
+                    self.threaded = threaded;
+                    return Some(threaded);
+                }
+            }
+        }
+    }
+
+    /// Sends every target of `args` in one message, passing each result to
+    /// `on_finished` as it arrives, in the order of [`KarmaArgs::requests`].
+    /// Once `on_finished` returns `false` the targets not answered yet are
+    /// cancelled. Returns `None` when the worker closes first.
+    pub async fn calculate_batch(
+        &mut self,
+        cars: &[Car],
+        args: KarmaArgs,
+        on_progress: impl Fn(usize, usize),
+        mut on_finished: impl FnMut(WorkerResult) -> bool,
+    ) -> Option<()> {
+        self.register(cars).await?;
+        let request_id = args.metadata.request_id;
+        let mut unanswered = args.requests().count();
+        self.bridge.send(WorkerInput::Calculate(args)).await.ok()?;
+        self.pending = Some(request_id);
+        while unanswered > 0 {
+            match self.bridge.next().await? {
+                WorkerOutput::Registered { .. } => {}
+                WorkerOutput::Progress { run, total, .. } => on_progress(run, total),
+                // Answers to a batch stopped early may still arrive.
+                WorkerOutput::Finished(result) if answered_id(&result) != request_id => {}
+                WorkerOutput::Finished(result) => {
+                    unanswered -= 1;
+                    if !on_finished(result) && unanswered > 0 {
+                        self.bridge.send_input(WorkerInput::Cancel { request_id });
+                        break;
+                    }
+                }
+            }
+        }
+        self.pending = None;
+        Some(())
+    }
+}
+
+fn answered_id(result: &WorkerResult) -> u64 {
+    match result {
+        Ok(success) => success.metadata.request_id,
+        Err(failure) => failure.metadata.request_id,
+    }
+}
+
+impl Drop for TaskBridge {
+    fn drop(&mut self) {
+        if let Some(request_id) = self.pending {
+            self.bridge.send_input(WorkerInput::Cancel { request_id });
+        }
+    }
+}
+
+/// Runs one calculation on `cars` on an exclusively owned [`TaskBridge`],
+/// released once it answers. Pre-caching pauses meanwhile; see
+/// [`interactive_calculation_running`].
 ///
-/// Aborting drops the bridge, terminating the corresponding browser worker.
+/// `on_progress` receives the finished and total runs as they complete.
+/// Aborting drops the bridge, cancelling the request and closing the
+/// worker once it is idle, and returns `None`. A worker that stops without
+/// answering is reported as [`WorkerError::Deserialization`].
 pub async fn run_worker(
+    cars: &[Car],
     args: KarmaArgs,
     abort_registration: AbortRegistration,
-) -> Option<KarmaResult> {
+    on_progress: impl Fn(usize, usize),
+) -> Option<WorkerResult> {
+    let metadata = args.metadata.clone();
     let task = async {
-        let mut bridge = <KarmaTask as Spawnable>::spawner().spawn(crate::config::WORKER_SCRIPT);
-        bridge.send(args).await.ok()?;
-        bridge.next().await
+        let _interactive = InteractiveCalculation::start();
+        let mut bridge = TaskBridge::acquire();
+        let response = bridge.calculate(cars, args, on_progress).await;
+        if response.is_some() {
+            bridge.release();
+        }
+        response
     };
-    Abortable::new(task, abort_registration)
-        .await
-        .ok()
-        .flatten()
+    match Abortable::new(task, abort_registration).await.ok()? {
+        Some(response) => Some(response),
+        None => Some(Err(KarmaFailure {
+            metadata,
+            error: WorkerError::Deserialization("it closed without an answer".to_string()),
+        })),
+    }
 }
 
 /// Updates chart and cache side effects, returning only the state needed by Yew.
-pub fn apply_result(response: KarmaResult) -> CalculationOutcome {
+/// `cars` are the cars the request ran on, with `options`. A seeded result
+/// is neither cached nor used to seed neighbors: the cache key has no seed,
+/// so it would stand in for the unseeded result of its target.
+pub fn apply_result(
+    cars: &[Car],
+    response: WorkerResult,
+    options: &SolverOptions,
+) -> CalculationOutcome {
     match response {
         Ok(success) => {
             add_similarity_data(
                 success.calculated_target,
                 success.similarity * 100.0,
+                success.weighted_similarity * 100.0,
                 success.metadata.lap_count as u32,
                 success.metadata.player_count as u32,
             );
-            let value = (success.sets, success.similarity, success.calculated_target);
-            CACHE_STORE.with(|cache| {
-                cache
-                    .borrow_mut()
-                    .insert(cache_key(&success.metadata), value.clone());
-            });
-            CalculationOutcome::Success(value)
+            let value = (
+                success.sets,
+                success.similarity,
+                success.calculated_target,
+                success.weighted_similarity,
+            );
+            if options.seed.is_none() {
+                CACHE_STORE.with(|cache| {
+                    cache
+                        .borrow_mut()
+                        .insert(cache_key(&success.metadata), &value);
+                });
+                seed_neighbors(cars, &success.metadata, &value.0);
+            }
+            CalculationOutcome::Success(value, success.runs)
         }
         Err(failure) => {
-            add_failed_target_marker(
-                failure.metadata.target,
-                failure.metadata.lap_count as u32,
-                failure.metadata.player_count as u32,
-            );
-            CalculationOutcome::Failure(failure.error)
+            // Only the solver's failures say something about the target.
+            if failure.error.subset_error().is_some() {
+                let profile = DifficultyProfile::for_config(cars, &failure.metadata.config());
+                add_failed_target_marker(&failure.metadata, &failure.error, &profile);
+            }
+            let message = match &failure.error {
+                WorkerError::Timeout(error) => format!(
+                    "No result within {} s: {error}",
+                    failure.metadata.timeout_ms / 1000.0
+                ),
+                error => error.to_string(),
+            };
+            let suggestions = failure.error.suggestions();
+            if suggestions.is_empty() {
+                CalculationOutcome::Failure(message)
+            } else {
+                CalculationOutcome::Failure(format!("{message}. Try: {}", suggestions.join("; ")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use random_karma::worker_agent::KarmaSuccess;
+    use random_karma::SolverStrategy;
+
+    fn success(target: u32) -> KarmaSuccess {
+        KarmaSuccess {
+            metadata: RequestMetadata {
+                request_id: 1,
+                dataset_generation: 0,
+                dataset_hash: 7,
+                target,
+                lap_count: 2,
+                player_count: 2,
+                timeout_ms: 1_000.0,
+                tolerance_percent: 1.0,
+                strategy: SolverStrategy::Bounded,
+                min_lap_time: None,
+                max_lap_time: None,
+            },
+            sets: vec![vec![0, 1], vec![2, 3]],
+            similarity: 0.0,
+            weighted_similarity: 0.0,
+            calculated_target: target,
+            exact_hits: 0,
+            quality: 0.0,
+            runs: Vec::new(),
         }
     }
+
+    #[test]
+    fn seeded_results_stay_out_of_the_cache() {
+        let cars = (0..4)
+            .map(|index| Car::new(format!("car-{index}"), 100_000 + index * 1_000))
+            .collect::<Vec<_>>();
+        let seeded = SolverOptions {
+            seed: Some(42),
+            ..SolverOptions::default()
+        };
+        assert!(matches!(
+            apply_result(&cars, Ok(success(203_000)), &seeded),
+            CalculationOutcome::Success(..)
+        ));
+        let metadata = success(203_000).metadata;
+        assert!(cached_result(&metadata).is_none());
+        assert!(cached_targets(&metadata).is_empty());
+
+        apply_result(&cars, Ok(success(203_000)), &SolverOptions::default());
+        assert!(cached_result(&metadata).is_some());
+    }
+
+    #[test]
+    fn task_bridges_drive_the_task_on_native_threads() {
+        let cars = (0..9)
+            .map(|index| Car::new(format!("car-{index}"), 100_000 + 2 * index))
+            .collect::<Vec<_>>();
+        let args = KarmaArgs {
+            batch_targets: vec![300_010],
+            ..KarmaArgs::new(RequestMetadata {
+                request_id: 1,
+                dataset_generation: 0,
+                dataset_hash: dataset_hash(&cars),
+                target: 300_000,
+                lap_count: 3,
+                player_count: 3,
+                timeout_ms: 60_000.0,
+                tolerance_percent: 1.0,
+                strategy: SolverStrategy::Bounded,
+                min_lap_time: None,
+                max_lap_time: None,
+            })
+        };
+        let mut bridge = TaskBridge::spawn();
+        let progress = Cell::new(0);
+        let mut targets = Vec::new();
+        futures::executor::block_on(async {
+            assert_eq!(bridge.register(&cars).await, Some(false));
+            bridge
+                .calculate_batch(
+                    &cars,
+                    args,
+                    |_, _| progress.set(progress.get() + 1),
+                    |result| {
+                        targets.push(result.unwrap().metadata.target);
+                        true
+                    },
+                )
+                .await
+        })
+        .unwrap();
+        assert_eq!(targets, [300_000, 300_010]);
+        assert_eq!(progress.get(), 6);
+    }
 }
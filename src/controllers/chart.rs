@@ -4,12 +4,14 @@ use random_karma::SolverStrategy;
 
 #[derive(Clone, Copy)]
 pub struct ChartCacheFilter {
-    pub dataset_generation: u64,
+    pub dataset_hash: u64,
     pub lap_count: usize,
     pub player_count: usize,
     pub timeout_ms: f64,
     pub tolerance_percent: f64,
     pub strategy: SolverStrategy,
+    pub min_lap_time: Option<u32>,
+    pub max_lap_time: Option<u32>,
 }
 
 /// Initializes the chart and replays a sorted, settings-specific cache snapshot.
@@ -25,27 +27,30 @@ pub fn initialize_and_replay(min: u32, max: u32, filter: ChartCacheFilter) {
         filter.player_count as u32,
     );
 
-    let mut entries: Vec<(u32, f64)> = CACHE_STORE.with(|cache| {
+    let mut entries: Vec<(u32, f64, f64)> = CACHE_STORE.with(|cache| {
         cache
             .borrow()
             .iter()
             .filter(|(key, _)| {
-                key.dataset_generation == filter.dataset_generation
+                key.dataset_hash == filter.dataset_hash
                     && key.lap_count == filter.lap_count
                     && key.player_count == filter.player_count
                     && key.timeout_ms_bits == filter.timeout_ms.to_bits()
                     && key.tolerance_percent_bits == filter.tolerance_percent.to_bits()
                     && key.strategy == filter.strategy
+                    && key.min_lap_time == filter.min_lap_time
+                    && key.max_lap_time == filter.max_lap_time
             })
-            .map(|(key, (_, similarity, _))| (key.target_ms, *similarity))
+            .map(|(key, value)| (key.target_ms, value.similarity, value.weighted_similarity))
             .collect()
     });
-    entries.sort_by_key(|(target, _)| *target);
+    entries.sort_by_key(|(target, _, _)| *target);
 
-    for (target, similarity) in entries {
+    for (target, similarity, weighted) in entries {
         add_similarity_data(
             target,
             similarity * 100.0,
+            weighted * 100.0,
             filter.lap_count as u32,
             filter.player_count as u32,
         );
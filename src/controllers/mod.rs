@@ -1,3 +1,5 @@
 pub mod calculation;
 pub mod chart;
 pub mod precache;
+pub mod throttle;
+pub mod worker_count;
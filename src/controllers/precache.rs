@@ -1,18 +1,28 @@
-use crate::cache::CACHE_STORE;
+use crate::cache::{CacheKey, CACHE_STORE};
 use crate::chart::{add_failed_target_marker, add_similarity_data};
-use crate::controllers::calculation::cache_key;
-use crate::utils::{base_target_step, spread_indices};
+use crate::controllers::calculation::{
+    cache_key, interactive_calculation_running, seed_neighbors, TaskBridge,
+};
+use crate::controllers::throttle::{self, ConcurrencyLimiter};
+use crate::controllers::worker_count::detected_worker_limit;
+use crate::utils::{base_target_range, base_target_step};
 use futures::future::{AbortHandle, Abortable};
-use futures::{Sink, SinkExt, Stream, StreamExt};
-use random_karma::worker_agent::{KarmaArgs, KarmaResult, KarmaTask, RequestMetadata};
-use random_karma::{get_target_range_for_subset, Car, SolverStrategy};
+use gloo_timers::future::TimeoutFuture;
+use random_karma::difficulty::HOPELESS_PROBABILITY;
+use random_karma::worker_agent::{KarmaArgs, Priority, RequestMetadata, WorkerError, WorkerResult};
+use random_karma::{dataset_hash, Car, DifficultyProfile, SolverStrategy, SubsetCalculationConfig};
 use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
 use std::rc::Rc;
 use yew::UseStateHandle;
-use yew_agent::Spawnable;
 
-const WORKER_COUNT: usize = 4;
 const UPDATE_BATCH_SIZE: usize = 8;
+/// Targets each worker is sent per message, or at least, for a worker
+/// solving them together on its thread pool, one per core.
+const TARGETS_PER_REQUEST: usize = 8;
+/// How often a paused worker checks whether the interactive calculation
+/// or the [`ConcurrencyLimiter`] it waits for lets it go on.
+const PAUSE_POLL_MS: u32 = 100;
 
 pub struct PrecacheConfig {
     pub cars: Vec<Car>,
@@ -21,6 +31,59 @@ pub struct PrecacheConfig {
     pub timeout_secs: f64,
     pub tolerance_percent: f64,
     pub strategy: SolverStrategy,
+    pub min_lap_time: Option<u32>,
+    pub max_lap_time: Option<u32>,
+    /// Workers solving targets at once, at most.
+    pub worker_count: usize,
+    /// The chart's visible range, pre-cached in finer steps while it is
+    /// narrower than the slider's.
+    pub zoom: Option<(u32, u32)>,
+}
+
+/// Targets of the current run, for the settings panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PrecacheProgress {
+    /// Targets the run caches, leaving out those it deems hopeless.
+    pub planned: usize,
+    /// Planned targets cached, by this run or earlier, or failed.
+    pub done: usize,
+    /// Targets sent to a worker and not answered yet.
+    pub running: usize,
+    pub failed: usize,
+}
+
+impl PrecacheProgress {
+    pub fn queued(&self) -> usize {
+        self.planned.saturating_sub(self.done + self.running)
+    }
+}
+
+/// Counts a run's targets as its workers send and answer them.
+struct ProgressTracker {
+    planned: Vec<CacheKey>,
+    running: Cell<usize>,
+    failed: Cell<usize>,
+}
+
+impl ProgressTracker {
+    fn publish(&self, context: &PrecacheExecutionContext) {
+        if !is_current(context) {
+            return;
+        }
+        let cached = CACHE_STORE.with(|cache| {
+            let cache = cache.borrow();
+            self.planned
+                .iter()
+                .filter(|key| cache.contains_key(key))
+                .count()
+        });
+        context.progress.set(PrecacheProgress {
+            planned: self.planned.len(),
+            done: (cached + self.failed.get()).min(self.planned.len()),
+            running: self.running.get(),
+            failed: self.failed.get(),
+        });
+    }
 }
 
 #[derive(Clone)]
@@ -28,6 +91,13 @@ pub struct PrecacheExecutionContext {
     pub cache_version: UseStateHandle<usize>,
     pub error_count: UseStateHandle<usize>,
     pub failed_targets: UseStateHandle<Rc<Vec<u32>>>,
+    pub progress: UseStateHandle<PrecacheProgress>,
+    /// Holds workers back before their next batch while set, and cuts a
+    /// running batch short after its current target.
+    pub paused: Rc<Cell<bool>>,
+    /// The target slider's position. Workers take the pending targets
+    /// nearest it first, and a batch taken before it moved is cut short.
+    pub focus: Rc<Cell<usize>>,
     pub dataset_generation: Rc<Cell<u64>>,
     pub expected_dataset_generation: u64,
     pub precache_generation: Rc<Cell<u64>>,
@@ -58,6 +128,7 @@ fn update_cache_version(cache_version: &UseStateHandle<usize>) {
 
 fn flush_updates(
     context: &PrecacheExecutionContext,
+    tracker: &ProgressTracker,
     completed_since_update: &mut usize,
     failed: &mut Vec<u32>,
 ) {
@@ -65,6 +136,8 @@ fn flush_updates(
         return;
     }
     update_cache_version(&context.cache_version);
+    tracker.failed.set(tracker.failed.get() + failed.len());
+    tracker.publish(context);
     if !failed.is_empty() {
         context.error_count.set(*context.error_count + failed.len());
         let mut all_failed = (*context.failed_targets).to_vec();
@@ -74,19 +147,52 @@ fn flush_updates(
     *completed_since_update = 0;
 }
 
-async fn process_target(
-    bridge: &mut (impl Stream<Item = KarmaResult> + Sink<KarmaArgs> + Unpin),
-    args: KarmaArgs,
-    context: &PrecacheExecutionContext,
-) -> Result<(), ()> {
-    let metadata = args.metadata.clone();
-    if !is_current(context) {
-        return Err(());
+/// Removes and returns up to `count` of `pending`, nearest `focus` first
+/// and the lower of two equally near.
+fn take_nearest(pending: &mut BTreeSet<u32>, focus: u32, count: usize) -> Vec<u32> {
+    let mut below = pending.range(..focus).rev().peekable();
+    let mut above = pending.range(focus..).peekable();
+    let mut taken = Vec::with_capacity(count);
+    while taken.len() < count {
+        let next = match (below.peek(), above.peek()) {
+            (Some(&&low), Some(&&high)) if focus - low <= high - focus => below.next(),
+            (_, Some(_)) => above.next(),
+            (Some(_), None) => below.next(),
+            (None, None) => break,
+        };
+        taken.extend(next.copied());
+    }
+    for target in &taken {
+        pending.remove(target);
     }
-    bridge.send(args).await.map_err(|_| ())?;
-    let response = bridge.next().await.ok_or(())?;
+    taken
+}
+
+/// `count` targets spread evenly over `min..=max`.
+fn zoom_targets(min: u32, max: u32, count: usize) -> impl Iterator<Item = u32> {
+    let span = u64::from(max.saturating_sub(min));
+    let last = count.saturating_sub(1).max(1) as u64;
+    (0..count as u64).map(move |index| min + (span * index / last) as u32)
+}
+
+/// Why a target was not cached.
+enum TargetError {
+    /// The solver found no result; the target is marked on the chart.
+    Failed,
+    /// The run was superseded or the worker is gone, so the loop stops.
+    Stopped,
+}
+
+/// Caches or marks the `response` to the request for one target.
+fn process_response(
+    cars: &[Car],
+    metadata: RequestMetadata,
+    response: WorkerResult,
+    context: &PrecacheExecutionContext,
+    difficulty: &DifficultyProfile,
+) -> Result<(), TargetError> {
     if !is_current(context) {
-        return Err(());
+        return Err(TargetError::Stopped);
     }
 
     match response {
@@ -94,26 +200,36 @@ async fn process_target(
             add_similarity_data(
                 success.calculated_target,
                 success.similarity * 100.0,
+                success.weighted_similarity * 100.0,
                 metadata.lap_count as u32,
                 metadata.player_count as u32,
             );
-            CACHE_STORE.with(|cache| {
-                cache.borrow_mut().insert(
-                    cache_key(&metadata),
-                    (success.sets, success.similarity, success.calculated_target),
-                );
-            });
+            let value = (
+                success.sets,
+                success.similarity,
+                success.calculated_target,
+                success.weighted_similarity,
+            );
+            CACHE_STORE.with(|cache| cache.borrow_mut().insert(cache_key(&metadata), &value));
+            // Neighbors derived here are skipped by later batches in `run`.
+            seed_neighbors(cars, &metadata, &value.0);
             Ok(())
         }
+        Err(failure)
+            if matches!(
+                failure.error,
+                WorkerError::Deserialization(_)
+                    | WorkerError::Cancelled
+                    | WorkerError::UnknownDataset { .. }
+            ) =>
+        {
+            Err(TargetError::Stopped)
+        }
         Err(failure) if failure.metadata == metadata => {
-            add_failed_target_marker(
-                metadata.target,
-                metadata.lap_count as u32,
-                metadata.player_count as u32,
-            );
-            Err(())
+            add_failed_target_marker(&metadata, &failure.error, difficulty);
+            Err(TargetError::Failed)
         }
-        _ => Err(()),
+        _ => Err(TargetError::Failed),
     }
 }
 
@@ -131,60 +247,250 @@ pub fn run(job: PrecacheJob) {
         timeout_secs,
         tolerance_percent,
         strategy,
+        min_lap_time,
+        max_lap_time,
+        worker_count,
+        zoom,
     } = config;
-    let (min, max) = get_target_range_for_subset(&cars, lap_count);
+    let (min, max) = base_target_range(
+        &cars,
+        lap_count,
+        player_count,
+        strategy,
+        (min_lap_time, max_lap_time),
+    );
     let step = base_target_step(min, max);
-    let order = Rc::new(spread_indices(crate::config::SLIDER_MAX_INDEX + 1));
-
-    for worker_idx in 0..WORKER_COUNT {
-        let cars = cars.clone();
-        let context = context.clone();
-        let request_ids = request_ids.clone();
-        let order = order.clone();
-        let (abort_handle, abort_registration) = AbortHandle::new_pair();
-        abort_handles.borrow_mut().push(abort_handle);
-
-        wasm_bindgen_futures::spawn_local(async move {
-            let worker = async move {
-                let mut bridge =
-                    <KarmaTask as Spawnable>::spawner().spawn(crate::config::WORKER_SCRIPT);
-                let mut completed_since_update = 0usize;
-                let mut failed = Vec::new();
-
-                for pos in (worker_idx..order.len()).step_by(WORKER_COUNT) {
-                    if !is_current(&context) {
-                        return;
-                    }
-                    let target = (min + step * order[pos] as u32).min(max);
-                    let metadata = RequestMetadata {
-                        request_id: next_request_id(&request_ids),
-                        dataset_generation: context.expected_dataset_generation,
-                        target,
-                        lap_count,
-                        player_count,
-                        timeout_ms: timeout_secs * 1000.0,
-                        tolerance_percent,
-                        strategy,
-                    };
-                    if CACHE_STORE.with(|cache| cache.borrow().contains_key(&cache_key(&metadata)))
-                    {
-                        continue;
-                    }
-                    let args = KarmaArgs {
-                        cars: cars.clone(),
-                        metadata,
-                    };
-                    if process_target(&mut bridge, args, &context).await.is_err() {
-                        failed.push(target);
-                    }
-                    completed_since_update += 1;
-                    if completed_since_update >= UPDATE_BATCH_SIZE {
-                        flush_updates(&context, &mut completed_since_update, &mut failed);
+    let cars_hash = dataset_hash(&cars);
+    let dataset_generation = context.expected_dataset_generation;
+    let request = move |target| RequestMetadata {
+        request_id: 0,
+        dataset_generation,
+        dataset_hash: cars_hash,
+        target,
+        lap_count,
+        player_count,
+        timeout_ms: timeout_secs * 1000.0,
+        tolerance_percent,
+        strategy,
+        min_lap_time,
+        max_lap_time,
+    };
+    // Targets the estimate rules out would only run into the timeout; they
+    // are left to an explicit calculation.
+    let difficulty = Rc::new(DifficultyProfile::for_config(
+        &cars,
+        &SubsetCalculationConfig {
+            lap_count,
+            player_count,
+            strategy,
+            min_lap_time,
+            max_lap_time,
+            ..SubsetCalculationConfig::default()
+        },
+    ));
+    let is_planned = {
+        let difficulty = difficulty.clone();
+        move |target| {
+            difficulty.success_probability(target, tolerance_percent) >= HOPELESS_PROBABILITY
+        }
+    };
+    let zoomed = zoom
+        .map(|(low, high)| (low.max(min), high.min(max)))
+        .filter(|&(low, high)| low < high && (low > min || high < max))
+        .map(|(low, high)| zoom_targets(low, high, crate::config::ZOOM_TARGET_COUNT));
+    let pending = (0..=crate::config::SLIDER_MAX_INDEX as u32)
+        .map(|index| (min + step * index).min(max))
+        .chain(zoomed.into_iter().flatten())
+        .filter(|&target| is_planned(target))
+        .collect::<BTreeSet<_>>();
+    let tracker = Rc::new(ProgressTracker {
+        planned: pending
+            .iter()
+            .map(|&target| cache_key(&request(target)))
+            .collect(),
+        running: Cell::new(0),
+        failed: Cell::new(0),
+    });
+    tracker.publish(&context);
+    let pending = Rc::new(RefCell::new(pending));
+    let focus_target = move |focus: usize| (min + step * focus as u32).min(max);
+
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    abort_handles.borrow_mut().push(abort_handle);
+    let start = async move {
+        // A worker whose thread pool started keeps every core busy on its
+        // own, so it replaces the workers the targets are otherwise spread
+        // over. Only the worker knows whether its pool started.
+        let mut first = TaskBridge::acquire();
+        let Some(threaded) = first.register(&cars).await else {
+            return;
+        };
+        let (worker_count, targets_per_request) = if threaded {
+            (1, TARGETS_PER_REQUEST.max(detected_worker_limit()))
+        } else {
+            (worker_count, TARGETS_PER_REQUEST)
+        };
+        let limiter = ConcurrencyLimiter::new(worker_count);
+        {
+            let limiter = limiter.clone();
+            let context = context.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                // The workers hold the other references, so sampling stops once
+                // they are all done.
+                let sampled = limiter.clone();
+                throttle::monitor(sampled, || {
+                    Rc::strong_count(&limiter) > 2 && is_current(&context)
+                })
+                .await;
+            });
+        }
+
+        let mut first = Some(first);
+        for _ in 0..worker_count {
+            let bridge = first.take();
+            let cars = cars.clone();
+            let limiter = limiter.clone();
+            let context = context.clone();
+            let request_ids = request_ids.clone();
+            let pending = pending.clone();
+            let tracker = tracker.clone();
+            let difficulty = difficulty.clone();
+            let (abort_handle, abort_registration) = AbortHandle::new_pair();
+            abort_handles.borrow_mut().push(abort_handle);
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let worker = async move {
+                    let mut bridge = bridge.unwrap_or_else(TaskBridge::acquire);
+                    let mut completed_since_update = 0usize;
+                    let mut failed = Vec::new();
+
+                    loop {
+                        // Held until the batch returns, which is cut short when
+                        // the limit drops, the slider moves, or an interactive
+                        // calculation starts.
+                        let _slot = loop {
+                            if !is_current(&context) {
+                                return;
+                            }
+                            if !interactive_calculation_running() && !context.paused.get() {
+                                if let Some(slot) = limiter.try_acquire() {
+                                    break slot;
+                                }
+                            }
+                            flush_updates(
+                                &context,
+                                &tracker,
+                                &mut completed_since_update,
+                                &mut failed,
+                            );
+                            TimeoutFuture::new(PAUSE_POLL_MS).await;
+                        };
+                        let focus = context.focus.get();
+                        let mut batch = Vec::new();
+                        while batch.is_empty() && !pending.borrow().is_empty() {
+                            batch = take_nearest(
+                                &mut pending.borrow_mut(),
+                                focus_target(focus),
+                                targets_per_request,
+                            );
+                            batch.retain(|&target| {
+                                let key = cache_key(&request(target));
+                                !CACHE_STORE.with(|cache| cache.borrow().contains_key(&key))
+                            });
+                        }
+                        let Some((&first, rest)) = batch.split_first() else {
+                            break;
+                        };
+                        let args = KarmaArgs {
+                            batch_targets: rest.to_vec(),
+                            priority: Priority::Background,
+                            ..KarmaArgs::new(RequestMetadata {
+                                request_id: next_request_id(&request_ids),
+                                ..request(first)
+                            })
+                        };
+                        let mut requests = args.requests().collect::<Vec<_>>().into_iter();
+                        let mut stopped = false;
+                        tracker.running.set(tracker.running.get() + batch.len());
+                        tracker.publish(&context);
+                        let on_finished = |response| {
+                            let Some(metadata) = requests.next() else {
+                                return false;
+                            };
+                            tracker.running.set(tracker.running.get() - 1);
+                            let target = metadata.target;
+                            match process_response(&cars, metadata, response, &context, &difficulty)
+                            {
+                                Ok(()) => {}
+                                Err(TargetError::Failed) => failed.push(target),
+                                Err(TargetError::Stopped) => {
+                                    stopped = true;
+                                    return false;
+                                }
+                            }
+                            completed_since_update += 1;
+                            if completed_since_update >= UPDATE_BATCH_SIZE {
+                                flush_updates(
+                                    &context,
+                                    &tracker,
+                                    &mut completed_since_update,
+                                    &mut failed,
+                                );
+                            }
+                            // Cancels the rest of the batch to make way.
+                            !interactive_calculation_running()
+                                && !limiter.over_limit()
+                                && !context.paused.get()
+                                && context.focus.get() == focus
+                        };
+                        let answered = bridge
+                            .calculate_batch(&cars, args, |_, _| {}, on_finished)
+                            .await;
+                        tracker.running.set(tracker.running.get() - requests.len());
+                        tracker.publish(&context);
+                        if stopped || answered.is_none() {
+                            flush_updates(
+                                &context,
+                                &tracker,
+                                &mut completed_since_update,
+                                &mut failed,
+                            );
+                            return;
+                        }
+                        pending
+                            .borrow_mut()
+                            .extend(requests.map(|metadata| metadata.target));
                     }
-                }
-                flush_updates(&context, &mut completed_since_update, &mut failed);
-            };
-            let _ = Abortable::new(worker, abort_registration).await;
-        });
+                    flush_updates(&context, &tracker, &mut completed_since_update, &mut failed);
+                    bridge.release();
+                };
+                let _ = Abortable::new(worker, abort_registration).await;
+            });
+        }
+    };
+    wasm_bindgen_futures::spawn_local(async move {
+        let _ = Abortable::new(start, abort_registration).await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_nearest_the_focus_are_taken_first() {
+        let mut pending = BTreeSet::from([10, 20, 30, 40, 50]);
+        assert_eq!(take_nearest(&mut pending, 32, 3), [30, 40, 20]);
+        assert_eq!(take_nearest(&mut pending, 30, 1), [10]);
+        assert_eq!(take_nearest(&mut pending, 0, 5), [50]);
+        assert!(pending.is_empty());
+        assert!(take_nearest(&mut pending, 30, 2).is_empty());
+    }
+
+    #[test]
+    fn zoomed_ranges_are_spread_evenly() {
+        let targets = zoom_targets(1_000, 2_000, 5).collect::<Vec<_>>();
+        assert_eq!(targets, [1_000, 1_250, 1_500, 1_750, 2_000]);
+        assert_eq!(zoom_targets(1_000, 2_000, 1).collect::<Vec<_>>(), [1_000]);
     }
 }
@@ -0,0 +1,137 @@
+//! Adapts how many pre-cache workers solve at once to the page's
+//! responsiveness.
+//!
+//! Every worker holds a [`Slot`] of a [`ConcurrencyLimiter`] while it has a
+//! batch out. [`monitor`] samples how late the main thread runs a timer and
+//! lowers the limit while it lags or the tab is hidden, raising it again one
+//! worker at a time once the page is idle.
+
+use gloo_timers::future::TimeoutFuture;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Interval between two lag samples.
+const SAMPLE_MS: u32 = 250;
+/// Lag above which the page counts as janky, about three frames.
+const SLOW_LAG_MS: f64 = 50.0;
+/// Lag below which the page counts as idle.
+const IDLE_LAG_MS: f64 = 10.0;
+
+pub struct ConcurrencyLimiter {
+    max: usize,
+    limit: Cell<usize>,
+    active: Cell<usize>,
+}
+
+/// A worker's permission to solve, returned to the limiter when dropped.
+pub struct Slot(Rc<ConcurrencyLimiter>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.active.set(self.0.active.get() - 1);
+    }
+}
+
+impl ConcurrencyLimiter {
+    /// A limiter allowing all of `max` workers, and at least one.
+    pub fn new(max: usize) -> Rc<Self> {
+        let max = max.max(1);
+        Rc::new(Self {
+            max,
+            limit: Cell::new(max),
+            active: Cell::new(0),
+        })
+    }
+
+    /// A slot, unless the limit is reached.
+    pub fn try_acquire(self: &Rc<Self>) -> Option<Slot> {
+        if self.active.get() >= self.limit.get() {
+            return None;
+        }
+        self.active.set(self.active.get() + 1);
+        Some(Slot(self.clone()))
+    }
+
+    /// Whether more slots are held than the limit allows now, so the next
+    /// worker to finish a target should give its slot back.
+    pub fn over_limit(&self) -> bool {
+        self.active.get() > self.limit.get()
+    }
+
+    /// Adjusts the limit to a sample of the main thread's timer lag.
+    pub fn observe(&self, lag_ms: f64, hidden: bool) {
+        let limit = self.limit.get();
+        let limit = if hidden {
+            1
+        } else if lag_ms > SLOW_LAG_MS {
+            limit.saturating_sub(1).max(1)
+        } else if lag_ms < IDLE_LAG_MS {
+            (limit + 1).min(self.max)
+        } else {
+            limit
+        };
+        self.limit.set(limit);
+    }
+}
+
+/// Samples the main thread's lag into `limiter` until `running` returns
+/// `false`.
+pub async fn monitor(limiter: Rc<ConcurrencyLimiter>, running: impl Fn() -> bool) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let (Some(performance), Some(document)) = (window.performance(), window.document()) else {
+        return;
+    };
+    while running() {
+        let start = performance.now();
+        TimeoutFuture::new(SAMPLE_MS).await;
+        let lag_ms = performance.now() - start - f64::from(SAMPLE_MS);
+        limiter.observe(lag_ms, document.hidden());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How many slots can be taken while none are held.
+    fn limit(limiter: &Rc<ConcurrencyLimiter>) -> usize {
+        let slots = (0..10)
+            .filter_map(|_| limiter.try_acquire())
+            .collect::<Vec<_>>();
+        slots.len()
+    }
+
+    #[test]
+    fn limit_follows_lag_and_slots_respect_it() {
+        let limiter = ConcurrencyLimiter::new(3);
+        let slots = (0..3)
+            .filter_map(|_| limiter.try_acquire())
+            .collect::<Vec<_>>();
+        assert_eq!(slots.len(), 3);
+        assert!(limiter.try_acquire().is_none());
+
+        limiter.observe(80.0, false);
+        assert!(limiter.over_limit());
+        drop(slots);
+        assert!(!limiter.over_limit());
+        assert_eq!(limit(&limiter), 2);
+
+        limiter.observe(30.0, false);
+        assert_eq!(limit(&limiter), 2);
+        limiter.observe(0.0, true);
+        assert_eq!(limit(&limiter), 1);
+        limiter.observe(500.0, false);
+        assert_eq!(limit(&limiter), 1);
+        let slot = limiter.try_acquire();
+        assert!(slot.is_some());
+        assert!(limiter.try_acquire().is_none());
+        drop(slot);
+
+        for _ in 0..5 {
+            limiter.observe(2.0, false);
+        }
+        assert_eq!(limit(&limiter), 3);
+    }
+}
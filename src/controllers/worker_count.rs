@@ -0,0 +1,97 @@
+//! How many workers pre-cache at once.
+//!
+//! By default one fewer than the processor's logical cores, leaving one for
+//! the page. A count chosen on the settings slider, which goes up to one
+//! worker per core, replaces it and is kept in `localStorage`.
+
+use crate::config::{MAX_WORKER_COUNT, WORKER_COUNT_STORAGE_KEY};
+
+/// Used when the browser does not report its core count.
+const FALLBACK_WORKER_COUNT: usize = 4;
+
+/// Workers for `hardware_concurrency` logical cores, which browsers that
+/// do not tell report as 0.
+pub fn default_worker_count(hardware_concurrency: f64) -> usize {
+    if !hardware_concurrency.is_finite() || hardware_concurrency < 1.0 {
+        return FALLBACK_WORKER_COUNT;
+    }
+    clamp_worker_count(hardware_concurrency as usize - 1)
+}
+
+/// Most workers offered for `hardware_concurrency` logical cores: one per
+/// core, or [`MAX_WORKER_COUNT`] when the browser does not tell.
+pub fn worker_count_limit(hardware_concurrency: f64) -> usize {
+    if !hardware_concurrency.is_finite() || hardware_concurrency < 1.0 {
+        return MAX_WORKER_COUNT;
+    }
+    clamp_worker_count(hardware_concurrency as usize)
+}
+
+pub fn clamp_worker_count(count: usize) -> usize {
+    count.clamp(1, MAX_WORKER_COUNT)
+}
+
+/// Reads a count typed into the settings, clamped; `None` for an empty or
+/// unreadable entry, which restores the default.
+pub fn parse_worker_count(text: &str) -> Option<usize> {
+    text.trim().parse().ok().map(clamp_worker_count)
+}
+
+fn hardware_concurrency() -> f64 {
+    web_sys::window()
+        .map(|window| window.navigator().hardware_concurrency())
+        .unwrap_or(0.0)
+}
+
+pub fn detected_worker_count() -> usize {
+    default_worker_count(hardware_concurrency())
+}
+
+pub fn detected_worker_limit() -> usize {
+    worker_count_limit(hardware_concurrency())
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+/// The count chosen in an earlier session, if any.
+pub fn stored_worker_count() -> Option<usize> {
+    let stored = local_storage()?.get_item(WORKER_COUNT_STORAGE_KEY).ok()??;
+    parse_worker_count(&stored)
+}
+
+/// Keeps `count` for later sessions, or forgets the choice for `None`.
+/// Pages without storage keep nothing.
+pub fn store_worker_count(count: Option<usize>) {
+    let Some(storage) = local_storage() else {
+        return;
+    };
+    let _ = match count {
+        Some(count) => storage.set_item(WORKER_COUNT_STORAGE_KEY, &count.to_string()),
+        None => storage.remove_item(WORKER_COUNT_STORAGE_KEY),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_leave_a_core_and_stay_in_range() {
+        assert_eq!(default_worker_count(8.0), 7);
+        assert_eq!(default_worker_count(1.0), 1);
+        assert_eq!(default_worker_count(128.0), MAX_WORKER_COUNT);
+        assert_eq!(default_worker_count(0.0), FALLBACK_WORKER_COUNT);
+        assert_eq!(default_worker_count(f64::NAN), FALLBACK_WORKER_COUNT);
+        assert_eq!(worker_count_limit(8.0), 8);
+        assert_eq!(worker_count_limit(128.0), MAX_WORKER_COUNT);
+        assert_eq!(worker_count_limit(0.0), MAX_WORKER_COUNT);
+
+        assert_eq!(parse_worker_count(" 3 "), Some(3));
+        assert_eq!(parse_worker_count("0"), Some(1));
+        assert_eq!(parse_worker_count("999"), Some(MAX_WORKER_COUNT));
+        assert_eq!(parse_worker_count(""), None);
+        assert_eq!(parse_worker_count("many"), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! Similarity of calculated subsets across the range of reachable targets.
+//!
+//! [`compute_similarity_curve`] solves evenly spaced targets between the
+//! fastest and slowest reachable totals and records the similarity of each
+//! result: the data behind the UI's similarity chart, produced in one call
+//! for native callers.
+
+use crate::difficulty::{DifficultyProfile, HOPELESS_PROBABILITY};
+use crate::{
+    compute_jaccard_similarity, compute_weighted_jaccard_similarity, elapsed_ms,
+    get_target_range_for_players, perform_multiple_runs, Car, SubsetCalculationConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// What happened at one target of a [`SimilarityCurve`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CurveOutcome {
+    Solved {
+        similarity: f64,
+        weighted_similarity: f64,
+    },
+    /// The calculation failed with this [`SubsetError::code`](crate::SubsetError::code).
+    Failed { code: String },
+    /// The difficulty estimate ruled the target out, or the budget ran out
+    /// before it was reached.
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurvePoint {
+    pub target: u32,
+    #[serde(flatten)]
+    pub outcome: CurveOutcome,
+}
+
+/// Similarity by target, in ascending target order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SimilarityCurve {
+    pub lap_count: usize,
+    pub player_count: usize,
+    pub points: Vec<CurvePoint>,
+}
+
+/// Solves `resolution` targets across the reachable range of `lap_count`
+/// laps for `player_count` players with the default solver settings, within
+/// `budget_ms` in total. See [`compute_similarity_curve_with_config`].
+pub fn compute_similarity_curve(
+    cars: &[Car],
+    lap_count: usize,
+    player_count: usize,
+    resolution: usize,
+    budget_ms: f64,
+) -> SimilarityCurve {
+    let config = SubsetCalculationConfig {
+        lap_count,
+        player_count,
+        ..SubsetCalculationConfig::default()
+    };
+    compute_similarity_curve_with_config(cars, &config, resolution, budget_ms)
+}
+
+/// Solves `resolution` evenly spaced targets with every setting of `config`
+/// but its target.
+///
+/// Targets are visited coarse to fine, the ends of the range first, so a
+/// budget that runs out still leaves an even outline of the curve. Each
+/// target gets an equal share of the budget left, at most
+/// `config.timeout_ms`.
+pub fn compute_similarity_curve_with_config(
+    cars: &[Car],
+    config: &SubsetCalculationConfig,
+    resolution: usize,
+    budget_ms: f64,
+) -> SimilarityCurve {
+    #[cfg(not(target_arch = "wasm32"))]
+    let start_time = std::time::Instant::now();
+    #[cfg(target_arch = "wasm32")]
+    let start_time = js_sys::Date::now();
+
+    let mut curve = SimilarityCurve {
+        lap_count: config.lap_count,
+        player_count: config.player_count,
+        points: Vec::new(),
+    };
+    if cars.is_empty() || resolution == 0 {
+        return curve;
+    }
+    let (min, max) = get_target_range_for_players(cars, config.lap_count, config.disjoint_runs());
+    let step = if resolution > 1 {
+        (max - min).div_ceil(resolution as u32 - 1)
+    } else {
+        0
+    };
+    let mut targets = (0..resolution as u32)
+        .map(|index| min.saturating_add(step.saturating_mul(index)).min(max))
+        .collect::<Vec<_>>();
+    targets.dedup();
+
+    let difficulty = DifficultyProfile::for_config(cars, config);
+    let mut outcomes = vec![CurveOutcome::Skipped; targets.len()];
+    let pending = spread_indices(targets.len())
+        .into_iter()
+        .filter(|&index| {
+            difficulty.success_probability(targets[index], config.tolerance_percent)
+                >= HOPELESS_PROBABILITY
+        })
+        .collect::<Vec<_>>();
+    for (done, &index) in pending.iter().enumerate() {
+        let remaining_ms = budget_ms - elapsed_ms(start_time);
+        if remaining_ms <= 0.0 {
+            break;
+        }
+        let point_config = SubsetCalculationConfig {
+            target: targets[index],
+            timeout_ms: config
+                .timeout_ms
+                .min(remaining_ms / (pending.len() - done) as f64),
+            ..config.clone()
+        };
+        outcomes[index] = match perform_multiple_runs(cars, &point_config) {
+            Ok(sets) => CurveOutcome::Solved {
+                similarity: compute_jaccard_similarity(&sets).unwrap_or(0.0),
+                weighted_similarity: compute_weighted_jaccard_similarity(cars, &sets)
+                    .unwrap_or(0.0),
+            },
+            Err(error) => CurveOutcome::Failed {
+                code: error.code().to_string(),
+            },
+        };
+    }
+
+    curve.points = targets
+        .into_iter()
+        .zip(outcomes)
+        .map(|(target, outcome)| CurvePoint { target, outcome })
+        .collect();
+    curve
+}
+
+/// Return indices `0..n` in a “spread-out” order (0, n-1, mid, …).
+pub fn spread_indices(n: usize) -> Vec<usize> {
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut out = Vec::with_capacity(n);
+    let mut seen = vec![false; n];
+    let mut q: VecDeque<(usize, usize)> = VecDeque::new();
+
+    out.push(0);
+    seen[0] = true;
+    if n > 1 {
+        out.push(n - 1);
+        seen[n - 1] = true;
+    }
+    q.push_back((0, n - 1));
+
+    while out.len() < n {
+        let (lo, hi) = q.pop_front().unwrap();
+        if hi - lo <= 1 {
+            continue;
+        }
+        let mid = (lo + hi) / 2;
+        if !seen[mid] {
+            out.push(mid);
+            seen[mid] = true;
+        }
+        q.push_back((lo, mid));
+        q.push_back((mid, hi));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cars() -> Vec<Car> {
+        (0..12)
+            .map(|index| Car::new(format!("car-{index}"), 100_000 + index * 1_000))
+            .collect()
+    }
+
+    #[test]
+    fn curves_cover_the_reachable_range_in_target_order() {
+        let cars = cars();
+        let curve = compute_similarity_curve(&cars, 3, 2, 9, 60_000.0);
+        let targets = curve
+            .points
+            .iter()
+            .map(|point| point.target)
+            .collect::<Vec<_>>();
+        assert_eq!(targets.first(), Some(&303_000));
+        assert_eq!(targets.last(), Some(&330_000));
+        assert!(targets.windows(2).all(|pair| pair[0] < pair[1]));
+        for point in &curve.points[1..curve.points.len() - 1] {
+            let CurveOutcome::Solved { similarity, .. } = point.outcome else {
+                panic!("{point:?}");
+            };
+            assert!((0.0..=1.0).contains(&similarity));
+        }
+
+        let json = serde_json::to_string(&curve.points[1]).unwrap();
+        assert!(json.contains("\"status\":\"solved\""), "{json}");
+    }
+
+    #[test]
+    fn an_exhausted_budget_skips_the_remaining_targets() {
+        let curve = compute_similarity_curve(&cars(), 3, 2, 5, 0.0);
+        assert_eq!(curve.points.len(), 5);
+        assert!(curve
+            .points
+            .iter()
+            .all(|point| point.outcome == CurveOutcome::Skipped));
+        assert!(compute_similarity_curve(&[], 3, 2, 5, 1_000.0)
+            .points
+            .is_empty());
+    }
+
+    #[test]
+    fn spread_order_visits_every_index_once() {
+        assert_eq!(spread_indices(5), [0, 4, 2, 1, 3]);
+        let mut order = spread_indices(100);
+        order.sort_unstable();
+        assert_eq!(order, (0..100).collect::<Vec<_>>());
+    }
+}
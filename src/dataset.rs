@@ -0,0 +1,107 @@
+//! A car pool together with where it came from.
+//!
+//! A [`Dataset`] keeps the [`dataset_hash`] of its cars next to them, so
+//! caches, exports, and provenance checks can tell which pool a result
+//! was calculated on without hashing the cars again.
+
+use crate::{dataset_hash, read_cars_from_json, Car, CsvImportReport};
+
+/// Cars with a name for the pool and the track they were timed on.
+///
+/// The cars are read-only so the hash always describes them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dataset {
+    pub name: String,
+    pub track: Option<String>,
+    cars: Vec<Car>,
+    hash: u64,
+}
+
+impl Dataset {
+    /// The track is the one every car names, if they agree on one.
+    pub fn new(name: impl Into<String>, cars: Vec<Car>) -> Self {
+        let track = cars
+            .first()
+            .and_then(|car| car.track.clone())
+            .filter(|track| cars.iter().all(|car| car.track.as_ref() == Some(track)));
+        Self {
+            name: name.into(),
+            track,
+            hash: dataset_hash(&cars),
+            cars,
+        }
+    }
+
+    /// See [`read_cars_from_json`].
+    pub fn from_json(name: impl Into<String>, json: &str) -> Result<Self, String> {
+        Ok(Self::new(name, read_cars_from_json(json)?))
+    }
+
+    pub fn cars(&self) -> &[Car] {
+        &self.cars
+    }
+
+    pub fn into_cars(self) -> Vec<Car> {
+        self.cars
+    }
+
+    /// [`dataset_hash`] of the cars.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn len(&self) -> usize {
+        self.cars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cars.is_empty()
+    }
+}
+
+impl CsvImportReport {
+    /// The accepted cars as a dataset called `name`.
+    pub fn into_dataset(self, name: impl Into<String>) -> Dataset {
+        Dataset::new(name, self.cars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iracing::read_iracing_results_csv;
+    use crate::read_cars_from_csv_string_detailed;
+
+    #[test]
+    fn datasets_hash_their_cars_and_share_a_common_track() {
+        let dataset = read_cars_from_csv_string_detailed("a,01:00.000\nb,01:01.000\n")
+            .into_dataset("Bundled");
+        assert_eq!(dataset.name, "Bundled");
+        assert_eq!(dataset.len(), 2);
+        assert_eq!(dataset.track, None);
+        assert_eq!(dataset.hash(), dataset_hash(dataset.cars()));
+
+        let dataset = read_iracing_results_csv(
+            "Track\nMonza\nName,Fastest Lap Time\nAnn,1:47.000\nBen,1:48.000\n",
+        )
+        .into_dataset("League night");
+        assert_eq!(dataset.track.as_deref(), Some("Monza"));
+
+        let mut cars = dataset.clone().into_cars();
+        cars[1].track = Some("Spa".to_string());
+        assert_eq!(Dataset::new("Mixed", cars).track, None);
+    }
+
+    #[test]
+    fn json_datasets_match_their_csv_equivalent() {
+        let json = Dataset::from_json(
+            "Pasted",
+            r#"[{"id": "a", "lap_time": "1:00.000"}, {"id": "b", "lap_time": 61000}]"#,
+        )
+        .unwrap();
+        let csv =
+            read_cars_from_csv_string_detailed("a,01:00.000\nb,01:01.000\n").into_dataset("Pasted");
+        assert_eq!(json, csv);
+        assert!(Dataset::from_json("Pasted", "{}").is_err());
+    }
+}
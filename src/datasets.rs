@@ -0,0 +1,54 @@
+//! Car pools bundled with the application, one per track.
+//!
+//! Each pool is a CSV file next to this module in the format of
+//! `transform_csv.py`. The first pool loads on startup; the dataset selector
+//! offers the others. Cached results are keyed by the hash of their cars, so
+//! every pool keeps its own entries.
+
+use random_karma::{dataset_hash, read_cars_from_csv_string, Car};
+
+pub struct BuiltinDataset {
+    pub name: &'static str,
+    csv: &'static str,
+}
+
+impl BuiltinDataset {
+    pub fn cars(&self) -> Vec<Car> {
+        read_cars_from_csv_string(self.csv).unwrap_or_default()
+    }
+}
+
+pub const BUILTIN_DATASETS: &[BuiltinDataset] = &[BuiltinDataset {
+    name: "Time trial vehicles",
+    csv: include_str!("cars.csv"),
+}];
+
+/// Index of the bundled pool `cars` are, unchanged, or `None` for pasted
+/// and edited pools.
+pub fn builtin_index(cars: &[Car]) -> Option<usize> {
+    let hash = dataset_hash(cars);
+    BUILTIN_DATASETS
+        .iter()
+        .position(|dataset| dataset_hash(&dataset.cars()) == hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn bundled_pools_load_and_are_told_apart() {
+        let mut hashes = HashSet::new();
+        for (index, dataset) in BUILTIN_DATASETS.iter().enumerate() {
+            let cars = dataset.cars();
+            assert!(!cars.is_empty(), "{} has no cars", dataset.name);
+            assert!(hashes.insert(dataset_hash(&cars)));
+            assert_eq!(builtin_index(&cars), Some(index));
+        }
+
+        let mut edited = BUILTIN_DATASETS[0].cars();
+        edited.pop();
+        assert_eq!(builtin_index(&edited), None);
+    }
+}
@@ -0,0 +1,369 @@
+//! Upfront checks of a calculation request.
+//!
+//! [`validate_request`] inspects a dataset and configuration without running
+//! the solver, so impossible requests can be reported immediately instead of
+//! after a timeout.
+
+use crate::difficulty::gcd;
+use crate::{
+    accepted_sum_interval, filter_by_lap_time, format_ms_to_minsecms, get_target_range_for_players,
+    get_target_range_for_subset, shared_cap_min_cars, Car, SubsetCalculationConfig,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The request cannot succeed as configured.
+    Error,
+    /// The request can run but is likely to disappoint.
+    Warning,
+}
+
+/// One finding about a request, with a message suitable for display.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable snake_case identifier of the check that produced this finding.
+    pub code: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(code: &str, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: code.to_string(),
+            message,
+        }
+    }
+
+    fn warning(code: &str, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code: code.to_string(),
+            message,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// Checks `config` against `cars` and returns every problem found, errors
+/// first. An empty list means nothing is known to prevent success.
+pub fn validate_request(cars: &[Car], config: &SubsetCalculationConfig) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if let Err(error) = config.validate() {
+        diagnostics.push(Diagnostic::error(error.code(), error.to_string()));
+        return diagnostics;
+    }
+
+    let kept = filter_by_lap_time(cars, config.min_lap_time, config.max_lap_time);
+    let pool = kept
+        .iter()
+        .map(|&index| cars[index].clone())
+        .collect::<Vec<_>>();
+    let enabled = cars.iter().filter(|car| car.enabled).count();
+    let filtered = pool.len() < enabled;
+    let pool_description = if filtered {
+        format!("{} cars match the lap-time filter", pool.len())
+    } else if enabled < cars.len() {
+        format!(
+            "{} of the dataset's {} cars are enabled",
+            pool.len(),
+            cars.len()
+        )
+    } else {
+        format!("the dataset has {} cars", pool.len())
+    };
+
+    if pool.is_empty() {
+        diagnostics.push(Diagnostic::error(
+            "empty_pool",
+            if filtered {
+                "No cars match the lap-time filter; widen or clear it".to_string()
+            } else if !cars.is_empty() {
+                "Every car is disabled; enable some before calculating".to_string()
+            } else {
+                "Load car data before calculating".to_string()
+            },
+        ));
+        return diagnostics;
+    }
+    if config.lap_count > pool.len() {
+        diagnostics.push(Diagnostic::error(
+            "impossible_count",
+            format!(
+                "{} laps are requested but {}; lower the lap count",
+                config.lap_count, pool_description
+            ),
+        ));
+        return diagnostics;
+    }
+
+    let (accepted_min, accepted_max) =
+        accepted_sum_interval(config.target, config.tolerance_percent);
+    for (player, ids) in config.player_pools.iter().enumerate() {
+        let player = player + 1;
+        let garage = pool
+            .iter()
+            .filter(|car| ids.contains(&car.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        if garage.len() < config.lap_count {
+            diagnostics.push(Diagnostic::error(
+                "player_pool_too_small",
+                format!(
+                    "Player {} can drive {} of the available cars but each subset needs {}",
+                    player,
+                    garage.len(),
+                    config.lap_count
+                ),
+            ));
+            continue;
+        }
+        let (min, max) = get_target_range_for_subset(&garage, config.lap_count);
+        if accepted_max < u64::from(min) || accepted_min > u64::from(max) {
+            diagnostics.push(Diagnostic::error(
+                "player_target_out_of_range",
+                format!(
+                    "Player {}'s cars reach only {} – {} for {} laps",
+                    player,
+                    format_ms_to_minsecms(min),
+                    format_ms_to_minsecms(max),
+                    config.lap_count
+                ),
+            ));
+        }
+    }
+
+    let selections = config.lap_count.saturating_mul(config.player_count);
+    if config.full_coverage && selections < pool.len() {
+        diagnostics.push(Diagnostic::error(
+            "insufficient_selections_for_coverage",
+            format!(
+                "Full coverage needs at least {} selections but {} laps × {} players make {}; add players or laps",
+                pool.len(),
+                config.lap_count,
+                config.player_count,
+                selections
+            ),
+        ));
+    }
+
+    if config.disjoint && selections > pool.len() {
+        diagnostics.push(Diagnostic::error(
+            "disjoint_infeasible",
+            format!(
+                "Disjoint subsets need {} cars but {}; at most {} players fit",
+                selections,
+                pool_description,
+                pool.len() / config.lap_count.max(1)
+            ),
+        ));
+        return diagnostics;
+    }
+    if let Some(max_shared) = config.max_shared_cars {
+        let required = shared_cap_min_cars(config.lap_count, config.player_count, max_shared);
+        if required > pool.len() {
+            diagnostics.push(Diagnostic::error(
+                "shared_cars_infeasible",
+                format!(
+                    "Subsets sharing at most {} cars need {} cars but {}; allow more shared cars",
+                    max_shared, required, pool_description
+                ),
+            ));
+            return diagnostics;
+        }
+    }
+
+    let disjoint_runs = config.disjoint_runs();
+    let (min, max) = get_target_range_for_players(&pool, config.lap_count, disjoint_runs);
+    if accepted_max < u64::from(min) || accepted_min > u64::from(max) {
+        diagnostics.push(Diagnostic::error(
+            "target_out_of_range",
+            format!(
+                "Target {} is outside the reachable range {} – {} for {}",
+                format_ms_to_minsecms(config.target),
+                format_ms_to_minsecms(min),
+                format_ms_to_minsecms(max),
+                if disjoint_runs > 1 {
+                    format!(
+                        "{} disjoint runs of {} laps",
+                        disjoint_runs, config.lap_count
+                    )
+                } else {
+                    format!("{} laps", config.lap_count)
+                }
+            ),
+        ));
+    } else if config.lap_count > 0 {
+        // Every sum is a multiple of the lap times' common divisor, e.g. a
+        // whole second for data recorded without milliseconds.
+        let granularity = pool
+            .iter()
+            .fold(0_u64, |divisor, car| gcd(divisor, u64::from(car.lap_time)));
+        if granularity > 1 {
+            let first_reachable = accepted_min.div_ceil(granularity) * granularity;
+            let step = format_ms_to_minsecms(granularity as u32);
+            if first_reachable > accepted_max {
+                diagnostics.push(Diagnostic::error(
+                    "tolerance_below_granularity",
+                    format!(
+                        "Lap times are multiples of {} but the tolerance window around the target holds none; raise the tolerance",
+                        step
+                    ),
+                ));
+            } else if accepted_max - accepted_min < granularity {
+                diagnostics.push(Diagnostic::warning(
+                    "tolerance_below_granularity",
+                    format!(
+                        "Lap times are multiples of {}, so only an exact total of {} is accepted",
+                        step,
+                        format_ms_to_minsecms(first_reachable as u32)
+                    ),
+                ));
+            }
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| !diagnostic.is_error());
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolverStrategy;
+
+    fn cars(lap_times: &[u32]) -> Vec<Car> {
+        lap_times
+            .iter()
+            .enumerate()
+            .map(|(index, &lap_time)| Car::new(format!("car-{index}"), lap_time))
+            .collect()
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code.as_str())
+            .collect()
+    }
+
+    fn config(target: u32, lap_count: usize, player_count: usize) -> SubsetCalculationConfig {
+        SubsetCalculationConfig::builder(target, lap_count, player_count)
+            .tolerance_percent(1.0)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn feasible_requests_have_no_diagnostics() {
+        let pool = cars(&[100_001, 100_502, 101_003, 99_004]);
+        assert!(validate_request(&pool, &config(200_000, 2, 2)).is_empty());
+    }
+
+    #[test]
+    fn impossible_counts_and_targets_are_errors() {
+        let pool = cars(&[100_000, 101_000, 102_000]);
+        assert_eq!(
+            codes(&validate_request(&pool, &config(400_000, 4, 1))),
+            ["impossible_count"]
+        );
+        assert_eq!(
+            codes(&validate_request(&pool, &config(500_000, 2, 1))),
+            ["target_out_of_range"]
+        );
+        // One car laps in about 102 s, but two disjoint runs average at most
+        // 101.5 s, and only the legacy strategy keeps runs disjoint.
+        let mut legacy = config(102_000, 1, 2);
+        legacy.tolerance_percent = 0.1;
+        let pool = cars(&[100_001, 101_003, 102_007]);
+        assert!(validate_request(&pool, &legacy).is_empty());
+        legacy.strategy = SolverStrategy::Legacy;
+        assert_eq!(
+            codes(&validate_request(&pool, &legacy)),
+            ["target_out_of_range"]
+        );
+
+        let mut filtered = config(100_000, 1, 1);
+        filtered.min_lap_time = Some(150_000);
+        assert_eq!(codes(&validate_request(&pool, &filtered)), ["empty_pool"]);
+
+        let mut capped = config(200_000, 2, 3);
+        capped.max_shared_cars = Some(0);
+        assert_eq!(
+            codes(&validate_request(&pool, &capped)),
+            ["shared_cars_infeasible"]
+        );
+        capped.max_shared_cars = Some(1);
+        assert!(validate_request(&pool, &capped).is_empty());
+
+        let mut invalid = config(100_000, 1, 1);
+        invalid.tolerance_percent = -1.0;
+        assert_eq!(
+            codes(&validate_request(&pool, &invalid)),
+            ["invalid_tolerance"]
+        );
+    }
+
+    #[test]
+    fn tolerance_is_checked_against_lap_time_granularity() {
+        // Whole-second data: totals come in steps of 1000 ms.
+        let pool = cars(&[100_000, 101_000, 102_000, 103_000]);
+        let mut request = config(201_500, 2, 1);
+        request.tolerance_percent = 0.1;
+        let diagnostics = validate_request(&pool, &request);
+        assert_eq!(codes(&diagnostics), ["tolerance_below_granularity"]);
+        assert!(diagnostics[0].is_error());
+
+        request.target = 202_000;
+        let diagnostics = validate_request(&pool, &request);
+        assert_eq!(codes(&diagnostics), ["tolerance_below_granularity"]);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn player_pools_are_checked_per_player() {
+        let pool = cars(&[100_001, 101_003, 102_007, 150_011, 151_013]);
+        let mut request = config(201_000, 2, 3);
+        request.player_pools = vec![
+            vec!["car-0".into(), "car-1".into(), "car-2".into()],
+            vec!["car-3".into(), "car-4".into()],
+            vec!["car-0".into(), "car-9".into()],
+        ];
+        let diagnostics = validate_request(&pool, &request);
+        assert_eq!(
+            codes(&diagnostics),
+            ["player_target_out_of_range", "player_pool_too_small"]
+        );
+        assert!(diagnostics[0].message.starts_with("Player 2"));
+        assert!(diagnostics[1].message.starts_with("Player 3"));
+
+        request.player_pools.pop();
+        assert_eq!(
+            codes(&validate_request(&pool, &request)),
+            ["player_pool_count_mismatch"]
+        );
+    }
+
+    #[test]
+    fn errors_precede_warnings() {
+        let pool = cars(&[100_000, 101_000, 102_000]);
+        let mut request = config(101_000, 1, 1);
+        request.tolerance_percent = 0.1;
+        request.full_coverage = true;
+        let diagnostics = validate_request(&pool, &request);
+        assert_eq!(
+            codes(&diagnostics),
+            [
+                "insufficient_selections_for_coverage",
+                "tolerance_below_granularity"
+            ]
+        );
+        assert!(diagnostics[0].is_error() && !diagnostics[1].is_error());
+    }
+}
@@ -0,0 +1,398 @@
+//! Fast success estimates for calculation targets.
+//!
+//! A [`DifficultyProfile`] tabulates how the totals of all subsets of a
+//! dataset are distributed, and turns the number of subsets near a target
+//! into a chance of success for each player's run. It is a heuristic: it
+//! needs no solver work, so it can rate every slider position, but it
+//! ignores the solver's own search limits and assumes the cars earlier runs
+//! used were typical of the dataset.
+
+use crate::{
+    accepted_sum_interval, filter_by_lap_time, get_target_range_for_players, Car,
+    SubsetCalculationConfig,
+};
+use serde::{Deserialize, Serialize};
+
+/// Success probability below which a target is not worth calculating.
+pub const HOPELESS_PROBABILITY: f64 = 0.01;
+/// Success probability from which a target counts as easy.
+pub const LIKELY_PROBABILITY: f64 = 0.9;
+
+/// Coarse rating of a [`DifficultyEstimate`] for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DifficultyLevel {
+    Easy,
+    Moderate,
+    Hard,
+    Hopeless,
+}
+
+impl DifficultyLevel {
+    pub fn from_probability(probability: f64) -> Self {
+        if probability >= LIKELY_PROBABILITY {
+            Self::Easy
+        } else if probability >= 0.5 {
+            Self::Moderate
+        } else if probability >= HOPELESS_PROBABILITY {
+            Self::Hard
+        } else {
+            Self::Hopeless
+        }
+    }
+
+    /// Lowercase name, used as a CSS class.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Moderate => "moderate",
+            Self::Hard => "hard",
+            Self::Hopeless => "hopeless",
+        }
+    }
+}
+
+/// Number of buckets the distribution of subset totals is tracked in.
+const BUCKETS: usize = 1024;
+
+/// Distribution of subset totals for one lap and player count, from which
+/// the success probability of any target can be read off cheaply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifficultyProfile {
+    /// Range every player's subset can reach, from
+    /// [`get_target_range_for_players`].
+    pub reachable: (u32, u32),
+    /// Common divisor of the lap times; only its multiples are reachable.
+    pub granularity: u64,
+    /// Natural log of the number of subsets each player's run chooses from.
+    log_subsets: Vec<f64>,
+    /// Share of subsets whose total falls in each bucket. The subsets
+    /// tracked are the complements of the requested ones when `complement`
+    /// is set, which keeps the table small for large lap counts.
+    shares: Vec<f64>,
+    offset: f64,
+    bucket_width: f64,
+    complement: Option<u64>,
+}
+
+impl DifficultyProfile {
+    /// Profiles `player_count` runs that avoid cars earlier runs used while
+    /// enough unused ones remain. For strategies that reuse cars, pass
+    /// [`SolverStrategy::disjoint_runs`](crate::SolverStrategy::disjoint_runs)
+    /// instead of the real player count, or use [`Self::for_config`].
+    /// Disabled cars are left out.
+    pub fn new(cars: &[Car], lap_count: usize, player_count: usize) -> Self {
+        if cars.iter().any(|car| !car.enabled) {
+            let enabled = cars
+                .iter()
+                .filter(|car| car.enabled)
+                .cloned()
+                .collect::<Vec<_>>();
+            return Self::new(&enabled, lap_count, player_count);
+        }
+        let mut profile = Self {
+            reachable: get_target_range_for_players(cars, lap_count, player_count),
+            granularity: cars
+                .iter()
+                .fold(0_u64, |divisor, car| gcd(divisor, u64::from(car.lap_time))),
+            log_subsets: Vec::new(),
+            shares: Vec::new(),
+            offset: 0.0,
+            bucket_width: 1.0,
+            complement: None,
+        };
+        if cars.is_empty() || lap_count == 0 || lap_count > cars.len() || player_count == 0 {
+            return profile;
+        }
+
+        // Runs draw from unused cars while enough remain, then from all.
+        // Fewer cars shrink the count of subsets without moving their totals.
+        let disjoint_runs = cars.len() / lap_count;
+        profile.log_subsets = (0..player_count)
+            .map(|run| {
+                let available = if run < disjoint_runs {
+                    cars.len() - run * lap_count
+                } else {
+                    cars.len()
+                };
+                log_binomial(available, lap_count)
+            })
+            .collect();
+
+        let size = if lap_count * 2 > cars.len() {
+            profile.complement = Some(cars.iter().map(|car| u64::from(car.lap_time)).sum());
+            cars.len() - lap_count
+        } else {
+            lap_count
+        };
+        let fastest = cars.iter().map(|car| car.lap_time).min().unwrap_or(0);
+        let slowest = cars.iter().map(|car| car.lap_time).max().unwrap_or(0);
+        profile.offset = f64::from(fastest) * size as f64;
+        profile.bucket_width =
+            (f64::from(slowest - fastest) * size as f64 / (BUCKETS - 1) as f64).max(1.0);
+
+        // shares[j] is the distribution of totals of a uniformly random
+        // j-subset of the cars seen so far, relative to j fastest laps. Each
+        // new car joins such a subset with probability j / seen.
+        let mut shares = vec![vec![0.0; BUCKETS + 1]; size + 1];
+        shares[0][0] = 1.0;
+        for (seen, car) in cars.iter().enumerate().map(|(index, car)| (index + 1, car)) {
+            let position = f64::from(car.lap_time - fastest) / profile.bucket_width;
+            let (shift, fraction) = (position.floor() as usize, position.fract());
+            for chosen in (1..=size.min(seen)).rev() {
+                let keep = (seen - chosen) as f64 / seen as f64;
+                let join = chosen as f64 / seen as f64;
+                let (smaller, rest) = shares.split_at_mut(chosen);
+                let (previous, current) = (&smaller[chosen - 1], &mut rest[0]);
+                for bucket in (0..=BUCKETS).rev() {
+                    let mut joined = 0.0;
+                    if bucket >= shift {
+                        joined += previous[bucket - shift] * (1.0 - fraction);
+                    }
+                    if bucket > shift {
+                        joined += previous[bucket - shift - 1] * fraction;
+                    }
+                    current[bucket] = current[bucket] * keep + joined * join;
+                }
+            }
+        }
+        profile.shares = shares.swap_remove(size);
+        profile
+    }
+
+    /// Profiles the pool and runs `config` describes; its target and
+    /// tolerance are left to the queries.
+    pub fn for_config(cars: &[Car], config: &SubsetCalculationConfig) -> Self {
+        let pool = filter_by_lap_time(cars, config.min_lap_time, config.max_lap_time)
+            .into_iter()
+            .map(|index| cars[index].clone())
+            .collect::<Vec<_>>();
+        Self::new(&pool, config.lap_count, config.disjoint_runs())
+    }
+
+    /// Estimated probability that every run finds a subset within
+    /// `tolerance_percent` of `target`.
+    pub fn success_probability(&self, target: u32, tolerance_percent: f64) -> f64 {
+        let (lower, upper) = accepted_sum_interval(target, tolerance_percent);
+        let lower = lower.max(u64::from(self.reachable.0));
+        let upper = upper.min(u64::from(self.reachable.1));
+        if lower > upper || self.shares.is_empty() {
+            return 0.0;
+        }
+        // A single run can always reach the extremes: the fastest and the
+        // slowest laps.
+        if self.log_subsets.len() == 1
+            && (lower == u64::from(self.reachable.0) || upper == u64::from(self.reachable.1))
+        {
+            return 1.0;
+        }
+        let granularity = self.granularity.max(1);
+        let (first, last) = (lower.div_ceil(granularity), upper / granularity);
+        if first > last {
+            return 0.0;
+        }
+
+        // Each reachable total stands for the granularity-wide slice around it.
+        let half_step = granularity as f64 / 2.0;
+        let (mut low, mut high) = (
+            (first * granularity) as f64 - half_step,
+            (last * granularity) as f64 + half_step,
+        );
+        if let Some(total) = self.complement {
+            (low, high) = (total as f64 - high, total as f64 - low);
+        }
+        let (low, high) = (
+            (low - self.offset) / self.bucket_width,
+            (high - self.offset) / self.bucket_width,
+        );
+        // Bucket b holds the totals around b bucket widths above the offset.
+        let first_bucket = (low + 0.5).floor().max(0.0) as usize;
+        let last_bucket = (high + 0.5).floor().clamp(0.0, BUCKETS as f64) as usize;
+        let share = (first_bucket..=last_bucket)
+            .map(|bucket| {
+                let center = bucket as f64;
+                let overlap = (high.min(center + 0.5) - low.max(center - 0.5)).max(0.0);
+                self.shares[bucket] * overlap
+            })
+            .sum::<f64>();
+        if share <= 0.0 {
+            return 0.0;
+        }
+
+        // Matches per run are roughly Poisson distributed, so a run fails
+        // with probability exp(-expected).
+        self.log_subsets
+            .iter()
+            .map(|log_subsets| 1.0 - (-(log_subsets + share.ln()).exp()).exp())
+            .product()
+    }
+
+    pub fn level(&self, target: u32, tolerance_percent: f64) -> DifficultyLevel {
+        DifficultyLevel::from_probability(self.success_probability(target, tolerance_percent))
+    }
+
+    /// Smallest tolerance, in hundredths of a percent, at which `target`
+    /// succeeds with at least `probability`, or `None` when not even
+    /// `max_tolerance_percent` does.
+    pub fn tolerance_for(
+        &self,
+        target: u32,
+        probability: f64,
+        max_tolerance_percent: f64,
+    ) -> Option<f64> {
+        let succeeds = |hundredths: u32| {
+            self.success_probability(target, f64::from(hundredths) / 100.0) >= probability
+        };
+        let max = (max_tolerance_percent * 100.0).floor() as u32;
+        if !succeeds(max) {
+            return None;
+        }
+        // The probability grows with the tolerance, so `high` always succeeds.
+        let (mut low, mut high) = (0, max);
+        while low < high {
+            let middle = (low + high) / 2;
+            if succeeds(middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        Some(f64::from(high) / 100.0)
+    }
+}
+
+/// A [`DifficultyProfile`] evaluated at one target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifficultyEstimate {
+    pub target: u32,
+    pub profile: DifficultyProfile,
+}
+
+impl DifficultyEstimate {
+    /// Estimated probability that every run finds a subset within
+    /// `tolerance_percent` of the target.
+    pub fn success_probability(&self, tolerance_percent: f64) -> f64 {
+        self.profile
+            .success_probability(self.target, tolerance_percent)
+    }
+
+    pub fn level(&self, tolerance_percent: f64) -> DifficultyLevel {
+        self.profile.level(self.target, tolerance_percent)
+    }
+}
+
+/// Estimates how likely `player_count` runs of `lap_count` cars are to reach
+/// `target`. Build a [`DifficultyProfile`] instead to rate many targets.
+pub fn estimate_difficulty(
+    cars: &[Car],
+    target: u32,
+    lap_count: usize,
+    player_count: usize,
+) -> DifficultyEstimate {
+    DifficultyEstimate {
+        target,
+        profile: DifficultyProfile::new(cars, lap_count, player_count),
+    }
+}
+
+fn log_binomial(n: usize, k: usize) -> f64 {
+    let k = k.min(n - k);
+    (0..k)
+        .map(|i| ((n - i) as f64).ln() - ((i + 1) as f64).ln())
+        .sum()
+}
+
+pub(crate) fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cars(lap_times: impl IntoIterator<Item = u32>) -> Vec<Car> {
+        lap_times
+            .into_iter()
+            .enumerate()
+            .map(|(index, lap_time)| Car::new(format!("car-{index}"), lap_time))
+            .collect()
+    }
+
+    #[test]
+    fn central_targets_are_easy_and_unreachable_ones_hopeless() {
+        let pool = cars((0..200).map(|i| 90_000 + i * 157));
+        let central = estimate_difficulty(&pool, 3 * 105_600, 3, 4);
+        assert_eq!(central.level(0.5), DifficultyLevel::Easy);
+
+        let (_, max) = central.profile.reachable;
+        let beyond = estimate_difficulty(&pool, max + 5_000, 3, 4);
+        assert_eq!(beyond.success_probability(0.5), 0.0);
+        assert_eq!(beyond.level(0.5), DifficultyLevel::Hopeless);
+    }
+
+    #[test]
+    fn probability_grows_with_tolerance_and_shrinks_with_players() {
+        let pool = cars((0..12).map(|i| 100_000 + i * 1_013));
+        let target = 2 * 108_000;
+        let one = estimate_difficulty(&pool, target, 2, 1);
+        let many = estimate_difficulty(&pool, target, 2, 5);
+
+        let mut previous = 0.0;
+        for tolerance in [0.0, 0.01, 0.1, 1.0] {
+            let probability = one.success_probability(tolerance);
+            assert!(probability >= previous);
+            assert!(many.success_probability(tolerance) <= probability);
+            previous = probability;
+        }
+        assert!(one.success_probability(0.0) < HOPELESS_PROBABILITY);
+    }
+
+    #[test]
+    fn reusing_strategies_reach_the_extremes() {
+        let pool = cars((0..30).map(|i| 90_000 + i * 1_009));
+        let config = SubsetCalculationConfig::builder(0, 3, 8).build().unwrap();
+        let profile = DifficultyProfile::for_config(&pool, &config);
+        assert_eq!(
+            profile.reachable,
+            (3 * 90_000 + 3 * 1_009, 3 * 90_000 + 84 * 1_009)
+        );
+        assert_eq!(profile.success_probability(profile.reachable.1, 0.0), 1.0);
+
+        let legacy = DifficultyProfile::for_config(
+            &pool,
+            &SubsetCalculationConfig {
+                strategy: crate::SolverStrategy::Legacy,
+                ..config
+            },
+        );
+        assert!(legacy.success_probability(legacy.reachable.1, 0.0) < HOPELESS_PROBABILITY);
+    }
+
+    #[test]
+    fn windows_between_reachable_sums_are_hopeless() {
+        // Whole-second data cannot total 201.5 s.
+        let pool = cars((0..20).map(|i| 100_000 + i * 1_000));
+        let estimate = estimate_difficulty(&pool, 201_500, 2, 1);
+        assert_eq!(estimate.profile.granularity, 1_000);
+        assert_eq!(estimate.success_probability(0.1), 0.0);
+        assert!(estimate.success_probability(1.0) > 0.9);
+    }
+
+    #[test]
+    fn tolerances_are_found_for_a_success_probability() {
+        let pool = cars((0..20).map(|i| 100_000 + i * 1_000));
+        let profile = DifficultyProfile::new(&pool, 2, 1);
+        let tolerance = profile
+            .tolerance_for(201_500, LIKELY_PROBABILITY, 5.0)
+            .unwrap();
+        assert!(tolerance > 0.1 && tolerance <= 1.0);
+        assert!(profile.success_probability(201_500, tolerance) >= LIKELY_PROBABILITY);
+        assert!(profile.success_probability(201_500, tolerance - 0.01) < LIKELY_PROBABILITY);
+
+        let (_, max) = profile.reachable;
+        assert_eq!(profile.tolerance_for(max + 50_000, 0.5, 5.0), None);
+    }
+}
@@ -0,0 +1,19 @@
+//! Saves generated text as a file through a temporary object URL.
+
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Offers `contents` for download as `file_name`.
+pub fn download(file_name: &str, mime_type: &str, contents: &str) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no global `window` exists")?;
+    let document = window.document().ok_or("no document")?;
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let parts = js_sys::Array::of1(&JsValue::from_str(contents));
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let link: web_sys::HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    web_sys::Url::revoke_object_url(&url)
+}
@@ -0,0 +1,174 @@
+//! Quality evaluation of the solver over many synthetic calculations.
+//!
+//! An [`EvaluationPlan`] generates datasets from a [`PoolSpec`], draws
+//! targets from each dataset's reachable range, and runs the configured
+//! solver on every pair. The resulting [`EvaluationReport`] gives success
+//! rate, accuracy, and similarity figures that can be compared between
+//! solver changes.
+
+use crate::generators::PoolSpec;
+use crate::{
+    accuracy_percent, calculate_subset_sum, compute_jaccard_similarity,
+    get_target_range_for_subset, perform_multiple_runs, SubsetCalculationConfig,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What to evaluate and on how much data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluationPlan {
+    pub pool: PoolSpec,
+    pub datasets: usize,
+    pub targets_per_dataset: usize,
+    /// Portion of each dataset's reachable single-subset range that targets
+    /// are drawn from, as fractions from its minimum to its maximum.
+    pub target_range: (f64, f64),
+    /// Solver settings; `target` is replaced for every calculation.
+    pub config: SubsetCalculationConfig,
+    /// Seeds datasets and targets. The solver's own choices stay random.
+    pub seed: u64,
+}
+
+impl EvaluationPlan {
+    pub fn new(pool: PoolSpec, config: SubsetCalculationConfig) -> Self {
+        Self {
+            pool,
+            datasets: 10,
+            targets_per_dataset: 10,
+            target_range: (0.2, 0.8),
+            config,
+            seed: 0,
+        }
+    }
+}
+
+/// Aggregate outcome of an evaluation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvaluationReport {
+    pub calculations: usize,
+    pub successes: usize,
+    /// Mean sum of a returned subset as a percentage of its target.
+    pub mean_accuracy_percent: Option<f64>,
+    /// Mean absolute distance of a returned subset from 100% of its target.
+    pub mean_deviation_percent: Option<f64>,
+    /// Mean pairwise Jaccard similarity of successful calculations.
+    pub mean_similarity: Option<f64>,
+    /// Failed calculations by [`SubsetError::code`](crate::SubsetError::code).
+    pub failures: BTreeMap<String, usize>,
+}
+
+impl EvaluationReport {
+    pub fn success_rate(&self) -> f64 {
+        if self.calculations == 0 {
+            0.0
+        } else {
+            self.successes as f64 / self.calculations as f64
+        }
+    }
+}
+
+/// Runs every calculation described by `plan`.
+pub fn evaluate(plan: &EvaluationPlan) -> EvaluationReport {
+    let mut rng = StdRng::seed_from_u64(plan.seed);
+    let mut report = EvaluationReport::default();
+    let mut accuracies = Vec::new();
+    let mut similarities = Vec::new();
+
+    for dataset in 0..plan.datasets {
+        let cars = plan
+            .pool
+            .generate_seeded(plan.seed.wrapping_add(dataset as u64));
+        let (min, max) = get_target_range_for_subset(&cars, plan.config.lap_count);
+        let span = f64::from(max - min);
+        let (low, high) = plan.target_range;
+        let (low, high) = (low.clamp(0.0, 1.0), high.clamp(0.0, 1.0));
+        let (low, high) = (low.min(high), low.max(high));
+
+        for _ in 0..plan.targets_per_dataset {
+            let fraction = if high > low {
+                rng.random_range(low..=high)
+            } else {
+                low
+            };
+            let target = min + (span * fraction).round() as u32;
+            let config = SubsetCalculationConfig {
+                target,
+                ..plan.config.clone()
+            };
+
+            report.calculations += 1;
+            match perform_multiple_runs(&cars, &config) {
+                Ok(sets) => {
+                    report.successes += 1;
+                    accuracies.extend(
+                        sets.iter()
+                            .map(|set| accuracy_percent(calculate_subset_sum(&cars, set), target)),
+                    );
+                    if let Ok(similarity) = compute_jaccard_similarity(&sets) {
+                        similarities.push(similarity);
+                    }
+                }
+                Err(error) => {
+                    *report.failures.entry(error.code().to_string()).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    report.mean_accuracy_percent = mean(accuracies.iter().copied());
+    report.mean_deviation_percent =
+        mean(accuracies.iter().map(|accuracy| (accuracy - 100.0).abs()));
+    report.mean_similarity = mean(similarities.into_iter());
+    report
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0_usize), |(sum, count), value| {
+        (sum + value, count + 1)
+    });
+    (count > 0).then(|| sum / count as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluation_reports_success_accuracy_and_similarity() {
+        let config = SubsetCalculationConfig::builder(0, 3, 2)
+            .tolerance_percent(1.0)
+            .build()
+            .unwrap();
+        let plan = EvaluationPlan {
+            datasets: 3,
+            targets_per_dataset: 4,
+            ..EvaluationPlan::new(PoolSpec::normal(40, 100_000, 3_000), config)
+        };
+
+        let report = evaluate(&plan);
+        assert_eq!(report.calculations, 12);
+        assert_eq!(
+            report.successes + report.failures.values().sum::<usize>(),
+            12
+        );
+        assert!(report.success_rate() > 0.5, "{report:?}");
+        assert!(report.mean_deviation_percent.unwrap() <= 1.0);
+        assert!((0.0..=1.0).contains(&report.mean_similarity.unwrap()));
+    }
+
+    #[test]
+    fn impossible_plans_record_failure_codes() {
+        let config = SubsetCalculationConfig::builder(0, 5, 1).build().unwrap();
+        let plan = EvaluationPlan {
+            datasets: 2,
+            targets_per_dataset: 1,
+            ..EvaluationPlan::new(PoolSpec::normal(3, 100_000, 3_000), config)
+        };
+
+        let report = evaluate(&plan);
+        assert_eq!(report.success_rate(), 0.0);
+        assert_eq!(report.failures.get("impossible_count"), Some(&2));
+        assert_eq!(report.mean_accuracy_percent, None);
+    }
+}
@@ -0,0 +1,248 @@
+//! Structured progress reporting for the subset solver.
+//!
+//! The solver narrates its decisions as [`SolverEvent`]s delivered to an
+//! [`EventSink`], so frontends can render a run's progress instead of
+//! scraping log output. [`LogSink`] keeps the historical log narration.
+
+use crate::{accuracy_percent, CarIndex};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+/// A decision or milestone reported by the solver.
+///
+/// Car indexes refer to the slice passed to the public entry point, even when
+/// the solver internally works on a filtered pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SolverEvent {
+    /// A run (one player's subset) is starting.
+    RunStarted {
+        run: usize,
+        player_count: usize,
+        available: usize,
+    },
+    /// The legacy heuristic added a car to the subset it is building.
+    SelectionMade {
+        car: CarIndex,
+        lap_time: u32,
+        current_sum: u32,
+        target: u32,
+        selected: usize,
+        lap_count: usize,
+    },
+    /// No car fit the valid range, so the car closest to the needed average
+    /// was taken instead.
+    FallbackUsed {
+        car: CarIndex,
+        remaining_needed: usize,
+    },
+    /// A finished attempt missed the tolerance and the run is retried.
+    AttemptRejected {
+        run: usize,
+        accuracy: f64,
+        tolerance_percent: f64,
+    },
+    /// A run produced its subset.
+    RunCompleted {
+        run: usize,
+        player_count: usize,
+        subset: Vec<CarIndex>,
+        sum: u32,
+        accuracy: f64,
+        /// `sum` equals the target. Further attempts of the run are skipped
+        /// once one hits it.
+        #[serde(default)]
+        exact: bool,
+        /// Solver attempts made for this run, including rejected ones.
+        attempts: usize,
+        /// Wall-clock time spent on this run.
+        elapsed_ms: f64,
+    },
+    /// A run used up its [`run_timeout_ms`] budget of `budget_ms` without a
+    /// subset. Its subset is left empty and the next run starts.
+    ///
+    /// [`run_timeout_ms`]: crate::SubsetCalculationConfig::run_timeout_ms
+    RunSkipped {
+        run: usize,
+        player_count: usize,
+        budget_ms: f64,
+        /// Solver attempts made for this run.
+        attempts: usize,
+        elapsed_ms: f64,
+    },
+    /// A finished attempt shared more than `max_shared` cars with the subset
+    /// of `earlier_run`, so the run is retried.
+    SharedCarsRejected {
+        run: usize,
+        earlier_run: usize,
+        shared: usize,
+        max_shared: usize,
+    },
+    /// The minimax objective traded cars between the finished subsets. The
+    /// deviations are those of the subset farthest from the target, as a
+    /// percentage of it.
+    Rebalanced {
+        moves: usize,
+        worst_deviation_before: f64,
+        worst_deviation_after: f64,
+    },
+    /// The swap pass exchanged cars between the finished subsets. Deviations
+    /// are the mean distance of the subsets from the target, as a percentage
+    /// of it, and similarities their mean pairwise Jaccard similarity.
+    SwapPassCompleted {
+        moves: usize,
+        deviation_before: f64,
+        deviation_after: f64,
+        similarity_before: f64,
+        similarity_after: f64,
+    },
+    /// Every subset was within `achieved_percent` of the target, so the
+    /// calculation is repeated at `tolerance_percent`.
+    ToleranceTightened {
+        achieved_percent: f64,
+        tolerance_percent: f64,
+    },
+}
+
+impl SolverEvent {
+    /// Rewrites every car index carried by the event.
+    pub(crate) fn map_cars(mut self, map: impl Fn(CarIndex) -> CarIndex) -> Self {
+        match &mut self {
+            Self::SelectionMade { car, .. } | Self::FallbackUsed { car, .. } => *car = map(*car),
+            Self::RunCompleted { subset, .. } => {
+                for car in subset {
+                    *car = map(*car);
+                }
+            }
+            Self::RunStarted { .. }
+            | Self::RunSkipped { .. }
+            | Self::AttemptRejected { .. }
+            | Self::SharedCarsRejected { .. }
+            | Self::Rebalanced { .. }
+            | Self::SwapPassCompleted { .. }
+            | Self::ToleranceTightened { .. } => {}
+        }
+        self
+    }
+}
+
+/// Receiver for solver events.
+pub trait EventSink {
+    fn emit(&mut self, event: SolverEvent);
+}
+
+/// Collects events in order, e.g. to replay them after a search finishes.
+impl EventSink for Vec<SolverEvent> {
+    fn emit(&mut self, event: SolverEvent) {
+        self.push(event);
+    }
+}
+
+impl<T: EventSink + ?Sized> EventSink for &mut T {
+    fn emit(&mut self, event: SolverEvent) {
+        (**self).emit(event);
+    }
+}
+
+/// Delivers every event to both sinks, e.g. to log while collecting metrics.
+impl<A: EventSink, B: EventSink> EventSink for (A, B) {
+    fn emit(&mut self, event: SolverEvent) {
+        self.0.emit(event.clone());
+        self.1.emit(event);
+    }
+}
+
+/// Writes events through the `log` crate, as the solver always has.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogSink;
+
+impl EventSink for LogSink {
+    fn emit(&mut self, event: SolverEvent) {
+        match event {
+            SolverEvent::RunStarted {
+                run,
+                player_count,
+                available,
+            } => {
+                info!("\n=== Run {}/{} ===", run, player_count);
+                info!("Available pool size: {} numbers", available);
+            }
+            SolverEvent::SelectionMade {
+                lap_time,
+                current_sum,
+                target,
+                ..
+            } => debug!(
+                "Added: {}. New sum: {}/{} ({}%)",
+                lap_time,
+                current_sum,
+                target,
+                accuracy_percent(current_sum, target)
+            ),
+            SolverEvent::FallbackUsed { car, .. } => {
+                debug!("No valid candidates in range! Using fallback car {}", car)
+            }
+            SolverEvent::AttemptRejected {
+                accuracy,
+                tolerance_percent,
+                ..
+            } => warn!(
+                "Current run's sum is more than {}% off ({}%), retrying...",
+                tolerance_percent, accuracy
+            ),
+            SolverEvent::RunCompleted {
+                run,
+                player_count,
+                sum,
+                accuracy,
+                ..
+            } => info!(
+                "Run {}/{} complete: sum = {} ({}% of target)",
+                run, player_count, sum, accuracy
+            ),
+            SolverEvent::RunSkipped {
+                run,
+                player_count,
+                budget_ms,
+                ..
+            } => warn!(
+                "Run {}/{} skipped: no subset within its {} ms budget",
+                run, player_count, budget_ms
+            ),
+            SolverEvent::SharedCarsRejected {
+                run,
+                earlier_run,
+                shared,
+                max_shared,
+            } => warn!(
+                "Run {} shares {} cars with run {} (at most {} allowed), retrying...",
+                run, shared, earlier_run, max_shared
+            ),
+            SolverEvent::Rebalanced {
+                moves,
+                worst_deviation_before,
+                worst_deviation_after,
+            } => info!(
+                "Rebalanced with {} moves: worst deviation {}% -> {}%",
+                moves, worst_deviation_before, worst_deviation_after
+            ),
+            SolverEvent::SwapPassCompleted {
+                moves,
+                deviation_before,
+                deviation_after,
+                similarity_before,
+                similarity_after,
+            } => info!(
+                "Swap pass made {} moves: mean deviation {}% -> {}%, similarity {} -> {}",
+                moves, deviation_before, deviation_after, similarity_before, similarity_after
+            ),
+            SolverEvent::ToleranceTightened {
+                achieved_percent,
+                tolerance_percent,
+            } => info!(
+                "All runs within {}% of target, retrying at {}%",
+                achieved_percent, tolerance_percent
+            ),
+        }
+    }
+}
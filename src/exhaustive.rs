@@ -0,0 +1,292 @@
+//! Exhaustive search for pools small enough to enumerate.
+//!
+//! When a pool has only a few subsets of the requested size, every subset
+//! within tolerance is listed and the players' subsets are chosen among them
+//! to minimize the mean pairwise Jaccard similarity, not just to keep it low.
+//! Ties between equally dissimilar choices are broken at random.
+
+use crate::{calculate_subset_sum_u64, Car, CarIndex, SubsetError};
+use rand::seq::SliceRandom;
+
+/// Number of `k`-car subsets of `n` cars, saturating at `u64::MAX`.
+pub(crate) fn subset_count(n: usize, k: usize) -> u64 {
+    if k > n {
+        return 0;
+    }
+    let k = k.min(n - k);
+    let mut count: u128 = 1;
+    for step in 0..k {
+        // Exact at every step: the product of `step + 1` consecutive
+        // integers divides by `(step + 1)!`.
+        count = count * (n - step) as u128 / (step + 1) as u128;
+        if count > u128::from(u64::MAX) {
+            return u64::MAX;
+        }
+    }
+    count as u64
+}
+
+pub(crate) struct ExhaustiveRequest<'a> {
+    pub lap_count: usize,
+    pub player_count: usize,
+    pub accepted: (u64, u64),
+    /// Cars each player may drive; empty lets every player use every car.
+    pub player_pools: &'a [Vec<bool>],
+    /// Most cars two chosen subsets may share.
+    pub max_shared: Option<usize>,
+    /// Quantile of every car, and the number of quantiles each subset must
+    /// draw from.
+    pub quantiles: Option<(&'a [usize], usize)>,
+}
+
+/// Picks one subset per player from every subset within the accepted range,
+/// minimizing their mean pairwise Jaccard similarity. A subset is reused only
+/// when no cheaper choice exists. If `timed_out` fires, the best choice found
+/// so far is returned.
+pub(crate) fn exhaustive_runs<R: rand::Rng, F: FnMut() -> bool>(
+    cars: &[Car],
+    request: ExhaustiveRequest<'_>,
+    rng: &mut R,
+    timed_out: F,
+) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
+    let mut valid = valid_subsets(cars, &request);
+    if valid.is_empty() {
+        return Err(SubsetError::NoValidSubset);
+    }
+    valid.shuffle(rng);
+
+    let candidates = (0..request.player_count)
+        .map(|player| match request.player_pools.get(player) {
+            Some(allowed) => (0..valid.len())
+                .filter(|&subset| valid[subset].iter().all(|&car| allowed[car]))
+                .collect(),
+            None => (0..valid.len()).collect::<Vec<_>>(),
+        })
+        .collect::<Vec<_>>();
+    if let Some(player) = candidates.iter().position(Vec::is_empty) {
+        return Err(SubsetError::NotEnoughSuccessfulRuns {
+            required: request.player_count,
+            found: player,
+        });
+    }
+
+    let lap_count = request.lap_count;
+    let mut search = Search {
+        valid: &valid,
+        candidates: &candidates,
+        // Subsets have equal sizes, so a shared count of `i` cars is a
+        // Jaccard similarity of `i / (2k - i)`.
+        similarity: (0..=lap_count)
+            .map(|shared| shared as f64 / (2 * lap_count - shared).max(1) as f64)
+            .collect(),
+        max_shared: request.max_shared.unwrap_or(lap_count),
+        interchangeable: request.player_pools.is_empty(),
+        chosen: Vec::with_capacity(request.player_count),
+        best: None,
+        timed_out,
+        nodes: 0,
+        stopped: false,
+    };
+    search.descend(0, 0.0);
+    let Some((_, best)) = search.best else {
+        // Only the shared-car cap can rule out every choice.
+        return Err(SubsetError::NoValidSubset);
+    };
+
+    let mut subsets = best
+        .into_iter()
+        .map(|subset| valid[subset].clone())
+        .collect::<Vec<_>>();
+    if request.player_pools.is_empty() {
+        // The search lists interchangeable subsets in ascending order.
+        subsets.shuffle(rng);
+    }
+    Ok(subsets)
+}
+
+fn valid_subsets(cars: &[Car], request: &ExhaustiveRequest<'_>) -> Vec<Vec<CarIndex>> {
+    let (low, high) = request.accepted;
+    let k = request.lap_count;
+    let mut valid = Vec::new();
+    if k == 0 || k > cars.len() {
+        return valid;
+    }
+    let mut subset: Vec<CarIndex> = (0..k).collect();
+    loop {
+        let sum = calculate_subset_sum_u64(cars, &subset);
+        let spread = request.quantiles.is_none_or(|(bins, bin_count)| {
+            (0..bin_count).all(|bin| subset.iter().any(|&car| bins[car] == bin))
+        });
+        if (low..=high).contains(&sum) && spread {
+            valid.push(subset.clone());
+        }
+
+        // Advance to the next combination in lexicographic order.
+        let Some(position) = (0..k).rev().find(|&i| subset[i] < cars.len() - k + i) else {
+            return valid;
+        };
+        subset[position] += 1;
+        for i in position + 1..k {
+            subset[i] = subset[i - 1] + 1;
+        }
+    }
+}
+
+struct Search<'a, F> {
+    valid: &'a [Vec<CarIndex>],
+    candidates: &'a [Vec<usize>],
+    similarity: Vec<f64>,
+    max_shared: usize,
+    /// Players share one candidate list, so choices are only tried in
+    /// ascending order.
+    interchangeable: bool,
+    chosen: Vec<usize>,
+    best: Option<(f64, Vec<usize>)>,
+    timed_out: F,
+    nodes: usize,
+    stopped: bool,
+}
+
+impl<F: FnMut() -> bool> Search<'_, F> {
+    fn descend(&mut self, first: usize, cost: f64) {
+        let player = self.chosen.len();
+        if player == self.candidates.len() {
+            if self.best.as_ref().is_none_or(|(best, _)| cost < *best) {
+                self.best = Some((cost, self.chosen.clone()));
+            }
+            return;
+        }
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(1024) && (self.timed_out)() {
+            self.stopped = true;
+        }
+
+        let options = self.candidates[player].len();
+        for option in first..options {
+            // Keep the first choice found when time runs out mid-search.
+            if self.stopped && self.best.is_some() {
+                return;
+            }
+            if self.best.as_ref().is_some_and(|(best, _)| *best == 0.0) {
+                return;
+            }
+            let subset = self.candidates[player][option];
+            let mut added = 0.0;
+            let mut allowed = true;
+            for &other in &self.chosen {
+                let shared = shared(&self.valid[subset], &self.valid[other]);
+                allowed &= shared <= self.max_shared;
+                added += self.similarity[shared];
+            }
+            if !allowed {
+                continue;
+            }
+            if self
+                .best
+                .as_ref()
+                .is_some_and(|(best, _)| cost + added >= *best)
+            {
+                continue;
+            }
+            self.chosen.push(subset);
+            let next = if self.interchangeable { option } else { 0 };
+            self.descend(next, cost + added);
+            self.chosen.pop();
+        }
+    }
+}
+
+/// Cars present in both sorted subsets.
+fn shared(left: &[CarIndex], right: &[CarIndex]) -> usize {
+    let (mut i, mut j, mut count) = (0, 0, 0);
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                count += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_jaccard_similarity, perform_multiple_runs, SubsetCalculationConfig};
+
+    fn cars(lap_times: &[u32]) -> Vec<Car> {
+        lap_times
+            .iter()
+            .enumerate()
+            .map(|(index, &lap_time)| Car::new(format!("car-{index}"), lap_time))
+            .collect()
+    }
+
+    #[test]
+    fn subset_counts_are_binomial_coefficients() {
+        assert_eq!(subset_count(5, 2), 10);
+        assert_eq!(subset_count(5, 0), 1);
+        assert_eq!(subset_count(2, 3), 0);
+        assert_eq!(subset_count(60, 30), 118_264_581_564_861_424);
+        assert_eq!(subset_count(200, 100), u64::MAX);
+    }
+
+    #[test]
+    fn exhaustive_mode_finds_the_least_similar_subsets() {
+        // Many pairs reach 200 s within 0.5%, but only one way of splitting
+        // the six cars near 100 s into three pairs shares no car.
+        let pool = cars(&[
+            99_000, 101_000, 99_500, 100_500, 100_000, 100_100, 130_000, 140_000,
+        ]);
+        let config = SubsetCalculationConfig::builder(200_000, 2, 3)
+            .tolerance_percent(0.5)
+            .exhaustive_limit(100)
+            .build()
+            .unwrap();
+        for _ in 0..20 {
+            let subsets = perform_multiple_runs(&pool, &config).unwrap();
+            assert_eq!(subsets.len(), 3);
+            assert_eq!(compute_jaccard_similarity(&subsets).unwrap(), 0.0);
+        }
+
+        // A fourth pair must reuse two cars; at best each is shared with one
+        // other pair, 2 of 6 pairs at a similarity of 1/3.
+        let config = SubsetCalculationConfig {
+            player_count: 4,
+            ..config
+        };
+        let subsets = perform_multiple_runs(&pool, &config).unwrap();
+        let mut used = subsets.concat();
+        used.sort_unstable();
+        used.dedup();
+        assert_eq!(used.len(), 6);
+        let similarity = compute_jaccard_similarity(&subsets).unwrap();
+        assert!((similarity - 1.0 / 9.0).abs() < 1e-9, "{similarity}");
+    }
+
+    #[test]
+    fn player_pools_limit_each_players_subsets() {
+        let pool = cars(&[99_000, 101_000, 99_500, 100_500, 100_000, 100_100]);
+        let allowed = |cars: &[usize]| (0..6).map(|car| cars.contains(&car)).collect::<Vec<_>>();
+        let pools = [allowed(&[0, 1]), allowed(&[0, 1, 2, 3])];
+        let subsets = exhaustive_runs(
+            &pool,
+            ExhaustiveRequest {
+                lap_count: 2,
+                player_count: 2,
+                accepted: (199_000, 201_000),
+                player_pools: &pools,
+                max_shared: None,
+                quantiles: None,
+            },
+            &mut rand::rng(),
+            || false,
+        )
+        .unwrap();
+        assert_eq!(subsets, [vec![0, 1], vec![2, 3]]);
+    }
+}
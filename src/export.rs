@@ -0,0 +1,286 @@
+//! Self-contained JSON archive of a calculation.
+//!
+//! A [`KarmaExport`] records the configuration, a fingerprint of the
+//! dataset, every subset with its cars resolved, the figures shown with
+//! the results, and their [`AnalysisReport`]. Reading one back gives the subsets as car indexes again,
+//! once [`KarmaExport::matches_dataset`] confirms they index the same cars.
+
+use crate::compression::{decode_text, gzip};
+use crate::{
+    accuracy_percent, analyze_multiple_runs, calculate_subset_sum, compute_jaccard_similarity,
+    compute_weighted_jaccard_similarity, dataset_hash, format_ms_to_minsecms, quality_score,
+    tolerance_probability, AnalysisReport, Car, CarIndex, QualityWeights, SubsetCalculationConfig,
+};
+use serde::{Deserialize, Serialize};
+
+/// Version of the [`KarmaExport`] layout, raised on incompatible changes.
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// A car of an exported subset with its index in the dataset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedCar {
+    pub index: CarIndex,
+    #[serde(flatten)]
+    pub car: Car,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedSubset {
+    pub cars: Vec<ExportedCar>,
+    /// Sum of the lap times in milliseconds.
+    pub total: u32,
+    /// Signed distance of `total` from the target, in percent.
+    pub deviation_percent: f64,
+    /// See [`tolerance_probability`].
+    pub tolerance_probability: f64,
+    /// Name of the player the subset went to, when a roster was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KarmaExport {
+    pub format_version: u32,
+    pub config: SubsetCalculationConfig,
+    /// [`dataset_hash`] of the cars, as 16 hexadecimal digits.
+    pub dataset_hash: String,
+    pub car_count: usize,
+    pub subsets: Vec<ExportedSubset>,
+    /// Mean pairwise Jaccard similarity; `None` for fewer than two subsets.
+    pub similarity: Option<f64>,
+    pub weighted_similarity: Option<f64>,
+    /// [`quality_score`] with the default weights.
+    pub quality: f64,
+    /// Missing from exports written before it was added.
+    #[serde(default)]
+    pub analysis: AnalysisReport,
+}
+
+impl KarmaExport {
+    /// Describes `results` of a calculation with `config` on `cars`. Indexes
+    /// outside `cars` are left out.
+    pub fn new(cars: &[Car], config: &SubsetCalculationConfig, results: &[Vec<CarIndex>]) -> Self {
+        let subsets = results
+            .iter()
+            .map(|subset| {
+                let total = calculate_subset_sum(cars, subset);
+                ExportedSubset {
+                    cars: subset
+                        .iter()
+                        .filter_map(|&index| {
+                            Some(ExportedCar {
+                                index,
+                                car: cars.get(index)?.clone(),
+                            })
+                        })
+                        .collect(),
+                    total,
+                    deviation_percent: accuracy_percent(total, config.target) - 100.0,
+                    tolerance_probability: tolerance_probability(
+                        cars,
+                        subset,
+                        config.target,
+                        config.tolerance_percent,
+                    ),
+                    player: None,
+                }
+            })
+            .collect();
+        Self {
+            format_version: EXPORT_FORMAT_VERSION,
+            config: config.clone(),
+            dataset_hash: format!("{:016x}", dataset_hash(cars)),
+            car_count: cars.len(),
+            subsets,
+            similarity: compute_jaccard_similarity(results).ok(),
+            weighted_similarity: compute_weighted_jaccard_similarity(cars, results).ok(),
+            quality: quality_score(
+                cars,
+                results,
+                config.target,
+                config.tolerance_percent,
+                QualityWeights::default(),
+            )
+            .score,
+            analysis: analyze_multiple_runs(cars, results),
+        }
+    }
+
+    /// Names the player of each subset in order; subsets beyond `players`
+    /// or named `""` stay unnamed.
+    pub fn with_players(mut self, players: &[String]) -> Self {
+        for (subset, player) in self.subsets.iter_mut().zip(players) {
+            subset.player = Some(player.clone()).filter(|player| !player.is_empty());
+        }
+        self
+    }
+
+    /// A plain-text summary for pasting into chat: the parameters, the
+    /// dataset, then one line per subset with its total and car ids.
+    pub fn to_text(&self) -> String {
+        let config = &self.config;
+        let mut parameters = format!(
+            "Target {} · {} laps · {} players · tolerance {}% · {:?} solver",
+            format_ms_to_minsecms(config.target),
+            config.lap_count,
+            config.player_count,
+            config.tolerance_percent,
+            config.strategy,
+        );
+        if let Some(seed) = config.seed {
+            parameters.push_str(&format!(" · seed {seed}"));
+        }
+        let mut lines = vec![
+            parameters,
+            format!("Dataset {} · {} cars", self.dataset_hash, self.car_count),
+        ];
+        for (index, subset) in self.subsets.iter().enumerate() {
+            let label = subset
+                .player
+                .clone()
+                .unwrap_or_else(|| format!("Set #{}", index + 1));
+            let cars = subset
+                .cars
+                .iter()
+                .map(|car| car.car.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            lines.push(format!(
+                "{label}: {} ({:+.2}%) {cars}",
+                format_ms_to_minsecms(subset.total),
+                subset.deviation_percent
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// A file name for this export with `extension`, telling apart the
+    /// dataset and parameters of different downloads.
+    pub fn file_name(&self, extension: &str) -> String {
+        format!(
+            "random-karma-{}-{}-laps-{}-players-{}ms.{extension}",
+            &self.dataset_hash[..8],
+            self.config.lap_count,
+            self.config.player_count,
+            self.config.target
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("exports contain only serializable values")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// [`Self::to_json`], gzipped.
+    pub fn to_json_gzip(&self) -> Vec<u8> {
+        gzip(self.to_json().as_bytes())
+    }
+
+    /// Reads an export from JSON bytes, gzipped or not.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        Self::from_json(&decode_text(bytes)?).map_err(|error| format!("Invalid export: {error}"))
+    }
+
+    /// Whether the subsets index `cars`, i.e. they are the exported dataset.
+    pub fn matches_dataset(&self, cars: &[Car]) -> bool {
+        self.car_count == cars.len() && self.dataset_hash == format!("{:016x}", dataset_hash(cars))
+    }
+
+    /// The subsets as car indexes, as returned by the solver.
+    pub fn results(&self) -> Vec<Vec<CarIndex>> {
+        self.subsets
+            .iter()
+            .map(|subset| subset.cars.iter().map(|car| car.index).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cars() -> Vec<Car> {
+        (0..6)
+            .map(|index| Car {
+                uncertainty_ms: (index % 2 == 0).then_some(200),
+                ..Car::new(format!("car-{index}"), 100_000 + index * 1_000)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exports_round_trip_through_json() {
+        let cars = cars();
+        let config = SubsetCalculationConfig::builder(203_000, 2, 2)
+            .tolerance_percent(1.0)
+            .seed(3)
+            .build()
+            .unwrap();
+        let results = vec![vec![0, 3], vec![1, 2]];
+        let export = KarmaExport::new(&cars, &config, &results);
+
+        assert_eq!(export.subsets[0].total, 203_000);
+        assert_eq!(export.subsets[0].deviation_percent, 0.0);
+        assert_eq!(export.subsets[0].cars[1].car.id, "car-3");
+        assert_eq!(export.similarity, Some(0.0));
+        assert_eq!(export.analysis.reuse_counts, [2, 4]);
+
+        let json = export.to_json();
+        assert!(json.contains("\"lap_time\": 103000"), "{json}");
+        let imported = KarmaExport::from_json(&json).unwrap();
+        assert_eq!(imported, export);
+        assert_eq!(imported.results(), results);
+        assert_eq!(imported.config.seed, Some(3));
+        assert_eq!(KarmaExport::from_bytes(&export.to_json_gzip()), Ok(export));
+        assert!(imported.matches_dataset(&cars));
+
+        assert!(!json.contains("\"player\""));
+        let named = imported.clone().with_players(&["Ana".to_string()]);
+        assert_eq!(named.subsets[0].player.as_deref(), Some("Ana"));
+        assert_eq!(named.subsets[1].player, None);
+        assert_eq!(
+            named.to_text().lines().skip(2).collect::<Vec<_>>(),
+            [
+                "Ana: 03:23.000 (+0.00%) car-0, car-3",
+                "Set #2: 03:23.000 (+0.00%) car-1, car-2",
+            ]
+        );
+        assert!(named
+            .to_text()
+            .starts_with("Target 03:23.000 · 2 laps · 2 players"));
+        assert!(named.to_text().contains("seed 3"));
+        assert_eq!(
+            named.file_name("csv"),
+            format!(
+                "random-karma-{}-2-laps-2-players-203000ms.csv",
+                &named.dataset_hash[..8]
+            )
+        );
+        assert_eq!(KarmaExport::from_json(&named.to_json()).unwrap(), named);
+
+        let mut edited = cars.clone();
+        edited[4].lap_time += 1;
+        assert!(!imported.matches_dataset(&edited));
+        assert!(!imported.matches_dataset(&cars[..5]));
+    }
+
+    #[test]
+    fn dataset_hashes_depend_on_every_field_and_the_order() {
+        let cars = cars();
+        let hash = dataset_hash(&cars);
+        assert_eq!(hash, dataset_hash(&cars.clone()));
+
+        let mut reordered = cars.clone();
+        reordered.swap(0, 1);
+        let mut renamed = cars.clone();
+        renamed[0].id.push('x');
+        let mut uncertain = cars.clone();
+        uncertain[1].uncertainty_ms = Some(0);
+        for other in [reordered, renamed, uncertain] {
+            assert_ne!(dataset_hash(&other), hash);
+        }
+    }
+}
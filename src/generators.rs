@@ -0,0 +1,193 @@
+//! Synthetic car pools for tests, benchmarks, and experiments.
+//!
+//! A [`PoolSpec`] describes a pool's size and lap-time distribution, plus the
+//! quirks real exports tend to have: cars sharing a lap time and a few laps
+//! far from the rest. Generation is deterministic for a given seed.
+
+use crate::Car;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+/// Shape of the generated lap times, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LapTimeDistribution {
+    /// Every lap time in `min..=max` is equally likely.
+    Uniform { min: u32, max: u32 },
+    /// A single class of cars around `mean`.
+    Normal { mean: u32, spread: u32 },
+    /// Two classes, e.g. GT3 and GT4, with `fast_share` of the cars in the
+    /// faster one.
+    Bimodal {
+        fast_mean: u32,
+        slow_mean: u32,
+        spread: u32,
+        fast_share: f64,
+    },
+}
+
+/// Description of a synthetic pool.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PoolSpec {
+    pub size: usize,
+    pub distribution: LapTimeDistribution,
+    /// Fraction of cars that copy the lap time of an earlier car.
+    pub duplicate_fraction: f64,
+    /// Fraction of cars placed 4 to 8 spreads away from their class.
+    pub outlier_fraction: f64,
+}
+
+impl PoolSpec {
+    pub fn uniform(size: usize, min: u32, max: u32) -> Self {
+        Self::with_distribution(size, LapTimeDistribution::Uniform { min, max })
+    }
+
+    pub fn normal(size: usize, mean: u32, spread: u32) -> Self {
+        Self::with_distribution(size, LapTimeDistribution::Normal { mean, spread })
+    }
+
+    pub fn bimodal(size: usize, fast_mean: u32, slow_mean: u32, spread: u32) -> Self {
+        Self::with_distribution(
+            size,
+            LapTimeDistribution::Bimodal {
+                fast_mean,
+                slow_mean,
+                spread,
+                fast_share: 0.5,
+            },
+        )
+    }
+
+    fn with_distribution(size: usize, distribution: LapTimeDistribution) -> Self {
+        Self {
+            size,
+            distribution,
+            duplicate_fraction: 0.0,
+            outlier_fraction: 0.0,
+        }
+    }
+
+    pub fn duplicates(mut self, fraction: f64) -> Self {
+        self.duplicate_fraction = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn outliers(mut self, fraction: f64) -> Self {
+        self.outlier_fraction = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Generates the pool from a fixed seed.
+    pub fn generate_seeded(&self, seed: u64) -> Vec<Car> {
+        self.generate(&mut StdRng::seed_from_u64(seed))
+    }
+
+    /// Generates the pool. Car ids are `synthetic-<n>` in generation order.
+    pub fn generate<R: Rng>(&self, rng: &mut R) -> Vec<Car> {
+        let mut lap_times: Vec<u32> = Vec::with_capacity(self.size);
+        for _ in 0..self.size {
+            let lap_time = if !lap_times.is_empty() && rng.random_bool(self.duplicate_fraction) {
+                lap_times[rng.random_range(0..lap_times.len())]
+            } else {
+                self.sample(rng)
+            };
+            lap_times.push(lap_time);
+        }
+
+        lap_times
+            .into_iter()
+            .enumerate()
+            .map(|(index, lap_time)| Car::new(format!("synthetic-{index}"), lap_time))
+            .collect()
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> u32 {
+        let outlier = rng.random_bool(self.outlier_fraction);
+        let (center, spread) = match self.distribution {
+            LapTimeDistribution::Uniform { min, max } => {
+                let (min, max) = (min.min(max), min.max(max));
+                if !outlier {
+                    return rng.random_range(min..=max).max(1);
+                }
+                (min / 2 + max / 2, (max - min) / 2)
+            }
+            LapTimeDistribution::Normal { mean, spread } => (mean, spread),
+            LapTimeDistribution::Bimodal {
+                fast_mean,
+                slow_mean,
+                spread,
+                fast_share,
+            } => {
+                let mean = if rng.random_bool(fast_share.clamp(0.0, 1.0)) {
+                    fast_mean
+                } else {
+                    slow_mean
+                };
+                (mean, spread)
+            }
+        };
+
+        let offset = if outlier {
+            let distance = rng.random_range(4.0..8.0) * f64::from(spread.max(1));
+            if rng.random_bool(0.5) {
+                distance
+            } else {
+                -distance
+            }
+        } else {
+            Normal::new(0.0, f64::from(spread))
+                .expect("a non-negative spread is a valid standard deviation")
+                .sample(rng)
+        };
+        (f64::from(center) + offset)
+            .round()
+            .clamp(1.0, f64::from(u32::MAX)) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn seeded_pools_are_reproducible() {
+        let spec = PoolSpec::normal(100, 100_000, 2_000);
+        assert_eq!(spec.generate_seeded(7), spec.generate_seeded(7));
+        assert_ne!(spec.generate_seeded(7), spec.generate_seeded(8));
+        assert_eq!(spec.generate_seeded(7).len(), 100);
+    }
+
+    #[test]
+    fn distributions_respect_their_parameters() {
+        let uniform = PoolSpec::uniform(500, 90_000, 95_000).generate_seeded(1);
+        assert!(uniform
+            .iter()
+            .all(|car| (90_000..=95_000).contains(&car.lap_time)));
+
+        let bimodal = PoolSpec::bimodal(1_000, 90_000, 110_000, 1_000).generate_seeded(1);
+        let fast = bimodal.iter().filter(|car| car.lap_time < 100_000).count();
+        assert!((350..=650).contains(&fast), "fast class had {fast} cars");
+    }
+
+    #[test]
+    fn duplicates_and_outliers_are_injected() {
+        let duplicated = PoolSpec::normal(1_000, 100_000, 5_000)
+            .duplicates(0.5)
+            .generate_seeded(3);
+        let distinct = duplicated
+            .iter()
+            .map(|car| car.lap_time)
+            .collect::<HashSet<_>>();
+        assert!(distinct.len() < 700);
+
+        let outliers = PoolSpec::normal(1_000, 100_000, 1_000)
+            .outliers(0.05)
+            .generate_seeded(3);
+        let far = outliers
+            .iter()
+            .filter(|car| car.lap_time.abs_diff(100_000) >= 4_000)
+            .count();
+        assert!((20..=80).contains(&far), "{far} outliers");
+    }
+}
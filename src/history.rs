@@ -0,0 +1,108 @@
+//! Car usage carried over between sessions.
+//!
+//! A [`UsageHistory`] counts how often each car was driven in earlier
+//! sessions. Passed in [`SubsetCalculationConfig::usage_history`], it makes
+//! the solver prefer cars that were driven less, so a season rotates through
+//! the whole garage. It is stored as a JSON object from car id to count.
+//!
+//! [`SubsetCalculationConfig::usage_history`]: crate::SubsetCalculationConfig::usage_history
+
+use crate::{Car, CarIndex};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UsageHistory {
+    counts: BTreeMap<String, u32>,
+}
+
+impl UsageHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Times the car with `id` was used; unknown cars count as unused.
+    pub fn count(&self, id: &str) -> u32 {
+        self.counts.get(id).copied().unwrap_or(0)
+    }
+
+    /// Records `times` further uses of the car with `id`.
+    pub fn add(&mut self, id: &str, times: u32) {
+        if times > 0 {
+            let count = self.counts.entry(id.to_string()).or_default();
+            *count = count.saturating_add(times);
+        }
+    }
+
+    /// Records every car selected in a session's `subsets`, which index
+    /// `cars` as returned by [`perform_multiple_runs`](crate::perform_multiple_runs).
+    pub fn record_session(&mut self, cars: &[Car], subsets: &[Vec<CarIndex>]) {
+        for &index in subsets.iter().flatten() {
+            self.add(&cars[index].id, 1);
+        }
+    }
+
+    /// Adds the counts of `other`, e.g. a history exported elsewhere.
+    pub fn merge(&mut self, other: &UsageHistory) {
+        for (id, &times) in &other.counts {
+            self.add(id, times);
+        }
+    }
+
+    /// Cars with their counts, in id order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.counts.iter().map(|(id, &count)| (id.as_str(), count))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a map of strings to integers serializes")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Counts for `cars` by index.
+    pub(crate) fn counts_for(&self, cars: &[Car]) -> Vec<usize> {
+        cars.iter()
+            .map(|car| self.count(&car.id) as usize)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn car(id: &str) -> Car {
+        Car::new(id, 100_000)
+    }
+
+    #[test]
+    fn sessions_accumulate_and_round_trip_through_json() {
+        let cars = vec![car("a"), car("b"), car("c")];
+        let mut history = UsageHistory::new();
+        history.record_session(&cars, &[vec![0, 1], vec![1, 2]]);
+        history.record_session(&cars, &[vec![1]]);
+        assert_eq!(history.count("a"), 1);
+        assert_eq!(history.count("b"), 3);
+        assert_eq!(history.count("missing"), 0);
+
+        let json = history.to_json();
+        assert!(json.contains("\"b\": 3"));
+        assert_eq!(UsageHistory::from_json(&json).unwrap(), history);
+        assert!(UsageHistory::from_json("[1, 2]").is_err());
+
+        let mut merged = UsageHistory::from_json(r#"{"a": 2, "d": 1}"#).unwrap();
+        merged.merge(&history);
+        assert_eq!(
+            merged.iter().collect::<Vec<_>>(),
+            [("a", 3), ("b", 3), ("c", 1), ("d", 1)]
+        );
+    }
+}
@@ -1,106 +1,209 @@
+//! Text fields that edit a typed value.
+//!
+//! [`use_validated_input`] keeps a field's text and error beside a value
+//! owned by the caller: committing the text sets the value when it is
+//! valid, and setting the value anywhere else shows it in the field.
+
+use std::fmt::Display;
 use std::rc::Rc;
-use yew::prelude::*;
 use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+type Parse<T> = Rc<dyn Fn(&str) -> Result<T, String>>;
+type Format<T> = Rc<dyn Fn(&T) -> String>;
+type RangeMessage = Rc<dyn Fn(&str, &str) -> String>;
+
+/// How a [`use_validated_input`] field reads, checks, and shows its value,
+/// and what else committing it does.
+pub struct InputRules<T> {
+    parse: Parse<T>,
+    format: Format<T>,
+    range: Option<(T, T, RangeMessage)>,
+    allow_blank: bool,
+    on_commit: Callback<T>,
+}
+
+impl<T: Display + 'static> InputRules<T> {
+    /// Rules for a field read by `parse` and shown as the value displays.
+    pub fn new(parse: impl Fn(&str) -> Result<T, String> + 'static) -> Self {
+        Self::with_format(parse, T::to_string)
+    }
+}
+
+impl<T> InputRules<T> {
+    /// Rules for a field read by `parse` and shown by `format`, for values
+    /// that do not display themselves, such as optional ones.
+    pub fn with_format(
+        parse: impl Fn(&str) -> Result<T, String> + 'static,
+        format: impl Fn(&T) -> String + 'static,
+    ) -> Self {
+        Self {
+            parse: Rc::new(parse),
+            format: Rc::new(format),
+            range: None,
+            allow_blank: false,
+            on_commit: Callback::noop(),
+        }
+    }
+}
+
+impl<T: PartialOrd> InputRules<T> {
+    /// Shows values with `format` instead.
+    pub fn format(mut self, format: impl Fn(&T) -> String + 'static) -> Self {
+        self.format = Rc::new(format);
+        self
+    }
+
+    /// Rejects values outside `min..=max` with `message`, given both bounds
+    /// as formatted.
+    pub fn range(
+        mut self,
+        min: T,
+        max: T,
+        message: impl Fn(&str, &str) -> String + 'static,
+    ) -> Self {
+        self.range = Some((min, max, Rc::new(message)));
+        self
+    }
+
+    /// Lets blank text be committed, which clears the error and keeps the
+    /// value.
+    pub fn allow_blank(mut self) -> Self {
+        self.allow_blank = true;
+        self
+    }
+
+    /// Also passes every value committed from the field to `on_commit`.
+    pub fn on_commit(mut self, on_commit: impl Fn(T) + 'static) -> Self {
+        self.on_commit = Callback::from(on_commit);
+        self
+    }
+
+    /// The value `text` commits, none for blank text kept as the value, or
+    /// why it is invalid.
+    pub fn read(&self, text: &str) -> Result<Option<T>, String> {
+        if self.allow_blank && text.trim().is_empty() {
+            return Ok(None);
+        }
+        let value = (self.parse)(text)?;
+        match &self.range {
+            Some((min, max, message)) if value < *min || value > *max => {
+                Err(message(&(self.format)(min), &(self.format)(max)))
+            }
+            _ => Ok(Some(value)),
+        }
+    }
+}
 
-/// Holds the state and callbacks for a validated input field.
+/// The text, error, and callbacks of a [`use_validated_input`] field.
 #[derive(Clone)]
-pub struct ValidatedInput<T: Clone + PartialEq + 'static> {
-    /// The current text content of the input field.
+pub struct ValidatedInput {
     pub text: String,
-    /// The current parsed and validated numeric/typed value.
-    pub value: T,
-    /// An optional error message if validation failed.
+    /// Why the text last committed was invalid.
     pub error: Option<String>,
-    /// Callback for the text input's `oninput` event. Updates the internal text state.
+    /// For `oninput`: keeps the text typed.
     pub on_text_input: Callback<InputEvent>,
-    /// Callback to trigger parsing and validation of the current text.
-    /// Typically used with `onchange` or after an Enter key press on the text input.
+    /// For `onchange`: commits the text, setting the value when it is valid
+    /// and the error otherwise.
     pub on_commit: Callback<()>,
-    /// Callback to programmatically set the numeric/typed value.
-    /// This will also update the text representation and clear any errors.
-    pub set_value: Callback<T>,
+    /// For `onkeydown`: commits the text on Enter.
+    pub on_keydown: Callback<KeyboardEvent>,
 }
 
-/// Custom hook to manage state for a validated input field.
-pub fn use_validated_input<T: Clone + PartialEq + std::fmt::Display + 'static>(
-    initial_value: T,
-    parse_and_validate: Rc<dyn Fn(&str) -> Result<T, String>>,
-) -> ValidatedInput<T> {
-    // These are the actual UseStateHandles.
-    let numeric_state_handle: UseStateHandle<T> = use_state(|| initial_value.clone());
-    let text_state_handle: UseStateHandle<String> = use_state(|| initial_value.to_string());
-    let error_state_handle: UseStateHandle<Option<String>> = use_state(|| None::<String>);
+/// Edits `value` through a text field following `rules`.
+#[hook]
+pub fn use_validated_input<T>(value: UseStateHandle<T>, rules: InputRules<T>) -> ValidatedInput
+where
+    T: Clone + PartialEq + PartialOrd + 'static,
+{
+    let text = use_state(|| (rules.format)(&value));
+    let error = use_state(|| None::<String>);
+
+    // A value set elsewhere, such as by undo or the slider, replaces the
+    // text and its error.
+    {
+        let text = text.clone();
+        let error = error.clone();
+        let format = rules.format.clone();
+        use_effect_with((*value).clone(), move |value| {
+            let formatted = format(value);
+            if *text != formatted {
+                text.set(formatted);
+                error.set(None);
+            }
+        });
+    }
 
     let on_text_input = {
-        // Clone the handle for the closure.
-        let text_setter = text_state_handle.clone();
-        Callback::from(move |e: InputEvent| {
-            let input: HtmlInputElement = e.target_unchecked_into();
-            text_setter.set(input.value());
+        let text = text.clone();
+        Callback::from(move |event: InputEvent| {
+            let input: HtmlInputElement = event.target_unchecked_into();
+            text.set(input.value());
         })
     };
-
     let on_commit = {
-        // Clone handles for the closure.
-        let current_text_handle = text_state_handle.clone();
-        let numeric_setter = numeric_state_handle.clone();
-        let text_setter_on_commit = text_state_handle.clone();
-        let error_setter = error_state_handle.clone();
-        let parse_fn = parse_and_validate.clone();
-
-        Callback::from(move |_| {
-            // Dereference the handle to get the current String value.
-            match parse_fn(&(*current_text_handle)) {
-                Ok(parsed_val) => {
-                    numeric_setter.set(parsed_val.clone());
-                    text_setter_on_commit.set(parsed_val.to_string()); // Update text to canonical form
-                    error_setter.set(None);
-                }
-                Err(err_msg) => {
-                    error_setter.set(Some(err_msg));
-                }
+        let text = text.clone();
+        let error = error.clone();
+        Callback::from(move |_: ()| match rules.read(&text) {
+            Ok(Some(committed)) => {
+                error.set(None);
+                value.set(committed.clone());
+                text.set((rules.format)(&committed));
+                rules.on_commit.emit(committed);
             }
+            Ok(None) => error.set(None),
+            Err(message) => error.set(Some(message)),
         })
     };
-
-    let set_value = {
-        // Clone handles for the closure.
-        let numeric_setter = numeric_state_handle.clone();
-        let text_setter = text_state_handle.clone();
-        let error_setter = error_state_handle.clone();
-        Callback::from(move |new_val: T| {
-            numeric_setter.set(new_val.clone());
-            text_setter.set(new_val.to_string());
-            error_setter.set(None); // Assume programmatic set is valid
+    let on_keydown = {
+        let on_commit = on_commit.clone();
+        Callback::from(move |event: KeyboardEvent| {
+            if event.key() == "Enter" {
+                on_commit.emit(());
+            }
         })
     };
 
-    // Effect to update text_state if numeric_state_handle's value changes programmatically
-    {
-        // Clone the value for the dependency array.
-        let numeric_value_snapshot = (*numeric_state_handle).clone();
-        // Clone handles for the effect closure.
-        let text_setter_for_effect = text_state_handle.clone();
-        let current_text_handle_for_effect = text_state_handle.clone();
-
-
-        use_effect_with(numeric_value_snapshot, move |current_numeric_value_dep| {
-            let formatted_numeric_text = current_numeric_value_dep.to_string();
-            // Dereference handle to get current text for comparison.
-            if *current_text_handle_for_effect != formatted_numeric_text {
-                 text_setter_for_effect.set(formatted_numeric_text);
-            }
-            || (()) // Return a no-op destructor
-        });
-    }
-
     ValidatedInput {
-        // Dereference handles to get current values for the returned struct.
-        text: (*text_state_handle).clone(),
-        value: (*numeric_state_handle).clone(),
-        error: (*error_state_handle).clone(),
+        text: (*text).clone(),
+        error: (*error).clone(),
         on_text_input,
         on_commit,
-        set_value,
+        on_keydown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_check_the_range_and_blank_text() {
+        let rules = InputRules::new(|text: &str| {
+            text.parse::<f64>()
+                .map_err(|_| "Invalid number".to_string())
+        })
+        .range(1.0, 5.0, |min, max| format!("From {min} to {max}"));
+        assert_eq!(rules.read("2.5"), Ok(Some(2.5)));
+        assert_eq!(rules.read("6"), Err("From 1 to 5".to_string()));
+        assert_eq!(rules.read(""), Err("Invalid number".to_string()));
+
+        let rules = rules.allow_blank().format(|value| format!("{value:.1}s"));
+        assert_eq!(rules.read(" "), Ok(None));
+        assert_eq!(rules.read("0"), Err("From 1.0s to 5.0s".to_string()));
+
+        let rules = InputRules::with_format(
+            |text: &str| match text.trim() {
+                "" => Ok(None),
+                text => text
+                    .parse::<u64>()
+                    .map(Some)
+                    .map_err(|_| "Whole".to_string()),
+            },
+            |value| value.map(|value| value.to_string()).unwrap_or_default(),
+        );
+        assert_eq!(rules.read(""), Ok(Some(None)));
+        assert_eq!(rules.read("7"), Ok(Some(Some(7))));
+        assert_eq!(rules.read("x"), Err("Whole".to_string()));
     }
 }
@@ -0,0 +1,246 @@
+//! Importer for iRacing session result exports.
+//!
+//! The CSV exported from an iRacing results page opens with a few lines
+//! describing the session, followed by a table with one row per driver.
+//! [`read_iracing_results_csv`] finds that table and turns each driver's
+//! fastest lap into a [`Car`], with the track from the session details.
+
+use crate::{parse_lap_time, Car, CsvImportReport, CsvImportWarning, CsvImportWarningKind};
+use std::collections::HashSet;
+
+const DRIVER_HEADER: &str = "Name";
+const CAR_HEADER: &str = "Car";
+const CLASS_HEADER: &str = "Car Class";
+const TRACK_HEADER: &str = "Track";
+/// Names of the best-lap column, newest export format first.
+const FASTEST_LAP_HEADERS: [&str; 2] = ["Fastest Lap Time", "Best Lap Time"];
+
+fn header_position(header: &csv::StringRecord, name: &str) -> Option<usize> {
+    header.iter().position(|cell| {
+        cell.trim_start_matches('\u{feff}')
+            .trim()
+            .eq_ignore_ascii_case(name)
+    })
+}
+
+/// Reads one car per driver from an iRacing session result export.
+///
+/// Each car is named after the driver and, when the export has a `Car`
+/// column, the car they drove, as in `"Ann Example (Porsche 911 GT3 R)"`.
+/// Its class is the `Car Class` and its track the session's, when given.
+/// Its lap time is the driver's fastest lap; drivers who set no timed lap
+/// are reported as [`CsvImportWarningKind::MissingLapTime`]. Without a
+/// driver table the report holds a single
+/// [`CsvImportWarningKind::MissingHeader`] warning and no header row.
+pub fn read_iracing_results_csv(csv_content: &str) -> CsvImportReport {
+    let mut cars = Vec::new();
+    let mut warnings = Vec::new();
+    let mut seen_ids = HashSet::new();
+    let mut row_count = 0;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(csv_content.as_bytes());
+    let mut records = reader.records().enumerate();
+
+    // The session details are a header row and a row of values above the
+    // driver table.
+    let mut track_position = None;
+    let mut track = None;
+    let header = records.by_ref().find_map(|(i, result)| {
+        let record = result.ok()?;
+        if let Some(position) = track_position.take() {
+            track = record
+                .get(position)
+                .map(str::trim)
+                .filter(|track| !track.is_empty())
+                .map(str::to_string);
+        }
+        track_position = header_position(&record, TRACK_HEADER);
+        let driver = header_position(&record, DRIVER_HEADER)?;
+        let fastest_lap = FASTEST_LAP_HEADERS
+            .iter()
+            .find_map(|name| header_position(&record, name))?;
+        Some((
+            i + 1,
+            driver,
+            header_position(&record, CAR_HEADER),
+            header_position(&record, CLASS_HEADER),
+            fastest_lap,
+        ))
+    });
+    let Some((header_row, driver_column, car_column, class_column, fastest_lap_column)) = header
+    else {
+        return CsvImportReport {
+            cars,
+            warnings: vec![CsvImportWarning {
+                row: 0,
+                kind: CsvImportWarningKind::MissingHeader,
+                message: format!(
+                    "no header row names '{DRIVER_HEADER}' and '{}'",
+                    FASTEST_LAP_HEADERS[0]
+                ),
+            }],
+            row_count: 0,
+            accepted_count: 0,
+            rejected_count: 0,
+            merged_count: 0,
+            header_row: None,
+        };
+    };
+
+    for (i, result) in records {
+        let row = i + 1;
+        row_count += 1;
+        let record = match result {
+            Ok(record) => record,
+            Err(error) => {
+                warnings.push(CsvImportWarning {
+                    row,
+                    kind: CsvImportWarningKind::MalformedCsv,
+                    message: error.to_string(),
+                });
+                continue;
+            }
+        };
+        let driver = record.get(driver_column).unwrap_or_default().trim();
+        if driver.is_empty() {
+            warnings.push(CsvImportWarning {
+                row,
+                kind: CsvImportWarningKind::EmptyId,
+                message: "driver name is empty".to_string(),
+            });
+            continue;
+        }
+        let id = match car_column
+            .and_then(|column| record.get(column))
+            .map(str::trim)
+        {
+            Some(car) if !car.is_empty() => format!("{driver} ({car})"),
+            _ => driver.to_string(),
+        };
+        // Drivers without a timed lap show an empty cell or a dash.
+        let time_str = record.get(fastest_lap_column).unwrap_or_default().trim();
+        if time_str.is_empty() || time_str == "-" {
+            warnings.push(CsvImportWarning {
+                row,
+                kind: CsvImportWarningKind::MissingLapTime,
+                message: format!("no timed lap for '{id}'"),
+            });
+            continue;
+        }
+        let lap_time = match parse_lap_time(time_str) {
+            Ok(time) => time,
+            Err(message) => {
+                warnings.push(CsvImportWarning {
+                    row,
+                    kind: CsvImportWarningKind::InvalidLapTime,
+                    message,
+                });
+                continue;
+            }
+        };
+        if !seen_ids.insert(id.clone()) {
+            warnings.push(CsvImportWarning {
+                row,
+                kind: CsvImportWarningKind::DuplicateId,
+                message: format!("duplicate ID '{id}'"),
+            });
+            continue;
+        }
+        cars.push(Car {
+            id,
+            lap_time,
+            uncertainty_ms: None,
+            name: None,
+            class: class_column
+                .and_then(|column| record.get(column))
+                .map(str::trim)
+                .filter(|class| !class.is_empty())
+                .map(str::to_string),
+            track: track.clone(),
+            enabled: true,
+        });
+    }
+
+    let accepted_count = cars.len();
+    CsvImportReport {
+        cars,
+        warnings,
+        row_count,
+        accepted_count,
+        rejected_count: row_count - accepted_count,
+        merged_count: 0,
+        header_row: Some(header_row),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "\u{feff}\"Start Time\",\"Track\",\"Series\"\n\
+        \"2024-03-02 19:00:00\",\"Spa-Francorchamps - Grand Prix Pits\",\"League GT3\"\n\
+        \n\
+        \"Fin Pos\",\"Car ID\",\"Car\",\"Car Class\",\"Cust ID\",\"Name\",\"Interval\",\"Average Lap Time\",\"Fastest Lap Time\",\"Fast Lap#\",\"Laps Comp\"\n\
+        \"1\",\"132\",\"Porsche 911 GT3 R\",\"GT3\",\"1001\",\"Ann Example\",\"-\",\"2:20.515\",\"2:18.904\",\"7\",\"20\"\n\
+        \"2\",\"156\",\"BMW M4 GT3\",\"GT3\",\"1002\",\"Ben Sample\",\"-4.102\",\"2:20.811\",\"2:19.007\",\"12\",\"20\"\n\
+        \"3\",\"156\",\"BMW M4 GT3\",\"GT3\",\"1003\",\"Cy Test\",\"-1L\",\"\",\"\",\"\",\"0\"\n\
+        \"4\",\"132\",\"Porsche 911 GT3 R\",\"GT3\",\"1001\",\"Ann Example\",\"-1L\",\"2:21.000\",\"2:19.500\",\"3\",\"19\"\n";
+
+    #[test]
+    fn driver_table_is_found_below_the_session_details() {
+        let report = read_iracing_results_csv(EXPORT);
+
+        assert_eq!(report.header_row, Some(3));
+        assert_eq!(
+            report.cars,
+            [
+                Car {
+                    id: "Ann Example (Porsche 911 GT3 R)".to_string(),
+                    lap_time: 138_904,
+                    uncertainty_ms: None,
+                    name: None,
+                    class: Some("GT3".to_string()),
+                    track: Some("Spa-Francorchamps - Grand Prix Pits".to_string()),
+                    enabled: true,
+                },
+                Car {
+                    id: "Ben Sample (BMW M4 GT3)".to_string(),
+                    lap_time: 139_007,
+                    uncertainty_ms: None,
+                    name: None,
+                    class: Some("GT3".to_string()),
+                    track: Some("Spa-Francorchamps - Grand Prix Pits".to_string()),
+                    enabled: true,
+                },
+            ]
+        );
+        let kinds = report
+            .warnings
+            .iter()
+            .map(|warning| (warning.row, warning.kind.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                (6, CsvImportWarningKind::MissingLapTime),
+                (7, CsvImportWarningKind::DuplicateId),
+            ]
+        );
+        assert_eq!((report.row_count, report.rejected_count), (4, 2));
+    }
+
+    #[test]
+    fn exports_without_a_driver_table_are_rejected() {
+        let report = read_iracing_results_csv("GT3-01,1:42.000\nGT3-02,1:43.000\n");
+        assert_eq!(report.header_row, None);
+        assert!(report.cars.is_empty());
+        assert_eq!(report.warnings[0].kind, CsvImportWarningKind::MissingHeader);
+
+        let report = read_iracing_results_csv("Name,Best Lap Time\nAnn,58.250\n");
+        assert_eq!(report.cars[0].id, "Ann");
+        assert_eq!(report.cars[0].lap_time, 58_250);
+        assert_eq!(report.cars[0].track, None);
+    }
+}
@@ -1,15 +1,27 @@
 use log::{debug, info, warn};
-use rand::distr::weighted::WeightedIndex;
-
 use rand::seq::SliceRandom;
-use rand_distr::Distribution;
-use std::cmp::Ordering;
-use std::collections::HashSet;
-use std::fmt;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
 use wasm_bindgen::prelude::*;
 
+pub use analysis::{
+    analyze_multiple_runs, diff_results, lap_time_histogram, result_overlap, subset_stats,
+    summarize_results, AnalysisReport, ResultOverlap, ResultSummary, SubsetDiff, SubsetStats,
+};
+pub use cancel::CancellationToken;
+pub use dataset::Dataset;
+pub use diagnostics::{validate_request, Diagnostic, Severity};
+pub use difficulty::{estimate_difficulty, DifficultyEstimate, DifficultyLevel, DifficultyProfile};
+pub use events::{EventSink, LogSink, SolverEvent};
+pub use history::UsageHistory;
+pub use metrics::{measure_multiple_runs, MetricsRecorder, MetricsSnapshot, RunReport};
+pub use trace::{trace_multiple_runs, ConvergenceTrace, Decision, TraceRecorder};
+
 /// Default calculation parameters
 pub mod defaults {
     pub const TIMEOUT_MS: f64 = 5000.0;
@@ -20,90 +32,378 @@ pub mod defaults {
 pub struct Car {
     pub id: String,
     pub lap_time: u32,
+    /// Standard deviation of the car's real lap times in milliseconds, when
+    /// known. Read from an optional third CSV column.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uncertainty_ms: Option<u32>,
+    /// Display name such as "Porsche 992 GT3 Cup", shown in place of the id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    /// Track the lap time was set on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track: Option<String>,
+    /// Disabled cars stay in the dataset but are never selected, as if
+    /// absent.
+    #[serde(default = "enabled_by_default", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+impl Car {
+    /// An enabled car with only an id and a lap time.
+    pub fn new(id: impl Into<String>, lap_time: u32) -> Self {
+        Self {
+            id: id.into(),
+            lap_time,
+            uncertainty_ms: None,
+            name: None,
+            class: None,
+            track: None,
+            enabled: true,
+        }
+    }
+
+    /// The name to show for the car: its display name, else its id.
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
 }
 
 pub type CarIndex = usize;
 
+/// Fingerprint of a dataset's cars, in order, that is stable across
+/// platforms and releases: 64-bit FNV-1a over every id, lap time, and
+/// uncertainty, plus a marker for each disabled car. Name, class, and track
+/// only describe a car, so editing them keeps the hash.
+pub fn dataset_hash(cars: &[Car]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    let mut hash = OFFSET_BASIS;
+    let mut feed = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(PRIME);
+        }
+    };
+    for car in cars {
+        // The length prefix keeps ids from running into the fields after
+        // them.
+        feed(&(car.id.len() as u64).to_le_bytes());
+        feed(car.id.as_bytes());
+        feed(&car.lap_time.to_le_bytes());
+        match car.uncertainty_ms {
+            Some(uncertainty) => {
+                feed(&[1]);
+                feed(&uncertainty.to_le_bytes());
+            }
+            None => feed(&[0]),
+        }
+        if !car.enabled {
+            feed(&[2]);
+        }
+    }
+    hash
+}
+
 // Custom error type for subset search operations
-#[derive(Debug)]
+///
+/// Serialized errors are adjacently tagged: `code` holds the same stable
+/// identifier as [`SubsetError::code`] and `details` the variant's fields.
+#[derive(Debug, Clone, PartialEq, thiserror::Error, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "code", content = "details", rename_all = "snake_case")]
 pub enum SubsetError {
+    #[error("Failed to find a valid subset")]
     NoValidSubset,
+    #[error("Found subset is outside tolerance: {0}% of target (acceptable range: 99-101%)")]
     OutsideTolerance(f64),
+    #[error("Insufficient candidates: needed {0}, but only {1} available")]
     InsufficientCandidates(usize, usize),
     // New, more specific error variants
+    #[error(
+        "Target {target} is unreachable. Current sum: {current_sum}. Possible range: [{} to {}]",
+        u64::from(*.current_sum) + u64::from(*.min_possible),
+        u64::from(*.current_sum) + u64::from(*.max_possible)
+    )]
     TargetUnreachable {
         target: u32,
         current_sum: u32,
         min_possible: u32,
         max_possible: u32,
     },
+    #[error("No previously selected numbers available to use when needed")]
     NoPreviouslySelectedAvailable,
-    PreviouslySelectedInsufficient {
-        needed: usize,
-        available: usize,
-    },
+    #[error("Even with previously selected numbers, still insufficient: needed {needed}, but only {available} available")]
+    PreviouslySelectedInsufficient { needed: usize, available: usize },
     /// Less than the required number of subsets could be produced
-    NotEnoughSuccessfulRuns {
-        required: usize,
-        found: usize,
-    },
+    #[error("Only {found}/{required} satisfactory subsets found within tolerance")]
+    NotEnoughSuccessfulRuns { required: usize, found: usize },
+    #[error("Invalid tolerance: {0}")]
     InvalidTolerance(f64),
+    #[error("Invalid timeout: {0}")]
     InvalidTimeout(f64),
+    #[error("Invalid prior index: {0}")]
     InvalidPriorIndex(CarIndex),
-    ImpossibleCount {
-        requested: usize,
+    #[error("Cannot select {requested} unique cars from {available} cars")]
+    ImpossibleCount { requested: usize, available: usize },
+    /// Full coverage needs at least one selection slot per car.
+    #[error("Full coverage needs at least {cars} selections, but only {selections} are requested")]
+    InsufficientSelectionsForCoverage { selections: usize, cars: usize },
+    /// A run could not include the remaining uncovered cars within tolerance.
+    #[error("Run {run} cannot cover the remaining {uncovered} unused cars within tolerance of the target")]
+    CoverageInfeasible { run: usize, uncovered: usize },
+    /// A subset is too small to hold one car from every lap-time quantile.
+    #[error(
+        "Cannot place a car from each of {bins} lap-time quantiles in a {lap_count}-car subset"
+    )]
+    QuantileSpreadInfeasible { bins: usize, lap_count: usize },
+    /// The lap-time filter's minimum is greater than its maximum.
+    #[error(
+        "Minimum lap time {} is greater than maximum lap time {}",
+        format_ms_to_minsecms(*.min),
+        format_ms_to_minsecms(*.max)
+    )]
+    InvalidLapTimeFilter { min: u32, max: u32 },
+    /// Per-player pools were given, but not one for every player.
+    #[error("{pools} player car pools were given for {players} players")]
+    PlayerPoolCountMismatch { pools: usize, players: usize },
+    /// A player's pool holds too few usable cars for one subset.
+    #[error("Player {player} has {available} usable cars but each subset needs {lap_count}")]
+    PlayerPoolTooSmall {
+        player: usize,
         available: usize,
+        lap_count: usize,
+    },
+    /// Strictly disjoint subsets need more cars than the pool holds.
+    #[error("{player_count} disjoint subsets of {lap_count} cars need {} cars but only {cars} are available; at most {max_players} players fit", .lap_count.saturating_mul(*.player_count))]
+    DisjointInfeasible {
+        lap_count: usize,
+        player_count: usize,
+        cars: usize,
+        max_players: usize,
     },
+    /// [`SubsetCalculationConfig::max_shared_cars`] needs more cars than the
+    /// pool has, however the subsets are chosen.
+    #[error("{player_count} subsets of {lap_count} cars sharing at most {max_shared} need at least {required} cars but only {cars} are available")]
+    SharedCarsInfeasible {
+        player_count: usize,
+        lap_count: usize,
+        max_shared: usize,
+        required: usize,
+        cars: usize,
+    },
+    /// These runs used up their [`SubsetCalculationConfig::run_timeout_ms`]
+    /// and were skipped, so the players after them still got their time.
+    #[error("Run {} did not finish within its {budget_ms} ms budget", run_list(.runs))]
+    RunsSkipped { runs: Vec<usize>, budget_ms: f64 },
+    /// A single subset was to be re-solved for a player the results lack.
+    #[error("Player {player} has no subset; the results hold {player_count}")]
+    InvalidPlayerIndex { player: usize, player_count: usize },
+    /// [`SubsetCalculationConfig::cancellation`] was cancelled.
+    #[error("The calculation was cancelled")]
+    Cancelled,
+    /// A solver attempt run on its own thread panicked.
+    #[error("A solver attempt failed unexpectedly: {message}")]
+    AttemptPanicked { message: String },
 }
 
-impl fmt::Display for SubsetError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl SubsetError {
+    /// Stable, machine-readable identifier of the error kind.
+    ///
+    /// Codes are part of the public contract: callers may branch on them, so
+    /// an existing code is never renamed or reused for a different failure.
+    pub fn code(&self) -> &'static str {
         match self {
-            SubsetError::NoValidSubset => write!(f, "Failed to find a valid subset"),
-            SubsetError::OutsideTolerance(accuracy) => write!(
-                f,
-                "Found subset is outside tolerance: {}% of target (acceptable range: 99-101%)",
-                accuracy
-            ),
-            SubsetError::InsufficientCandidates(needed, available) => write!(
-                f,
-                "Insufficient candidates: needed {}, but only {} available",
-                needed, available
-            ),
-            SubsetError::TargetUnreachable { target, current_sum, min_possible, max_possible } => write!(
-                f,
-                "Target {} is unreachable. Current sum: {}. Possible range: [{} to {}]",
-                target,
+            SubsetError::NoValidSubset => "no_valid_subset",
+            SubsetError::OutsideTolerance(_) => "outside_tolerance",
+            SubsetError::InsufficientCandidates(..) => "insufficient_candidates",
+            SubsetError::TargetUnreachable { .. } => "target_unreachable",
+            SubsetError::NoPreviouslySelectedAvailable => "no_previously_selected_available",
+            SubsetError::PreviouslySelectedInsufficient { .. } => {
+                "previously_selected_insufficient"
+            }
+            SubsetError::NotEnoughSuccessfulRuns { .. } => "not_enough_successful_runs",
+            SubsetError::InvalidTolerance(_) => "invalid_tolerance",
+            SubsetError::InvalidTimeout(_) => "invalid_timeout",
+            SubsetError::InvalidPriorIndex(_) => "invalid_prior_index",
+            SubsetError::ImpossibleCount { .. } => "impossible_count",
+            SubsetError::InsufficientSelectionsForCoverage { .. } => {
+                "insufficient_selections_for_coverage"
+            }
+            SubsetError::CoverageInfeasible { .. } => "coverage_infeasible",
+            SubsetError::QuantileSpreadInfeasible { .. } => "quantile_spread_infeasible",
+            SubsetError::InvalidLapTimeFilter { .. } => "invalid_lap_time_filter",
+            SubsetError::PlayerPoolCountMismatch { .. } => "player_pool_count_mismatch",
+            SubsetError::PlayerPoolTooSmall { .. } => "player_pool_too_small",
+            SubsetError::DisjointInfeasible { .. } => "disjoint_infeasible",
+            SubsetError::SharedCarsInfeasible { .. } => "shared_cars_infeasible",
+            SubsetError::RunsSkipped { .. } => "runs_skipped",
+            SubsetError::InvalidPlayerIndex { .. } => "invalid_player_index",
+            SubsetError::Cancelled => "cancelled",
+            SubsetError::AttemptPanicked { .. } => "attempt_panicked",
+        }
+    }
+
+    /// Concrete changes to the request that address this error, most
+    /// direct first, phrased for display.
+    pub fn suggestions(&self) -> Vec<String> {
+        match *self {
+            SubsetError::NoValidSubset => vec![
+                "Raise the tolerance".to_string(),
+                "Move the target toward the middle of the reachable range".to_string(),
+            ],
+            SubsetError::OutsideTolerance(accuracy) => vec![format!(
+                "Raise the tolerance to at least {:.2}%",
+                ((accuracy - 100.0).abs() * 100.0).ceil() / 100.0
+            )],
+            SubsetError::InsufficientCandidates(needed, available) => vec![
+                format!("Lower the lap count to at most {}", available),
+                format!("Add at least {} cars", needed.saturating_sub(available)),
+            ],
+            SubsetError::TargetUnreachable {
                 current_sum,
-                u64::from(*current_sum) + u64::from(*min_possible),
-                u64::from(*current_sum) + u64::from(*max_possible)
-            ),
-            SubsetError::NoPreviouslySelectedAvailable => write!(
-                f,
-                "No previously selected numbers available to use when needed"
-            ),
-            SubsetError::PreviouslySelectedInsufficient { needed, available } => write!(
-                f,
-                "Even with previously selected numbers, still insufficient: needed {}, but only {} available",
-                needed, available
-            ),
-            SubsetError::NotEnoughSuccessfulRuns { required, found } => write!(
-                f,
-                "Only {}/{} satisfactory subsets found within tolerance",
-                found, required
-            ),
-            SubsetError::InvalidTolerance(value) => write!(f, "Invalid tolerance: {value}"),
-            SubsetError::InvalidTimeout(value) => write!(f, "Invalid timeout: {value}"),
-            SubsetError::InvalidPriorIndex(index) => write!(f, "Invalid prior index: {index}"),
-            SubsetError::ImpossibleCount { requested, available } => write!(
-                f,
-                "Cannot select {requested} unique cars from {available} cars"
-            ),
+                min_possible,
+                max_possible,
+                ..
+            } => {
+                let reachable = |offset: u32| {
+                    let total = u64::from(current_sum) + u64::from(offset);
+                    format_ms_to_minsecms(total.min(u64::from(u32::MAX)) as u32)
+                };
+                vec![format!(
+                    "Choose a target between {} and {}, the range for these settings",
+                    reachable(min_possible),
+                    reachable(max_possible)
+                )]
+            }
+            SubsetError::NoPreviouslySelectedAvailable => vec![
+                "Reduce the number of players".to_string(),
+                "Add cars to the dataset".to_string(),
+            ],
+            SubsetError::PreviouslySelectedInsufficient { available, .. } => vec![
+                format!("Lower the lap count to at most {}", available),
+                "Reduce the number of players".to_string(),
+            ],
+            SubsetError::NotEnoughSuccessfulRuns { found, .. } => {
+                let mut suggestions = Vec::new();
+                if found > 0 {
+                    suggestions.push(format!("Reduce the players to at most {}", found));
+                }
+                suggestions.push("Raise the tolerance".to_string());
+                suggestions.push("Allow a longer timeout".to_string());
+                suggestions
+            }
+            SubsetError::InvalidTolerance(_) => {
+                vec!["Use a tolerance of at least 0%".to_string()]
+            }
+            SubsetError::InvalidTimeout(_) => {
+                vec!["Use a timeout of at least 0 ms".to_string()]
+            }
+            SubsetError::InvalidPriorIndex(_) => {
+                vec!["Only refer to cars in the dataset".to_string()]
+            }
+            SubsetError::ImpossibleCount {
+                requested,
+                available,
+            } => vec![
+                format!("Lower the lap count to at most {}", available),
+                format!("Add at least {} cars", requested.saturating_sub(available)),
+            ],
+            SubsetError::InsufficientSelectionsForCoverage { cars, .. } => vec![
+                format!(
+                    "Raise laps × players to at least {} so every car fits",
+                    cars
+                ),
+                "Turn off full coverage".to_string(),
+            ],
+            SubsetError::CoverageInfeasible { uncovered, .. } => vec![
+                "Raise the tolerance".to_string(),
+                format!(
+                    "Add players so later runs can cover the {} unused cars",
+                    uncovered
+                ),
+                "Turn off full coverage".to_string(),
+            ],
+            SubsetError::QuantileSpreadInfeasible { bins, lap_count } => vec![
+                format!("Raise the lap count to at least {}", bins),
+                format!("Use at most {} lap-time quantiles", lap_count),
+            ],
+            SubsetError::InvalidLapTimeFilter { max, .. } => vec![format!(
+                "Lower the minimum lap time to at most {}",
+                format_ms_to_minsecms(max)
+            )],
+            SubsetError::PlayerPoolCountMismatch { players, .. } => vec![format!(
+                "Give exactly {} player pools, or none to share every car",
+                players
+            )],
+            SubsetError::PlayerPoolTooSmall {
+                player,
+                available,
+                lap_count,
+            } => vec![
+                format!(
+                    "Add at least {} cars to player {}'s pool",
+                    lap_count.saturating_sub(available),
+                    player
+                ),
+                format!("Lower the lap count to at most {}", available),
+            ],
+            SubsetError::DisjointInfeasible {
+                player_count,
+                cars,
+                max_players,
+                ..
+            } => vec![
+                format!("Reduce the players to at most {}", max_players),
+                format!(
+                    "Lower the lap count to at most {}",
+                    cars / player_count.max(1)
+                ),
+                "Allow subsets to share cars".to_string(),
+            ],
+            SubsetError::SharedCarsInfeasible {
+                max_shared,
+                player_count,
+                ..
+            } => vec![
+                format!("Allow subsets to share more than {} cars", max_shared),
+                format!("Reduce the players to fewer than {}", player_count),
+                "Lower the lap count".to_string(),
+            ],
+            SubsetError::RunsSkipped { budget_ms, .. } => vec![
+                format!("Allow more than {} ms per run", budget_ms),
+                "Raise the tolerance".to_string(),
+            ],
+            SubsetError::InvalidPlayerIndex { player_count, .. } => {
+                vec![format!("Choose a player between 1 and {}", player_count)]
+            }
+            SubsetError::Cancelled | SubsetError::AttemptPanicked { .. } => Vec::new(),
         }
     }
 }
 
-impl std::error::Error for SubsetError {}
+/// Run numbers as "2", "2 and 5", or "2, 4 and 5".
+fn run_list(runs: &[usize]) -> String {
+    match runs {
+        [] => String::new(),
+        [run] => run.to_string(),
+        [rest @ .., last] => format!(
+            "{} and {}",
+            rest.iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            last
+        ),
+    }
+}
 
 pub fn get_lap_time(cars: &[Car], index: CarIndex) -> u32 {
     cars[index].lap_time
@@ -171,7 +471,7 @@ fn target_is_reachable(
 
 fn handle_last_number(
     cars: &[Car],
-    candidates_for_current_selection: &[CarIndex], // Changed from &mut to &
+    candidates: &SortedPool,
     current_sum: u32,
     target: u32,
     tolerance_percent: f64,
@@ -179,23 +479,21 @@ fn handle_last_number(
     let needed = target.saturating_sub(current_sum);
 
     // Binary search to find closest element to needed time
-    let best_match_idx = find_closest_time(cars, candidates_for_current_selection, needed);
+    let best_match_idx = candidates
+        .closest(needed)
+        .expect("the last selection always has a candidate");
     let best_match_sum = current_sum.saturating_add(get_lap_time(cars, best_match_idx));
 
     // use new helpers
     let accuracy = accuracy_percent(best_match_sum, target);
-    let within_tolerance = within_tolerance(accuracy, tolerance_percent);
-
-    if !within_tolerance {
-        debug!("Last number outside tolerance, calling fallback_strategy");
-        // Need to make a mutable copy for fallback_strategy
-        let mut candidates_copy: Vec<CarIndex> = candidates_for_current_selection.to_vec();
-        let (fallback_idx, _) =
-            fallback_strategy(cars, &mut candidates_copy, current_sum, target, 1);
-        return (
-            fallback_idx,
-            current_sum.saturating_add(get_lap_time(cars, fallback_idx)),
+    if !within_tolerance(accuracy, tolerance_percent) {
+        // No other car gets closer to the remaining time, so the caller's
+        // tolerance check decides whether the run is retried.
+        debug!(
+            "Last number outside tolerance (accuracy: {:.2}%), using closest available",
+            accuracy
         );
+        return (best_match_idx, best_match_sum);
     }
 
     debug!(
@@ -208,89 +506,17 @@ fn handle_last_number(
     (best_match_idx, best_match_sum)
 }
 
-/// Finds the closest time to a target value using binary search.
-///
-/// # Arguments
-/// * `cars` - Slice of cars to search through
-/// * `indexes` - Sorted indices into the cars array
-/// * `target_time` - The target time to find the closest match for
-///
-/// # Returns
-/// The index of the car with the lap time closest to `target_time`
-///
-/// # Panics
-/// Panics if `indexes` is empty
-fn find_closest_time(cars: &[Car], indexes: &[CarIndex], target_time: u32) -> CarIndex {
-    assert!(
-        !indexes.is_empty(),
-        "Cannot find closest time in empty array"
-    );
-
-    if indexes.len() == 1 {
-        return indexes[0];
-    }
-
-    // Binary search
-    let mut left = 0;
-    let mut right = indexes.len() - 1;
-
-    // Handle edge cases first
-    if target_time <= get_lap_time(cars, indexes[left]) {
-        return indexes[left];
-    }
-    if target_time >= get_lap_time(cars, indexes[right]) {
-        return indexes[right];
-    }
-
-    // Binary search to find the two elements that target_time is between
-    while left + 1 < right {
-        let mid = left + (right - left) / 2;
-        let mid_time = get_lap_time(cars, indexes[mid]);
-
-        match mid_time.cmp(&target_time) {
-            Ordering::Equal => return indexes[mid], // Exact match
-            Ordering::Less => left = mid,
-            Ordering::Greater => right = mid,
-        }
-    }
-
-    // Now we have two candidates: indexes[left] and indexes[right]
-    // Return the closer one
-    let left_diff = get_lap_time(cars, indexes[left]).abs_diff(target_time);
-    let right_diff = get_lap_time(cars, indexes[right]).abs_diff(target_time);
-
-    if left_diff <= right_diff {
-        indexes[left]
-    } else {
-        indexes[right]
-    }
-}
-
-fn fallback_strategy(
+/// Widens the remaining pool with previously selected cars that are not yet
+/// part of the subset being built.
+fn extend_with_previous(
     cars: &[Car],
-    candidates_for_current_selection: &mut [CarIndex],
-    current_sum: u32,
-    target: u32,
-    remaining_needed: usize,
-) -> (CarIndex, bool) {
-    // Use saturating_sub to avoid underflow when current_sum > target
-    let remaining_target = target.saturating_sub(current_sum);
-    let current_target_avg = if remaining_target == 0 {
-        0
-    } else {
-        remaining_target / remaining_needed as u32
-    };
-
-    // Sort to find the best match in current pool
-    candidates_for_current_selection
-        .sort_by_key(|&idx| get_lap_time(cars, idx).abs_diff(current_target_avg));
-    let best_match_idx = candidates_for_current_selection[0];
-
-    // Previously selected cars are added to the candidate pool only when the
-    // unused pool cannot satisfy this selection. Never bypass that pool here.
-
-    // Return final chosen car and a flag indicating we used a "backtrack"
-    (best_match_idx, true)
+    pool: &SortedPool,
+    previously_selected: &HashSet<CarIndex>,
+    selected: &[CarIndex],
+) -> Option<SortedPool> {
+    let mut candidates: Vec<CarIndex> = pool.available().collect();
+    try_extend_with_previous(cars, &mut candidates, previously_selected, selected)
+        .then(|| SortedPool::new(cars, &candidates))
 }
 
 fn try_extend_with_previous(
@@ -312,7 +538,9 @@ fn try_extend_with_previous(
         false
     } else {
         candidate_indexes.extend(available_previous);
-        candidate_indexes.sort_unstable_by_key(|&idx| get_lap_time(cars, idx));
+        // Break lap-time ties by index so the set's iteration order cannot
+        // change the result of a seeded run.
+        candidate_indexes.sort_unstable_by_key(|&idx| (get_lap_time(cars, idx), idx));
         candidate_indexes.dedup();
         true
     }
@@ -336,6 +564,9 @@ fn calculate_subset_sum(cars: &[Car], subset: &[CarIndex]) -> u32 {
         .unwrap_or(u32::MAX)
 }
 
+/// Shortest time a calculation is given, whatever its `timeout_ms`.
+const MIN_RUNTIME_MS: f64 = 100.0;
+
 /// Helper function to check if we need to abort due to timeout
 #[cfg(not(target_arch = "wasm32"))]
 #[inline]
@@ -349,6 +580,17 @@ fn is_timeout_exceeded(start_time: f64, max_runtime_ms: f64) -> bool {
     js_sys::Date::now() - start_time > max_runtime_ms
 }
 
+/// Milliseconds since `start_time`, with sub-millisecond precision natively.
+#[cfg(not(target_arch = "wasm32"))]
+fn elapsed_ms(start_time: std::time::Instant) -> f64 {
+    start_time.elapsed().as_secs_f64() * 1_000.0
+}
+
+#[cfg(target_arch = "wasm32")]
+fn elapsed_ms(start_time: f64) -> f64 {
+    js_sys::Date::now() - start_time
+}
+
 /// Selects which solver implementation backs the stable public API.
 ///
 /// The legacy strategy remains available so a future solver can be introduced
@@ -359,6 +601,19 @@ pub enum SolverStrategy {
     Bounded,
 }
 
+impl SolverStrategy {
+    /// Number of runs that draw disjoint subsets while enough unused cars
+    /// remain: every run for the legacy strategy, which removes selected
+    /// cars from the pool, and one for the bounded strategy, which only
+    /// prefers less used cars and so lets every run reach the same totals.
+    pub fn disjoint_runs(self, player_count: usize) -> usize {
+        match self {
+            Self::Legacy => player_count,
+            Self::Bounded => player_count.min(1),
+        }
+    }
+}
+
 /// Change this one constant to `Legacy` to roll back the public solver.
 pub const DEFAULT_SOLVER_STRATEGY: SolverStrategy = SolverStrategy::Bounded;
 
@@ -367,6 +622,47 @@ const BOUNDED_RANDOM_ATTEMPTS: usize = 512;
 
 const EXACT_POOL_LIMIT: usize = 20;
 const DEADLINE_CHECK_INTERVAL: usize = 256;
+/// Draws of [`shared_car_exclusions`] a run makes before a failed search on
+/// the cars left to it stands.
+const SHARED_CAR_DRAWS: usize = 16;
+
+/// Fewest cars `player_count` subsets of `lap_count` cars need when no two
+/// share more than `max_shared`: each subset adds at least its size less
+/// `max_shared` for every subset before it.
+pub(crate) fn shared_cap_min_cars(
+    lap_count: usize,
+    player_count: usize,
+    max_shared: usize,
+) -> usize {
+    (0..player_count)
+        .map(|earlier| lap_count.saturating_sub(earlier.saturating_mul(max_shared)))
+        .fold(0, usize::saturating_add)
+}
+
+/// Cars to leave out of a selection so it shares at most `max_shared` cars
+/// with each of the `earlier` subsets: all but `max_shared` random cars of
+/// each subset that are still in. Any selection of the other cars keeps to
+/// the cap.
+fn shared_car_exclusions<S: AsRef<[CarIndex]>, R: rand::Rng + ?Sized>(
+    earlier: &[S],
+    max_shared: usize,
+    rng: &mut R,
+) -> HashSet<CarIndex> {
+    let mut excluded = HashSet::new();
+    for subset in earlier {
+        let mut kept = subset
+            .as_ref()
+            .iter()
+            .copied()
+            .filter(|car| !excluded.contains(car))
+            .collect::<Vec<_>>();
+        if kept.len() > max_shared {
+            kept.shuffle(rng);
+            excluded.extend(kept.drain(max_shared..));
+        }
+    }
+    excluded
+}
 
 pub fn find_approximate_subset(
     cars: &[Car],
@@ -416,20 +712,23 @@ fn find_approximate_subset_with_strategy_and_rng<R: rand::Rng>(
         .filter(|idx| !previously_selected.contains(idx))
         .collect();
     match strategy {
-        SolverStrategy::Legacy => legacy_find_approximate_subset_from_candidates_with_rng(
+        SolverStrategy::Legacy => legacy_find_approximate_subset_with_rng(
             cars,
-            target,
-            lap_count,
-            &available_indexes,
-            previously_selected,
-            tolerance_percent,
+            LegacyRequest {
+                target,
+                lap_count,
+                candidate_indexes: &available_indexes,
+                previously_selected,
+                tolerance_percent,
+            },
             rng,
+            &mut LogSink,
         ),
         SolverStrategy::Bounded => {
             let request = BoundedRequest {
                 target,
                 lap_count,
-                tolerance_percent,
+                accepted: accepted_sum_interval(target, tolerance_percent),
                 unused: &available_indexes,
                 previously_selected,
                 usage_counts: None,
@@ -476,7 +775,8 @@ fn validate_bounded_subset(
 struct BoundedRequest<'a> {
     target: u32,
     lap_count: usize,
-    tolerance_percent: f64,
+    /// Inclusive range of subset sums that satisfy the request.
+    accepted: (u64, u64),
     unused: &'a [CarIndex],
     previously_selected: &'a HashSet<CarIndex>,
     usage_counts: Option<&'a [usize]>,
@@ -987,7 +1287,7 @@ fn bounded_find_approximate_subset_with_rng<R: rand::Rng, F: FnMut() -> bool>(
     rng: &mut R,
     mut deadline_exceeded: F,
 ) -> Result<Vec<CarIndex>, SubsetError> {
-    let original_accepted = accepted_sum_interval(request.target, request.tolerance_percent);
+    let original_accepted = request.accepted;
     if request.lap_count == 0 {
         let result = Vec::new();
         validate_bounded_subset(cars, &result, 0, request.target, original_accepted)?;
@@ -1104,35 +1404,102 @@ fn bounded_find_approximate_subset_with_rng<R: rand::Rng, F: FnMut() -> bool>(
     Err(SubsetError::NoValidSubset)
 }
 
-fn legacy_find_approximate_subset_from_candidates(
-    cars: &[Car],
+/// A single selection restricted to an explicit candidate pool.
+struct PoolRequest<'a> {
     target: u32,
     lap_count: usize,
-    candidate_indexes: &[CarIndex],
-    previously_selected: &HashSet<CarIndex>,
     tolerance_percent: f64,
-) -> Result<Vec<CarIndex>, SubsetError> {
-    let mut rng = rand::rng();
-    legacy_find_approximate_subset_from_candidates_with_rng(
-        cars,
-        target,
-        lap_count,
-        candidate_indexes,
-        previously_selected,
-        tolerance_percent,
-        &mut rng,
-    )
+    /// Cars the remaining slots may be filled from. Must not contain `required`.
+    pool: &'a [CarIndex],
+    /// Cars that every returned subset contains.
+    required: &'a [CarIndex],
+    usage_counts: Option<&'a [usize]>,
 }
 
-fn legacy_find_approximate_subset_from_candidates_with_rng<R: rand::Rng>(
+/// Runs the bounded search on `pool` only, with `required` cars fixed.
+///
+/// The bounded search assumes it may use (or exclude, for complements) every
+/// car it is given, so the pool is remapped into its own car list and the
+/// accepted interval is shifted by the sum of the required cars.
+fn bounded_find_in_pool<R: rand::Rng, F: FnMut() -> bool>(
     cars: &[Car],
+    request: PoolRequest<'_>,
+    rng: &mut R,
+    deadline_exceeded: F,
+) -> Result<Vec<CarIndex>, SubsetError> {
+    let needed = request
+        .lap_count
+        .checked_sub(request.required.len())
+        .ok_or(SubsetError::NoValidSubset)?;
+    if needed > request.pool.len() {
+        return Err(SubsetError::ImpossibleCount {
+            requested: needed,
+            available: request.pool.len(),
+        });
+    }
+    let required_sum = calculate_subset_sum_u64(cars, request.required);
+    let (lower, upper) = accepted_sum_interval(request.target, request.tolerance_percent);
+    if required_sum > upper {
+        return Err(SubsetError::NoValidSubset);
+    }
+
+    let pool_cars: Vec<Car> = request
+        .pool
+        .iter()
+        .map(|&index| cars[index].clone())
+        .collect();
+    let pool_usage: Option<Vec<usize>> = request
+        .usage_counts
+        .map(|counts| request.pool.iter().map(|&index| counts[index]).collect());
+    let unused: Vec<CarIndex> = (0..pool_cars.len()).collect();
+    let previously_selected = HashSet::new();
+    let selected = bounded_find_approximate_subset_with_rng(
+        &pool_cars,
+        BoundedRequest {
+            target: u64::from(request.target)
+                .saturating_sub(required_sum)
+                .min(u64::from(u32::MAX)) as u32,
+            lap_count: needed,
+            accepted: (lower.saturating_sub(required_sum), upper - required_sum),
+            unused: &unused,
+            previously_selected: &previously_selected,
+            usage_counts: pool_usage.as_deref(),
+        },
+        rng,
+        deadline_exceeded,
+    )?;
+
+    let mut result: Vec<CarIndex> = request
+        .required
+        .iter()
+        .copied()
+        .chain(selected.into_iter().map(|index| request.pool[index]))
+        .collect();
+    result.shuffle(rng);
+    Ok(result)
+}
+
+struct LegacyRequest<'a> {
     target: u32,
     lap_count: usize,
-    candidate_indexes: &[CarIndex],
-    previously_selected: &HashSet<CarIndex>,
+    candidate_indexes: &'a [CarIndex],
+    previously_selected: &'a HashSet<CarIndex>,
     tolerance_percent: f64,
+}
+
+fn legacy_find_approximate_subset_with_rng<R: rand::Rng>(
+    cars: &[Car],
+    request: LegacyRequest<'_>,
     rng: &mut R,
+    events: &mut dyn EventSink,
 ) -> Result<Vec<CarIndex>, SubsetError> {
+    let LegacyRequest {
+        target,
+        lap_count,
+        candidate_indexes,
+        previously_selected,
+        tolerance_percent,
+    } = request;
     if !tolerance_percent.is_finite() || tolerance_percent < 0.0 {
         return Err(SubsetError::NoValidSubset);
     }
@@ -1146,9 +1513,7 @@ fn legacy_find_approximate_subset_from_candidates_with_rng<R: rand::Rng>(
 
     let mut selected = Vec::new();
     let mut current_sum = 0;
-    let mut remaining_indexes = candidate_indexes.to_vec();
-    remaining_indexes.sort_unstable_by_key(|&idx| get_lap_time(cars, idx));
-    remaining_indexes.dedup();
+    let mut pool = SortedPool::new(cars, candidate_indexes);
     let mut total_backtracks = 0;
 
     while selected.len() < lap_count {
@@ -1162,47 +1527,45 @@ fn legacy_find_approximate_subset_from_candidates_with_rng<R: rand::Rng>(
             current_sum
         );
 
-        // Create candidates for this selection - start with remaining pool
-        let mut candidates_for_current_selection = remaining_indexes.clone();
-        let mut using_previous_cars = false;
+        // Candidates for this selection are the remaining pool, unless it has
+        // to be widened with previously selected cars.
+        let mut extended_pool = None;
 
-        if remaining_needed > candidates_for_current_selection.len() {
+        if remaining_needed > pool.available_len() {
             debug!(
                 "Not enough numbers left. Need {}, have {}",
                 remaining_needed,
-                candidates_for_current_selection.len()
+                pool.available_len()
             );
             // Check if we have previously selected numbers we could use
-            if !try_extend_with_previous(
-                cars,
-                &mut candidates_for_current_selection,
-                previously_selected,
-                &selected,
-            ) {
+            let Some(extended) = extend_with_previous(cars, &pool, previously_selected, &selected)
+            else {
                 debug!("No previously selected numbers available");
                 return Err(SubsetError::InsufficientCandidates(
                     remaining_needed,
-                    candidates_for_current_selection.len(),
+                    pool.available_len(),
                 ));
-            } else {
-                using_previous_cars = true;
-                debug!(
-                    "Expanded candidate pool to {} numbers",
-                    candidates_for_current_selection.len()
-                );
+            };
+            debug!(
+                "Expanded candidate pool to {} numbers",
+                extended.available_len()
+            );
 
-                if candidates_for_current_selection.len() < remaining_needed {
-                    debug!("Still not enough numbers after adding previously selected ones");
-                    return Err(SubsetError::PreviouslySelectedInsufficient {
-                        needed: remaining_needed,
-                        available: candidates_for_current_selection.len(),
-                    });
-                }
+            if extended.available_len() < remaining_needed {
+                debug!("Still not enough numbers after adding previously selected ones");
+                return Err(SubsetError::PreviouslySelectedInsufficient {
+                    needed: remaining_needed,
+                    available: extended.available_len(),
+                });
             }
+            extended_pool = Some(extended);
         }
+        let using_previous_cars = extended_pool.is_some();
 
-        let (min_possible, max_possible) =
-            calculate_min_max_sums(cars, &candidates_for_current_selection, remaining_needed);
+        let (min_possible, max_possible) = extended_pool
+            .as_ref()
+            .unwrap_or(&pool)
+            .min_max_sums(remaining_needed);
         debug!(
             "Range check: Need min {} to max {} for remaining {} numbers",
             min_possible, max_possible, remaining_needed
@@ -1225,49 +1588,7 @@ fn legacy_find_approximate_subset_from_candidates_with_rng<R: rand::Rng>(
             );
 
             // Consider previously selected numbers for this selection only if we haven't already
-            if !using_previous_cars {
-                if !try_extend_with_previous(
-                    cars,
-                    &mut candidates_for_current_selection,
-                    previously_selected,
-                    &selected,
-                ) {
-                    debug!("No previously selected numbers available to use");
-                    return Err(SubsetError::TargetUnreachable {
-                        target,
-                        current_sum,
-                        min_possible,
-                        max_possible,
-                    });
-                } else {
-                    // Re-calculate min/max possible sums with expanded pool
-                    let (new_min, new_max) = calculate_min_max_sums(
-                        cars,
-                        &candidates_for_current_selection,
-                        remaining_needed,
-                    );
-                    debug!(
-                        "After adding previously selected numbers, new range: [{}, {}]",
-                        u64::from(current_sum) + u64::from(new_min),
-                        u64::from(current_sum) + u64::from(new_max)
-                    );
-
-                    // Check whether the expanded range reaches the tolerance-adjusted target.
-                    if target_is_reachable(current_sum, new_min, new_max, target, tolerance_percent)
-                    {
-                        debug!("Target is now reachable with previously selected numbers");
-                        // Continue with expanded pool
-                    } else {
-                        debug!("Target still not reachable even with previously selected numbers");
-                        return Err(SubsetError::TargetUnreachable {
-                            target,
-                            current_sum,
-                            min_possible: new_min,
-                            max_possible: new_max,
-                        });
-                    }
-                }
-            } else {
+            if using_previous_cars {
                 debug!("Already using previously selected numbers but target still unreachable");
                 return Err(SubsetError::TargetUnreachable {
                     target,
@@ -1276,47 +1597,82 @@ fn legacy_find_approximate_subset_from_candidates_with_rng<R: rand::Rng>(
                     max_possible,
                 });
             }
+            let Some(extended) = extend_with_previous(cars, &pool, previously_selected, &selected)
+            else {
+                debug!("No previously selected numbers available to use");
+                return Err(SubsetError::TargetUnreachable {
+                    target,
+                    current_sum,
+                    min_possible,
+                    max_possible,
+                });
+            };
+
+            // Re-calculate min/max possible sums with expanded pool
+            let (new_min, new_max) = extended.min_max_sums(remaining_needed);
+            debug!(
+                "After adding previously selected numbers, new range: [{}, {}]",
+                u64::from(current_sum) + u64::from(new_min),
+                u64::from(current_sum) + u64::from(new_max)
+            );
+
+            // Check whether the expanded range reaches the tolerance-adjusted target.
+            if !target_is_reachable(current_sum, new_min, new_max, target, tolerance_percent) {
+                debug!("Target still not reachable even with previously selected numbers");
+                return Err(SubsetError::TargetUnreachable {
+                    target,
+                    current_sum,
+                    min_possible: new_min,
+                    max_possible: new_max,
+                });
+            }
+            debug!("Target is now reachable with previously selected numbers");
+            extended_pool = Some(extended);
         }
+        let candidates = extended_pool.as_ref().unwrap_or(&pool);
 
         // Special case for the last number
         if remaining_needed == 1 {
-            let (final_choice, _) = handle_last_number(
-                cars,
-                &candidates_for_current_selection, // No longer needs mut
-                current_sum,
-                target,
-                tolerance_percent,
-            );
+            let (final_choice, final_sum) =
+                handle_last_number(cars, candidates, current_sum, target, tolerance_percent);
             selected.push(final_choice);
+            events.emit(SolverEvent::SelectionMade {
+                car: final_choice,
+                lap_time: get_lap_time(cars, final_choice),
+                current_sum: final_sum,
+                target,
+                selected: selected.len(),
+                lap_count,
+            });
             break;
         }
 
         let chosen = select_candidate(
-            &mut candidates_for_current_selection,
+            candidates,
             CandidateSelectionContext {
-                cars,
                 current_sum,
                 target,
                 remaining_needed,
                 rng,
                 total_backtracks: &mut total_backtracks,
+                events: &mut *events,
             },
         );
 
         current_sum = current_sum.saturating_add(get_lap_time(cars, chosen));
         selected.push(chosen);
-        debug!(
-            "Added: {}. New sum: {}/{} ({}%)",
-            get_lap_time(cars, chosen),
+        events.emit(SolverEvent::SelectionMade {
+            car: chosen,
+            lap_time: get_lap_time(cars, chosen),
             current_sum,
             target,
-            accuracy_percent(current_sum, target)
-        );
+            selected: selected.len(),
+            lap_count,
+        });
 
-        // Remove the chosen number from the original remaining numbers if it was from there
-        if let Some(pos) = remaining_indexes.iter().position(|&idx| idx == chosen) {
-            remaining_indexes.remove(pos);
-        }
+        // Previously selected cars are not part of the pool, so taking one of
+        // them leaves the pool unchanged.
+        pool.take(cars, chosen);
         // Note: We don't modify the previously_selected set here, as that happens in the main function
     }
 
@@ -1345,30 +1701,65 @@ fn legacy_find_approximate_subset_from_candidates_with_rng<R: rand::Rng>(
     Err(SubsetError::NoValidSubset)
 }
 
+/// Picks a candidate with probability proportional to
+/// `1 / (|lap_time - needed_avg| + 1)`, or `None` if there are no candidates.
+///
+/// Every selection needs a fresh distribution and draws from it once, so
+/// building a `WeightedIndex` (or an alias table) allocates and prepares work
+/// that is never reused. Two passes over the window, one to total the weights
+/// and one to locate the draw, need no allocation, and reading lap times from
+/// the pool's contiguous copy avoids chasing indexes into `cars`.
+fn sample_by_closeness<I, R>(candidates: I, needed_avg: f64, rng: &mut R) -> Option<CarIndex>
+where
+    I: Iterator<Item = (CarIndex, u32)> + Clone,
+    R: rand::Rng,
+{
+    let weight = |lap_time: u32| 1.0 / ((lap_time as f64 - needed_avg).abs() + 1.0);
+    let total: f64 = candidates
+        .clone()
+        .map(|(_, lap_time)| weight(lap_time))
+        .sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut remaining = rng.random::<f64>() * total;
+    let mut last = None;
+    for (idx, lap_time) in candidates {
+        remaining -= weight(lap_time);
+        if remaining < 0.0 {
+            return Some(idx);
+        }
+        last = Some(idx);
+    }
+    // Rounding can leave a sliver of the total unclaimed.
+    last
+}
+
 struct CandidateSelectionContext<'a, R> {
-    cars: &'a [Car],
     current_sum: u32,
     target: u32,
     remaining_needed: usize,
     rng: &'a mut R,
     total_backtracks: &'a mut u32,
+    events: &'a mut dyn EventSink,
 }
 
 fn select_candidate<R: rand::Rng>(
-    candidates_for_current_selection: &mut [CarIndex],
+    candidates: &SortedPool,
     context: CandidateSelectionContext<'_, R>,
 ) -> CarIndex {
     let CandidateSelectionContext {
-        cars,
         current_sum,
         target,
         remaining_needed,
         rng,
         total_backtracks,
+        events,
     } = context;
 
     let (min_possible_remaining, max_possible_remaining) =
-        calculate_min_max_sums(cars, candidates_for_current_selection, remaining_needed - 1);
+        candidates.min_max_sums(remaining_needed - 1);
 
     let min_valid = u64::from(target)
         .saturating_sub(u64::from(current_sum) + u64::from(max_possible_remaining))
@@ -1382,79 +1773,258 @@ fn select_candidate<R: rand::Rng>(
         min_valid, max_valid
     );
 
-    // Collect only the candidates that are inside the valid range once.
-    let filtered: Vec<CarIndex> = candidates_for_current_selection
-        .iter()
-        .copied()
-        .filter(|&idx| {
-            let t = get_lap_time(cars, idx);
-            t >= min_valid && t <= max_valid
-        })
-        .collect();
-
-    if !filtered.is_empty() {
-        let needed_avg = (target.saturating_sub(current_sum)) as f64 / (remaining_needed as f64);
-        debug!(
-            "Needed average for next number: {} ({}% of target)",
-            needed_avg,
-            (needed_avg / target as f64 * 100.0)
-        );
-
-        // Build weights paralleling `filtered` so indices match 1-to-1
-        let weights = filtered
-            .iter()
-            .map(|&idx| 1.0 / ((get_lap_time(cars, idx) as f64 - needed_avg).abs() + 1.0));
-
-        let dist = WeightedIndex::new(weights).expect("Non-empty filtered vec guarantees Ok");
+    let needed_avg = (target.saturating_sub(current_sum)) as f64 / (remaining_needed as f64);
+    debug!(
+        "Needed average for next number: {} ({}% of target)",
+        needed_avg,
+        (needed_avg / target as f64 * 100.0)
+    );
 
-        return filtered[dist.sample(rng)];
+    // Only the sorted window inside the valid range is visited.
+    if let Some(chosen) =
+        sample_by_closeness(candidates.in_range(min_valid, max_valid), needed_avg, rng)
+    {
+        return chosen;
     }
 
-    debug!("No valid candidates in range! Using fallback strategy");
+    *total_backtracks += 1;
 
-    let (chosen_temp, used_backtrack) = fallback_strategy(
-        cars,
-        candidates_for_current_selection,
-        current_sum,
-        target,
+    // Fall back to the car closest to the average still needed per selection.
+    // Previously selected cars are added to the candidate pool only when the
+    // unused pool cannot satisfy this selection. Never bypass that pool here.
+    let remaining_target = target.saturating_sub(current_sum);
+    let fallback = candidates
+        .closest(remaining_target / remaining_needed as u32)
+        .expect("selections are only made from non-empty candidate pools");
+    events.emit(SolverEvent::FallbackUsed {
+        car: fallback,
         remaining_needed,
-    );
-    if used_backtrack {
-        *total_backtracks += 1;
+    });
+    fallback
+}
+
+/// Lap-time prefix sums over an index list sorted by lap time ascending.
+///
+/// Built once per run, so the smallest and largest sum of any `x` candidates
+/// is answered in O(1) instead of re-summing slices in the selection loop.
+struct SortedLapSums {
+    /// `prefix[i]` is the total lap time of the first `i` sorted entries.
+    prefix: Vec<u64>,
+}
+
+impl SortedLapSums {
+    fn new(cars: &[Car], sorted_indexes: &[CarIndex]) -> Self {
+        let mut prefix = Vec::with_capacity(sorted_indexes.len() + 1);
+        let mut total = 0_u64;
+        prefix.push(total);
+        for &idx in sorted_indexes {
+            total = total.saturating_add(u64::from(get_lap_time(cars, idx)));
+            prefix.push(total);
+        }
+        Self { prefix }
+    }
+
+    fn len(&self) -> usize {
+        self.prefix.len() - 1
+    }
+
+    /// Returns the (min, max) sum of `x` entries, or (0, 0) when `x` is zero
+    /// or exceeds the number of entries. Sums saturate at `u32::MAX`.
+    fn min_max_sums(&self, x: usize) -> (u32, u32) {
+        let len = self.len();
+        if x == 0 || x > len {
+            return (0, 0);
+        }
+        let clamp = |sum: u64| sum.min(u64::from(u32::MAX)) as u32;
+        (
+            clamp(self.prefix[x]),
+            clamp(self.prefix[len] - self.prefix[len - x]),
+        )
     }
-    chosen_temp
 }
 
-fn calculate_min_max_sums(cars: &[Car], indexes: &[CarIndex], x: usize) -> (u32, u32) {
-    // Assuming 'indexes' is already sorted by lap_time ascending.
-    if x > indexes.len() {
-        return (0, 0);
+/// Candidate indexes sorted by lap time plus a bitmap of the cars already
+/// taken for the subset being built.
+///
+/// Taking a car only flips its bit, so the selection loop never clones or
+/// shifts the candidate list. The prefix sums over the full order stay valid
+/// and the few taken positions are skipped when answering range queries.
+struct SortedPool {
+    order: Vec<CarIndex>,
+    /// Lap times parallel to `order`, so binary searches avoid indirection.
+    lap_times: Vec<u32>,
+    sums: SortedLapSums,
+    taken: Vec<bool>,
+    /// Positions of taken entries, ascending. Bounded by the subset size.
+    taken_positions: Vec<usize>,
+}
+
+impl SortedPool {
+    fn new(cars: &[Car], indexes: &[CarIndex]) -> Self {
+        let mut order = indexes.to_vec();
+        order.sort_unstable_by_key(|&idx| (get_lap_time(cars, idx), idx));
+        order.dedup();
+        let lap_times = order.iter().map(|&idx| get_lap_time(cars, idx)).collect();
+        let sums = SortedLapSums::new(cars, &order);
+        let taken = vec![false; order.len()];
+        Self {
+            order,
+            lap_times,
+            sums,
+            taken,
+            taken_positions: Vec::new(),
+        }
     }
-    if indexes.is_empty() || x == 0 {
-        return (0, 0);
+
+    fn available_len(&self) -> usize {
+        self.order.len() - self.taken_positions.len()
+    }
+
+    /// Available cars, fastest first.
+    fn available(&self) -> impl Iterator<Item = CarIndex> + '_ {
+        self.order
+            .iter()
+            .zip(&self.taken)
+            .filter(|&(_, &taken)| !taken)
+            .map(|(&idx, _)| idx)
+    }
+
+    /// Returns the (min, max) sum of `x` available cars, or (0, 0) when `x`
+    /// is zero or exceeds the number of available cars.
+    ///
+    /// Each taken position inside the fastest (or slowest) `x` entries widens
+    /// that window by one, so the cost is bounded by the subset size.
+    fn min_max_sums(&self, x: usize) -> (u32, u32) {
+        if x == 0 || x > self.available_len() {
+            return (0, 0);
+        }
+        let prefix = &self.sums.prefix;
+        let len = self.order.len();
+
+        let (mut end, mut skipped_fast) = (x, 0_u64);
+        for &position in &self.taken_positions {
+            if position >= end {
+                break;
+            }
+            end += 1;
+            skipped_fast += u64::from(self.lap_times[position]);
+        }
+        let (mut start, mut skipped_slow) = (len - x, 0_u64);
+        for &position in self.taken_positions.iter().rev() {
+            if position < start {
+                break;
+            }
+            start -= 1;
+            skipped_slow += u64::from(self.lap_times[position]);
+        }
+
+        let clamp = |sum: u64| sum.min(u64::from(u32::MAX)) as u32;
+        (
+            clamp(prefix[end] - skipped_fast),
+            clamp(prefix[len] - prefix[start] - skipped_slow),
+        )
+    }
+
+    /// Available cars with a lap time in `min..=max`, fastest first, paired
+    /// with their lap times.
+    fn in_range(&self, min: u32, max: u32) -> impl Iterator<Item = (CarIndex, u32)> + Clone + '_ {
+        let start = self.lap_times.partition_point(|&time| time < min);
+        let end = self
+            .lap_times
+            .partition_point(|&time| time <= max)
+            .max(start);
+        (start..end)
+            .filter(|&position| !self.taken[position])
+            .map(|position| (self.order[position], self.lap_times[position]))
+    }
+
+    /// The available car whose lap time is closest to `lap_time`. Ties prefer
+    /// the faster car.
+    fn closest(&self, lap_time: u32) -> Option<CarIndex> {
+        let split = self.lap_times.partition_point(|&time| time < lap_time);
+        let below = (0..split).rev().find(|&position| !self.taken[position]);
+        let above = (split..self.order.len()).find(|&position| !self.taken[position]);
+        let position = match (below, above) {
+            (Some(below), Some(above)) => {
+                if lap_time - self.lap_times[below] <= self.lap_times[above] - lap_time {
+                    below
+                } else {
+                    above
+                }
+            }
+            (below, above) => below.or(above)?,
+        };
+        Some(self.order[position])
+    }
+
+    /// Marks `idx` as taken. Returns false if it is not an available entry.
+    fn take(&mut self, cars: &[Car], idx: CarIndex) -> bool {
+        let lap_time = get_lap_time(cars, idx);
+        let start = self.lap_times.partition_point(|&time| time < lap_time);
+        let Some(position) = (start..self.order.len())
+            .take_while(|&position| self.lap_times[position] == lap_time)
+            .find(|&position| self.order[position] == idx && !self.taken[position])
+        else {
+            return false;
+        };
+        self.taken[position] = true;
+        let at = self
+            .taken_positions
+            .partition_point(|&taken| taken < position);
+        self.taken_positions.insert(at, position);
+        true
     }
-    // For min sum, sum the first x lap times.
-    // For max sum, sum the last x lap times.
-    let sum = |values: &mut dyn Iterator<Item = CarIndex>| {
-        values
-            .map(|idx| u64::from(get_lap_time(cars, idx)))
-            .try_fold(0_u64, u64::checked_add)
-            .unwrap_or(u64::MAX)
-            .min(u64::from(u32::MAX)) as u32
-    };
-    let min_sum = sum(&mut indexes.iter().copied().take(x));
-    let max_sum = sum(&mut indexes.iter().rev().copied().take(x));
-    (min_sum, max_sum)
 }
 
 /// Returns the minimum and maximum possible target sum for a given subset size and car list.
+/// Disabled cars are left out.
 pub fn get_target_range_for_subset(cars: &[Car], lap_count: usize) -> (u32, u32) {
-    if cars.is_empty() || lap_count == 0 {
+    let indexes = enabled_by_lap_time(cars);
+    if indexes.is_empty() || lap_count == 0 {
         return (0, 0);
     }
-    let mut indexes: Vec<CarIndex> = (0..cars.len()).collect();
+    SortedLapSums::new(cars, &indexes).min_max_sums(lap_count)
+}
+
+/// Indexes of the enabled cars, fastest first.
+fn enabled_by_lap_time(cars: &[Car]) -> Vec<CarIndex> {
+    let mut indexes = (0..cars.len())
+        .filter(|&index| cars[index].enabled)
+        .collect::<Vec<_>>();
     indexes.sort_by_key(|&idx| get_lap_time(cars, idx));
-    calculate_min_max_sums(cars, &indexes, lap_count)
+    indexes
+}
+
+/// Returns the (min, max) target that `player_count` subsets of `lap_count`
+/// cars can all reach.
+///
+/// Runs that draw from unused cars until fewer than `lap_count` remain (see
+/// [`SolverStrategy::disjoint_runs`]) make the first
+/// `min(player_count, cars.len() / lap_count)` subsets disjoint.
+/// Their combined total must lie between the smallest and largest sum of that
+/// many cars, which bounds the average subset, and therefore the shared
+/// target, more tightly than [`get_target_range_for_subset`] does once
+/// several players are involved. With one player the two ranges agree.
+/// Disabled cars are left out.
+pub fn get_target_range_for_players(
+    cars: &[Car],
+    lap_count: usize,
+    player_count: usize,
+) -> (u32, u32) {
+    let indexes = enabled_by_lap_time(cars);
+    if indexes.is_empty() || lap_count == 0 || lap_count > indexes.len() {
+        return get_target_range_for_subset(cars, lap_count);
+    }
+    let disjoint_runs = player_count.min(indexes.len() / lap_count).max(1);
+    let drawn = disjoint_runs * lap_count;
+    let sums = SortedLapSums::new(cars, &indexes);
+    let len = sums.len();
+    let runs = disjoint_runs as u64;
+    let clamp = |sum: u64| sum.min(u64::from(u32::MAX)) as u32;
+    (
+        clamp(sums.prefix[drawn].div_ceil(runs)),
+        clamp((sums.prefix[len] - sums.prefix[len - drawn]) / runs),
+    )
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -1463,7 +2033,12 @@ pub enum CsvImportWarningKind {
     EmptyId,
     MissingLapTime,
     InvalidLapTime,
+    InvalidUncertainty,
+    InvalidEnabled,
     DuplicateId,
+    /// No row names every column requested by header. Reported for row 0,
+    /// as it concerns the whole file.
+    MissingHeader,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -1481,18 +2056,309 @@ pub struct CsvImportReport {
     pub row_count: usize,
     pub accepted_count: usize,
     pub rejected_count: usize,
+    /// Rows folded into an earlier car by [`DuplicateIdPolicy`].
+    pub merged_count: usize,
+    /// One-based record number of the header row, when columns were named.
+    pub header_row: Option<usize>,
+}
+
+/// A CSV column, by zero-based position or by the name in its header cell.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvColumn {
+    Index(usize),
+    /// Matched against header cells ignoring case and surrounding spaces.
+    Header(String),
+}
+
+impl CsvColumn {
+    fn header(&self) -> Option<&str> {
+        match self {
+            CsvColumn::Index(_) => None,
+            CsvColumn::Header(name) => Some(name),
+        }
+    }
+}
+
+/// How several lap-time columns of a row combine into its lap time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LapTimeAggregate {
+    #[default]
+    Min,
+    /// Rounded to the nearest millisecond.
+    Mean,
+    /// The mean of the two middle times for an even count.
+    Median,
+}
+
+impl LapTimeAggregate {
+    /// Combines non-empty `lap_times`.
+    fn apply(self, lap_times: &mut [u32]) -> u32 {
+        let count = lap_times.len() as u64;
+        match self {
+            LapTimeAggregate::Min => lap_times.iter().copied().min().unwrap_or(0),
+            LapTimeAggregate::Mean => {
+                let sum = lap_times.iter().map(|&time| u64::from(time)).sum::<u64>();
+                ((sum + count / 2) / count) as u32
+            }
+            LapTimeAggregate::Median => {
+                lap_times.sort_unstable();
+                let upper = lap_times[lap_times.len() / 2];
+                if count % 2 == 1 {
+                    upper
+                } else {
+                    let lower = lap_times[lap_times.len() / 2 - 1];
+                    ((u64::from(lower) + u64::from(upper)).div_ceil(2)) as u32
+                }
+            }
+        }
+    }
+}
+
+/// What an import does with a row whose id an earlier row already has.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateIdPolicy {
+    /// Keep the first row and report the others as
+    /// [`CsvImportWarningKind::DuplicateId`].
+    #[default]
+    Skip,
+    /// Keep the row with the fastest lap time.
+    KeepFastest,
+    /// Keep the row with the slowest lap time.
+    KeepSlowest,
+    /// Keep one car at the mean lap time of all rows, rounded to the
+    /// nearest millisecond, with the first row's uncertainty.
+    Average,
+}
+
+/// Where [`read_cars_from_csv_string_with_options`] finds each field.
+///
+/// When any column is named, the first row that contains every named column
+/// is taken as the header and data starts on the row after it, so preamble
+/// lines of an export need not be removed. Rows may then differ in length,
+/// as they may when `data_start_line` skips a preamble.
+///
+/// With `extra_lap_time_columns`, such as the times of earlier sessions,
+/// a row's lap time is the `lap_time_aggregate` of every one of its
+/// lap-time cells that is not blank.
+///
+/// The name, class, and track columns fill the [`Car`] fields of the same
+/// names; blank cells leave them unset. The enabled column takes `yes`/`no`,
+/// `true`/`false`, `on`/`off`, or `1`/`0`, and a blank cell enables the car.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CsvImportOptions {
+    pub id_column: CsvColumn,
+    pub lap_time_column: CsvColumn,
+    #[serde(default)]
+    pub extra_lap_time_columns: Vec<CsvColumn>,
+    #[serde(default)]
+    pub lap_time_aggregate: LapTimeAggregate,
+    pub uncertainty_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub name_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub class_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub track_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub enabled_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub duplicate_ids: DuplicateIdPolicy,
+    pub delimiter: u8,
+    /// One-based line of the first row to read; earlier lines are neither
+    /// read nor counted.
+    pub data_start_line: u64,
+}
+
+impl Default for CsvImportOptions {
+    /// Id, lap time, and uncertainty in the first three columns, without a
+    /// header.
+    fn default() -> Self {
+        Self {
+            id_column: CsvColumn::Index(0),
+            lap_time_column: CsvColumn::Index(1),
+            extra_lap_time_columns: Vec::new(),
+            lap_time_aggregate: LapTimeAggregate::default(),
+            uncertainty_column: Some(CsvColumn::Index(2)),
+            name_column: None,
+            class_column: None,
+            track_column: None,
+            enabled_column: None,
+            duplicate_ids: DuplicateIdPolicy::default(),
+            delimiter: b',',
+            data_start_line: 1,
+        }
+    }
+}
+
+impl CsvImportOptions {
+    /// Columns named by header, such as `"Driver"` and `"Best Lap"`.
+    pub fn by_header(id: &str, lap_time: &str) -> Self {
+        Self {
+            id_column: CsvColumn::Header(id.to_string()),
+            lap_time_column: CsvColumn::Header(lap_time.to_string()),
+            uncertainty_column: None,
+            ..Self::default()
+        }
+    }
+
+    /// Columns named by header with the lap time aggregated over several,
+    /// such as `["Session 1", "Session 2", "Session 3"]`.
+    ///
+    /// # Panics
+    ///
+    /// If `lap_times` is empty.
+    pub fn by_headers(id: &str, lap_times: &[&str], aggregate: LapTimeAggregate) -> Self {
+        let (first, extra) = lap_times
+            .split_first()
+            .expect("at least one lap-time column");
+        Self {
+            extra_lap_time_columns: extra
+                .iter()
+                .map(|name| CsvColumn::Header(name.to_string()))
+                .collect(),
+            lap_time_aggregate: aggregate,
+            ..Self::by_header(id, first)
+        }
+    }
+
+    fn columns(&self) -> impl Iterator<Item = &CsvColumn> {
+        [&self.id_column, &self.lap_time_column]
+            .into_iter()
+            .chain(&self.extra_lap_time_columns)
+            .chain(&self.uncertainty_column)
+            .chain(&self.name_column)
+            .chain(&self.class_column)
+            .chain(&self.track_column)
+            .chain(&self.enabled_column)
+    }
+}
+
+/// Positions of the id, lap-time, uncertainty, name, class, track, and
+/// enabled columns, then of the extra lap-time columns, with named columns
+/// looked up in `header`; `None` unless it names all of them.
+fn column_positions(
+    options: &CsvImportOptions,
+    header: &csv::StringRecord,
+) -> Option<([Option<usize>; 7], Vec<usize>)> {
+    let position = |column: &CsvColumn| match column {
+        CsvColumn::Index(index) => Some(*index),
+        CsvColumn::Header(name) => header.iter().position(|cell| {
+            cell.trim_start_matches('\u{feff}')
+                .trim()
+                .eq_ignore_ascii_case(name.trim())
+        }),
+    };
+    let optional = |column: &Option<CsvColumn>| match column {
+        Some(column) => position(column).map(Some),
+        None => Some(None),
+    };
+    Some((
+        [
+            Some(position(&options.id_column)?),
+            Some(position(&options.lap_time_column)?),
+            optional(&options.uncertainty_column)?,
+            optional(&options.name_column)?,
+            optional(&options.class_column)?,
+            optional(&options.track_column)?,
+            optional(&options.enabled_column)?,
+        ],
+        options
+            .extra_lap_time_columns
+            .iter()
+            .map(position)
+            .collect::<Option<_>>()?,
+    ))
+}
+
+/// One-based line on which a record of `csv_content` starts. The reader
+/// reports a record at the blank lines skipped before it, so they are
+/// skipped here too.
+fn record_line(csv_content: &str, result: &csv::Result<csv::StringRecord>) -> Option<u64> {
+    let position = match result {
+        Ok(record) => record.position(),
+        Err(error) => error.position(),
+    }?;
+    let start = usize::try_from(position.byte())
+        .ok()?
+        .min(csv_content.len());
+    let blank_lines = csv_content[start..]
+        .bytes()
+        .take_while(|&byte| byte == b'\n' || byte == b'\r')
+        .filter(|&byte| byte == b'\n')
+        .count();
+    Some(position.line() + blank_lines as u64)
+}
+
+fn field(record: &csv::StringRecord, column: Option<usize>) -> Option<&str> {
+    record.get(column?)
 }
 
 pub fn read_cars_from_csv_string_detailed(csv_content: &str) -> CsvImportReport {
+    read_cars_from_csv_string_with_options(csv_content, &CsvImportOptions::default())
+}
+
+/// Reads cars from the columns described by `options`.
+pub fn read_cars_from_csv_string_with_options(
+    csv_content: &str,
+    options: &CsvImportOptions,
+) -> CsvImportReport {
     let mut cars = Vec::new();
     let mut warnings = Vec::new();
-    let mut seen_ids = HashSet::new();
+    // Index of each accepted id's car, and the sum and count of its lap times.
+    let mut seen_ids = HashMap::new();
+    let mut lap_time_sums: Vec<(u64, u64)> = Vec::new();
+    let mut merged_count = 0;
     let mut row_count = 0;
+    let named = options.columns().any(|column| column.header().is_some());
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(false)
+        .delimiter(options.delimiter)
+        .flexible(named || options.data_start_line > 1)
         .from_reader(csv_content.as_bytes());
+    let mut records = reader.records().enumerate().filter(|(_, result)| {
+        record_line(csv_content, result).is_none_or(|line| line >= options.data_start_line)
+    });
+
+    let header = if named {
+        records.by_ref().find_map(|(i, result)| {
+            let record = result.ok()?;
+            Some((Some(i + 1), column_positions(options, &record)?))
+        })
+    } else {
+        column_positions(options, &csv::StringRecord::new()).map(|positions| (None, positions))
+    };
+    let Some((
+        header_row,
+        (
+            [id_column, lap_time_column, uncertainty_column, name_column, class_column, track_column, enabled_column],
+            extra_lap_time_columns,
+        ),
+    )) = header
+    else {
+        let names = options
+            .columns()
+            .filter_map(CsvColumn::header)
+            .collect::<Vec<_>>()
+            .join("', '");
+        return CsvImportReport {
+            cars,
+            warnings: vec![CsvImportWarning {
+                row: 0,
+                kind: CsvImportWarningKind::MissingHeader,
+                message: format!("no header row names '{names}'"),
+            }],
+            row_count: 0,
+            accepted_count: 0,
+            rejected_count: 0,
+            merged_count: 0,
+            header_row: None,
+        };
+    };
 
-    for (i, result) in reader.records().enumerate() {
+    for (i, result) in records {
         let row = i + 1;
         row_count += 1;
         let record = match result {
@@ -1506,7 +2372,10 @@ pub fn read_cars_from_csv_string_detailed(csv_content: &str) -> CsvImportReport
                 continue;
             }
         };
-        let id = record.get(0).unwrap_or_default().trim().to_string();
+        let id = field(&record, id_column)
+            .unwrap_or_default()
+            .trim()
+            .to_string();
         if id.is_empty() {
             warnings.push(CsvImportWarning {
                 row,
@@ -1515,16 +2384,28 @@ pub fn read_cars_from_csv_string_detailed(csv_content: &str) -> CsvImportReport
             });
             continue;
         }
-        let Some(time_str) = record.get(1).map(str::trim) else {
+        // A single lap-time column must hold a time; of several, blank cells
+        // such as a missed session are left out.
+        let aggregated = !extra_lap_time_columns.is_empty();
+        let time_strs = std::iter::once(lap_time_column)
+            .chain(extra_lap_time_columns.iter().copied().map(Some))
+            .filter_map(|column| field(&record, column).map(str::trim))
+            .filter(|time_str| !aggregated || !time_str.is_empty())
+            .collect::<Vec<_>>();
+        if time_strs.is_empty() {
             warnings.push(CsvImportWarning {
                 row,
                 kind: CsvImportWarningKind::MissingLapTime,
                 message: format!("missing lap time for ID '{id}'"),
             });
             continue;
-        };
-        let lap_time = match parse_lap_time(time_str) {
-            Ok(time) => time,
+        }
+        let lap_times = time_strs
+            .into_iter()
+            .map(parse_lap_time)
+            .collect::<Result<Vec<_>, _>>();
+        let lap_time = match lap_times {
+            Ok(mut times) => options.lap_time_aggregate.apply(&mut times),
             Err(message) => {
                 warnings.push(CsvImportWarning {
                     row,
@@ -1534,16 +2415,84 @@ pub fn read_cars_from_csv_string_detailed(csv_content: &str) -> CsvImportReport
                 continue;
             }
         };
+        let uncertainty_ms = match field(&record, uncertainty_column).map(str::trim) {
+            None | Some("") => None,
+            Some(uncertainty) => match parse_uncertainty(uncertainty) {
+                Ok(uncertainty) => Some(uncertainty),
+                Err(message) => {
+                    warnings.push(CsvImportWarning {
+                        row,
+                        kind: CsvImportWarningKind::InvalidUncertainty,
+                        message,
+                    });
+                    continue;
+                }
+            },
+        };
+        let enabled = match field(&record, enabled_column).map(str::trim) {
+            None | Some("") => true,
+            Some(enabled) => match parse_enabled(enabled) {
+                Ok(enabled) => enabled,
+                Err(message) => {
+                    warnings.push(CsvImportWarning {
+                        row,
+                        kind: CsvImportWarningKind::InvalidEnabled,
+                        message,
+                    });
+                    continue;
+                }
+            },
+        };
         // Only accepted rows reserve an ID, so an invalid row cannot suppress a later valid one.
-        if !seen_ids.insert(id.clone()) {
-            warnings.push(CsvImportWarning {
-                row,
-                kind: CsvImportWarningKind::DuplicateId,
-                message: format!("duplicate ID '{id}'"),
-            });
+        if let Some(&index) = seen_ids.get(&id) {
+            let car: &mut Car = &mut cars[index];
+            let replace = match options.duplicate_ids {
+                DuplicateIdPolicy::Skip => {
+                    warnings.push(CsvImportWarning {
+                        row,
+                        kind: CsvImportWarningKind::DuplicateId,
+                        message: format!("duplicate ID '{id}'"),
+                    });
+                    continue;
+                }
+                DuplicateIdPolicy::KeepFastest => lap_time < car.lap_time,
+                DuplicateIdPolicy::KeepSlowest => lap_time > car.lap_time,
+                DuplicateIdPolicy::Average => {
+                    let (sum, count) = &mut lap_time_sums[index];
+                    *sum += u64::from(lap_time);
+                    *count += 1;
+                    false
+                }
+            };
+            if replace {
+                car.lap_time = lap_time;
+                car.uncertainty_ms = uncertainty_ms;
+            }
+            merged_count += 1;
             continue;
         }
-        cars.push(Car { id, lap_time });
+        let metadata = |column| {
+            field(&record, column)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        seen_ids.insert(id.clone(), cars.len());
+        lap_time_sums.push((u64::from(lap_time), 1));
+        cars.push(Car {
+            id,
+            lap_time,
+            uncertainty_ms,
+            name: metadata(name_column),
+            class: metadata(class_column),
+            track: metadata(track_column),
+            enabled,
+        });
+    }
+    if options.duplicate_ids == DuplicateIdPolicy::Average {
+        for (car, (sum, count)) in cars.iter_mut().zip(lap_time_sums) {
+            car.lap_time = ((sum + count / 2) / count) as u32;
+        }
     }
 
     let accepted_count = cars.len();
@@ -1552,14 +2501,135 @@ pub fn read_cars_from_csv_string_detailed(csv_content: &str) -> CsvImportReport
         warnings,
         row_count,
         accepted_count,
-        rejected_count: row_count - accepted_count,
+        rejected_count: row_count - accepted_count - merged_count,
+        merged_count,
+        header_row,
+    }
+}
+
+/// Delimiter and first data line of a CSV file, as found by
+/// [`sniff_csv_layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CsvLayout {
+    pub delimiter: u8,
+    /// One-based line of the last row above the data that holds no car,
+    /// taken as the header.
+    pub header_line: Option<u64>,
+    /// One-based line of the first row holding a car.
+    pub data_start_line: u64,
+}
+
+impl Default for CsvLayout {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header_line: None,
+            data_start_line: 1,
+        }
+    }
+}
+
+impl CsvLayout {
+    /// Options reading the default columns with this layout.
+    pub fn options(&self) -> CsvImportOptions {
+        CsvImportOptions {
+            delimiter: self.delimiter,
+            data_start_line: self.data_start_line,
+            ..CsvImportOptions::default()
+        }
+    }
+
+    /// A short summary such as "semicolon-separated, data starts at line 5".
+    pub fn describe(&self) -> String {
+        let delimiter = match self.delimiter {
+            b',' => "comma".to_string(),
+            b';' => "semicolon".to_string(),
+            b'\t' => "tab".to_string(),
+            b'|' => "pipe".to_string(),
+            other => format!("'{}'", char::from(other)),
+        };
+        format!(
+            "{delimiter}-separated, data starts at line {}",
+            self.data_start_line
+        )
+    }
+}
+
+const SNIFFED_DELIMITERS: [u8; 4] = *b",;\t|";
+
+/// Detects the delimiter and the first data line of `csv_content`.
+///
+/// Each candidate delimiter is tried, and the one that yields the most rows
+/// with an id and a lap time in the first two columns wins, the comma on a
+/// tie. Data starts at the first such row; a row above it is taken as the
+/// header. Content without any car row gets the default layout.
+pub fn sniff_csv_layout(csv_content: &str) -> CsvLayout {
+    let mut best: Option<(usize, CsvLayout)> = None;
+    for delimiter in SNIFFED_DELIMITERS {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(csv_content.as_bytes());
+        let mut previous_line = None;
+        let mut first_data = None;
+        let mut car_rows = 0;
+        for result in reader.records() {
+            let line = record_line(csv_content, &result).unwrap_or(1);
+            let Ok(record) = result else {
+                continue;
+            };
+            // Another candidate inside the id suggests it is the real
+            // delimiter, as in `Ann;1:32,456` split at the decimal comma.
+            // The delimiter itself can only appear quoted.
+            let is_car = record.len() >= 2
+                && !record[0].trim().is_empty()
+                && !record[0]
+                    .bytes()
+                    .any(|byte| byte != delimiter && SNIFFED_DELIMITERS.contains(&byte))
+                && parse_lap_time(record[1].trim()).is_ok();
+            if !is_car {
+                previous_line = Some(line);
+                continue;
+            }
+            car_rows += 1;
+            first_data.get_or_insert(CsvLayout {
+                delimiter,
+                header_line: previous_line,
+                data_start_line: line,
+            });
+        }
+        if let Some(layout) = first_data {
+            if best.as_ref().is_none_or(|(rows, _)| car_rows > *rows) {
+                best = Some((car_rows, layout));
+            }
+        }
     }
+    best.map(|(_, layout)| layout).unwrap_or_default()
 }
 
+/// Reads cars with the layout found by [`sniff_csv_layout`]. A header above
+/// the data naming `name`, `class`, `track`, or `enabled` columns, as
+/// [`write_cars_to_csv`] writes, fills those fields too.
 pub fn read_cars_from_csv_string(
     csv_content: &str,
 ) -> Result<Vec<Car>, Box<dyn std::error::Error>> {
-    let report = read_cars_from_csv_string_detailed(csv_content);
+    let layout = sniff_csv_layout(csv_content);
+    debug!("Detected CSV layout: {}", layout.describe());
+    let mut options = layout.options();
+    if let Some(header) = header_record(csv_content, &layout) {
+        for (index, cell) in header.iter().enumerate() {
+            let column = match cell.trim().to_ascii_lowercase().as_str() {
+                "name" => &mut options.name_column,
+                "class" => &mut options.class_column,
+                "track" => &mut options.track_column,
+                "enabled" => &mut options.enabled_column,
+                _ => continue,
+            };
+            *column = Some(CsvColumn::Index(index));
+        }
+    }
+    let report = read_cars_from_csv_string_with_options(csv_content, &options);
     for warning in &report.warnings {
         debug!("CSV row {}: {}", warning.row, warning.message);
     }
@@ -1570,101 +2640,419 @@ pub fn read_cars_from_csv_string(
     Ok(report.cars)
 }
 
-fn parse_lap_time(time_str: &str) -> Result<u32, String> {
-    // Split by colon first (minutes:rest)
-    let parts: Vec<&str> = time_str.split(':').collect();
+/// The row at `layout.header_line`, if any.
+fn header_record(csv_content: &str, layout: &CsvLayout) -> Option<csv::StringRecord> {
+    let header_line = layout.header_line?;
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(layout.delimiter)
+        .flexible(true)
+        .from_reader(csv_content.as_bytes())
+        .records()
+        .find(|result| record_line(csv_content, result) == Some(header_line))?
+        .ok()
+}
+
+/// Parses an uncertainty in whole milliseconds, optionally written as
+/// `±250` or `+-250`.
+fn parse_uncertainty(uncertainty: &str) -> Result<u32, String> {
+    let digits = uncertainty
+        .strip_prefix('±')
+        .or_else(|| uncertainty.strip_prefix("+-"))
+        .unwrap_or(uncertainty)
+        .trim();
+    digits.parse::<u32>().map_err(|_| {
+        format!(
+            "Invalid uncertainty: '{}', expected milliseconds such as ±250",
+            uncertainty
+        )
+    })
+}
 
-    if parts.len() != 2 {
-        return Err(format!(
-            "Invalid lap time format: '{}', expected MM:SS.mmm",
-            time_str
-        ));
+/// Parses an enabled flag such as `yes`, `false`, or `1`, ignoring case.
+fn parse_enabled(enabled: &str) -> Result<bool, String> {
+    match enabled.to_ascii_lowercase().as_str() {
+        "yes" | "y" | "true" | "on" | "1" => Ok(true),
+        "no" | "n" | "false" | "off" | "0" => Ok(false),
+        _ => Err(format!(
+            "Invalid enabled flag: '{}', expected yes or no",
+            enabled
+        )),
     }
+}
 
-    // Parse minutes
-    let minutes = match parts[0].trim().parse::<u32>() {
-        Ok(min) => min,
-        Err(_) => return Err(format!("Failed to parse minutes part: '{}'", parts[0])),
-    };
+/// A lap time in JSON: milliseconds, or a string in any format
+/// [`parse_lap_time`] accepts.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum JsonLapTime {
+    Milliseconds(u32),
+    Formatted(String),
+}
 
-    // Split the second part by dot (seconds with optional milliseconds).
-    let sec_parts: Vec<&str> = parts[1].split('.').collect();
-    if sec_parts.len() > 2 {
-        return Err(format!(
-            "Invalid seconds format: '{}', expected SS or SS.mmm",
-            parts[1]
-        ));
-    }
+#[derive(serde::Deserialize)]
+struct JsonCar {
+    id: String,
+    #[serde(alias = "lapTime")]
+    lap_time: JsonLapTime,
+    #[serde(default, alias = "uncertaintyMs")]
+    uncertainty_ms: Option<u32>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    class: Option<String>,
+    #[serde(default)]
+    track: Option<String>,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+}
 
-    // Parse seconds
-    let seconds = match sec_parts[0].trim().parse::<u32>() {
-        Ok(sec) => sec,
-        Err(_) => return Err(format!("Failed to parse seconds part: '{}'", sec_parts[0])),
-    };
+/// Reads cars from a JSON array of objects with an `id` and a `lap_time`,
+/// given in milliseconds or as a formatted time such as `"1:32.456"`, and
+/// optionally an `uncertainty_ms`, `name`, `class`, `track`, and `enabled`.
+/// Other fields are ignored.
+///
+/// Unlike the CSV readers, which skip bad rows, any invalid or duplicate car
+/// fails the whole import, naming the offending entry by its zero-based
+/// position.
+pub fn read_cars_from_json(json: &str) -> Result<Vec<Car>, String> {
+    let entries: Vec<JsonCar> =
+        serde_json::from_str(json).map_err(|error| format!("Invalid car list: {error}"))?;
+    let mut seen_ids = HashSet::new();
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(position, entry)| {
+            let id = entry.id.trim().to_string();
+            if id.is_empty() {
+                return Err(format!("Car {position}: vehicle ID is empty"));
+            }
+            if !seen_ids.insert(id.clone()) {
+                return Err(format!("Car {position}: duplicate ID '{id}'"));
+            }
+            let lap_time = match entry.lap_time {
+                JsonLapTime::Milliseconds(lap_time) => lap_time,
+                JsonLapTime::Formatted(text) => {
+                    parse_lap_time(&text).map_err(|error| format!("Car {position}: {error}"))?
+                }
+            };
+            Ok(Car {
+                id,
+                lap_time,
+                uncertainty_ms: entry.uncertainty_ms,
+                name: entry.name,
+                class: entry.class,
+                track: entry.track,
+                enabled: entry.enabled,
+            })
+        })
+        .collect()
+}
 
-    // Validate seconds are in range 0-59
-    if seconds > 59 {
-        return Err(format!("Seconds must be between 0 and 59, got {}", seconds));
+/// Reads cars from CSV or JSON bytes, gzipped or not, the way a pasted car
+/// list is read: a JSON array, an iRacing result export, or a CSV car list
+/// in the layout [`sniff_csv_layout`] finds. Returns the cars and a short
+/// description of the format found, such as "gzip, JSON".
+pub fn read_cars_from_bytes(bytes: &[u8]) -> Result<(Vec<Car>, String), String> {
+    let text = compression::decode_text(bytes)?;
+    let mut format = if compression::is_gzip(bytes) {
+        "gzip, ".to_string()
+    } else {
+        String::new()
+    };
+    if text.trim_start().starts_with('[') {
+        format.push_str("JSON");
+        return Ok((read_cars_from_json(&text)?, format));
     }
+    let iracing = iracing::read_iracing_results_csv(&text);
+    if iracing.header_row.is_some() {
+        format.push_str("iRacing results");
+        return Ok((iracing.cars, format));
+    }
+    format.push_str(&sniff_csv_layout(&text).describe());
+    let cars = read_cars_from_csv_string(&text).map_err(|error| error.to_string())?;
+    Ok((cars, format))
+}
 
-    // Parse optional milliseconds; whole-second values default to zero.
-    let milliseconds_str = sec_parts.get(1).copied().unwrap_or("0").trim();
-    if milliseconds_str.is_empty() || milliseconds_str.len() > 3 {
-        return Err(format!(
-            "Milliseconds must contain between 1 and 3 digits, got '{}'",
-            milliseconds_str
-        ));
+/// Header [`write_cars_to_csv`] writes above cars with metadata.
+const CAR_CSV_HEADER: [&str; 7] = [
+    "id",
+    "lap_time",
+    "uncertainty_ms",
+    "name",
+    "class",
+    "track",
+    "enabled",
+];
+
+/// Writes `cars` in the layout of the bundled car list: one headerless row
+/// per car with its id and a lap time from [`format_ms_to_minsecms`], plus a
+/// third column of uncertainties when any car has one. When any car has a
+/// name, class, or track, or is disabled, a header row comes first and those
+/// fields follow in columns of their own. The result reads back through
+/// [`read_cars_from_csv_string`] to the same cars.
+pub fn write_cars_to_csv(cars: &[Car]) -> String {
+    let with_metadata = cars.iter().any(|car| {
+        !car.enabled || car.name.is_some() || car.class.is_some() || car.track.is_some()
+    });
+    let with_uncertainty = with_metadata || cars.iter().any(|car| car.uncertainty_ms.is_some());
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    if with_metadata {
+        writer
+            .write_record(CAR_CSV_HEADER)
+            .expect("writing CSV to memory cannot fail");
     }
-    let mut milliseconds = match milliseconds_str.parse::<u64>() {
-        Ok(ms) => ms,
-        Err(_) => {
-            return Err(format!(
-                "Failed to parse milliseconds part: '{}'",
-                milliseconds_str
-            ));
+    for car in cars {
+        let mut record = vec![car.id.clone(), format_ms_to_minsecms(car.lap_time)];
+        if with_uncertainty {
+            record.push(
+                car.uncertainty_ms
+                    .map(|uncertainty| uncertainty.to_string())
+                    .unwrap_or_default(),
+            );
         }
-    };
-
-    // Adjust milliseconds based on number of digits
-    if milliseconds_str.len() == 1 {
-        milliseconds *= 100; // e.g., "4" → 400ms
-    } else if milliseconds_str.len() == 2 {
-        milliseconds *= 10; // e.g., "43" → 430ms
+        if with_metadata {
+            for field in [&car.name, &car.class, &car.track] {
+                record.push(field.clone().unwrap_or_default());
+            }
+            record.push(if car.enabled { "yes" } else { "no" }.to_string());
+        }
+        writer
+            .write_record(record)
+            .expect("writing CSV to memory cannot fail");
     }
-
-    // Convert to total milliseconds
-    let total_ms = u64::from(minutes)
-        .checked_mul(60_000)
-        .and_then(|value| value.checked_add(u64::from(seconds) * 1_000))
-        .and_then(|value| value.checked_add(milliseconds))
-        .ok_or_else(|| format!("Lap time is too large: '{time_str}'"))?;
-
-    u32::try_from(total_ms).map_err(|_| format!("Lap time is too large: '{time_str}'"))
+    let bytes = writer
+        .into_inner()
+        .expect("flushing CSV to memory cannot fail");
+    String::from_utf8(bytes).expect("CSV written from strings is UTF-8")
 }
 
-pub fn format_ms_to_minsecms(ms: u32) -> String {
-    let total_seconds = ms / 1000;
-    let minutes = total_seconds / 60;
-    let seconds = total_seconds % 60;
-    let milliseconds = ms % 1000;
-    format!("{:02}:{:02}.{:03}", minutes, seconds, milliseconds)
+/// Writes `results` as CSV with a header and one row per subset: its number,
+/// total, deviation from `target` in percent, then every car's id and lap
+/// time. Times use [`format_ms_to_minsecms`], so the lap columns can be read
+/// back by [`read_cars_from_csv_string`]. Indexes outside `cars` are
+/// skipped, and shorter rows are padded with empty cells.
+pub fn export_results_csv(cars: &[Car], results: &[Vec<CarIndex>], target: u32) -> String {
+    export_named_results_csv(cars, results, target, &[])
 }
 
-pub fn compute_jaccard_similarity(results: &[Vec<CarIndex>]) -> Result<f64, String> {
-    if results.len() < 2 {
-        info!("Only one or no valid subsets found, skipping similarity measurement");
-        return Err(
-            "Only one or no valid subsets found, skipping similarity measurement".to_string(),
-        );
+/// [`export_results_csv`] with a `player` column after the set number,
+/// naming the player of each subset in order. Subsets beyond `players` get
+/// an empty name; without players the column is left out.
+pub fn export_named_results_csv(
+    cars: &[Car],
+    results: &[Vec<CarIndex>],
+    target: u32,
+    players: &[String],
+) -> String {
+    let rows = results
+        .iter()
+        .map(|subset| {
+            subset
+                .iter()
+                .filter_map(|&index| cars.get(index))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let named = !players.is_empty();
+    let mut header = vec!["set".to_string()];
+    if named {
+        header.push("player".to_string());
     }
-    if results[1..].iter().all(|subset| subset == &results[0]) {
-        return Ok(1.0);
+    header.extend(["total".to_string(), "deviation_percent".to_string()]);
+    let leading = header.len();
+    for position in 1..=width {
+        header.push(format!("car_{position}"));
+        header.push(format!("lap_time_{position}"));
     }
-
-    // Compress the arbitrary index values before making dense bitsets. This avoids sizing an
-    // allocation from the largest index (which may be usize::MAX) while retaining exact set
-    // semantics for duplicates.
-    let mut indices: Vec<CarIndex> = results.iter().flatten().copied().collect();
+    let mut records = vec![header];
+    for (set, row) in rows.iter().enumerate() {
+        let total = row
+            .iter()
+            .map(|car| car.lap_time)
+            .fold(0_u32, u32::saturating_add);
+        let mut record = vec![(set + 1).to_string()];
+        if named {
+            record.push(players.get(set).cloned().unwrap_or_default());
+        }
+        record.push(format_ms_to_minsecms(total));
+        record.push(format!("{:.3}", accuracy_percent(total, target) - 100.0));
+        for car in row {
+            record.push(car.id.clone());
+            record.push(format_ms_to_minsecms(car.lap_time));
+        }
+        record.resize(leading + 2 * width, String::new());
+        records.push(record);
+    }
+    for record in records {
+        writer
+            .write_record(record)
+            .expect("writing CSV to memory cannot fail");
+    }
+    let bytes = writer
+        .into_inner()
+        .expect("flushing CSV to memory cannot fail");
+    String::from_utf8(bytes).expect("CSV written from strings is UTF-8")
+}
+
+/// Formats one subset for pasting into chat as Markdown that also reads as
+/// plain text: `label` with the total and its deviation from `target` in
+/// percent, then one line per car with its id and lap time. Indexes outside
+/// `cars` are skipped.
+pub fn format_subset_markdown(
+    cars: &[Car],
+    subset: &[CarIndex],
+    target: u32,
+    label: &str,
+) -> String {
+    let cars = subset
+        .iter()
+        .filter_map(|&index| cars.get(index))
+        .collect::<Vec<_>>();
+    let total = cars
+        .iter()
+        .map(|car| car.lap_time)
+        .fold(0_u32, u32::saturating_add);
+    let mut text = format!(
+        "**{label}** · {} ({:+.2}%)\n",
+        format_ms_to_minsecms(total),
+        accuracy_percent(total, target) - 100.0
+    );
+    for car in cars {
+        text.push_str(&format!(
+            "- {} · {}\n",
+            car.id,
+            format_ms_to_minsecms(car.lap_time)
+        ));
+    }
+    text
+}
+
+/// Parses a lap time to milliseconds. Accepted forms are `M:SS`,
+/// `H:MM:SS`, seconds written as `SS.mmm` or `SSs`, minutes and seconds
+/// written `2m 30s`, and raw milliseconds such as `150000`. Seconds and
+/// colon forms take a fraction of one to three digits, after a decimal point
+/// or a decimal comma as in `1:32,456`.
+pub fn parse_lap_time(time_str: &str) -> Result<u32, String> {
+    let decimal_point;
+    let mut input = time_str.trim();
+    if !input.contains('.') && input.matches(',').count() == 1 {
+        decimal_point = input.replace(',', ".");
+        input = &decimal_point;
+    }
+    let too_large = || format!("Lap time is too large: '{time_str}'");
+    if input.is_empty() {
+        return Err("Lap time is empty".to_string());
+    }
+    if input.bytes().all(|byte| byte.is_ascii_digit()) {
+        return input.parse::<u32>().map_err(|_| too_large());
+    }
+
+    // Hours and minutes, most significant first, then the seconds.
+    let (larger, seconds_str) = match input.strip_suffix('s') {
+        Some(rest) => match rest.split_once('m') {
+            Some((minutes, seconds)) => (vec![minutes.trim()], seconds.trim()),
+            None => (Vec::new(), rest.trim()),
+        },
+        None if input.contains(':') => {
+            let mut parts = input.split(':').map(str::trim).collect::<Vec<_>>();
+            let seconds = parts.pop().unwrap_or_default();
+            (parts, seconds)
+        }
+        // Seconds alone need a fraction; whole numbers are milliseconds.
+        None if input.contains('.') => (Vec::new(), input),
+        None => (Vec::new(), ""),
+    };
+    if larger.len() > 2 || seconds_str.is_empty() {
+        return Err(format!(
+            "Invalid lap time format: '{}', expected M:SS.mmm, H:MM:SS.mmm, SS.mmm, SSs, Mm SSs, or milliseconds",
+            time_str
+        ));
+    }
+
+    let mut total_ms = 0_u64;
+    for (position, part) in larger.iter().enumerate() {
+        let unit = if position + 1 == larger.len() {
+            "minutes"
+        } else {
+            "hours"
+        };
+        if part.is_empty() || !part.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(format!("Failed to parse {} part: '{}'", unit, part));
+        }
+        let value = part.parse::<u64>().map_err(|_| too_large())?;
+        // Only the leading part may exceed its unit.
+        if position > 0 && value > 59 {
+            return Err(format!("Minutes must be between 0 and 59, got {}", value));
+        }
+        total_ms = total_ms.checked_mul(60).ok_or_else(too_large)?;
+        total_ms = total_ms.checked_add(value).ok_or_else(too_large)?;
+    }
+    total_ms = total_ms.checked_mul(60_000).ok_or_else(too_large)?;
+
+    let (whole, fraction) = match seconds_str.split_once('.') {
+        Some((whole, fraction)) => (whole.trim(), Some(fraction.trim())),
+        None => (seconds_str, None),
+    };
+    if whole.is_empty() || !whole.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(format!("Failed to parse seconds part: '{}'", whole));
+    }
+    let seconds = whole.parse::<u64>().map_err(|_| too_large())?;
+    if !larger.is_empty() && seconds > 59 {
+        return Err(format!("Seconds must be between 0 and 59, got {}", seconds));
+    }
+
+    // Optional fractions scale to milliseconds: "4" is 400 ms, "43" 430 ms.
+    let milliseconds = match fraction {
+        None => 0,
+        Some(digits)
+            if (1..=3).contains(&digits.len())
+                && digits.bytes().all(|byte| byte.is_ascii_digit()) =>
+        {
+            digits.parse::<u64>().map_err(|_| too_large())? * 10_u64.pow(3 - digits.len() as u32)
+        }
+        Some(digits) => {
+            return Err(format!(
+                "Milliseconds must contain between 1 and 3 digits, got '{}'",
+                digits
+            ));
+        }
+    };
+
+    let total_ms = seconds
+        .checked_mul(1_000)
+        .and_then(|value| value.checked_add(milliseconds))
+        .and_then(|value| value.checked_add(total_ms))
+        .ok_or_else(too_large)?;
+    u32::try_from(total_ms).map_err(|_| too_large())
+}
+
+pub fn format_ms_to_minsecms(ms: u32) -> String {
+    let total_seconds = ms / 1000;
+    let minutes = total_seconds / 60;
+    let seconds = total_seconds % 60;
+    let milliseconds = ms % 1000;
+    format!("{:02}:{:02}.{:03}", minutes, seconds, milliseconds)
+}
+
+pub fn compute_jaccard_similarity(results: &[Vec<CarIndex>]) -> Result<f64, String> {
+    if results.len() < 2 {
+        info!("Only one or no valid subsets found, skipping similarity measurement");
+        return Err(
+            "Only one or no valid subsets found, skipping similarity measurement".to_string(),
+        );
+    }
+    if results[1..].iter().all(|subset| subset == &results[0]) {
+        return Ok(1.0);
+    }
+
+    // Compress the arbitrary index values before making dense bitsets. This avoids sizing an
+    // allocation from the largest index (which may be usize::MAX) while retaining exact set
+    // semantics for duplicates.
+    let mut indices: Vec<CarIndex> = results.iter().flatten().copied().collect();
     indices.sort_unstable();
     indices.dedup();
     let words_per_set = indices.len().div_ceil(u64::BITS as usize);
@@ -1721,431 +3109,2604 @@ pub fn compute_jaccard_similarity(results: &[Vec<CarIndex>]) -> Result<f64, Stri
     }
 }
 
-/// Configuration for subset calculation
-#[derive(Clone)]
-pub struct SubsetCalculationConfig {
-    pub target: u32,
-    pub lap_count: usize,
-    pub player_count: usize,
-    pub timeout_ms: f64,
-    pub tolerance_percent: f64,
-}
-
-impl Default for SubsetCalculationConfig {
-    fn default() -> Self {
-        Self {
-            target: 0,
-            lap_count: 0,
-            player_count: 0,
-            timeout_ms: defaults::TIMEOUT_MS,
-            tolerance_percent: defaults::TOLERANCE_PERCENT,
-        }
-    }
-}
-
-/// Performs multiple subset calculations with progress tracking and timeout handling.
-///
-/// This is the main entry point for the karma calculation algorithm. It attempts to find
-/// `player_count` subsets of size `lap_count` from the provided cars, where each subset
-/// sums to approximately the target time.
-///
-/// # Algorithm
-/// 1. Sort available cars by lap time
-/// 2. For each run, attempt to find a valid subset
-/// 3. Remove selected cars from the pool for subsequent runs
-/// 4. Track previously selected cars for potential reuse
-/// 5. Apply timeout and tolerance constraints
-///
-/// # Arguments
-/// * `global_cars` - All available cars to select from
-/// * `target` - Target sum in milliseconds
-/// * `lap_count` - Number of cars per subset
-/// * `player_count` - Number of subsets to generate
-/// * `timeout_ms` - Maximum time allowed for calculation
-/// * `tolerance_percent` - Acceptable deviation from target (e.g., 0.5 for ±0.5%)
+/// Mean pairwise Jaccard similarity with every car weighted by its lap time.
 ///
-/// # Returns
-/// * `Ok(Vec<Vec<CarIndex>>)` - Successfully found all requested subsets
-/// * `Err(SubsetError)` - Failed to find valid subsets within constraints
-pub fn perform_multiple_runs(
-    global_cars: &[Car],
-    target: u32,
-    lap_count: usize,
-    player_count: usize,
-    timeout_ms: f64,
-    tolerance_percent: f64,
-) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
-    perform_multiple_runs_with_strategy(
-        DEFAULT_SOLVER_STRATEGY,
-        global_cars,
-        target,
-        lap_count,
-        player_count,
-        timeout_ms,
-        tolerance_percent,
-    )
-}
-
-/// Performs multiple runs using the explicitly selected solver implementation.
-pub fn perform_multiple_runs_with_strategy(
-    strategy: SolverStrategy,
-    global_cars: &[Car],
-    target: u32,
-    lap_count: usize,
-    player_count: usize,
-    timeout_ms: f64,
-    tolerance_percent: f64,
-) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
-    if !timeout_ms.is_finite() || timeout_ms < 0.0 {
-        return Err(SubsetError::InvalidTimeout(timeout_ms));
+/// Two subsets are compared by the lap time of the cars they share over the
+/// lap time of all cars in either, so sharing two 90-second cars counts more
+/// than sharing two 35-second ones. `results` index `cars`.
+pub fn compute_weighted_jaccard_similarity(
+    cars: &[Car],
+    results: &[Vec<CarIndex>],
+) -> Result<f64, String> {
+    if results.len() < 2 {
+        return Err(
+            "Only one or no valid subsets found, skipping similarity measurement".to_string(),
+        );
     }
-    if !tolerance_percent.is_finite() || tolerance_percent < 0.0 {
-        return Err(SubsetError::InvalidTolerance(tolerance_percent));
+    if let Some(&index) = results.iter().flatten().find(|&&index| index >= cars.len()) {
+        return Err(format!("Car index {index} is out of range"));
     }
-    if lap_count > global_cars.len() {
-        return Err(SubsetError::ImpossibleCount {
-            requested: lap_count,
-            available: global_cars.len(),
-        });
+
+    let sets = results
+        .iter()
+        .map(|subset| subset.iter().copied().collect::<HashSet<_>>())
+        .collect::<Vec<_>>();
+    let weight = |set: &HashSet<CarIndex>| -> u64 {
+        set.iter().map(|&car| u64::from(cars[car].lap_time)).sum()
+    };
+    let weights = sets.iter().map(weight).collect::<Vec<_>>();
+
+    let mut total_similarity = 0.0;
+    let mut count = 0;
+    for i in 0..sets.len() {
+        for j in (i + 1)..sets.len() {
+            let shared = sets[i]
+                .intersection(&sets[j])
+                .map(|&car| u64::from(cars[car].lap_time))
+                .sum::<u64>();
+            let union = weights[i] + weights[j] - shared;
+            total_similarity += if union == 0 {
+                1.0
+            } else {
+                shared as f64 / union as f64
+            };
+            count += 1;
+        }
     }
 
-    // ---------- timeout set-up ----------
-    let max_runtime_ms: f64 = timeout_ms.max(100.0);
-    #[cfg(not(target_arch = "wasm32"))]
-    let start_time = Instant::now();
-    #[cfg(target_arch = "wasm32")]
-    let start_time = js_sys::Date::now();
-    // ---------- existing logging ----------
-    info!("Starting search for multiple subset sum approximations");
-    info!("-----------------------------------------------------");
-    info!("Performing {} runs", player_count);
+    let average = total_similarity / count as f64;
     info!(
-        "Each run: finding {} numbers that sum approximately to {}",
-        lap_count, target
+        "Pairwise lap-time-weighted Jaccard similarity: {:.4}",
+        average
     );
-    info!("Initial pool size: {} numbers", global_cars.len());
-    info!("-----------------------------------------------------");
-
-    let mut available_indexes: Vec<CarIndex> = (0..global_cars.len()).collect();
-    let mut all_results: Vec<Vec<CarIndex>> = Vec::with_capacity(player_count);
-    let mut previously_selected = HashSet::new();
-    let mut usage_counts = vec![0_usize; global_cars.len()];
-    let bounded_indexes = (0..global_cars.len()).collect::<Vec<_>>();
-    let bounded_previous = HashSet::new();
-
-    for run in 1..=player_count {
-        info!("\n=== Run {}/{} ===", run, player_count);
-        info!("Available pool size: {} numbers", available_indexes.len());
-
-        let result = loop {
-            // Check timeout using helper function
-            #[cfg(not(target_arch = "wasm32"))]
-            if is_timeout_exceeded(start_time, max_runtime_ms) {
-                warn!(
-                    "Timeout while searching, produced {}/{} subsets",
-                    all_results.len(),
-                    player_count
-                );
-                return Err(SubsetError::NotEnoughSuccessfulRuns {
-                    required: player_count,
-                    found: all_results.len(),
-                });
-            }
-            #[cfg(target_arch = "wasm32")]
-            if is_timeout_exceeded(start_time, max_runtime_ms) {
-                warn!(
-                    "Timeout while searching, produced {}/{} subsets",
-                    all_results.len(),
-                    player_count
-                );
-                return Err(SubsetError::NotEnoughSuccessfulRuns {
-                    required: player_count,
-                    found: all_results.len(),
-                });
-            }
+    Ok(average)
+}
 
-            let mut rng = rand::rng();
-            let attempt = match match strategy {
-                SolverStrategy::Legacy => legacy_find_approximate_subset_from_candidates(
-                    global_cars,
-                    target,
-                    lap_count,
-                    &available_indexes,
-                    &previously_selected,
-                    tolerance_percent,
-                ),
-                SolverStrategy::Bounded => bounded_find_approximate_subset_with_rng(
-                    global_cars,
-                    BoundedRequest {
-                        target,
-                        lap_count,
-                        tolerance_percent,
-                        unused: &bounded_indexes,
-                        previously_selected: &bounded_previous,
-                        usage_counts: Some(&usage_counts),
-                    },
-                    &mut rng,
-                    || is_timeout_exceeded(start_time, max_runtime_ms),
-                ),
-            } {
-                Ok(subset) => subset,
-                Err(err) => {
-                    if is_timeout_exceeded(start_time, max_runtime_ms) {
-                        return Err(SubsetError::NotEnoughSuccessfulRuns {
-                            required: player_count,
-                            found: all_results.len(),
-                        });
-                    }
-                    warn!(
-                        "Run {}/{}: Failed to find a valid subset: {}",
-                        run, player_count, err
-                    );
-                    return Err(err);
-                }
-            };
+/// What a calculation optimizes across its subsets.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum Objective {
+    /// Every run is solved on its own and only has to meet the tolerance.
+    #[default]
+    PerRun,
+    /// After all runs, cars are traded between subsets, and swapped in from
+    /// the rest of the pool, to lower the largest deviation of any subset
+    /// from the target. The exhaustive search keeps its own choice.
+    Minimax,
+}
 
-            if strategy == SolverStrategy::Legacy {
-                let subset_sum = calculate_subset_sum(global_cars, &attempt);
-                let accuracy = accuracy_percent(subset_sum, target);
-                if !within_tolerance(accuracy, tolerance_percent) {
-                    warn!(
-                        "Current run's sum is more than {}% off ({}%), retrying...",
-                        tolerance_percent, accuracy
-                    );
-                    continue;
-                }
-            }
-            break attempt;
-        };
+/// Recommended driving order applied to each subset after selection.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum LapOrder {
+    /// Random order, drawn from the calculation's generator.
+    #[default]
+    Shuffled,
+    /// Alternate between the fastest and the slowest remaining laps.
+    AlternateFastSlow,
+    /// From the fastest lap to the slowest.
+    FastestFirst,
+    /// From the slowest lap to the fastest.
+    FastestLast,
+    /// The order in which the cars appear in the dataset.
+    DatasetOrder,
+}
 
-        // Update our previously selected numbers set
-        for &idx in &result {
-            previously_selected.insert(idx);
-            usage_counts[idx] += 1;
+/// Reorders one subset in place according to `order`.
+///
+/// Equal lap times keep a stable order by car index so the recommendation is
+/// reproducible. [`LapOrder::Shuffled`] leaves the subset as it is; the
+/// solver shuffles it when returning results.
+pub fn order_laps(cars: &[Car], subset: &mut [CarIndex], order: LapOrder) {
+    let by_pace = |&index: &CarIndex| (get_lap_time(cars, index), index);
+    match order {
+        LapOrder::Shuffled => {}
+        LapOrder::DatasetOrder => subset.sort_unstable(),
+        LapOrder::FastestFirst => subset.sort_unstable_by_key(by_pace),
+        LapOrder::FastestLast => {
+            subset.sort_unstable_by_key(|&index| (Reverse(get_lap_time(cars, index)), index))
         }
-
-        // Remove selected numbers from the pool
-        for &idx in &result {
-            if let Some(pos) = available_indexes.iter().position(|&i| i == idx) {
-                available_indexes.remove(pos);
+        LapOrder::AlternateFastSlow => {
+            let mut sorted = subset.to_vec();
+            sorted.sort_unstable_by_key(by_pace);
+            let (mut fast, mut slow) = (0, sorted.len());
+            for (position, slot) in subset.iter_mut().enumerate() {
+                *slot = if position % 2 == 0 {
+                    fast += 1;
+                    sorted[fast - 1]
+                } else {
+                    slow -= 1;
+                    sorted[slow]
+                };
             }
         }
-
-        all_results.push(result);
-
-        // Quick summary of this run
-        let current_sum = calculate_subset_sum(global_cars, all_results.last().unwrap());
-        info!(
-            "Run {}/{} complete: sum = {} ({}% of target)",
-            run,
-            player_count,
-            current_sum,
-            accuracy_percent(current_sum, target)
-        );
     }
+}
 
-    // Print final summary
-    info!("\n=== FINAL RESULTS ===");
-    info!("Completed {} runs", all_results.len());
-
-    let mut total_elements = 0;
-    let mut total_sum = 0;
-
-    for (i, subset) in all_results.iter().enumerate() {
-        let subset_sum = calculate_subset_sum(global_cars, subset);
-        total_elements += subset.len();
-        total_sum = u64::from(total_sum)
-            .saturating_add(u64::from(subset_sum))
-            .min(u64::from(u32::MAX)) as u32;
-
-        info!(
-            "Run {}: {} numbers, sum = {} ({}% of target)",
-            i + 1,
-            subset.len(),
-            subset_sum,
-            accuracy_percent(subset_sum, target)
-        );
+/// Applies `order` to a returned subset. Search strategies build some
+/// subsets in index order, so [`LapOrder::Shuffled`] shuffles them here.
+fn arrange_laps(cars: &[Car], subset: &mut [CarIndex], order: LapOrder, rng: &mut dyn RngCore) {
+    match order {
+        LapOrder::Shuffled => subset.shuffle(rng),
+        order => order_laps(cars, subset, order),
     }
+}
 
-    if !all_results.is_empty() {
-        let avg_accuracy = all_results
+/// Scores one attempt of a multi-start run; higher is better.
+fn score_attempt(
+    cars: &[Car],
+    subset: &[CarIndex],
+    (target, tolerance_percent): (u32, f64),
+    usage_counts: &[usize],
+    weights: AttemptScoreWeights,
+) -> f64 {
+    let deviation = (accuracy_percent(calculate_subset_sum(cars, subset), target) - 100.0).abs();
+    let accuracy = 1.0 - (deviation / tolerance_percent.max(f64::EPSILON)).min(1.0);
+    let diversity = if subset.is_empty() {
+        1.0
+    } else {
+        subset
             .iter()
-            .map(|subset| accuracy_percent(calculate_subset_sum(global_cars, subset), target))
+            .map(|&index| 1.0 / (1 + usage_counts[index]) as f64)
             .sum::<f64>()
-            / all_results.len() as f64;
+            / subset.len() as f64
+    };
+    weights.accuracy * accuracy + weights.diversity * diversity
+}
 
-        info!("\n=== SUMMARY ===");
-        info!(
-            "Total numbers selected: {}/{}",
-            total_elements,
-            lap_count * all_results.len()
-        );
-        info!(
-            "Total sum across all runs: {}/{}",
-            total_sum,
-            u64::from(target).saturating_mul(all_results.len() as u64)
+/// Outcome of one solver attempt together with the events it emitted.
+type AttemptOutcome = (Result<Vec<CarIndex>, SubsetError>, Vec<SolverEvent>);
+
+/// Makes `attempts` independent calls to `solve` and keeps the successful
+/// subset with the highest score, or returns the last error if none succeed.
+///
+/// Each attempt records its events into its own buffer; only the buffer of
+/// the attempt that is returned is kept, so the caller can replay the
+/// decisions behind the chosen subset.
+///
+/// Once an attempt finds a subset that `exact` accepts, no better one can
+/// exist: the flag passed to `solve` is raised so running attempts can stop,
+/// later attempts are not started, and that subset is returned.
+///
+/// Every attempt gets its own generator, seeded from `rng` before any of
+/// them starts, so the outcome does not depend on which attempts run
+/// concurrently. At most one attempt per available core runs at a time, and
+/// an attempt that panics fails with [`SubsetError::AttemptPanicked`].
+fn best_of_attempts<S, F, E>(
+    attempts: usize,
+    solve: S,
+    score: F,
+    exact: E,
+    rng: &mut dyn RngCore,
+) -> AttemptOutcome
+where
+    S: Fn(
+            &mut Vec<SolverEvent>,
+            &AtomicBool,
+            &mut ChaCha20Rng,
+        ) -> Result<Vec<CarIndex>, SubsetError>
+        + Sync,
+    F: Fn(&[CarIndex]) -> f64,
+    E: Fn(&[CarIndex]) -> bool + Sync,
+{
+    let seeds = (0..attempts.max(1))
+        .map(|_| rng.next_u64())
+        .collect::<Vec<_>>();
+    let found_exact = AtomicBool::new(false);
+    let run = |seed: u64| {
+        let mut events = Vec::new();
+        let outcome = solve(
+            &mut events,
+            &found_exact,
+            &mut ChaCha20Rng::seed_from_u64(seed),
         );
-        info!("Average accuracy: {:.2}%", avg_accuracy);
-        info!("Remaining numbers in pool: {}", available_indexes.len());
-    } else {
-        warn!("No successful runs completed");
+        if outcome.as_deref().is_ok_and(&exact) {
+            found_exact.store(true, AtomicOrdering::Relaxed);
+        }
+        (outcome, events)
+    };
+    if attempts < 2 {
+        return run(seeds[0]);
     }
 
-    if all_results.len() < player_count {
-        return Err(SubsetError::NotEnoughSuccessfulRuns {
-            required: player_count,
-            found: all_results.len(),
-        });
+    #[cfg(not(target_arch = "wasm32"))]
+    let outcomes: Vec<_> = {
+        let threads = std::thread::available_parallelism().map_or(1, usize::from);
+        let mut outcomes = Vec::with_capacity(seeds.len());
+        for chunk in seeds.chunks(threads) {
+            if found_exact.load(AtomicOrdering::Relaxed) {
+                break;
+            }
+            std::thread::scope(|scope| {
+                let workers: Vec<_> = chunk
+                    .iter()
+                    .map(|&seed| scope.spawn(move || run(seed)))
+                    .collect();
+                outcomes.extend(workers.into_iter().map(|worker| {
+                    worker.join().unwrap_or_else(|panic| {
+                        let message = panic
+                            .downcast_ref::<&str>()
+                            .map(|message| message.to_string())
+                            .or_else(|| panic.downcast_ref::<String>().cloned())
+                            .unwrap_or_default();
+                        (Err(SubsetError::AttemptPanicked { message }), Vec::new())
+                    })
+                }));
+            });
+        }
+        outcomes
+    };
+    #[cfg(target_arch = "wasm32")]
+    let outcomes: Vec<_> = seeds
+        .iter()
+        .map_while(|&seed| (!found_exact.load(AtomicOrdering::Relaxed)).then(|| run(seed)))
+        .collect();
+
+    let mut best: Option<(f64, Vec<CarIndex>, Vec<SolverEvent>)> = None;
+    let mut last_error = None;
+    for (outcome, events) in outcomes {
+        match outcome {
+            Ok(subset) if exact(&subset) => return (Ok(subset), events),
+            Ok(subset) => {
+                let subset_score = score(&subset);
+                if best.as_ref().is_none_or(|(top, _, _)| subset_score > *top) {
+                    best = Some((subset_score, subset, events));
+                }
+            }
+            Err(error) => last_error = Some((error, events)),
+        }
+    }
+    match (best, last_error) {
+        (Some((_, subset, events)), _) => (Ok(subset), events),
+        (None, Some((error, events))) => (Err(error), events),
+        (None, None) => (Err(SubsetError::NoValidSubset), Vec::new()),
+    }
+}
+
+/// Splits the pool for a full-coverage run, or returns `None` once every car
+/// has been used.
+///
+/// While at least `lap_count` cars are uncovered they are the only candidates.
+/// The final partial run must take all remaining uncovered cars and fills its
+/// other slots from covered cars.
+fn coverage_pools(
+    usage_counts: &[usize],
+    allowed: Option<&[bool]>,
+    lap_count: usize,
+) -> Option<(Vec<CarIndex>, Vec<CarIndex>)> {
+    let (uncovered, covered): (Vec<CarIndex>, Vec<CarIndex>) = (0..usage_counts.len())
+        .filter(|&index| allowed.is_none_or(|allowed| allowed[index]))
+        .partition(|&index| usage_counts[index] == 0);
+    if uncovered.is_empty() {
+        None
+    } else if uncovered.len() >= lap_count {
+        Some((uncovered, Vec::new()))
+    } else {
+        Some((covered, uncovered))
+    }
+}
+
+const QUANTILE_ANCHOR_CANDIDATES: usize = 4;
+const QUANTILE_ANCHOR_ATTEMPTS: usize = 16;
+
+/// Assigns every car to one of `bins` equally sized lap-time quantiles.
+fn quantile_bins_of(cars: &[Car], bins: usize) -> Vec<usize> {
+    let mut order: Vec<CarIndex> = (0..cars.len()).collect();
+    order.sort_unstable_by_key(|&index| (cars[index].lap_time, index));
+    let mut assignment = vec![0; cars.len()];
+    for (rank, index) in order.into_iter().enumerate() {
+        assignment[index] = rank * bins / cars.len();
+    }
+    assignment
+}
+
+/// Moves one anchor car from `pool` into `required` for every quantile that
+/// `required` does not represent yet. Returns `false` if a quantile has no
+/// candidate left.
+///
+/// Quantiles are anchored in random order, each near the per-lap average the
+/// target still needs, so the last anchor can correct the earlier ones. Ties
+/// favor the least-used cars except when an anchor fills the final slot.
+fn add_quantile_anchors<R: rand::Rng>(
+    cars: &[Car],
+    run: &ConstrainedRun<'_>,
+    (bins, bin_count): (&[usize], usize),
+    pool: &mut Vec<CarIndex>,
+    required: &mut Vec<CarIndex>,
+    rng: &mut R,
+) -> bool {
+    let mut missing: Vec<usize> = (0..bin_count)
+        .filter(|&bin| required.iter().all(|&index| bins[index] != bin))
+        .collect();
+    missing.shuffle(rng);
+    for bin in missing {
+        let slots = run.lap_count.saturating_sub(required.len()).max(1);
+        let wanted = u64::from(run.target).saturating_sub(calculate_subset_sum_u64(cars, required))
+            / slots as u64;
+        let mut candidates: Vec<CarIndex> = pool
+            .iter()
+            .copied()
+            .filter(|&index| bins[index] == bin)
+            .collect();
+        if candidates.is_empty() {
+            return false;
+        }
+        candidates.sort_unstable_by_key(|&index| {
+            let distance = u64::from(cars[index].lap_time).abs_diff(wanted);
+            let usage = run.usage_weights[index];
+            if slots == 1 {
+                (distance, usage as u64)
+            } else {
+                (usage as u64, distance)
+            }
+        });
+        let choices = if slots == 1 {
+            1
+        } else {
+            candidates.len().min(QUANTILE_ANCHOR_CANDIDATES)
+        };
+        let anchor = candidates[rng.random_range(0..choices)];
+        pool.retain(|&index| index != anchor);
+        required.push(anchor);
+    }
+    true
+}
+
+/// Selection constraints that the plain per-run search cannot express.
+struct ConstrainedRun<'a> {
+    target: u32,
+    lap_count: usize,
+    tolerance_percent: f64,
+    usage_counts: &'a [usize],
+    /// What selection prefers to keep low; see `usage_weights` in
+    /// [`perform_multiple_runs_on_pool`].
+    usage_weights: &'a [usize],
+    full_coverage: bool,
+    /// Per-car quantile assignment and the number of quantiles.
+    quantiles: Option<(&'a [usize], usize)>,
+    /// Cars this run may use, when its player has their own pool.
+    allowed: Option<&'a [bool]>,
+}
+
+impl ConstrainedRun<'_> {
+    fn pool_request<'b>(
+        &'b self,
+        pool: &'b [CarIndex],
+        required: &'b [CarIndex],
+    ) -> PoolRequest<'b> {
+        PoolRequest {
+            target: self.target,
+            lap_count: self.lap_count,
+            tolerance_percent: self.tolerance_percent,
+            pool,
+            required,
+            usage_counts: Some(self.usage_weights),
+        }
+    }
+}
+
+fn constrained_run<R: rand::Rng, F: FnMut() -> bool>(
+    cars: &[Car],
+    run: ConstrainedRun<'_>,
+    rng: &mut R,
+    mut deadline_exceeded: F,
+) -> Result<Vec<CarIndex>, SubsetError> {
+    let (pool, required) = run
+        .full_coverage
+        .then(|| coverage_pools(run.usage_counts, run.allowed, run.lap_count))
+        .flatten()
+        .unwrap_or_else(|| {
+            let pool = (0..cars.len())
+                .filter(|&index| run.allowed.is_none_or(|allowed| allowed[index]))
+                .collect();
+            (pool, Vec::new())
+        });
+    let Some(quantiles) = run.quantiles else {
+        return bounded_find_in_pool(
+            cars,
+            run.pool_request(&pool, &required),
+            rng,
+            deadline_exceeded,
+        );
+    };
+
+    // Fixed anchors make the rest of the run unconstrained; different anchor
+    // draws are retried because a poor draw can make the target unreachable.
+    let mut last_error = SubsetError::NoValidSubset;
+    for _ in 0..QUANTILE_ANCHOR_ATTEMPTS {
+        let mut anchored_pool = pool.clone();
+        let mut anchored = required.clone();
+        if !add_quantile_anchors(
+            cars,
+            &run,
+            quantiles,
+            &mut anchored_pool,
+            &mut anchored,
+            rng,
+        ) {
+            return Err(SubsetError::NoValidSubset);
+        }
+        match bounded_find_in_pool(
+            cars,
+            run.pool_request(&anchored_pool, &anchored),
+            rng,
+            &mut deadline_exceeded,
+        ) {
+            Ok(subset) => return Ok(subset),
+            Err(error) => last_error = error,
+        }
+        if deadline_exceeded() {
+            break;
+        }
+    }
+    Err(last_error)
+}
+
+/// How a calculation target is given. Configurations and worker requests
+/// always carry the total; [`TargetSpec::total`] derives it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetSpec {
+    /// Sum of all lap times in a subset, in milliseconds.
+    Total(u32),
+    /// Average lap time in milliseconds, multiplied by the lap count.
+    AverageLap(u32),
+}
+
+impl TargetSpec {
+    /// Target sum for subsets of `lap_count` cars, saturating at `u32::MAX`.
+    pub fn total(self, lap_count: usize) -> u32 {
+        match self {
+            Self::Total(total) => total,
+            Self::AverageLap(average) => {
+                (u64::from(average) * lap_count as u64).min(u64::from(u32::MAX)) as u32
+            }
+        }
+    }
+}
+
+/// Configuration for subset calculation
+///
+/// Serialized with every field optional; missing fields take their defaults.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SubsetCalculationConfig {
+    pub target: u32,
+    pub lap_count: usize,
+    pub player_count: usize,
+    pub timeout_ms: f64,
+    /// Longest time one run may take, in addition to `timeout_ms` for the
+    /// whole calculation. A run that exceeds it is skipped and reported by
+    /// [`SolverEvent::RunSkipped`], so one hard run cannot take the time of
+    /// the players after it; they are still solved, and the calculation
+    /// then fails with [`SubsetError::RunsSkipped`] naming the runs skipped.
+    /// Tolerance tightening keeps the last pass without a skipped run.
+    pub run_timeout_ms: Option<f64>,
+    pub tolerance_percent: f64,
+    pub strategy: SolverStrategy,
+    /// Use every car at least once before any car is reused.
+    ///
+    /// Requires `lap_count * player_count >= cars.len()`.
+    pub full_coverage: bool,
+    /// Require every subset to contain at least one car from each of this
+    /// many equally sized lap-time quantiles (3 for terciles, 4 for
+    /// quartiles). Values below 2 disable the constraint.
+    pub quantile_bins: usize,
+    /// Driving order of the laps within each returned subset.
+    pub lap_order: LapOrder,
+    /// Cars faster than this lap time (ms) are excluded from the pool.
+    pub min_lap_time: Option<u32>,
+    /// Cars slower than this lap time (ms) are excluded from the pool.
+    pub max_lap_time: Option<u32>,
+    /// Independent randomized attempts per run; the best-scoring valid one is
+    /// kept. Values below 2 make a single attempt. Attempts run in parallel
+    /// natively and sequentially in wasm.
+    pub attempts_per_run: usize,
+    /// How attempts of one run are ranked against each other.
+    pub attempt_weights: AttemptScoreWeights,
+    /// Ids of the cars each player may drive, one list per player in run
+    /// order. Empty lets every player use every car. Ids missing from the
+    /// dataset are ignored, and cars outside every list are never selected.
+    pub player_pools: Vec<Vec<String>>,
+    /// Uses of each car in earlier sessions. Among cars used equally often
+    /// in this calculation, the bounded search and attempt scoring prefer
+    /// those with fewer earlier uses; the legacy heuristic itself ignores it.
+    pub usage_history: UsageHistory,
+    /// Treat `tolerance_percent` as a loose starting band. After every
+    /// success the calculation repeats with half the tolerance it achieved,
+    /// until a pass fails or `timeout_ms` is spent, and the closest result is
+    /// returned; see [`achieved_tolerance_percent`].
+    pub tighten_tolerance: bool,
+    /// Enumerate every subset when the pool has at most this many subsets of
+    /// `lap_count` cars, and choose the players' subsets among those within
+    /// tolerance so their mean pairwise Jaccard similarity is as low as
+    /// possible. This replaces `strategy` and `attempts_per_run`; full
+    /// coverage is not supported and keeps the regular solver. 0 disables
+    /// it.
+    pub exhaustive_limit: u64,
+    /// Never let two subsets share a car. Requests needing more than
+    /// `cars.len()` selections fail upfront with
+    /// [`SubsetError::DisjointInfeasible`] instead of reusing cars.
+    pub disjoint: bool,
+    /// Most cars any two subsets may have in common. Every attempt of a run
+    /// leaves out all but this many cars of each earlier subset, drawn anew
+    /// for each attempt, so whatever it selects keeps to the cap. Requests
+    /// needing more cars than the pool has fail upfront with
+    /// [`SubsetError::SharedCarsInfeasible`]. The exhaustive search applies
+    /// the cap while choosing.
+    pub max_shared_cars: Option<usize>,
+    pub objective: Objective,
+    /// After all runs, exchange cars between the subsets, or with unused
+    /// cars, while that improves accuracy or lowers similarity without
+    /// worsening the other, within the time left of `timeout_ms`. Runs after
+    /// the minimax rebalance when both are enabled.
+    pub swap_pass: bool,
+    /// Seeds the ChaCha20 generator behind every random choice, so a seeded
+    /// calculation returns the same subsets on wasm and native. Time limits
+    /// still end searches early, so runs cut short by `timeout_ms` may
+    /// differ. `None` seeds from the thread's generator.
+    pub seed: Option<u64>,
+    /// Stops the calculation with [`SubsetError::Cancelled`] once cancelled,
+    /// at the next point that also checks `timeout_ms`. Not serialized.
+    #[serde(skip)]
+    pub cancellation: Option<CancellationToken>,
+}
+
+/// Weights for ranking the attempts of a multi-start run. Both components
+/// are normalized to `0.0..=1.0`, so equal weights count them equally.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AttemptScoreWeights {
+    /// Rewards sums close to the target, relative to the tolerance.
+    pub accuracy: f64,
+    /// Rewards subsets made of cars used less often in earlier runs.
+    pub diversity: f64,
+}
+
+impl Default for AttemptScoreWeights {
+    fn default() -> Self {
+        Self {
+            accuracy: 1.0,
+            diversity: 1.0,
+        }
+    }
+}
+
+/// Weights of the components of a [`QualityScore`]. Every component is
+/// normalized to `0.0..=1.0`, so equal weights count them equally.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QualityWeights {
+    pub mean_accuracy: f64,
+    pub worst_accuracy: f64,
+    pub diversity: f64,
+}
+
+impl Default for QualityWeights {
+    fn default() -> Self {
+        Self {
+            mean_accuracy: 1.0,
+            worst_accuracy: 1.0,
+            diversity: 1.0,
+        }
+    }
+}
+
+/// Quality of a whole result set, for ranking alternative results for the
+/// same target; see [`quality_score`].
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QualityScore {
+    /// One minus the mean deviation of the subsets from the target, relative
+    /// to the tolerance.
+    pub mean_accuracy: f64,
+    /// One minus the largest deviation of any subset, relative to the
+    /// tolerance.
+    pub worst_accuracy: f64,
+    /// One minus the mean pairwise Jaccard similarity; 1 for fewer than two
+    /// subsets.
+    pub diversity: f64,
+    /// Weighted mean of the components, from 0 to 1; higher is better.
+    pub score: f64,
+}
+
+/// Scores `subsets` of `cars` against `target`. Deviations count relative to
+/// `tolerance_percent`, so a subset at the edge of the tolerance scores 0.
+pub fn quality_score(
+    cars: &[Car],
+    subsets: &[Vec<CarIndex>],
+    target: u32,
+    tolerance_percent: f64,
+    weights: QualityWeights,
+) -> QualityScore {
+    let accuracy =
+        |deviation: f64| 1.0 - (deviation / tolerance_percent.max(f64::EPSILON)).min(1.0);
+    let deviations = subsets
+        .iter()
+        .map(|subset| (accuracy_percent(calculate_subset_sum(cars, subset), target) - 100.0).abs())
+        .collect::<Vec<_>>();
+    let mean_deviation = deviations.iter().sum::<f64>() / deviations.len().max(1) as f64;
+    let worst_deviation = deviations.iter().copied().fold(0.0, f64::max);
+    let mean_accuracy = accuracy(mean_deviation);
+    let worst_accuracy = accuracy(worst_deviation);
+    let diversity = 1.0 - compute_jaccard_similarity(subsets).unwrap_or(0.0);
+
+    let total_weight = weights.mean_accuracy + weights.worst_accuracy + weights.diversity;
+    let score = if total_weight > 0.0 {
+        (weights.mean_accuracy * mean_accuracy
+            + weights.worst_accuracy * worst_accuracy
+            + weights.diversity * diversity)
+            / total_weight
+    } else {
+        0.0
+    };
+    QualityScore {
+        mean_accuracy,
+        worst_accuracy,
+        diversity,
+        score,
+    }
+}
+
+impl Default for SubsetCalculationConfig {
+    fn default() -> Self {
+        Self {
+            target: 0,
+            lap_count: 0,
+            player_count: 0,
+            timeout_ms: defaults::TIMEOUT_MS,
+            run_timeout_ms: None,
+            tolerance_percent: defaults::TOLERANCE_PERCENT,
+            strategy: DEFAULT_SOLVER_STRATEGY,
+            full_coverage: false,
+            quantile_bins: 0,
+            lap_order: LapOrder::Shuffled,
+            min_lap_time: None,
+            max_lap_time: None,
+            attempts_per_run: 1,
+            attempt_weights: AttemptScoreWeights::default(),
+            player_pools: Vec::new(),
+            usage_history: UsageHistory::default(),
+            tighten_tolerance: false,
+            exhaustive_limit: 0,
+            disjoint: false,
+            max_shared_cars: None,
+            objective: Objective::PerRun,
+            swap_pass: false,
+            seed: None,
+            cancellation: None,
+        }
+    }
+}
+
+impl SubsetCalculationConfig {
+    /// Starts a builder from the default configuration.
+    pub fn builder(
+        target: u32,
+        lap_count: usize,
+        player_count: usize,
+    ) -> SubsetCalculationConfigBuilder {
+        SubsetCalculationConfigBuilder {
+            config: SubsetCalculationConfig {
+                target,
+                lap_count,
+                player_count,
+                ..SubsetCalculationConfig::default()
+            },
+        }
+    }
+
+    /// Number of runs that draw disjoint subsets while enough unused cars
+    /// remain; see [`SolverStrategy::disjoint_runs`]. Coverage and quantile
+    /// constraints let any run reuse cars.
+    pub fn disjoint_runs(&self) -> usize {
+        if self.disjoint {
+            self.player_count
+        } else if self.full_coverage || self.quantile_bins > 1 {
+            self.player_count.min(1)
+        } else {
+            self.strategy.disjoint_runs(self.player_count)
+        }
+    }
+
+    /// Whether [`SubsetCalculationConfig::cancellation`] was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Checks the settings that do not depend on the car pool.
+    pub fn validate(&self) -> Result<(), SubsetError> {
+        if !self.timeout_ms.is_finite() || self.timeout_ms < 0.0 {
+            return Err(SubsetError::InvalidTimeout(self.timeout_ms));
+        }
+        if let Some(run_timeout_ms) = self.run_timeout_ms {
+            if !run_timeout_ms.is_finite() || run_timeout_ms < 0.0 {
+                return Err(SubsetError::InvalidTimeout(run_timeout_ms));
+            }
+        }
+        if !self.tolerance_percent.is_finite() || self.tolerance_percent < 0.0 {
+            return Err(SubsetError::InvalidTolerance(self.tolerance_percent));
+        }
+        if self.quantile_bins > 1 && self.lap_count < self.quantile_bins {
+            return Err(SubsetError::QuantileSpreadInfeasible {
+                bins: self.quantile_bins,
+                lap_count: self.lap_count,
+            });
+        }
+        if let (Some(min), Some(max)) = (self.min_lap_time, self.max_lap_time) {
+            if min > max {
+                return Err(SubsetError::InvalidLapTimeFilter { min, max });
+            }
+        }
+        if !self.player_pools.is_empty() && self.player_pools.len() != self.player_count {
+            return Err(SubsetError::PlayerPoolCountMismatch {
+                pools: self.player_pools.len(),
+                players: self.player_count,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Builds a validated [`SubsetCalculationConfig`].
+#[derive(Debug, Clone)]
+pub struct SubsetCalculationConfigBuilder {
+    config: SubsetCalculationConfig,
+}
+
+impl SubsetCalculationConfigBuilder {
+    /// Replaces the target, deriving the total from the lap count given to
+    /// [`SubsetCalculationConfig::builder`].
+    pub fn target(mut self, target: TargetSpec) -> Self {
+        self.config.target = target.total(self.config.lap_count);
+        self
+    }
+
+    pub fn timeout_ms(mut self, timeout_ms: f64) -> Self {
+        self.config.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Limits each run on its own; see
+    /// [`SubsetCalculationConfig::run_timeout_ms`].
+    pub fn run_timeout_ms(mut self, run_timeout_ms: f64) -> Self {
+        self.config.run_timeout_ms = Some(run_timeout_ms);
+        self
+    }
+
+    pub fn tolerance_percent(mut self, tolerance_percent: f64) -> Self {
+        self.config.tolerance_percent = tolerance_percent;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: SolverStrategy) -> Self {
+        self.config.strategy = strategy;
+        self
+    }
+
+    pub fn full_coverage(mut self, full_coverage: bool) -> Self {
+        self.config.full_coverage = full_coverage;
+        self
+    }
+
+    pub fn quantile_bins(mut self, quantile_bins: usize) -> Self {
+        self.config.quantile_bins = quantile_bins;
+        self
+    }
+
+    pub fn lap_order(mut self, lap_order: LapOrder) -> Self {
+        self.config.lap_order = lap_order;
+        self
+    }
+
+    pub fn lap_time_range(mut self, min_lap_time: Option<u32>, max_lap_time: Option<u32>) -> Self {
+        self.config.min_lap_time = min_lap_time;
+        self.config.max_lap_time = max_lap_time;
+        self
+    }
+
+    /// Restricts each player to their own cars; see
+    /// [`SubsetCalculationConfig::player_pools`].
+    pub fn player_pools(mut self, player_pools: Vec<Vec<String>>) -> Self {
+        self.config.player_pools = player_pools;
+        self
+    }
+
+    /// Biases selection away from cars used often in earlier sessions.
+    pub fn usage_history(mut self, usage_history: UsageHistory) -> Self {
+        self.config.usage_history = usage_history;
+        self
+    }
+
+    /// Searches for the closest result the time budget allows; see
+    /// [`SubsetCalculationConfig::tighten_tolerance`].
+    pub fn tighten_tolerance(mut self, tighten_tolerance: bool) -> Self {
+        self.config.tighten_tolerance = tighten_tolerance;
+        self
+    }
+
+    /// Forbids sharing cars between subsets; see
+    /// [`SubsetCalculationConfig::disjoint`].
+    pub fn disjoint(mut self, disjoint: bool) -> Self {
+        self.config.disjoint = disjoint;
+        self
+    }
+
+    /// Caps the cars any two subsets share; see
+    /// [`SubsetCalculationConfig::max_shared_cars`].
+    pub fn max_shared_cars(mut self, max_shared_cars: usize) -> Self {
+        self.config.max_shared_cars = Some(max_shared_cars);
+        self
+    }
+
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.config.objective = objective;
+        self
+    }
+
+    /// Makes the calculation reproducible; see
+    /// [`SubsetCalculationConfig::seed`].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// Lets `token` stop the calculation; see
+    /// [`SubsetCalculationConfig::cancellation`].
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.config.cancellation = Some(token);
+        self
+    }
+
+    /// Improves finished subsets by exchanging cars; see
+    /// [`SubsetCalculationConfig::swap_pass`].
+    pub fn swap_pass(mut self, swap_pass: bool) -> Self {
+        self.config.swap_pass = swap_pass;
+        self
+    }
+
+    /// Enables the exhaustive search for pools with at most `limit`
+    /// subsets; see [`SubsetCalculationConfig::exhaustive_limit`].
+    pub fn exhaustive_limit(mut self, limit: u64) -> Self {
+        self.config.exhaustive_limit = limit;
+        self
+    }
+
+    pub fn attempts_per_run(
+        mut self,
+        attempts_per_run: usize,
+        weights: AttemptScoreWeights,
+    ) -> Self {
+        self.config.attempts_per_run = attempts_per_run;
+        self.config.attempt_weights = weights;
+        self
+    }
+
+    /// Returns the configuration, or the first invalid setting.
+    pub fn build(self) -> Result<SubsetCalculationConfig, SubsetError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Performs multiple subset calculations with progress tracking and timeout handling.
+///
+/// This is the main entry point for the karma calculation algorithm. It attempts to find
+/// `config.player_count` subsets of size `config.lap_count` from the provided cars, where
+/// each subset sums to approximately `config.target`.
+///
+/// # Algorithm
+/// 1. Validate the configuration and apply the lap-time filters
+/// 2. For each run, attempt to find a valid subset
+/// 3. Remove selected cars from the pool for subsequent runs
+/// 4. Track previously selected cars for potential reuse
+/// 5. Apply timeout and tolerance constraints
+///
+/// Disabled cars and lap-time filters are left out first; every other
+/// constraint, including full coverage, then refers to the filtered pool. Returned indexes always
+/// refer to `global_cars`.
+///
+/// # Returns
+/// * `Ok(Vec<Vec<CarIndex>>)` - Successfully found all requested subsets
+/// * `Err(SubsetError)` - Invalid configuration, or no valid subsets within constraints
+pub fn perform_multiple_runs(
+    global_cars: &[Car],
+    config: &SubsetCalculationConfig,
+) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
+    perform_multiple_runs_with_events(global_cars, config, &mut LogSink)
+}
+
+/// [`perform_multiple_runs`], reporting the solver's progress and decisions
+/// to `events` instead of the log.
+///
+/// With several attempts per run, only the events of the attempt that was
+/// kept are reported.
+pub fn perform_multiple_runs_with_events(
+    global_cars: &[Car],
+    config: &SubsetCalculationConfig,
+    events: &mut dyn EventSink,
+) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
+    perform_multiple_runs_with_rng(global_cars, config, events, &mut calculation_rng(config))
+}
+
+/// The random number generator a calculation with `config` uses: ChaCha20
+/// seeded with [`SubsetCalculationConfig::seed`], or from the thread's
+/// generator when there is none.
+pub fn calculation_rng(config: &SubsetCalculationConfig) -> ChaCha20Rng {
+    match config.seed {
+        Some(seed) => ChaCha20Rng::seed_from_u64(seed),
+        None => ChaCha20Rng::from_rng(&mut rand::rng()),
+    }
+}
+
+/// [`perform_multiple_runs_with_events`] drawing every random choice from
+/// `rng`, which replaces [`SubsetCalculationConfig::seed`].
+///
+/// The same generator state produces the same subsets on every platform, as
+/// long as no time limit cuts a search short.
+pub fn perform_multiple_runs_with_rng<R: RngCore>(
+    global_cars: &[Car],
+    config: &SubsetCalculationConfig,
+    events: &mut dyn EventSink,
+    rng: &mut R,
+) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
+    config.validate()?;
+    let results = if config.tighten_tolerance {
+        perform_tightening_runs(global_cars, config, events, rng)
+    } else {
+        perform_filtered_runs(global_cars, config, events, rng)
+    };
+    // A cancelled search may still have finished, but nobody wants it now.
+    if config.is_cancelled() {
+        return Err(SubsetError::Cancelled);
+    }
+    results
+}
+
+/// Leaves out disabled cars, applies the lap-time filter and player pools,
+/// then runs the calculation on the cars that remain.
+fn perform_filtered_runs(
+    global_cars: &[Car],
+    config: &SubsetCalculationConfig,
+    events: &mut dyn EventSink,
+    rng: &mut dyn RngCore,
+) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
+    if config.min_lap_time.is_none()
+        && config.max_lap_time.is_none()
+        && config.player_pools.is_empty()
+        && global_cars.iter().all(|car| car.enabled)
+    {
+        return perform_multiple_runs_on_pool(global_cars, config, events, rng);
+    }
+
+    let kept = selectable_cars(global_cars, config);
+    info!(
+        "Lap-time filter and player pools kept {}/{} cars",
+        kept.len(),
+        global_cars.len()
+    );
+    let pool = kept
+        .iter()
+        .map(|&index| global_cars[index].clone())
+        .collect::<Vec<_>>();
+    let unfiltered = SubsetCalculationConfig {
+        min_lap_time: None,
+        max_lap_time: None,
+        ..config.clone()
+    };
+    let results = perform_multiple_runs_on_pool(
+        &pool,
+        &unfiltered,
+        &mut RemappedSink {
+            kept: &kept,
+            inner: events,
+        },
+        rng,
+    )?;
+    Ok(results
+        .into_iter()
+        .map(|subset| subset.into_iter().map(|index| kept[index]).collect())
+        .collect())
+}
+
+/// Indexes of the enabled cars within the lap-time filter of `config` that
+/// some player may drive.
+fn selectable_cars(global_cars: &[Car], config: &SubsetCalculationConfig) -> Vec<CarIndex> {
+    let mut kept = filter_by_lap_time(global_cars, config.min_lap_time, config.max_lap_time);
+    if !config.player_pools.is_empty() {
+        // Cars no player may drive can neither be selected nor need coverage.
+        let owned = config
+            .player_pools
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<HashSet<_>>();
+        kept.retain(|&index| owned.contains(global_cars[index].id.as_str()));
+    }
+    kept
+}
+
+/// Adapts `solved`, the result of the request `config` describes but for
+/// another target, to `config.target` by exchanging cars between its
+/// subsets or with the rest of the pool, as the minimax rebalance does.
+///
+/// A nearby target usually needs only a car or two changed, far less work
+/// than solving it. The derived subsets keep every constraint of `config`.
+/// Returns `None` when they cannot all be brought within tolerance before
+/// the timeout, or when `solved` does not fit `config`; the target then
+/// needs a calculation of its own. A cancelled `config` also returns `None`.
+pub fn derive_neighbor_result(
+    global_cars: &[Car],
+    solved: &[Vec<CarIndex>],
+    config: &SubsetCalculationConfig,
+) -> Option<Vec<Vec<CarIndex>>> {
+    config.validate().ok()?;
+    let kept = selectable_cars(global_cars, config);
+    let mut pool_index = vec![None; global_cars.len()];
+    for (position, &index) in kept.iter().enumerate() {
+        pool_index[index] = Some(position);
+    }
+    let mut subsets = solved
+        .iter()
+        .map(|subset| {
+            if subset.len() != config.lap_count {
+                return None;
+            }
+            subset
+                .iter()
+                .map(|&index| *pool_index.get(index)?)
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if subsets.is_empty() {
+        return None;
+    }
+    let pool = kept
+        .iter()
+        .map(|&index| global_cars[index].clone())
+        .collect::<Vec<_>>();
+    let player_pools = player_pool_masks(&pool, config).ok()?;
+    let quantiles =
+        (config.quantile_bins > 1).then(|| quantile_bins_of(&pool, config.quantile_bins));
+
+    let max_runtime_ms: f64 = config.timeout_ms.max(MIN_RUNTIME_MS);
+    #[cfg(not(target_arch = "wasm32"))]
+    let start_time = Instant::now();
+    #[cfg(target_arch = "wasm32")]
+    let start_time = js_sys::Date::now();
+    rebalance::minimize_worst_deviation(
+        &pool,
+        &mut subsets,
+        config.target,
+        &rebalance::Constraints {
+            player_pools: &player_pools,
+            disjoint: config.disjoint,
+            max_shared: config.max_shared_cars,
+            full_coverage: config.full_coverage,
+            quantiles: quantiles
+                .as_ref()
+                .map(|bins| (bins.as_slice(), config.quantile_bins)),
+        },
+        || config.is_cancelled() || is_timeout_exceeded(start_time, max_runtime_ms),
+    );
+    let (low, high) = accepted_sum_interval(config.target, config.tolerance_percent);
+    if config.is_cancelled()
+        || !subsets
+            .iter()
+            .all(|subset| (low..=high).contains(&calculate_subset_sum_u64(&pool, subset)))
+    {
+        return None;
+    }
+
+    let mut rng = calculation_rng(config);
+    Some(
+        subsets
+            .into_iter()
+            .map(|mut subset| {
+                arrange_laps(&pool, &mut subset, config.lap_order, &mut rng);
+                subset.into_iter().map(|index| kept[index]).collect()
+            })
+            .collect(),
+    )
+}
+
+/// Recomputes the subset of the player at `player_index` in `existing_results`
+/// and returns it, leaving every other subset as it is.
+///
+/// The other subsets act as fixed constraints: disjoint requests avoid their
+/// cars, [`SubsetCalculationConfig::max_shared_cars`] caps the overlap with
+/// each of them, and full coverage keeps every car that only the replaced
+/// subset held. Cars the replaced subset or other subsets used are
+/// preferred least, so a re-roll usually changes the selection. The bounded
+/// search is used regardless of [`SubsetCalculationConfig::strategy`].
+pub fn resolve_single_subset(
+    global_cars: &[Car],
+    existing_results: &[Vec<CarIndex>],
+    player_index: usize,
+    config: &SubsetCalculationConfig,
+) -> Result<Vec<CarIndex>, SubsetError> {
+    config.validate()?;
+    if player_index >= existing_results.len() {
+        return Err(SubsetError::InvalidPlayerIndex {
+            player: player_index + 1,
+            player_count: existing_results.len(),
+        });
+    }
+    if let Some(&index) = existing_results
+        .iter()
+        .flatten()
+        .find(|&&index| index >= global_cars.len())
+    {
+        return Err(SubsetError::InvalidPriorIndex(index));
+    }
+    let SubsetCalculationConfig {
+        target,
+        lap_count,
+        tolerance_percent,
+        quantile_bins,
+        lap_order,
+        ..
+    } = *config;
+
+    let others = existing_results
+        .iter()
+        .enumerate()
+        .filter(|&(player, _)| player != player_index)
+        .map(|(_, subset)| subset)
+        .collect::<Vec<_>>();
+    let mut usage_counts = vec![0_usize; global_cars.len()];
+    for &index in others.iter().copied().flatten() {
+        usage_counts[index] += 1;
+    }
+    let history_counts = config.usage_history.counts_for(global_cars);
+    let history_scale = history_counts.iter().max().map_or(1, |max| max + 1);
+    let mut usage_weights = history_counts;
+    for &index in others
+        .iter()
+        .copied()
+        .flatten()
+        .chain(&existing_results[player_index])
+    {
+        usage_weights[index] += history_scale;
+    }
+
+    let filtered = filter_by_lap_time(global_cars, config.min_lap_time, config.max_lap_time)
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let player_pool = player_pool_masks(global_cars, config)?
+        .into_iter()
+        .nth(player_index);
+    let allowed = (0..global_cars.len())
+        .map(|index| {
+            filtered.contains(&index)
+                && player_pool.as_ref().is_none_or(|allowed| allowed[index])
+                && (!config.disjoint || usage_counts[index] == 0)
+        })
+        .collect::<Vec<_>>();
+    let available = allowed.iter().filter(|&&allowed| allowed).count();
+    if available < lap_count {
+        return Err(SubsetError::ImpossibleCount {
+            requested: lap_count,
+            available,
+        });
+    }
+    // Quantiles split the pool the other subsets were selected from, as in
+    // `perform_multiple_runs`; other cars belong to none.
+    let quantiles = (quantile_bins > 1).then(|| {
+        let kept = selectable_cars(global_cars, config);
+        let pool = kept
+            .iter()
+            .map(|&index| global_cars[index].clone())
+            .collect::<Vec<_>>();
+        let mut bins = vec![usize::MAX; global_cars.len()];
+        for (&index, bin) in kept.iter().zip(quantile_bins_of(&pool, quantile_bins)) {
+            bins[index] = bin;
+        }
+        bins
+    });
+
+    let max_runtime_ms: f64 = config.timeout_ms.max(MIN_RUNTIME_MS);
+    #[cfg(not(target_arch = "wasm32"))]
+    let start_time = Instant::now();
+    #[cfg(target_arch = "wasm32")]
+    let start_time = js_sys::Date::now();
+    info!(
+        "Re-solving the subset of player {}/{}",
+        player_index + 1,
+        existing_results.len()
+    );
+
+    let mut rng = calculation_rng(config);
+    let mut shared_car_draws = 0;
+    loop {
+        if config.is_cancelled() {
+            return Err(SubsetError::Cancelled);
+        }
+        if is_timeout_exceeded(start_time, max_runtime_ms) {
+            return Err(SubsetError::NotEnoughSuccessfulRuns {
+                required: 1,
+                found: 0,
+            });
+        }
+        // Only a few cars of each other subset stay in, as in the runs of
+        // `perform_multiple_runs`.
+        let excluded = match config.max_shared_cars {
+            Some(max_shared) if max_shared < lap_count => {
+                shared_car_exclusions(&others, max_shared, &mut rng)
+            }
+            _ => HashSet::new(),
+        };
+        let run_mask = allowed
+            .iter()
+            .enumerate()
+            .map(|(index, &allowed)| allowed && !excluded.contains(&index))
+            .collect::<Vec<_>>();
+        let solved = constrained_run(
+            global_cars,
+            ConstrainedRun {
+                target,
+                lap_count,
+                tolerance_percent,
+                usage_counts: &usage_counts,
+                usage_weights: &usage_weights,
+                full_coverage: config.full_coverage,
+                quantiles: quantiles
+                    .as_ref()
+                    .map(|bins| (bins.as_slice(), quantile_bins)),
+                allowed: Some(&run_mask),
+            },
+            &mut rng,
+            || config.is_cancelled() || is_timeout_exceeded(start_time, max_runtime_ms),
+        );
+        match solved {
+            Ok(mut subset) => {
+                arrange_laps(global_cars, &mut subset, lap_order, &mut rng);
+                return Ok(subset);
+            }
+            Err(_) if !excluded.is_empty() && shared_car_draws < SHARED_CAR_DRAWS => {
+                shared_car_draws += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Number of `subsets` whose sum equals `target` exactly.
+pub fn exact_hit_count(cars: &[Car], subsets: &[Vec<CarIndex>], target: u32) -> usize {
+    subsets
+        .iter()
+        .filter(|subset| calculate_subset_sum_u64(cars, subset) == u64::from(target))
+        .count()
+}
+
+/// Probability that the real total of `subset` lands within
+/// `tolerance_percent` of `target`.
+///
+/// Each car's lap time is treated as an independent normal variable with its
+/// [`Car::uncertainty_ms`] as standard deviation, so the total's variance is
+/// the sum of the cars' variances. Cars without an uncertainty are exact;
+/// when no car has one, the result is 1 or 0.
+pub fn tolerance_probability(
+    cars: &[Car],
+    subset: &[CarIndex],
+    target: u32,
+    tolerance_percent: f64,
+) -> f64 {
+    let (low, high) = accepted_sum_interval(target, tolerance_percent);
+    let mean = calculate_subset_sum_u64(cars, subset) as f64;
+    let variance = subset
+        .iter()
+        .filter_map(|&index| cars.get(index)?.uncertainty_ms)
+        .map(|sigma| f64::from(sigma).powi(2))
+        .sum::<f64>();
+    if variance == 0.0 {
+        return if (low as f64..=high as f64).contains(&mean) {
+            1.0
+        } else {
+            0.0
+        };
+    }
+    let sigma = variance.sqrt();
+    let cdf = |value: f64| 0.5 * (1.0 + erf((value - mean) / (sigma * std::f64::consts::SQRT_2)));
+    (cdf(high as f64) - cdf(low as f64)).clamp(0.0, 1.0)
+}
+
+/// Error function, to within 1.5e-7 (Abramowitz and Stegun 7.1.26).
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    (1.0 - polynomial * (-x * x).exp()).copysign(x)
+}
+
+/// Largest deviation of any subset's sum from `target`, as a percentage:
+/// the smallest tolerance that accepts all of `subsets`.
+pub fn achieved_tolerance_percent(cars: &[Car], subsets: &[Vec<CarIndex>], target: u32) -> f64 {
+    subsets
+        .iter()
+        .map(|subset| (accuracy_percent(calculate_subset_sum(cars, subset), target) - 100.0).abs())
+        .fold(0.0, f64::max)
+}
+
+/// Repeats the calculation with ever tighter tolerances for as long as
+/// `config.timeout_ms` allows. Only the first pass may fail the call.
+fn perform_tightening_runs(
+    global_cars: &[Car],
+    config: &SubsetCalculationConfig,
+    events: &mut dyn EventSink,
+    rng: &mut dyn RngCore,
+) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let start_time = Instant::now();
+    #[cfg(target_arch = "wasm32")]
+    let start_time = js_sys::Date::now();
+    let budget_ms = config.timeout_ms.max(MIN_RUNTIME_MS);
+    let mut pass = SubsetCalculationConfig {
+        tighten_tolerance: false,
+        ..config.clone()
+    };
+    let mut best = perform_filtered_runs(global_cars, &pass, events, rng)?;
+
+    loop {
+        let achieved = achieved_tolerance_percent(global_cars, &best, config.target);
+        let remaining_ms = budget_ms - elapsed_ms(start_time);
+        // Each pass runs for at least the solver's minimum runtime, so a
+        // shorter remainder would overrun the budget.
+        if achieved == 0.0 || remaining_ms < MIN_RUNTIME_MS {
+            break;
+        }
+        pass.tolerance_percent = achieved / 2.0;
+        pass.timeout_ms = remaining_ms;
+        events.emit(SolverEvent::ToleranceTightened {
+            achieved_percent: achieved,
+            tolerance_percent: pass.tolerance_percent,
+        });
+        match perform_filtered_runs(global_cars, &pass, events, rng) {
+            Ok(closer) => best = closer,
+            Err(error) => {
+                info!("Stopped tightening at {}%: {}", achieved, error);
+                break;
+            }
+        }
+    }
+    Ok(best)
+}
+
+/// Positional form of [`perform_multiple_runs`] using the default strategy.
+#[deprecated(note = "use `perform_multiple_runs` with a `SubsetCalculationConfig`")]
+pub fn perform_multiple_runs_positional(
+    global_cars: &[Car],
+    target: u32,
+    lap_count: usize,
+    player_count: usize,
+    timeout_ms: f64,
+    tolerance_percent: f64,
+) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
+    perform_multiple_runs(
+        global_cars,
+        &SubsetCalculationConfig {
+            target,
+            lap_count,
+            player_count,
+            timeout_ms,
+            tolerance_percent,
+            ..SubsetCalculationConfig::default()
+        },
+    )
+}
+
+/// Positional form of [`perform_multiple_runs`] with an explicit strategy.
+#[deprecated(note = "use `perform_multiple_runs` with a `SubsetCalculationConfig`")]
+pub fn perform_multiple_runs_with_strategy(
+    strategy: SolverStrategy,
+    global_cars: &[Car],
+    target: u32,
+    lap_count: usize,
+    player_count: usize,
+    timeout_ms: f64,
+    tolerance_percent: f64,
+) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
+    perform_multiple_runs(
+        global_cars,
+        &SubsetCalculationConfig {
+            target,
+            lap_count,
+            player_count,
+            timeout_ms,
+            tolerance_percent,
+            strategy,
+            ..SubsetCalculationConfig::default()
+        },
+    )
+}
+
+/// Resolves [`SubsetCalculationConfig::player_pools`] to one membership mask
+/// per player, or none when every player shares the pool.
+fn player_pool_masks(
+    cars: &[Car],
+    config: &SubsetCalculationConfig,
+) -> Result<Vec<Vec<bool>>, SubsetError> {
+    config
+        .player_pools
+        .iter()
+        .enumerate()
+        .map(|(player, ids)| {
+            let ids = ids.iter().map(String::as_str).collect::<HashSet<_>>();
+            let allowed = cars
+                .iter()
+                .map(|car| ids.contains(car.id.as_str()))
+                .collect::<Vec<_>>();
+            let available = allowed.iter().filter(|&&allowed| allowed).count();
+            if available < config.lap_count {
+                return Err(SubsetError::PlayerPoolTooSmall {
+                    player: player + 1,
+                    available,
+                    lap_count: config.lap_count,
+                });
+            }
+            Ok(allowed)
+        })
+        .collect()
+}
+
+/// Translates events from a filtered pool back to the caller's indexes.
+struct RemappedSink<'a> {
+    kept: &'a [CarIndex],
+    inner: &'a mut dyn EventSink,
+}
+
+impl EventSink for RemappedSink<'_> {
+    fn emit(&mut self, event: SolverEvent) {
+        self.inner.emit(event.map_cars(|index| self.kept[index]));
+    }
+}
+
+/// Returns the indexes of enabled cars whose lap time lies within the
+/// inclusive bounds. A missing bound does not restrict that side.
+pub fn filter_by_lap_time(
+    cars: &[Car],
+    min_lap_time: Option<u32>,
+    max_lap_time: Option<u32>,
+) -> Vec<CarIndex> {
+    cars.iter()
+        .enumerate()
+        .filter(|(_, car)| {
+            car.enabled
+                && min_lap_time.is_none_or(|min| car.lap_time >= min)
+                && max_lap_time.is_none_or(|max| car.lap_time <= max)
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn perform_multiple_runs_on_pool(
+    global_cars: &[Car],
+    config: &SubsetCalculationConfig,
+    events: &mut dyn EventSink,
+    mut rng: &mut dyn RngCore,
+) -> Result<Vec<Vec<CarIndex>>, SubsetError> {
+    let SubsetCalculationConfig {
+        target,
+        lap_count,
+        player_count,
+        timeout_ms,
+        tolerance_percent,
+        strategy,
+        full_coverage,
+        quantile_bins,
+        lap_order,
+        attempts_per_run,
+        attempt_weights,
+        ..
+    } = *config;
+    if lap_count > global_cars.len() {
+        return Err(SubsetError::ImpossibleCount {
+            requested: lap_count,
+            available: global_cars.len(),
+        });
+    }
+    let selections = lap_count.saturating_mul(player_count);
+    if full_coverage && selections < global_cars.len() {
+        return Err(SubsetError::InsufficientSelectionsForCoverage {
+            selections,
+            cars: global_cars.len(),
+        });
+    }
+    if config.disjoint && selections > global_cars.len() {
+        return Err(SubsetError::DisjointInfeasible {
+            lap_count,
+            player_count,
+            cars: global_cars.len(),
+            max_players: global_cars.len() / lap_count.max(1),
+        });
+    }
+    if let Some(max_shared) = config.max_shared_cars {
+        let required = shared_cap_min_cars(lap_count, player_count, max_shared);
+        if required > global_cars.len() {
+            return Err(SubsetError::SharedCarsInfeasible {
+                player_count,
+                lap_count,
+                max_shared,
+                required,
+                cars: global_cars.len(),
+            });
+        }
+    }
+    let player_pools = player_pool_masks(global_cars, config)?;
+    let player_indexes = player_pools
+        .iter()
+        .map(|allowed| {
+            (0..global_cars.len())
+                .filter(|&index| allowed[index])
+                .collect()
+        })
+        .collect::<Vec<Vec<CarIndex>>>();
+
+    // ---------- timeout set-up ----------
+    let max_runtime_ms: f64 = timeout_ms.max(MIN_RUNTIME_MS);
+    #[cfg(not(target_arch = "wasm32"))]
+    let start_time = Instant::now();
+    #[cfg(target_arch = "wasm32")]
+    let start_time = js_sys::Date::now();
+    let mut available_indexes: Vec<CarIndex> = (0..global_cars.len()).collect();
+    let mut all_results: Vec<Vec<CarIndex>> = Vec::with_capacity(player_count);
+    let mut previously_selected = HashSet::new();
+    let mut usage_counts = vec![0_usize; global_cars.len()];
+    // Selection prefers low weights. A car's weight is its use in this
+    // session scaled past every count in the usage history, plus its count
+    // there, so earlier sessions decide between cars used equally often now.
+    let history_counts = config.usage_history.counts_for(global_cars);
+    let history_scale = history_counts.iter().max().map_or(1, |max| max + 1);
+    let mut usage_weights = history_counts;
+    let bounded_indexes = (0..global_cars.len()).collect::<Vec<_>>();
+    let bounded_previous = HashSet::new();
+    let quantiles = (quantile_bins > 1).then(|| quantile_bins_of(global_cars, quantile_bins));
+
+    if !full_coverage
+        && config.exhaustive_limit > 0
+        && exhaustive::subset_count(global_cars.len(), lap_count) <= config.exhaustive_limit
+    {
+        info!("Enumerating every {}-car subset", lap_count);
+        let mut results = exhaustive::exhaustive_runs(
+            global_cars,
+            exhaustive::ExhaustiveRequest {
+                lap_count,
+                player_count,
+                accepted: accepted_sum_interval(target, tolerance_percent),
+                player_pools: &player_pools,
+                max_shared: config.max_shared_cars,
+                quantiles: quantiles
+                    .as_ref()
+                    .map(|bins| (bins.as_slice(), quantile_bins)),
+            },
+            &mut rng,
+            || config.is_cancelled() || is_timeout_exceeded(start_time, max_runtime_ms),
+        )?;
+        let distinct = results.iter().flatten().collect::<HashSet<_>>().len();
+        if config.disjoint && distinct < selections {
+            // Even the least similar choice shares a car.
+            return Err(SubsetError::NoValidSubset);
+        }
+        // Every subset comes from the same search, so its time is split
+        // evenly between the runs.
+        let run_time_ms = elapsed_ms(start_time) / player_count.max(1) as f64;
+        for (run, subset) in results.iter_mut().enumerate() {
+            events.emit(SolverEvent::RunStarted {
+                run: run + 1,
+                player_count,
+                available: global_cars.len(),
+            });
+            let sum = calculate_subset_sum(global_cars, subset);
+            events.emit(SolverEvent::RunCompleted {
+                run: run + 1,
+                player_count,
+                subset: subset.clone(),
+                sum,
+                accuracy: accuracy_percent(sum, target),
+                exact: sum == target,
+                attempts: 1,
+                elapsed_ms: run_time_ms,
+            });
+            arrange_laps(global_cars, subset, lap_order, &mut rng);
+        }
+        return Ok(results);
+    }
+
+    let mut skipped_runs = Vec::new();
+    for run in 1..=player_count {
+        #[cfg(not(target_arch = "wasm32"))]
+        let run_start = Instant::now();
+        #[cfg(target_arch = "wasm32")]
+        let run_start = js_sys::Date::now();
+        let mut attempts = 0;
+        let mut shared_car_draws = 0;
+        events.emit(SolverEvent::RunStarted {
+            run,
+            player_count,
+            available: available_indexes.len(),
+        });
+        let run_budget_exceeded = || {
+            config
+                .run_timeout_ms
+                .is_some_and(|budget_ms| is_timeout_exceeded(run_start, budget_ms))
+        };
+        let calculation_timed_out =
+            || config.is_cancelled() || is_timeout_exceeded(start_time, max_runtime_ms);
+        let timed_out = || calculation_timed_out() || run_budget_exceeded();
+        let timeout_error = |found: usize| {
+            warn!(
+                "Timeout while searching, produced {}/{} subsets",
+                found, player_count
+            );
+            SubsetError::NotEnoughSuccessfulRuns {
+                required: player_count,
+                found,
+            }
+        };
+
+        // `None` once the run used up its own budget.
+        let result = loop {
+            if calculation_timed_out() {
+                return Err(timeout_error(all_results.len()));
+            }
+            if run_budget_exceeded() {
+                break None;
+            }
+
+            // Strictly disjoint runs may only use cars no earlier run took,
+            // and capped runs only the few cars of each earlier subset they
+            // may share.
+            let excluded = match config.max_shared_cars {
+                Some(max_shared) if max_shared < lap_count => {
+                    shared_car_exclusions(&all_results, max_shared, &mut rng)
+                }
+                _ => HashSet::new(),
+            };
+            let run_mask = (config.disjoint || !excluded.is_empty()).then(|| {
+                (0..global_cars.len())
+                    .map(|index| {
+                        (!config.disjoint || usage_counts[index] == 0)
+                            && !excluded.contains(&index)
+                            && player_pools
+                                .get(run - 1)
+                                .is_none_or(|allowed| allowed[index])
+                    })
+                    .collect::<Vec<_>>()
+            });
+            let run_indexes = run_mask.as_ref().map(|mask| {
+                (0..global_cars.len())
+                    .filter(|&index| mask[index])
+                    .collect::<Vec<_>>()
+            });
+            let allowed = run_mask
+                .as_deref()
+                .or(player_pools.get(run - 1).map(Vec::as_slice));
+            // A player with their own pool only sees the part of it that the
+            // legacy strategy has not used yet.
+            let legacy_pool = allowed.map(|allowed| {
+                (
+                    available_indexes
+                        .iter()
+                        .copied()
+                        .filter(|&index| allowed[index])
+                        .collect::<Vec<_>>(),
+                    previously_selected
+                        .iter()
+                        .copied()
+                        .filter(|&index| allowed[index])
+                        .collect::<HashSet<_>>(),
+                )
+            });
+            let (legacy_candidates, legacy_previous) = legacy_pool.as_ref().map_or(
+                (&available_indexes[..], &previously_selected),
+                |(candidates, previous)| (&candidates[..], previous),
+            );
+            let bounded_candidates = run_indexes
+                .as_deref()
+                .or(player_indexes.get(run - 1).map(Vec::as_slice))
+                .unwrap_or(&bounded_indexes);
+
+            let uncovered = usage_counts.iter().filter(|&&count| count == 0).count();
+            let coverage_active = full_coverage && uncovered > 0;
+            let constrained = coverage_active || quantiles.is_some();
+            let attempts_made = AtomicUsize::new(0);
+            let solve = |attempt_events: &mut Vec<SolverEvent>,
+                         found_exact: &AtomicBool,
+                         rng: &mut ChaCha20Rng| {
+                attempts_made.fetch_add(1, AtomicOrdering::Relaxed);
+                let deadline_exceeded = || found_exact.load(AtomicOrdering::Relaxed) || timed_out();
+                match (constrained, strategy) {
+                    // The legacy heuristic cannot honor required cars, so
+                    // constrained runs always use the bounded search.
+                    (true, _) => constrained_run(
+                        global_cars,
+                        ConstrainedRun {
+                            target,
+                            lap_count,
+                            tolerance_percent,
+                            usage_counts: &usage_counts,
+                            usage_weights: &usage_weights,
+                            full_coverage,
+                            quantiles: quantiles
+                                .as_ref()
+                                .map(|bins| (bins.as_slice(), quantile_bins)),
+                            allowed,
+                        },
+                        rng,
+                        deadline_exceeded,
+                    )
+                    .map_err(|error| {
+                        if coverage_active {
+                            SubsetError::CoverageInfeasible { run, uncovered }
+                        } else {
+                            error
+                        }
+                    }),
+                    (false, SolverStrategy::Legacy) => legacy_find_approximate_subset_with_rng(
+                        global_cars,
+                        LegacyRequest {
+                            target,
+                            lap_count,
+                            candidate_indexes: legacy_candidates,
+                            previously_selected: legacy_previous,
+                            tolerance_percent,
+                        },
+                        rng,
+                        attempt_events,
+                    )
+                    // The heuristic may return a subset outside tolerance,
+                    // which must not outscore a valid attempt.
+                    .and_then(|subset| {
+                        let accuracy =
+                            accuracy_percent(calculate_subset_sum(global_cars, &subset), target);
+                        if within_tolerance(accuracy, tolerance_percent) {
+                            return Ok(subset);
+                        }
+                        attempt_events.push(SolverEvent::AttemptRejected {
+                            run,
+                            accuracy,
+                            tolerance_percent,
+                        });
+                        Err(SubsetError::OutsideTolerance(accuracy))
+                    }),
+                    (false, SolverStrategy::Bounded) => bounded_find_approximate_subset_with_rng(
+                        global_cars,
+                        BoundedRequest {
+                            target,
+                            lap_count,
+                            accepted: accepted_sum_interval(target, tolerance_percent),
+                            unused: bounded_candidates,
+                            previously_selected: &bounded_previous,
+                            usage_counts: Some(&usage_weights),
+                        },
+                        rng,
+                        deadline_exceeded,
+                    ),
+                }
+            };
+            let score = |subset: &[CarIndex]| {
+                score_attempt(
+                    global_cars,
+                    subset,
+                    (target, tolerance_percent),
+                    &usage_weights,
+                    attempt_weights,
+                )
+            };
+            let exact = |subset: &[CarIndex]| calculate_subset_sum(global_cars, subset) == target;
+            let (outcome, attempt_events) =
+                best_of_attempts(attempts_per_run, solve, score, exact, &mut rng);
+            attempts += attempts_made.into_inner();
+            for event in attempt_events {
+                events.emit(event);
+            }
+            let attempt = match outcome {
+                Ok(subset) => subset,
+                Err(err) => {
+                    if calculation_timed_out() {
+                        return Err(timeout_error(all_results.len()));
+                    }
+                    if run_budget_exceeded() {
+                        break None;
+                    }
+                    // Every legacy attempt missed the tolerance.
+                    if matches!(err, SubsetError::OutsideTolerance(_)) {
+                        continue;
+                    }
+                    // Other cars of the earlier subsets may leave room.
+                    if !excluded.is_empty() && shared_car_draws < SHARED_CAR_DRAWS {
+                        shared_car_draws += 1;
+                        continue;
+                    }
+                    warn!(
+                        "Run {}/{}: Failed to find a valid subset: {}",
+                        run, player_count, err
+                    );
+                    return Err(err);
+                }
+            };
+
+            // Searching the complement of a large subset may take cars left
+            // out above, so the cap is checked once more.
+            if let Some(max_shared) = config.max_shared_cars {
+                let overlap = all_results
+                    .iter()
+                    .enumerate()
+                    .map(|(earlier, subset)| {
+                        let shared = attempt.iter().filter(|car| subset.contains(car)).count();
+                        (earlier + 1, shared)
+                    })
+                    .find(|&(_, shared)| shared > max_shared);
+                if let Some((earlier_run, shared)) = overlap {
+                    events.emit(SolverEvent::SharedCarsRejected {
+                        run,
+                        earlier_run,
+                        shared,
+                        max_shared,
+                    });
+                    continue;
+                }
+            }
+            break Some(attempt);
+        };
+        let Some(result) = result else {
+            events.emit(SolverEvent::RunSkipped {
+                run,
+                player_count,
+                budget_ms: config.run_timeout_ms.unwrap_or_default(),
+                attempts,
+                elapsed_ms: elapsed_ms(run_start),
+            });
+            // Kept empty so later runs still count earlier subsets by run.
+            skipped_runs.push(run);
+            all_results.push(Vec::new());
+            continue;
+        };
+
+        // Update our previously selected numbers set
+        for &idx in &result {
+            previously_selected.insert(idx);
+            usage_counts[idx] += 1;
+            usage_weights[idx] += history_scale;
+        }
+
+        // Remove selected numbers from the pool; a car's usage count is its
+        // selection bit, so this is a single linear pass per run.
+        available_indexes.retain(|&idx| usage_counts[idx] == 0);
+
+        // Quick summary of this run
+        let current_sum = calculate_subset_sum(global_cars, &result);
+        events.emit(SolverEvent::RunCompleted {
+            run,
+            player_count,
+            subset: result.clone(),
+            sum: current_sum,
+            accuracy: accuracy_percent(current_sum, target),
+            exact: current_sum == target,
+            attempts,
+            elapsed_ms: elapsed_ms(run_start),
+        });
+        all_results.push(result);
+    }
+
+    if all_results.len() < player_count {
+        return Err(SubsetError::NotEnoughSuccessfulRuns {
+            required: player_count,
+            found: all_results.len(),
+        });
+    }
+    if !skipped_runs.is_empty() {
+        return Err(SubsetError::RunsSkipped {
+            runs: skipped_runs,
+            budget_ms: config.run_timeout_ms.unwrap_or_default(),
+        });
+    }
+
+    if config.objective == Objective::Minimax {
+        let worst = |results: &[Vec<CarIndex>]| {
+            results
+                .iter()
+                .map(|subset| {
+                    (accuracy_percent(calculate_subset_sum(global_cars, subset), target) - 100.0)
+                        .abs()
+                })
+                .fold(0.0, f64::max)
+        };
+        let before = worst(&all_results);
+        let moves = rebalance::minimize_worst_deviation(
+            global_cars,
+            &mut all_results,
+            target,
+            &rebalance::Constraints {
+                player_pools: &player_pools,
+                disjoint: config.disjoint,
+                max_shared: config.max_shared_cars,
+                full_coverage,
+                quantiles: quantiles
+                    .as_ref()
+                    .map(|bins| (bins.as_slice(), quantile_bins)),
+            },
+            || config.is_cancelled() || is_timeout_exceeded(start_time, max_runtime_ms),
+        );
+        events.emit(SolverEvent::Rebalanced {
+            moves,
+            worst_deviation_before: before,
+            worst_deviation_after: worst(&all_results),
+        });
+    }
+
+    if config.swap_pass {
+        let mean_deviation = |results: &[Vec<CarIndex>]| {
+            results
+                .iter()
+                .map(|subset| {
+                    (accuracy_percent(calculate_subset_sum(global_cars, subset), target) - 100.0)
+                        .abs()
+                })
+                .sum::<f64>()
+                / results.len().max(1) as f64
+        };
+        let similarity =
+            |results: &[Vec<CarIndex>]| compute_jaccard_similarity(results).unwrap_or(0.0);
+        let deviation_before = mean_deviation(&all_results);
+        let similarity_before = similarity(&all_results);
+        let moves = rebalance::improve_by_swaps(
+            global_cars,
+            &mut all_results,
+            target,
+            accepted_sum_interval(target, tolerance_percent),
+            &rebalance::Constraints {
+                player_pools: &player_pools,
+                disjoint: config.disjoint,
+                max_shared: config.max_shared_cars,
+                full_coverage,
+                quantiles: quantiles
+                    .as_ref()
+                    .map(|bins| (bins.as_slice(), quantile_bins)),
+            },
+            &mut rng,
+            || config.is_cancelled() || is_timeout_exceeded(start_time, max_runtime_ms),
+        );
+        events.emit(SolverEvent::SwapPassCompleted {
+            moves,
+            deviation_before,
+            deviation_after: mean_deviation(&all_results),
+            similarity_before,
+            similarity_after: similarity(&all_results),
+        });
+    }
+
+    for subset in &mut all_results {
+        arrange_laps(global_cars, subset, lap_order, &mut rng);
+    }
+    Ok(all_results)
+}
+
+/// Web worker entry point for performing multiple subset calculations.
+///
+/// This function is designed to be called from JavaScript in a web worker context.
+/// It handles serialization/deserialization of data across the JS/WASM boundary.
+///
+/// # Arguments
+/// * `cars_js` - Serialized car data from JavaScript
+/// * `target` - Target sum in milliseconds
+/// * `lap_count` - Number of laps per subset
+/// * `player_count` - Number of players (subsets to generate)
+///
+/// # Returns
+/// Serialized result containing all subsets, or error details
+#[wasm_bindgen]
+pub async fn worker_perform_multiple_runs(
+    cars_js: JsValue,
+    target: u32,
+    lap_count: usize,
+    player_count: usize,
+) -> JsValue {
+    // Deserialize cars from JsValue
+    let cars: Vec<Car> = match serde_wasm_bindgen::from_value(cars_js) {
+        Ok(c) => c,
+        Err(e) => {
+            return serde_wasm_bindgen::to_value(&format!("Failed to deserialize cars: {}", e))
+                .unwrap_or(JsValue::NULL);
+        }
+    };
+
+    // Run the calculation with defined constants
+    let config = SubsetCalculationConfig {
+        target,
+        lap_count,
+        player_count,
+        ..SubsetCalculationConfig::default()
+    };
+    match perform_multiple_runs(&cars, &config) {
+        Ok(result) => serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL),
+        Err(e) => serde_wasm_bindgen::to_value(&format!("Calculation failed: {}", e))
+            .unwrap_or(JsValue::NULL),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::time::Instant;
+
+    fn car(id: &str, lap_time: u32) -> Car {
+        Car::new(id, lap_time)
+    }
+
+    #[test]
+    fn multiple_runs_use_unused_candidates_before_reusing_cars() {
+        let cars = vec![car("first", 10), car("second", 10)];
+
+        let results = perform_multiple_runs(&cars, &run_config(10, 1, 2, 1_000.0, 0.0)).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_ne!(results[0][0], results[1][0]);
+    }
+
+    fn run_config(
+        target: u32,
+        lap_count: usize,
+        player_count: usize,
+        timeout_ms: f64,
+        tolerance_percent: f64,
+    ) -> SubsetCalculationConfig {
+        SubsetCalculationConfig {
+            target,
+            lap_count,
+            player_count,
+            timeout_ms,
+            tolerance_percent,
+            ..SubsetCalculationConfig::default()
+        }
+    }
+
+    fn coverage_config(
+        target: u32,
+        lap_count: usize,
+        player_count: usize,
+    ) -> SubsetCalculationConfig {
+        SubsetCalculationConfig {
+            target,
+            lap_count,
+            player_count,
+            timeout_ms: 1_000.0,
+            tolerance_percent: 0.0,
+            full_coverage: true,
+            ..SubsetCalculationConfig::default()
+        }
+    }
+
+    #[test]
+    fn full_coverage_uses_every_car_before_reusing_any() {
+        let cars: Vec<_> = (0..5).map(|index| car(&index.to_string(), 10)).collect();
+
+        let results = perform_multiple_runs(&cars, &coverage_config(20, 2, 4)).unwrap();
+
+        assert_eq!(results.len(), 4);
+        let distinct =
+            |runs: &[Vec<CarIndex>]| runs.iter().flatten().copied().collect::<HashSet<_>>().len();
+        assert_eq!(distinct(&results[..2]), 4);
+        assert_eq!(distinct(&results[..3]), 5);
+    }
+
+    #[test]
+    fn full_coverage_reports_infeasible_and_undersized_requests() {
+        let cars = vec![car("a", 10), car("b", 10), car("c", 10), car("slow", 100)];
+
+        assert!(matches!(
+            perform_multiple_runs(&cars, &coverage_config(20, 2, 2)),
+            Err(SubsetError::CoverageInfeasible {
+                run: 2,
+                uncovered: 2
+            })
+        ));
+        assert!(matches!(
+            perform_multiple_runs(&cars, &coverage_config(20, 1, 3)),
+            Err(SubsetError::InsufficientSelectionsForCoverage {
+                selections: 3,
+                cars: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn quantile_spread_places_a_car_from_every_tercile_in_each_subset() {
+        let cars: Vec<_> = (1..=9)
+            .map(|step| car(&step.to_string(), step * 10))
+            .collect();
+        let config = SubsetCalculationConfig {
+            target: 150,
+            lap_count: 3,
+            player_count: 4,
+            timeout_ms: 1_000.0,
+            tolerance_percent: 0.0,
+            quantile_bins: 3,
+            ..SubsetCalculationConfig::default()
+        };
+
+        for _ in 0..10 {
+            let results = perform_multiple_runs(&cars, &config).unwrap();
+            for subset in &results {
+                assert_valid_subset(&cars, subset, 3, 150, 0.0);
+                let mut terciles: Vec<_> = subset.iter().map(|&index| index / 3).collect();
+                terciles.sort_unstable();
+                assert_eq!(terciles, vec![0, 1, 2], "subset {subset:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn quantile_spread_rejects_subsets_smaller_than_the_bin_count() {
+        let cars: Vec<_> = (1..=8).map(|step| car(&step.to_string(), step)).collect();
+        let config = SubsetCalculationConfig {
+            target: 5,
+            lap_count: 3,
+            player_count: 1,
+            quantile_bins: 4,
+            ..SubsetCalculationConfig::default()
+        };
+
+        assert!(matches!(
+            perform_multiple_runs(&cars, &config),
+            Err(SubsetError::QuantileSpreadInfeasible {
+                bins: 4,
+                lap_count: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn lap_order_strategies_arrange_subsets_by_pace() {
+        let cars = vec![
+            car("c", 30),
+            car("a", 10),
+            car("e", 50),
+            car("b", 20),
+            car("d", 40),
+        ];
+        let ordered = |order| {
+            let mut subset = vec![0, 1, 2, 3, 4];
+            order_laps(&cars, &mut subset, order);
+            subset
+        };
+
+        assert_eq!(ordered(LapOrder::Shuffled), vec![0, 1, 2, 3, 4]);
+        assert_eq!(ordered(LapOrder::FastestFirst), vec![1, 3, 0, 4, 2]);
+        assert_eq!(ordered(LapOrder::FastestLast), vec![2, 4, 0, 3, 1]);
+        assert_eq!(ordered(LapOrder::AlternateFastSlow), vec![1, 2, 3, 4, 0]);
+        let mut subset = vec![3, 0, 4];
+        order_laps(&cars, &mut subset, LapOrder::DatasetOrder);
+        assert_eq!(subset, [0, 3, 4]);
+
+        let tied = vec![car("x", 20), car("y", 10), car("z", 20), car("w", 10)];
+        for (order, expected) in [
+            (LapOrder::FastestFirst, [1, 3, 0, 2]),
+            (LapOrder::FastestLast, [0, 2, 1, 3]),
+        ] {
+            let mut subset = vec![3, 2, 1, 0];
+            order_laps(&tied, &mut subset, order);
+            assert_eq!(subset, expected, "{order:?}");
+        }
+    }
+
+    #[test]
+    fn every_strategy_returns_subsets_in_the_requested_order() {
+        let cars = (0..10)
+            .map(|index| car(&format!("car-{index}"), 100_000 + (index * 7_919) % 5_000))
+            .collect::<Vec<_>>();
+        for exhaustive_limit in [0, 1_000] {
+            for strategy in [SolverStrategy::Legacy, SolverStrategy::Bounded] {
+                let config = |order| {
+                    SubsetCalculationConfig::builder(306_000, 3, 3)
+                        .tolerance_percent(1.0)
+                        .strategy(strategy)
+                        .exhaustive_limit(exhaustive_limit)
+                        .lap_order(order)
+                        .build()
+                        .unwrap()
+                };
+                let pace = |subset: &[CarIndex]| {
+                    subset
+                        .iter()
+                        .map(|&index| (cars[index].lap_time, index))
+                        .collect::<Vec<_>>()
+                };
+                for subset in perform_multiple_runs(&cars, &config(LapOrder::DatasetOrder)).unwrap()
+                {
+                    assert!(subset.is_sorted(), "{subset:?}");
+                }
+                for subset in perform_multiple_runs(&cars, &config(LapOrder::FastestFirst)).unwrap()
+                {
+                    assert!(pace(&subset).is_sorted(), "{subset:?}");
+                }
+                for subset in perform_multiple_runs(&cars, &config(LapOrder::FastestLast)).unwrap()
+                {
+                    assert!(pace(&subset).iter().rev().is_sorted(), "{subset:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lap_time_filters_exclude_outliers_and_keep_original_indexes() {
+        let cars = vec![car("fast", 5), car("a", 20), car("b", 30), car("slow", 500)];
+        let config = SubsetCalculationConfig {
+            min_lap_time: Some(10),
+            max_lap_time: Some(100),
+            ..coverage_config(50, 2, 1)
+        };
+
+        let results = perform_multiple_runs(&cars, &config).expect("filtered run");
+        let mut subset = results[0].clone();
+        subset.sort_unstable();
+        assert_eq!(subset, vec![1, 2]);
+
+        let inverted = SubsetCalculationConfig {
+            min_lap_time: Some(100),
+            max_lap_time: Some(10),
+            ..config
+        };
+        assert!(matches!(
+            perform_multiple_runs(&cars, &inverted),
+            Err(SubsetError::InvalidLapTimeFilter { min: 100, max: 10 })
+        ));
+    }
+
+    #[test]
+    fn sorted_pool_skips_taken_cars_in_range_queries() {
+        let cars = vec![car("c", 30), car("a", 10), car("d", 40), car("b", 20)];
+        let mut pool = SortedPool::new(&cars, &[0, 1, 2, 3, 1]);
+
+        assert_eq!(pool.min_max_sums(2), (30, 70));
+        assert_eq!(pool.min_max_sums(5), (0, 0));
+
+        assert!(pool.take(&cars, 3));
+        assert!(!pool.take(&cars, 3));
+        assert_eq!(pool.available_len(), 3);
+        assert_eq!(pool.min_max_sums(2), (40, 70));
+        assert_eq!(pool.min_max_sums(3), (80, 80));
+        assert_eq!(pool.in_range(15, 35).collect::<Vec<_>>(), vec![(0, 30)]);
+        assert_eq!(pool.closest(21), Some(0));
+        assert_eq!(pool.closest(20), Some(1));
+    }
+
+    #[test]
+    fn best_of_attempts_keeps_the_highest_scoring_success() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let solve = |events: &mut Vec<SolverEvent>, _: &AtomicBool, _: &mut ChaCha20Rng| {
+            let call = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            events.push(SolverEvent::FallbackUsed {
+                car: call,
+                remaining_needed: 1,
+            });
+            match call {
+                0 => Err(SubsetError::NoValidSubset),
+                call => Ok(vec![call]),
+            }
+        };
+
+        let (best, events) = best_of_attempts(
+            4,
+            solve,
+            |subset| -((subset[0] as f64) - 2.0).abs(),
+            |_| false,
+            &mut ChaCha20Rng::seed_from_u64(0),
+        );
+        assert_eq!(best.unwrap(), vec![2]);
+        assert_eq!(
+            events,
+            vec![SolverEvent::FallbackUsed {
+                car: 2,
+                remaining_needed: 1
+            }]
+        );
+        assert!(matches!(
+            best_of_attempts(
+                3,
+                |_, _, _| Err(SubsetError::NoValidSubset),
+                |_| 0.0,
+                |_| false,
+                &mut ChaCha20Rng::seed_from_u64(0),
+            )
+            .0,
+            Err(SubsetError::NoValidSubset)
+        ));
+    }
+
+    #[test]
+    fn best_of_attempts_bounds_its_threads_and_survives_panics() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let (running, most) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let calls = AtomicUsize::new(0);
+        let solve = |_: &mut Vec<SolverEvent>, _: &AtomicBool, _: &mut ChaCha20Rng| {
+            most.fetch_max(running.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            running.fetch_sub(1, Ordering::SeqCst);
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => panic!("attempt 0"),
+                call => Ok(vec![call]),
+            }
+        };
+        let (best, _) = best_of_attempts(
+            64,
+            solve,
+            |subset| subset[0] as f64,
+            |_| false,
+            &mut ChaCha20Rng::seed_from_u64(0),
+        );
+        assert_eq!(best.unwrap(), vec![63]);
+        let threads = std::thread::available_parallelism().map_or(1, usize::from);
+        assert!(most.load(Ordering::SeqCst) <= threads);
+
+        let (failed, _) = best_of_attempts(
+            2,
+            |_, _, _| panic!("always"),
+            |_| 0.0,
+            |_| false,
+            &mut ChaCha20Rng::seed_from_u64(0),
+        );
+        assert_eq!(
+            failed,
+            Err(SubsetError::AttemptPanicked {
+                message: "always".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn seeded_runs_are_reproducible() {
+        let cars = (0..30)
+            .map(|index| car(&format!("car-{index}"), 90_000 + index * 757))
+            .collect::<Vec<_>>();
+        for strategy in [SolverStrategy::Legacy, SolverStrategy::Bounded] {
+            let config = |seed| {
+                SubsetCalculationConfig::builder(400_000, 4, 3)
+                    .tolerance_percent(2.0)
+                    .timeout_ms(60_000.0)
+                    .attempts_per_run(3, AttemptScoreWeights::default())
+                    .strategy(strategy)
+                    .seed(seed)
+                    .build()
+                    .unwrap()
+            };
+            let first = perform_multiple_runs(&cars, &config(7)).unwrap();
+            assert_eq!(perform_multiple_runs(&cars, &config(7)).unwrap(), first);
+            let mut rng = ChaCha20Rng::seed_from_u64(7);
+            assert_eq!(
+                perform_multiple_runs_with_rng(&cars, &config(7), &mut LogSink, &mut rng).unwrap(),
+                first
+            );
+            assert!(
+                (8..16).any(|seed| perform_multiple_runs(&cars, &config(seed)).unwrap() != first),
+                "{strategy:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn an_exact_attempt_stops_the_others() {
+        let calls = AtomicUsize::new(0);
+        let start = Instant::now();
+        // The first attempt hits the target; the others would search until
+        // told to stop and then return a worse but higher-scoring subset.
+        let solve = |_: &mut Vec<SolverEvent>, found_exact: &AtomicBool, _: &mut ChaCha20Rng| {
+            if calls.fetch_add(1, AtomicOrdering::SeqCst) == 0 {
+                return Ok(vec![0]);
+            }
+            while !found_exact.load(AtomicOrdering::SeqCst) {
+                if start.elapsed().as_secs() >= 5 {
+                    return Ok(vec![2]);
+                }
+                std::thread::yield_now();
+            }
+            Err(SubsetError::NoValidSubset)
+        };
+        let (best, _) = best_of_attempts(
+            4,
+            solve,
+            |subset| subset[0] as f64,
+            |subset| subset == [0],
+            &mut ChaCha20Rng::seed_from_u64(0),
+        );
+        assert_eq!(best.unwrap(), [0]);
+        assert!(start.elapsed().as_secs() < 5);
+
+        let cars = (0..6)
+            .map(|index| car(&format!("car-{index}"), 100_000 + index * 1_000))
+            .collect::<Vec<_>>();
+        let mut config = run_config(205_000, 2, 2, 1_000.0, 2.0);
+        config.attempts_per_run = 4;
+        let (results, metrics) = crate::metrics::measure_multiple_runs(&cars, &config);
+        let results = results.unwrap();
+        assert_eq!(
+            metrics.exact_hits,
+            exact_hit_count(&cars, &results, 205_000)
+        );
+    }
+
+    #[test]
+    fn legacy_attempts_outside_tolerance_do_not_outscore_valid_ones() {
+        let cars = (0..8)
+            .map(|index| car(&format!("car-{index}"), 90_000 + (index * 7_919) % 20_000))
+            .collect::<Vec<_>>();
+        // Diversity alone would favor an invalid attempt of unused cars, which
+        // was then rejected along with the valid attempts beside it.
+        for seed in [0, 4] {
+            let mut config = run_config(300_000, 3, 2, 5_000.0, 0.5);
+            config.strategy = SolverStrategy::Legacy;
+            config.attempts_per_run = 8;
+            config.attempt_weights = AttemptScoreWeights {
+                accuracy: 0.0,
+                diversity: 1.0,
+            };
+            config.seed = Some(seed);
+            let mut events = Vec::new();
+            let results = perform_multiple_runs_with_events(&cars, &config, &mut events).unwrap();
+            assert!(results.iter().all(|subset| within_tolerance(
+                accuracy_percent(calculate_subset_sum(&cars, subset), 300_000),
+                0.5
+            )));
+            assert!(
+                !events
+                    .iter()
+                    .any(|event| matches!(event, SolverEvent::AttemptRejected { .. })),
+                "seed {seed}"
+            );
+        }
+    }
+
+    #[test]
+    fn quality_scores_combine_accuracy_and_diversity() {
+        let cars = vec![
+            car("a", 100_000),
+            car("b", 100_000),
+            car("c", 101_000),
+            car("d", 99_000),
+        ];
+        // Exact and disjoint: every component is perfect.
+        let best = quality_score(
+            &cars,
+            &[vec![0, 1], vec![2, 3]],
+            200_000,
+            1.0,
+            QualityWeights::default(),
+        );
+        assert_eq!(
+            (best.mean_accuracy, best.worst_accuracy, best.diversity),
+            (1.0, 1.0, 1.0)
+        );
+        assert_eq!(best.score, 1.0);
+
+        // 201 s is half the tolerance off and shares one of three cars.
+        let subsets = [vec![0, 1], vec![0, 2]];
+        let quality = quality_score(&cars, &subsets, 200_000, 1.0, QualityWeights::default());
+        assert!((quality.mean_accuracy - 0.75).abs() < 1e-9);
+        assert!((quality.worst_accuracy - 0.5).abs() < 1e-9);
+        assert!((quality.diversity - 2.0 / 3.0).abs() < 1e-9);
+        assert!((quality.score - (0.75 + 0.5 + 2.0 / 3.0) / 3.0).abs() < 1e-9);
+        assert!(quality.score < best.score);
+
+        let diversity_only = QualityWeights {
+            mean_accuracy: 0.0,
+            worst_accuracy: 0.0,
+            diversity: 2.0,
+        };
+        let quality = quality_score(&cars, &subsets, 200_000, 1.0, diversity_only);
+        assert!((quality.score - 2.0 / 3.0).abs() < 1e-9);
     }
 
-    Ok(all_results)
-}
+    #[test]
+    fn attempt_scores_reward_accuracy_and_unused_cars() {
+        let cars = vec![car("a", 50), car("b", 50), car("c", 49)];
+        let usage = [1, 0, 0];
+        let score = |subset: &[CarIndex], weights| {
+            score_attempt(&cars, subset, (100, 1.0), &usage, weights)
+        };
+        let diversity_only = AttemptScoreWeights {
+            accuracy: 0.0,
+            diversity: 1.0,
+        };
 
-pub fn analyze_multiple_runs(
-    global_cars: &[Car],
-    all_results: &[Vec<CarIndex>],
-    total_elements: usize,
-) {
-    use std::collections::HashMap;
-    info!("\n=== CAR FREQUENCY ANALYSIS ===");
-    let mut car_id_freq: HashMap<String, usize> = HashMap::new();
-    let mut lap_time_freq: HashMap<u32, usize> = HashMap::new();
-
-    // Count how many times each car and lap time was selected
-    for subset in all_results {
-        for &idx in subset {
-            *car_id_freq
-                .entry(get_car_id(global_cars, idx).to_string())
-                .or_insert(0) += 1;
-            *lap_time_freq
-                .entry(get_lap_time(global_cars, idx))
-                .or_insert(0) += 1;
-        }
-    }
-
-    let mut car_freq: Vec<(&String, &usize)> = car_id_freq.iter().collect();
-    car_freq.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
-    let top_count = if car_freq.len() < 10 {
-        car_freq.len()
-    } else {
-        10
-    };
-    info!("Top {} most frequently selected cars:", top_count);
-    for (i, &(car_id, &count)) in car_freq.iter().take(top_count).enumerate() {
-        let car_lap_time = global_cars
-            .iter()
-            .find(|car| car.id == *car_id)
-            .map(|car| car.lap_time)
-            .unwrap_or(0);
-        info!(
-            "#{}: Car {} - lap time {} ms - used in {} runs ({:.0}%)",
-            i + 1,
-            car_id,
-            car_lap_time,
-            count,
-            (count as f64 / all_results.len() as f64 * 100.0).round()
+        assert!(
+            score(&[0, 1], AttemptScoreWeights::default())
+                > score(&[1, 2], AttemptScoreWeights::default())
         );
-    }
-
-    info!("\n=== LAP TIME DISTRIBUTION ANALYSIS ===");
-    if !lap_time_freq.is_empty() {
-        let min_time = lap_time_freq.keys().min().unwrap();
-        let max_time = lap_time_freq.keys().max().unwrap();
-        info!("Fastest lap time selected: {} ms", min_time);
-        info!("Slowest lap time selected: {} ms", max_time);
+        assert!(score(&[0, 1], diversity_only) < score(&[1, 2], diversity_only));
 
-        let ranges = [
-            (30000, 40000),
-            (40000, 50000),
-            (50000, 60000),
-            (60000, 70000),
-            (70000, 80000),
-            (80000, 90000),
-        ];
-        for (start, end) in ranges.iter() {
-            let count: usize = lap_time_freq
-                .iter()
-                .filter(|&(&time, _)| time >= *start && time < *end)
-                .map(|(_, &cnt)| cnt)
-                .sum();
-            if count > 0 {
-                info!(
-                    "Lap times {}-{} ms: {} selections ({:.0}%)",
-                    start,
-                    end,
-                    count,
-                    (count as f64 / total_elements as f64 * 100.0).round()
-                );
-            }
-        }
+        let multi_start = SubsetCalculationConfig {
+            attempts_per_run: 4,
+            full_coverage: false,
+            ..coverage_config(100, 2, 1)
+        };
+        let mut subset =
+            perform_multiple_runs(&cars, &multi_start).expect("multi-start")[0].clone();
+        subset.sort_unstable();
+        assert_eq!(subset, vec![0, 1]);
     }
-}
-
-/// Web worker entry point for performing multiple subset calculations.
-///
-/// This function is designed to be called from JavaScript in a web worker context.
-/// It handles serialization/deserialization of data across the JS/WASM boundary.
-///
-/// # Arguments
-/// * `cars_js` - Serialized car data from JavaScript
-/// * `target` - Target sum in milliseconds
-/// * `lap_count` - Number of laps per subset
-/// * `player_count` - Number of players (subsets to generate)
-///
-/// # Returns
-/// Serialized result containing all subsets, or error details
-#[wasm_bindgen]
-pub async fn worker_perform_multiple_runs(
-    cars_js: JsValue,
-    target: u32,
-    lap_count: usize,
-    player_count: usize,
-) -> JsValue {
-    // Deserialize cars from JsValue
-    let cars: Vec<Car> = match serde_wasm_bindgen::from_value(cars_js) {
-        Ok(c) => c,
-        Err(e) => {
-            return serde_wasm_bindgen::to_value(&format!("Failed to deserialize cars: {}", e))
-                .unwrap_or(JsValue::NULL);
-        }
-    };
 
-    // Run the calculation with defined constants
-    match perform_multiple_runs(
-        &cars,
-        target,
-        lap_count,
-        player_count,
-        defaults::TIMEOUT_MS,
-        defaults::TOLERANCE_PERCENT,
-    ) {
-        Ok(result) => serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL),
-        Err(e) => serde_wasm_bindgen::to_value(&format!("Calculation failed: {}", e))
-            .unwrap_or(JsValue::NULL),
+    #[test]
+    fn closeness_sampling_favors_cars_near_the_needed_average() {
+        let candidates = [(0, 100), (1, 10_100)];
+        let mut rng = StdRng::seed_from_u64(11);
+
+        let near = (0..1_000)
+            .filter(|_| sample_by_closeness(candidates.into_iter(), 100.0, &mut rng) == Some(0))
+            .count();
+        assert!(near > 990, "near car drawn {near} times");
+        assert_eq!(
+            sample_by_closeness(std::iter::empty(), 100.0, &mut rng),
+            None
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::{rngs::StdRng, Rng, SeedableRng};
-    use std::time::Instant;
+    #[test]
+    fn subset_errors_round_trip_with_their_stable_code() {
+        let errors = [
+            SubsetError::NoValidSubset,
+            SubsetError::InsufficientCandidates(3, 1),
+            SubsetError::TargetUnreachable {
+                target: 100,
+                current_sum: 10,
+                min_possible: 20,
+                max_possible: 30,
+            },
+            SubsetError::InvalidLapTimeFilter { min: 2, max: 1 },
+        ];
 
-    fn car(id: &str, lap_time: u32) -> Car {
-        Car {
-            id: id.to_string(),
-            lap_time,
+        for error in errors {
+            let json = serde_json::to_value(&error).unwrap();
+            assert_eq!(json["code"], error.code());
+            assert_eq!(serde_json::from_value::<SubsetError>(json).unwrap(), error);
         }
+        assert_eq!(
+            SubsetError::TargetUnreachable {
+                target: 100,
+                current_sum: 10,
+                min_possible: 20,
+                max_possible: 30,
+            }
+            .to_string(),
+            "Target 100 is unreachable. Current sum: 10. Possible range: [30 to 40]"
+        );
     }
 
     #[test]
-    fn multiple_runs_use_unused_candidates_before_reusing_cars() {
-        let cars = vec![car("first", 10), car("second", 10)];
-
-        let results = perform_multiple_runs(&cars, 10, 1, 2, 1_000.0, 0.0).unwrap();
+    fn config_builder_validates_and_positional_shim_matches() {
+        let config = SubsetCalculationConfig::builder(30, 2, 1)
+            .tolerance_percent(0.0)
+            .lap_order(LapOrder::FastestFirst)
+            .build()
+            .expect("valid config");
+        assert_eq!(config.lap_order, LapOrder::FastestFirst);
+        assert_eq!(
+            SubsetCalculationConfig::builder(30, 2, 1)
+                .timeout_ms(f64::NAN)
+                .build()
+                .unwrap_err()
+                .code(),
+            "invalid_timeout"
+        );
+        assert_eq!(
+            SubsetCalculationConfig::builder(30, 2, 1)
+                .lap_time_range(Some(9), Some(1))
+                .build()
+                .unwrap_err(),
+            SubsetError::InvalidLapTimeFilter { min: 9, max: 1 }
+        );
 
-        assert_eq!(results.len(), 2);
-        assert_ne!(results[0][0], results[1][0]);
+        let cars = vec![car("a", 10), car("b", 20)];
+        assert_eq!(
+            perform_multiple_runs(&cars, &config).unwrap(),
+            vec![vec![0, 1]]
+        );
+        #[allow(deprecated)]
+        let shim = perform_multiple_runs_positional(&cars, 30, 2, 1, 1_000.0, 0.0).unwrap();
+        assert_eq!(calculate_subset_sum(&cars, &shim[0]), 30);
     }
 
     #[test]
@@ -2166,8 +5727,9 @@ mod tests {
     #[test]
     fn closest_time_handles_full_u32_range() {
         let cars = vec![car("zero", 0), car("max", u32::MAX)];
+        let pool = SortedPool::new(&cars, &[0, 1]);
 
-        assert_eq!(find_closest_time(&cars, &[0, 1], u32::MAX - 1), 1);
+        assert_eq!(pool.closest(u32::MAX - 1), Some(1));
     }
 
     #[test]
@@ -2225,6 +5787,39 @@ mod tests {
         assert!(compute_jaccard_similarity(&[vec![1]]).is_err());
     }
 
+    #[test]
+    fn weighted_jaccard_counts_shared_lap_time() {
+        let cars = [90_000, 90_000, 35_000, 35_000, 60_000, 60_000]
+            .iter()
+            .enumerate()
+            .map(|(index, &lap_time)| car(&format!("car-{index}"), lap_time))
+            .collect::<Vec<_>>();
+        // Both pairs share two of their six cars, but the first shares the long
+        // laps: 180 s of the 370 s in either subset, against 70 s.
+        let long_shared = [vec![0, 1, 2, 4], vec![0, 1, 3, 5]];
+        let short_shared = [vec![2, 3, 0, 4], vec![2, 3, 1, 5]];
+        assert_eq!(compute_jaccard_similarity(&long_shared).unwrap(), 1.0 / 3.0);
+        assert_eq!(
+            compute_jaccard_similarity(&short_shared).unwrap(),
+            1.0 / 3.0
+        );
+        let long = compute_weighted_jaccard_similarity(&cars, &long_shared).unwrap();
+        let short = compute_weighted_jaccard_similarity(&cars, &short_shared).unwrap();
+        assert!((long - 180.0 / 370.0).abs() < 1e-12, "{long}");
+        assert!((short - 70.0 / 370.0).abs() < 1e-12, "{short}");
+
+        // Equal lap times weigh every car alike.
+        let even = (0..6)
+            .map(|index| car(&format!("car-{index}"), 50_000))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            compute_weighted_jaccard_similarity(&even, &long_shared).unwrap(),
+            compute_jaccard_similarity(&long_shared).unwrap()
+        );
+        assert!(compute_weighted_jaccard_similarity(&cars, &[vec![0]]).is_err());
+        assert!(compute_weighted_jaccard_similarity(&cars, &[vec![0], vec![6]]).is_err());
+    }
+
     #[test]
     fn jaccard_matches_reference_for_deterministic_random_inputs() {
         let mut rng = StdRng::seed_from_u64(0x4a_4143_4341_5244);
@@ -2271,27 +5866,433 @@ mod tests {
     }
 
     #[test]
-    fn csv_import_supports_quoted_fields_and_legacy_api() {
-        let input = "\"car, one\",\"01:02.3\"\n";
-        let report = read_cars_from_csv_string_detailed(input);
+    fn csv_import_supports_quoted_fields_and_legacy_api() {
+        let input = "\"car, one\",\"01:02.3\"\n";
+        let report = read_cars_from_csv_string_detailed(input);
+
+        assert_eq!(report.cars, vec![car("car, one", 62_300)]);
+        assert!(report.warnings.is_empty());
+        assert_eq!(read_cars_from_csv_string(input).unwrap(), report.cars);
+    }
+
+    #[test]
+    fn invalid_duplicate_does_not_reserve_id_and_valid_duplicates_are_rejected() {
+        let report =
+            read_cars_from_csv_string_detailed("same,not-a-time\nsame,00:01.000\nsame,00:02.000\n");
+
+        assert_eq!(report.cars, vec![car("same", 1_000)]);
+        assert_eq!(
+            report.warnings[0].kind,
+            CsvImportWarningKind::InvalidLapTime
+        );
+        assert_eq!(report.warnings[1].kind, CsvImportWarningKind::DuplicateId);
+        assert_eq!((report.accepted_count, report.rejected_count), (1, 2));
+    }
+
+    #[test]
+    fn csv_import_reads_optional_uncertainties() {
+        let report = read_cars_from_csv_string_detailed(
+            "a,01:00.000,250\nb,01:01.000,\nc,01:02.000,±40\nd,01:03.000,fast\n",
+        );
+
+        let uncertainties = report
+            .cars
+            .iter()
+            .map(|car| (car.id.as_str(), car.uncertainty_ms))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            uncertainties,
+            [("a", Some(250)), ("b", None), ("c", Some(40))]
+        );
+        assert_eq!(
+            report.warnings[0].kind,
+            CsvImportWarningKind::InvalidUncertainty
+        );
+        assert_eq!(report.warnings[0].row, 4);
+    }
+
+    #[test]
+    fn csv_columns_can_be_named_by_header() {
+        let input = "Session export\nDate,2024-05-01\n\nPos,Driver,Car,Best Lap\n1,Ann,GT3,01:40.000\n2,Bob,GT3\n3,,GT4,01:45.000\n";
+        let report = read_cars_from_csv_string_with_options(
+            input,
+            &CsvImportOptions::by_header("driver", " Best Lap "),
+        );
+
+        assert_eq!(report.header_row, Some(3));
+        assert_eq!(report.cars, vec![car("Ann", 100_000)]);
+        assert_eq!(
+            (
+                report.row_count,
+                report.accepted_count,
+                report.rejected_count
+            ),
+            (3, 1, 2)
+        );
+        assert_eq!(
+            report.warnings[0].kind,
+            CsvImportWarningKind::MissingLapTime
+        );
+        assert_eq!(report.warnings[0].row, 5);
+        assert_eq!(report.warnings[1].kind, CsvImportWarningKind::EmptyId);
+
+        // Named and positional columns can be mixed.
+        let options = CsvImportOptions {
+            uncertainty_column: Some(CsvColumn::Index(2)),
+            ..CsvImportOptions::by_header("Driver", "Best Lap")
+        };
+        let report = read_cars_from_csv_string_with_options(
+            "Driver,Best Lap\nAnn,01:40.000,200\n",
+            &options,
+        );
+        assert_eq!(report.header_row, Some(1));
+        assert_eq!(report.cars[0].uncertainty_ms, Some(200));
+
+        let report = read_cars_from_csv_string_with_options(
+            "Driver,Lap\nAnn,01:40.000\n",
+            &CsvImportOptions::by_header("Driver", "Best Lap"),
+        );
+        assert!(report.cars.is_empty());
+        assert_eq!(report.warnings[0].kind, CsvImportWarningKind::MissingHeader);
+        assert!(report.warnings[0].message.contains("'Best Lap'"));
+    }
+
+    #[test]
+    fn several_lap_time_columns_are_aggregated() {
+        let csv = "Driver,S1,S2,S3\nAnn,01:40.000,01:42.000,01:41.001\nBen,,01:50.000,01:51.000\nCy,,,\nDee,01:40.000,fast,\n";
+        let lap_times = |aggregate| {
+            let report = read_cars_from_csv_string_with_options(
+                csv,
+                &CsvImportOptions::by_headers("Driver", &["S1", "S2", "S3"], aggregate),
+            );
+            assert_eq!(
+                report
+                    .warnings
+                    .iter()
+                    .map(|warning| warning.kind.clone())
+                    .collect::<Vec<_>>(),
+                [
+                    CsvImportWarningKind::MissingLapTime,
+                    CsvImportWarningKind::InvalidLapTime
+                ]
+            );
+            report
+                .cars
+                .iter()
+                .map(|car| car.lap_time)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lap_times(LapTimeAggregate::Min), [100_000, 110_000]);
+        assert_eq!(lap_times(LapTimeAggregate::Mean), [101_000, 110_500]);
+        assert_eq!(lap_times(LapTimeAggregate::Median), [101_001, 110_500]);
+
+        let report = read_cars_from_csv_string_with_options(
+            csv,
+            &CsvImportOptions::by_headers("Driver", &["S1", "S4"], LapTimeAggregate::Min),
+        );
+        assert_eq!(report.warnings[0].kind, CsvImportWarningKind::MissingHeader);
+    }
+
+    #[test]
+    fn duplicate_ids_follow_the_import_policy() {
+        let csv =
+            "GT3,01:40.000,100\nGT4,01:45.000,\nGT3,01:38.000,300\nGT3,01:41.001,\nGT4,bad,\n";
+        let import = |policy| {
+            let options = CsvImportOptions {
+                duplicate_ids: policy,
+                ..CsvImportOptions::default()
+            };
+            read_cars_from_csv_string_with_options(csv, &options)
+        };
+
+        let report = import(DuplicateIdPolicy::Skip);
+        assert_eq!(report.cars[0].lap_time, 100_000);
+        assert_eq!(report.warnings.len(), 3);
+        assert_eq!(
+            (
+                report.accepted_count,
+                report.rejected_count,
+                report.merged_count
+            ),
+            (2, 3, 0)
+        );
+
+        let report = import(DuplicateIdPolicy::KeepFastest);
+        assert_eq!(
+            (report.cars[0].lap_time, report.cars[0].uncertainty_ms),
+            (98_000, Some(300))
+        );
+        assert_eq!(
+            (
+                report.accepted_count,
+                report.rejected_count,
+                report.merged_count
+            ),
+            (2, 1, 2)
+        );
+        assert_eq!(report.cars[1], car("GT4", 105_000));
+
+        let report = import(DuplicateIdPolicy::KeepSlowest);
+        assert_eq!(
+            (report.cars[0].lap_time, report.cars[0].uncertainty_ms),
+            (101_001, None)
+        );
+
+        let report = import(DuplicateIdPolicy::Average);
+        assert_eq!(
+            (report.cars[0].lap_time, report.cars[0].uncertainty_ms),
+            (99_667, Some(100))
+        );
+    }
+
+    #[test]
+    fn car_metadata_is_read_from_csv_columns_and_json() {
+        let options = CsvImportOptions {
+            name_column: Some(CsvColumn::Header("Model".to_string())),
+            class_column: Some(CsvColumn::Header("Class".to_string())),
+            ..CsvImportOptions::by_header("ID", "Lap")
+        };
+        let report = read_cars_from_csv_string_with_options(
+            "ID,Class,Lap,Model\ncup-7,GT3,01:40.000,Porsche 992 GT3 Cup\ncup-8, ,01:41.000,\n",
+            &options,
+        );
+        let cup = &report.cars[0];
+        assert_eq!(cup.name.as_deref(), Some("Porsche 992 GT3 Cup"));
+        assert_eq!(cup.class.as_deref(), Some("GT3"));
+        assert_eq!(cup.track, None);
+        assert_eq!(cup.display_name(), "Porsche 992 GT3 Cup");
+        assert_eq!(report.cars[1], car("cup-8", 101_000));
+        assert_eq!(report.cars[1].display_name(), "cup-8");
+
+        let cars = read_cars_from_json(
+            r#"[{"id": "cup-7", "lap_time": 100000, "name": "Porsche 992 GT3 Cup", "track": "Monza"}]"#,
+        )
+        .unwrap();
+        assert_eq!(cars[0].name, cup.name);
+        assert_eq!(cars[0].track.as_deref(), Some("Monza"));
+        let json = serde_json::to_string(&cars[0]).unwrap();
+        assert!(!json.contains("class"), "{json}");
+        assert_eq!(serde_json::from_str::<Car>(&json).unwrap(), cars[0]);
+    }
+
+    #[test]
+    fn disabled_cars_are_imported_but_never_selected() {
+        let options = CsvImportOptions {
+            enabled_column: Some(CsvColumn::Index(2)),
+            uncertainty_column: None,
+            ..CsvImportOptions::default()
+        };
+        let report = read_cars_from_csv_string_with_options(
+            "a,00:10.000,yes\nb,00:10.000,NO\nc,00:20.000,\nd,00:20.000,0\ne,00:30.000,maybe\n",
+            &options,
+        );
+        let flags = report
+            .cars
+            .iter()
+            .map(|car| car.enabled)
+            .collect::<Vec<_>>();
+        assert_eq!(flags, [true, false, true, false]);
+        assert_eq!(
+            report.warnings[0].kind,
+            CsvImportWarningKind::InvalidEnabled
+        );
+
+        let cars = report.cars;
+        assert_eq!(get_target_range_for_players(&cars, 1, 1), (10_000, 20_000));
+        for strategy in [SolverStrategy::Legacy, SolverStrategy::Bounded] {
+            let config = SubsetCalculationConfig::builder(30_000, 2, 3)
+                .strategy(strategy)
+                .build()
+                .unwrap();
+            let results = perform_multiple_runs(&cars, &config).unwrap();
+            assert!(results.iter().flatten().all(|&index| cars[index].enabled));
+        }
+        assert_ne!(dataset_hash(&cars), {
+            let mut enabled = cars.clone();
+            enabled[1].enabled = true;
+            dataset_hash(&enabled)
+        });
+
+        let json = serde_json::to_string(&cars[..2]).unwrap();
+        assert_eq!(json.matches("enabled").count(), 1, "{json}");
+        assert_eq!(read_cars_from_json(&json).unwrap(), cars[..2]);
+    }
+
+    #[test]
+    fn car_lists_are_read_from_plain_or_gzipped_bytes() {
+        let cars = vec![car("GT3", 92_456), car("LMP", 88_250)];
+        let csv = write_cars_to_csv(&cars);
+        assert_eq!(
+            read_cars_from_bytes(csv.as_bytes()).unwrap(),
+            (
+                cars.clone(),
+                "comma-separated, data starts at line 1".to_string()
+            )
+        );
+        let json = serde_json::to_string(&cars).unwrap();
+        assert_eq!(
+            read_cars_from_bytes(&compression::gzip(json.as_bytes())).unwrap(),
+            (cars, "gzip, JSON".to_string())
+        );
+        assert!(read_cars_from_bytes(&[0x1f, 0x8b, 0]).is_err());
+    }
+
+    #[test]
+    fn json_car_lists_accept_milliseconds_and_formatted_times() {
+        let cars = read_cars_from_json(
+            r#"[
+                {"id": "GT3", "lap_time": 92456, "livery": "red"},
+                {"id": " LMP ", "lapTime": "1:28,250", "uncertainty_ms": 150}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(cars[0], car("GT3", 92_456));
+        assert_eq!(
+            cars[1],
+            Car {
+                uncertainty_ms: Some(150),
+                ..car("LMP", 88_250)
+            }
+        );
+
+        for (json, error) in [
+            (r#"{"id": "GT3"}"#, "Invalid car list"),
+            (r#"[{"id": "GT3"}]"#, "Invalid car list"),
+            (r#"[{"id": "GT3", "lap_time": "fast"}]"#, "Car 0:"),
+            (
+                r#"[{"id": "a", "lap_time": 1}, {"id": "a", "lap_time": 2}]"#,
+                "Car 1: duplicate ID 'a'",
+            ),
+            (
+                r#"[{"id": " ", "lap_time": 1}]"#,
+                "Car 0: vehicle ID is empty",
+            ),
+        ] {
+            let message = read_cars_from_json(json).unwrap_err();
+            assert!(message.starts_with(error), "{message}");
+        }
+    }
+
+    #[test]
+    fn results_export_to_csv_with_totals_and_deviation() {
+        let cars = vec![
+            car("GT3, red", 60_000),
+            car("GT4", 61_500),
+            car("LMP", 58_250),
+        ];
+        let csv = export_results_csv(&cars, &[vec![0, 1], vec![2, 9, 1]], 120_000);
+        assert_eq!(
+            csv,
+            "set,total,deviation_percent,car_1,lap_time_1,car_2,lap_time_2\n\
+             1,02:01.500,1.250,\"GT3, red\",01:00.000,GT4,01:01.500\n\
+             2,01:59.750,-0.208,LMP,00:58.250,GT4,01:01.500\n"
+        );
+        assert_eq!(
+            export_results_csv(&cars, &[], 120_000),
+            "set,total,deviation_percent\n"
+        );
+
+        assert_eq!(
+            format_subset_markdown(&cars, &[2, 9, 1], 120_000, "Ana"),
+            "**Ana** · 01:59.750 (-0.21%)\n- LMP · 00:58.250\n- GT4 · 01:01.500\n"
+        );
+
+        let named = export_named_results_csv(
+            &cars,
+            &[vec![0, 1], vec![2, 1]],
+            120_000,
+            &["Ana".to_string()],
+        );
+        assert_eq!(
+            named,
+            "set,player,total,deviation_percent,car_1,lap_time_1,car_2,lap_time_2\n\
+             1,Ana,02:01.500,1.250,\"GT3, red\",01:00.000,GT4,01:01.500\n\
+             2,,01:59.750,-0.208,LMP,00:58.250,GT4,01:01.500\n"
+        );
+    }
+
+    #[test]
+    fn written_car_lists_read_back_unchanged() {
+        let mut cars = vec![
+            car("GT3, red", 60_000),
+            car("GT4 \"Evo\"", 61_512),
+            car("LMP", 3_725_001),
+        ];
+        assert_eq!(
+            write_cars_to_csv(&cars),
+            "\"GT3, red\",01:00.000\n\"GT4 \"\"Evo\"\"\",01:01.512\nLMP,62:05.001\n"
+        );
+        assert_eq!(
+            read_cars_from_csv_string(&write_cars_to_csv(&cars)).unwrap(),
+            cars
+        );
 
-        assert_eq!(report.cars, vec![car("car, one", 62_300)]);
-        assert!(report.warnings.is_empty());
-        assert_eq!(read_cars_from_csv_string(input).unwrap(), report.cars);
+        cars[1].uncertainty_ms = Some(250);
+        let csv = write_cars_to_csv(&cars);
+        assert!(csv.starts_with("\"GT3, red\",01:00.000,\n"), "{csv}");
+        assert_eq!(read_cars_from_csv_string(&csv).unwrap(), cars);
+        assert_eq!(write_cars_to_csv(&[]), "");
+
+        // Disabled cars and metadata come back too, under a header.
+        cars[0].enabled = false;
+        cars[1].name = Some("Porsche 992, Cup".to_string());
+        cars[1].class = Some("GT3".to_string());
+        cars[2].track = Some("Le Mans".to_string());
+        let csv = write_cars_to_csv(&cars);
+        assert_eq!(
+            csv,
+            "id,lap_time,uncertainty_ms,name,class,track,enabled\n\
+             \"GT3, red\",01:00.000,,,,,no\n\
+             \"GT4 \"\"Evo\"\"\",01:01.512,250,\"Porsche 992, Cup\",GT3,,yes\n\
+             LMP,62:05.001,,,,Le Mans,yes\n"
+        );
+        assert_eq!(read_cars_from_csv_string(&csv).unwrap(), cars);
+        assert_eq!(read_cars_from_bytes(csv.as_bytes()).unwrap().0, cars);
     }
 
     #[test]
-    fn invalid_duplicate_does_not_reserve_id_and_valid_duplicates_are_rejected() {
-        let report =
-            read_cars_from_csv_string_detailed("same,not-a-time\nsame,00:01.000\nsame,00:02.000\n");
-
-        assert_eq!(report.cars, vec![car("same", 1_000)]);
+    fn csv_layouts_are_sniffed_before_import() {
+        let input = "Results;Spa\nExported;2024-05-01\n\nCar;Lap\nBob;01:41,500\nCid;01:42,000\n";
+        let layout = sniff_csv_layout(input);
         assert_eq!(
-            report.warnings[0].kind,
-            CsvImportWarningKind::InvalidLapTime
+            layout,
+            CsvLayout {
+                delimiter: b';',
+                header_line: Some(4),
+                data_start_line: 5,
+            }
         );
-        assert_eq!(report.warnings[1].kind, CsvImportWarningKind::DuplicateId);
-        assert_eq!((report.accepted_count, report.rejected_count), (1, 2));
+        assert_eq!(
+            layout.describe(),
+            "semicolon-separated, data starts at line 5"
+        );
+        let report = read_cars_from_csv_string_with_options(input, &layout.options());
+        assert_eq!(report.cars, vec![car("Bob", 101_500), car("Cid", 102_000)]);
+        assert_eq!(report.row_count, 2);
+        assert_eq!(read_cars_from_csv_string(input).unwrap(), report.cars);
+
+        let tabbed = sniff_csv_layout("a\t01:00.000\nb\t01:01.000\n");
+        assert_eq!((tabbed.delimiter, tabbed.header_line), (b'\t', None));
+        assert_eq!(sniff_csv_layout("no cars here\n"), CsvLayout::default());
+    }
+
+    #[test]
+    fn tolerance_probabilities_propagate_lap_time_uncertainty() {
+        let mut cars = vec![car("a", 50_000), car("b", 50_000), car("c", 51_000)];
+        // Exact lap times are either within tolerance or not.
+        assert_eq!(tolerance_probability(&cars, &[0, 1], 100_000, 1.0), 1.0);
+        assert_eq!(tolerance_probability(&cars, &[0, 2], 100_000, 0.5), 0.0);
+
+        // Two cars of ±300 ms sum to ±424 ms, so a 1% window of ±1000 ms
+        // around the exact total holds about 98%.
+        cars[0].uncertainty_ms = Some(300);
+        cars[1].uncertainty_ms = Some(300);
+        let centered = tolerance_probability(&cars, &[0, 1], 100_000, 1.0);
+        assert!((centered - 0.981_6).abs() < 1e-3, "{centered}");
+        // A total at the edge of the window lands inside about half the time.
+        let edge = tolerance_probability(&cars, &[0, 2], 100_000, 1.0);
+        assert!((edge - 0.5).abs() < 1e-6, "{edge}");
+        assert!(tolerance_probability(&cars, &[0, 1], 100_000, 0.1) < centered);
     }
 
     #[test]
@@ -2314,6 +6315,125 @@ mod tests {
         assert_eq!(parse_lap_time("00:00.1").unwrap(), 100);
     }
 
+    #[test]
+    fn lap_time_parser_accepts_every_documented_format() {
+        for (input, expected) in [
+            ("1:32.456", 92_456),
+            ("01:32", 92_000),
+            ("1:01:02.5", 3_662_500),
+            ("92.456", 92_456),
+            (" 92.4 ", 92_400),
+            ("150s", 150_000),
+            ("150.25s", 150_250),
+            ("2m 30s", 150_000),
+            ("2m30s", 150_000),
+            ("150000", 150_000),
+            ("150:00", 9_000_000),
+            ("1:32,456", 92_456),
+            ("1:01:02,5", 3_662_500),
+            ("92,45", 92_450),
+            ("150,5s", 150_500),
+        ] {
+            assert_eq!(parse_lap_time(input), Ok(expected), "{input}");
+        }
+        for input in [
+            "",
+            "1:60",
+            "1:60:00",
+            "1:2:3:4",
+            "1.2.3",
+            "abc",
+            "1:-5",
+            "92.",
+            "m30s",
+            "4294967296",
+            "1:32,4,5",
+            "1:32.4,5",
+        ] {
+            assert!(parse_lap_time(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn player_target_range_narrows_to_what_disjoint_runs_can_share() {
+        let cars = [10, 20, 30, 40, 50, 60]
+            .iter()
+            .map(|&lap_time| car("car", lap_time))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            get_target_range_for_players(&cars, 2, 1),
+            get_target_range_for_subset(&cars, 2)
+        );
+        // Two disjoint pairs use four cars: at best 10+20+30+40 = 100 and at
+        // worst 30+40+50+60 = 180 shared between them.
+        assert_eq!(get_target_range_for_players(&cars, 2, 2), (50, 90));
+        // Only three runs can be disjoint; later runs reuse cars.
+        assert_eq!(get_target_range_for_players(&cars, 2, 3), (70, 70));
+        assert_eq!(get_target_range_for_players(&cars, 2, 10), (70, 70));
+        assert_eq!(get_target_range_for_players(&cars, 7, 2), (0, 0));
+
+        // Only the legacy strategy keeps every run away from used cars.
+        assert_eq!(SolverStrategy::Legacy.disjoint_runs(3), 3);
+        assert_eq!(SolverStrategy::Bounded.disjoint_runs(3), 1);
+        assert_eq!(SolverStrategy::Bounded.disjoint_runs(0), 0);
+    }
+
+    #[test]
+    fn errors_suggest_remedies_from_their_context() {
+        assert_eq!(
+            SubsetError::OutsideTolerance(100.853).suggestions(),
+            ["Raise the tolerance to at least 0.86%"]
+        );
+        assert_eq!(
+            SubsetError::NotEnoughSuccessfulRuns {
+                required: 30,
+                found: 28
+            }
+            .suggestions()[0],
+            "Reduce the players to at most 28"
+        );
+        assert_eq!(
+            SubsetError::TargetUnreachable {
+                target: 400_000,
+                current_sum: 100_000,
+                min_possible: 50_000,
+                max_possible: 250_000,
+            }
+            .suggestions(),
+            ["Choose a target between 02:30.000 and 05:50.000, the range for these settings"]
+        );
+        assert_eq!(
+            SubsetError::ImpossibleCount {
+                requested: 5,
+                available: 3
+            }
+            .suggestions(),
+            ["Lower the lap count to at most 3", "Add at least 2 cars"]
+        );
+        // Every error offers at least one remedy.
+        let config = SubsetCalculationConfig::builder(10, 1, 1).build().unwrap();
+        let error = perform_multiple_runs(&[], &config).unwrap_err();
+        assert!(!error.suggestions().is_empty());
+    }
+
+    #[test]
+    fn average_lap_targets_derive_the_total_from_the_lap_count() {
+        assert_eq!(TargetSpec::Total(300_000).total(3), 300_000);
+        assert_eq!(TargetSpec::AverageLap(112_000).total(3), 336_000);
+        assert_eq!(TargetSpec::AverageLap(u32::MAX).total(2), u32::MAX);
+
+        let config = SubsetCalculationConfig::builder(0, 25, 1)
+            .target(TargetSpec::AverageLap(112_000))
+            .build()
+            .unwrap();
+        assert_eq!(config.target, 2_800_000);
+        assert_eq!(
+            serde_json::to_string(&TargetSpec::AverageLap(112_000)).unwrap(),
+            r#"{"average_lap":112000}"#
+        );
+    }
+
     #[test]
     fn accumulated_subset_and_range_arithmetic_clamps_instead_of_overflowing() {
         let cars = vec![car("a", u32::MAX), car("b", u32::MAX), car("c", 1)];
@@ -2405,10 +6525,10 @@ mod tests {
     fn invalid_timeout_and_tolerance_are_rejected() {
         let cars = vec![car("exact", 100)];
 
-        assert!(perform_multiple_runs(&cars, 100, 1, 1, f64::NAN, 0.0).is_err());
-        assert!(perform_multiple_runs(&cars, 100, 1, 1, -1.0, 0.0).is_err());
-        assert!(perform_multiple_runs(&cars, 100, 1, 1, 100.0, f64::NAN).is_err());
-        assert!(perform_multiple_runs(&cars, 100, 1, 1, 100.0, -1.0).is_err());
+        assert!(perform_multiple_runs(&cars, &run_config(100, 1, 1, f64::NAN, 0.0)).is_err());
+        assert!(perform_multiple_runs(&cars, &run_config(100, 1, 1, -1.0, 0.0)).is_err());
+        assert!(perform_multiple_runs(&cars, &run_config(100, 1, 1, 100.0, f64::NAN)).is_err());
+        assert!(perform_multiple_runs(&cars, &run_config(100, 1, 1, 100.0, -1.0)).is_err());
     }
 
     #[test]
@@ -2730,11 +6850,13 @@ mod tests {
         let started = Instant::now();
         let results = perform_multiple_runs(
             &cars,
-            UI_TARGET,
-            UI_LAP_COUNT,
-            UI_PLAYER_COUNT,
-            defaults::TIMEOUT_MS,
-            defaults::TOLERANCE_PERCENT,
+            &run_config(
+                UI_TARGET,
+                UI_LAP_COUNT,
+                UI_PLAYER_COUNT,
+                defaults::TIMEOUT_MS,
+                defaults::TOLERANCE_PERCENT,
+            ),
         )
         .expect("bounded solver should complete within the UI timeout");
         let elapsed = started.elapsed();
@@ -2880,14 +7002,12 @@ mod tests {
                 ("legacy", SolverStrategy::Legacy, &mut legacy),
             ] {
                 let started = Instant::now();
-                let result = perform_multiple_runs_with_strategy(
-                    strategy,
+                let result = perform_multiple_runs(
                     &cars,
-                    target,
-                    LAP_COUNT,
-                    PLAYER_COUNT,
-                    TIMEOUT_MS,
-                    TOLERANCE,
+                    &SubsetCalculationConfig {
+                        strategy,
+                        ..run_config(target, LAP_COUNT, PLAYER_COUNT, TIMEOUT_MS, TOLERANCE)
+                    },
                 );
                 let elapsed = started.elapsed();
                 metrics.elapsed += elapsed;
@@ -3028,20 +7148,63 @@ mod tests {
         );
     }
 
+    #[test]
+    #[ignore = "weighted selection benchmark"]
+    fn weighted_selection_benchmark() {
+        use rand::distr::weighted::WeightedIndex;
+        use rand_distr::Distribution;
+
+        let cars: Vec<Car> = (0..20_000)
+            .map(|index| car(&index.to_string(), 60_000 + index * 7))
+            .collect();
+        let pool = SortedPool::new(&cars, &(0..cars.len()).collect::<Vec<_>>());
+        let needed_avg = 120_000.0;
+        let draws = 2_000;
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let started = Instant::now();
+        let mut checksum = 0;
+        for _ in 0..draws {
+            let window: Vec<CarIndex> = pool.in_range(0, u32::MAX).map(|(idx, _)| idx).collect();
+            let weights = window
+                .iter()
+                .map(|&idx| 1.0 / ((get_lap_time(&cars, idx) as f64 - needed_avg).abs() + 1.0));
+            let dist = WeightedIndex::new(weights).unwrap();
+            checksum += window[dist.sample(&mut rng)];
+        }
+        let weighted_index = started.elapsed();
+
+        let started = Instant::now();
+        for _ in 0..draws {
+            checksum +=
+                sample_by_closeness(pool.in_range(0, u32::MAX), needed_avg, &mut rng).unwrap();
+        }
+        let closeness = started.elapsed();
+
+        println!(
+            "{draws} draws over {} cars: WeightedIndex={weighted_index:?}, sample_by_closeness={closeness:?} (checksum {checksum})",
+            cars.len()
+        );
+    }
+
     fn run_increment_one_benchmark(lap_count: usize, player_count: usize, target: u32) {
         use std::io::Write;
         let cars = read_cars_from_csv_string(include_str!("cars.csv")).unwrap();
         print!("benchmark {lap_count}x{player_count} target={target}: starting... ");
         std::io::stdout().flush().unwrap();
         let started = Instant::now();
-        let result = perform_multiple_runs_with_strategy(
-            SolverStrategy::Bounded,
+        let result = perform_multiple_runs(
             &cars,
-            target,
-            lap_count,
-            player_count,
-            5_000.0,
-            defaults::TOLERANCE_PERCENT,
+            &SubsetCalculationConfig {
+                strategy: SolverStrategy::Bounded,
+                ..run_config(
+                    target,
+                    lap_count,
+                    player_count,
+                    5_000.0,
+                    defaults::TOLERANCE_PERCENT,
+                )
+            },
         );
         let elapsed = started.elapsed();
         let completed = result.as_ref().map_or(0, Vec::len);
@@ -3137,14 +7300,12 @@ mod tests {
             );
             std::io::stdout().flush().unwrap();
             let started = Instant::now();
-            let result = perform_multiple_runs_with_strategy(
-                SolverStrategy::Bounded,
+            let result = perform_multiple_runs(
                 &cars,
-                target,
-                LAP_COUNT,
-                PLAYER_COUNT,
-                TIMEOUT_MS,
-                TOLERANCE,
+                &SubsetCalculationConfig {
+                    strategy: SolverStrategy::Bounded,
+                    ..run_config(target, LAP_COUNT, PLAYER_COUNT, TIMEOUT_MS, TOLERANCE)
+                },
             );
             let elapsed = started.elapsed();
             let completed = result.as_ref().map_or_else(|_| 0, |subsets| subsets.len());
@@ -3216,11 +7377,553 @@ mod tests {
         std::io::stdout().flush().unwrap();
     }
 
+    #[test]
+    fn disjoint_mode_never_shares_cars_and_fails_upfront_when_cars_run_short() {
+        let cars = (0..9)
+            .map(|index| car(&format!("car-{index}"), 100_000 + index * 100))
+            .collect::<Vec<_>>();
+        for strategy in [SolverStrategy::Bounded, SolverStrategy::Legacy] {
+            let mut config = run_config(300_400, 3, 3, 5_000.0, 1.0);
+            config.strategy = strategy;
+            config.disjoint = true;
+            let results = perform_multiple_runs(&cars, &config).unwrap();
+            let mut used = results.concat();
+            used.sort_unstable();
+            used.dedup();
+            assert_eq!(used.len(), 9);
+        }
+
+        let mut config = run_config(300_400, 3, 4, 5_000.0, 1.0);
+        config.disjoint = true;
+        let started = Instant::now();
+        let error = perform_multiple_runs(&cars, &config).unwrap_err();
+        assert!(started.elapsed().as_millis() < 100);
+        assert_eq!(
+            error,
+            SubsetError::DisjointInfeasible {
+                lap_count: 3,
+                player_count: 4,
+                cars: 9,
+                max_players: 3
+            }
+        );
+        assert_eq!(error.suggestions()[0], "Reduce the players to at most 3");
+    }
+
+    #[test]
+    fn shared_car_cap_holds_between_every_pair_of_subsets() {
+        let cars = (0..9)
+            .map(|index| car(&format!("car-{index}"), 100_000 + index))
+            .collect::<Vec<_>>();
+        let mut config = run_config(300_000, 3, 4, 5_000.0, 1.0);
+        config.max_shared_cars = Some(1);
+        // Runs choose one after another, so an early pick can leave a later
+        // run without options. Four such triples fit in six cars, but only
+        // the exhaustive search reliably finds them.
+        for (pool, exhaustive_limit) in [(&cars[..], 0), (&cars[..6], 20)] {
+            config.exhaustive_limit = exhaustive_limit;
+            for _ in 0..10 {
+                let mut events = Vec::new();
+                let results =
+                    perform_multiple_runs_with_events(pool, &config, &mut events).unwrap();
+                // The cap is kept while choosing, not found broken after.
+                assert!(!events
+                    .iter()
+                    .any(|event| matches!(event, SolverEvent::SharedCarsRejected { .. })));
+                for (index, subset) in results.iter().enumerate() {
+                    for other in &results[index + 1..] {
+                        let shared = subset.iter().filter(|car| other.contains(car)).count();
+                        assert!(shared <= 1, "{results:?}");
+                    }
+                }
+            }
+        }
+
+        // Three triples sharing at most one car need six cars.
+        let mut config = run_config(300_000, 3, 3, 10_000.0, 1.0);
+        config.max_shared_cars = Some(1);
+        let started = Instant::now();
+        let error = perform_multiple_runs(&cars[..4], &config).unwrap_err();
+        assert!(started.elapsed().as_millis() < 100);
+        assert_eq!(
+            error,
+            SubsetError::SharedCarsInfeasible {
+                player_count: 3,
+                lap_count: 3,
+                max_shared: 1,
+                required: 6,
+                cars: 4
+            }
+        );
+        assert_eq!(error.code(), "shared_cars_infeasible");
+        assert_eq!(shared_cap_min_cars(3, 3, 0), 9);
+        assert_eq!(shared_cap_min_cars(3, 10, 2), 4);
+    }
+
+    #[test]
+    fn swap_pass_never_worsens_accuracy_or_similarity() {
+        let cars = (0..12)
+            .map(|index| car(&format!("car-{index}"), 90_000 + index * 1_300))
+            .collect::<Vec<_>>();
+        let mut config = run_config(300_000, 3, 6, 2_000.0, 3.0);
+        config.swap_pass = true;
+        for _ in 0..5 {
+            let mut events = Vec::new();
+            let results = perform_multiple_runs_with_events(&cars, &config, &mut events).unwrap();
+            let Some(&SolverEvent::SwapPassCompleted {
+                deviation_before,
+                deviation_after,
+                similarity_before,
+                similarity_after,
+                ..
+            }) = events.last()
+            else {
+                panic!("no swap pass reported: {events:?}");
+            };
+            assert!(deviation_after <= deviation_before + 1e-9);
+            assert!(similarity_after <= similarity_before + 1e-9);
+            assert!(
+                (similarity_after - compute_jaccard_similarity(&results).unwrap()).abs() < 1e-9
+            );
+            assert!(achieved_tolerance_percent(&cars, &results, 300_000) <= 3.0);
+        }
+    }
+
+    #[test]
+    fn single_subsets_are_resolved_against_the_others() {
+        let cars = (0..15)
+            .map(|index| car(&format!("car-{index}"), 100_000 + index * 100))
+            .collect::<Vec<_>>();
+        let mut config = run_config(300_000, 3, 3, 2_000.0, 1.0);
+        config.disjoint = true;
+        let results = perform_multiple_runs(&cars, &config).unwrap();
+        for _ in 0..10 {
+            let subset = resolve_single_subset(&cars, &results, 1, &config).unwrap();
+            assert_eq!(subset.len(), 3);
+            let accuracy = accuracy_percent(calculate_subset_sum(&cars, &subset), 300_000);
+            assert!(within_tolerance(accuracy, 1.0), "{accuracy}");
+            assert!(subset
+                .iter()
+                .all(|car| !results[0].contains(car) && !results[2].contains(car)));
+        }
+
+        // With only the replaced subset's cars left, it is the only choice.
+        let mut shared = config.clone();
+        shared.disjoint = false;
+        shared.max_shared_cars = Some(0);
+        let subset = resolve_single_subset(
+            &cars[..9],
+            &[vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8]],
+            2,
+            &shared,
+        );
+        let mut subset = subset.unwrap();
+        subset.sort_unstable();
+        assert_eq!(subset, [6, 7, 8]);
+
+        let error = resolve_single_subset(&cars, &results, 3, &config).unwrap_err();
+        assert_eq!(
+            error,
+            SubsetError::InvalidPlayerIndex {
+                player: 4,
+                player_count: 3
+            }
+        );
+        assert_eq!(error.suggestions(), ["Choose a player between 1 and 3"]);
+    }
+
+    #[test]
+    fn resolved_subsets_spread_over_the_quantiles_of_the_filtered_pool() {
+        // Twelve cars pass the filter, four per tercile; the three slow ones
+        // would shift every tercile boundary.
+        let cars = (0..12)
+            .map(|index| car(&format!("car-{index}"), 100_000 + index * 500))
+            .chain((12..15).map(|index| car(&format!("slow-{index}"), 200_000)))
+            .collect::<Vec<_>>();
+        let mut config = run_config(307_500, 3, 2, 2_000.0, 1.0);
+        config.max_lap_time = Some(150_000);
+        config.quantile_bins = 3;
+        let results = perform_multiple_runs(&cars, &config).unwrap();
+        for seed in 0..20 {
+            config.seed = Some(seed);
+            for player in 0..2 {
+                let subset = resolve_single_subset(&cars, &results, player, &config).unwrap();
+                let mut terciles = subset.iter().map(|&index| index / 4).collect::<Vec<_>>();
+                terciles.sort_unstable();
+                assert_eq!(terciles, [0, 1, 2], "seed {seed}: {subset:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn neighbor_results_are_derived_by_swaps() {
+        let cars = (0..15)
+            .map(|index| car(&format!("car-{index}"), 100_000 + index * 100))
+            .collect::<Vec<_>>();
+        let mut config = run_config(302_100, 3, 3, 2_000.0, 0.04);
+        config.disjoint = true;
+        let results = perform_multiple_runs(&cars, &config).unwrap();
+
+        for target in [302_400, 301_800, 303_000] {
+            let neighbor = SubsetCalculationConfig {
+                target,
+                ..config.clone()
+            };
+            let derived = derive_neighbor_result(&cars, &results, &neighbor).unwrap();
+            assert_eq!(derived.len(), 3);
+            for subset in &derived {
+                let accuracy = accuracy_percent(calculate_subset_sum(&cars, subset), target);
+                assert!(within_tolerance(accuracy, 0.04), "{accuracy}");
+            }
+            let mut used = derived.concat();
+            used.sort_unstable();
+            used.dedup();
+            assert_eq!(used.len(), 9);
+        }
+
+        // Out of reach of any three cars, and for a different lap count.
+        let unreachable = SubsetCalculationConfig {
+            target: 400_000,
+            ..config.clone()
+        };
+        assert_eq!(derive_neighbor_result(&cars, &results, &unreachable), None);
+        let longer = SubsetCalculationConfig {
+            lap_count: 4,
+            ..config
+        };
+        assert_eq!(derive_neighbor_result(&cars, &results, &longer), None);
+    }
+
+    #[test]
+    fn run_budgets_skip_a_stuck_run_and_keep_the_others() {
+        let mut cars = (0..10)
+            .map(|index| car(&format!("car-{index}"), 100_000 + index))
+            .collect::<Vec<_>>();
+        for (car, lap_time) in cars[6..].iter_mut().zip([90_000, 90_000, 110_000, 110_000]) {
+            car.lap_time = lap_time;
+        }
+        // No three of the second player's cars come near the target, so the
+        // legacy heuristic rejects their attempts until the run's budget
+        // runs out.
+        let mut config = run_config(300_003, 3, 3, 10_000.0, 0.01);
+        config.strategy = SolverStrategy::Legacy;
+        config.player_pools = [
+            &["car-0", "car-1", "car-2"][..],
+            &["car-6", "car-7", "car-8", "car-9"],
+            &["car-3", "car-4", "car-5"],
+        ]
+        .iter()
+        .map(|pool| pool.iter().map(|id| id.to_string()).collect())
+        .collect();
+        config.run_timeout_ms = Some(50.0);
+        let start = Instant::now();
+        let mut events = Vec::new();
+        let error = perform_multiple_runs_with_events(&cars, &config, &mut events).unwrap_err();
+        assert!(start.elapsed().as_millis() < 5_000);
+        // The caller learns which run was skipped, not a row without cars.
+        assert_eq!(
+            error,
+            SubsetError::RunsSkipped {
+                runs: vec![2],
+                budget_ms: 50.0
+            }
+        );
+        assert_eq!(error.code(), "runs_skipped");
+        assert_eq!(
+            error.to_string(),
+            "Run 2 did not finish within its 50 ms budget"
+        );
+        assert!(matches!(
+            worker_agent::WorkerError::from(error.clone()),
+            worker_agent::WorkerError::Timeout(_)
+        ));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            SolverEvent::RunSkipped { run: 2, budget_ms, attempts, .. }
+                if *budget_ms == 50.0 && *attempts > 0
+        )));
+        // The third player was still solved after the second was skipped.
+        let completed = events
+            .iter()
+            .filter_map(|event| match event {
+                SolverEvent::RunCompleted { run, subset, .. } => {
+                    let mut subset = subset.clone();
+                    subset.sort_unstable();
+                    Some((*run, subset))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(completed, [(1, vec![0, 1, 2]), (3, vec![3, 4, 5])]);
+        let (_, metrics) = measure_multiple_runs(&cars, &config);
+        assert_eq!(
+            metrics
+                .runs
+                .iter()
+                .map(|run| run.skipped)
+                .collect::<Vec<_>>(),
+            [false, true, false]
+        );
+
+        config.player_pools = vec![config.player_pools[1].clone(); 3];
+        let error = perform_multiple_runs(&cars, &config).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Run 1, 2 and 3 did not finish within its 50 ms budget"
+        );
+
+        config.strategy = SolverStrategy::Bounded;
+        config.player_pools.clear();
+        assert_eq!(perform_multiple_runs(&cars, &config).unwrap().len(), 3);
+        config.run_timeout_ms = Some(f64::NAN);
+        assert!(matches!(
+            perform_multiple_runs(&cars, &config),
+            Err(SubsetError::InvalidTimeout(_))
+        ));
+    }
+
+    #[test]
+    fn cancelled_calculations_stop_before_their_timeout() {
+        let mut cars = (0..9)
+            .map(|index| car(&format!("car-{index}"), 100_000 + index))
+            .collect::<Vec<_>>();
+        // No three of these cars come near the target, so the legacy
+        // heuristic retries until stopped.
+        let stuck = [90_000, 90_000, 110_000, 110_000]
+            .into_iter()
+            .enumerate()
+            .map(|(index, lap_time)| car(&format!("stuck-{index}"), lap_time))
+            .collect::<Vec<_>>();
+        let token = CancellationToken::new();
+        let mut config = run_config(300_003, 3, 3, 10_000.0, 0.01);
+        config.strategy = SolverStrategy::Legacy;
+        config.cancellation = Some(token.clone());
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            token.cancel();
+        });
+        let start = Instant::now();
+        let error = perform_multiple_runs(&stuck, &config).unwrap_err();
+        canceller.join().unwrap();
+        assert!(start.elapsed().as_millis() < 5_000);
+        assert_eq!(error, SubsetError::Cancelled);
+        assert_eq!(error.code(), "cancelled");
+
+        // Solvable, but cancelled before it started.
+        config.strategy = SolverStrategy::Bounded;
+        cars.truncate(9);
+        assert_eq!(
+            perform_multiple_runs(&cars, &config),
+            Err(SubsetError::Cancelled)
+        );
+        assert_eq!(
+            derive_neighbor_result(&cars, &[vec![0, 1, 2]], &config),
+            None
+        );
+
+        let json = serde_json::to_string(&config).unwrap();
+        let restored = serde_json::from_str::<SubsetCalculationConfig>(&json).unwrap();
+        assert_eq!(restored.cancellation, None);
+        assert!(!restored.is_cancelled());
+    }
+
+    #[test]
+    fn minimax_objective_lowers_the_worst_deviation() {
+        let cars = (0..30)
+            .map(|index| car(&format!("car-{index}"), 90_000 + index * 700))
+            .collect::<Vec<_>>();
+        let mut config = run_config(300_000, 3, 5, 5_000.0, 5.0);
+        config.objective = Objective::Minimax;
+
+        let mut events = Vec::new();
+        let results = perform_multiple_runs_with_events(&cars, &config, &mut events).unwrap();
+        let Some(SolverEvent::Rebalanced {
+            worst_deviation_before,
+            worst_deviation_after,
+            ..
+        }) = events.last()
+        else {
+            panic!("no rebalance reported: {events:?}");
+        };
+        assert!(worst_deviation_after <= worst_deviation_before);
+        let worst = achieved_tolerance_percent(&cars, &results, 300_000);
+        assert!((worst - worst_deviation_after).abs() < 1e-9);
+        // Sums step by 700 ms from 270 s; the closest to the target is
+        // 300.1 s, and every subset reaches it.
+        assert!((worst - 100.0 / 3_000.0).abs() < 1e-9, "{worst}");
+    }
+
+    #[test]
+    fn player_pools_restrict_each_run_to_its_cars() {
+        let cars = vec![
+            car("a", 100_000),
+            car("b", 101_000),
+            car("c", 102_000),
+            car("d", 100_500),
+            car("e", 101_500),
+            car("f", 102_500),
+            car("unowned", 101_200),
+        ];
+        let pools = vec![
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            vec!["d".to_string(), "e".to_string(), "f".to_string()],
+            vec!["b".to_string(), "e".to_string(), "missing".to_string()],
+        ];
+        for strategy in [SolverStrategy::Bounded, SolverStrategy::Legacy] {
+            let mut config = run_config(202_500, 2, 3, 5_000.0, 1.0);
+            config.strategy = strategy;
+            config.player_pools = pools.clone();
+
+            let results = perform_multiple_runs(&cars, &config).unwrap();
+            for (subset, pool) in results.iter().zip(&pools) {
+                assert_eq!(subset.len(), 2);
+                assert!(
+                    subset.iter().all(|&index| pool.contains(&cars[index].id)),
+                    "{strategy:?}: {subset:?} outside {pool:?}"
+                );
+            }
+        }
+
+        let mut config = run_config(202_500, 3, 3, 5_000.0, 1.0);
+        config.player_pools = pools.clone();
+        assert!(matches!(
+            perform_multiple_runs(&cars, &config),
+            Err(SubsetError::PlayerPoolTooSmall {
+                player: 3,
+                available: 2,
+                lap_count: 3
+            })
+        ));
+        config.player_count = 2;
+        assert_eq!(
+            perform_multiple_runs(&cars, &config).unwrap_err().code(),
+            "player_pool_count_mismatch"
+        );
+    }
+
+    #[test]
+    fn usage_history_steers_selection_to_rarely_driven_cars() {
+        let cars = (0..8)
+            .map(|index| car(&format!("car-{index}"), 100_000 + index))
+            .collect::<Vec<_>>();
+        let mut history = UsageHistory::new();
+        for id in ["car-0", "car-1", "car-2", "car-3"] {
+            history.add(id, 5);
+        }
+        history.add("car-4", 1);
+        let mut config = run_config(200_010, 2, 2, 5_000.0, 1.0);
+        config.usage_history = history;
+
+        for _ in 0..20 {
+            let results = perform_multiple_runs(&cars, &config).unwrap();
+            let mut used = results.concat();
+            used.sort_unstable();
+            assert_eq!(used, [4, 5, 6, 7]);
+        }
+    }
+
+    #[test]
+    fn tolerance_tightening_returns_closer_results_within_the_budget() {
+        let cars = (0..20)
+            .map(|index| car(&format!("car-{index}"), 990 + index))
+            .collect::<Vec<_>>();
+        let mut config = run_config(3_000, 3, 2, 2_000.0, 10.0);
+        config.tighten_tolerance = true;
+
+        let started = Instant::now();
+        let mut events = Vec::new();
+        let results = perform_multiple_runs_with_events(&cars, &config, &mut events).unwrap();
+        assert!(started.elapsed().as_millis() < 3_000);
+
+        assert_eq!(results.len(), 2);
+        let achieved = achieved_tolerance_percent(&cars, &results, 3_000);
+        assert!(achieved < 1.0, "achieved {achieved}%");
+        let tightened = events
+            .iter()
+            .filter_map(|event| match event {
+                SolverEvent::ToleranceTightened {
+                    tolerance_percent, ..
+                } => Some(*tolerance_percent),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(!tightened.is_empty());
+        assert!(tightened.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn event_sink_receives_selections_and_completed_runs() {
+        let cars = (0..12)
+            .map(|index| car(&format!("car-{index}"), 1_000 + index * 10))
+            .collect::<Vec<_>>();
+        let mut config = run_config(3_100, 3, 2, 5_000.0, 5.0);
+        config.strategy = SolverStrategy::Legacy;
+        config.min_lap_time = Some(1_020);
+
+        let mut events = Vec::new();
+        let results = perform_multiple_runs_with_events(&cars, &config, &mut events).unwrap();
+
+        let completed = events
+            .iter()
+            .filter_map(|event| match event {
+                SolverEvent::RunCompleted { subset, .. } => Some(subset.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(completed.len(), results.len());
+        for (reported, returned) in completed.iter().zip(&results) {
+            let mut reported = reported.clone();
+            let mut returned = returned.clone();
+            reported.sort_unstable();
+            returned.sort_unstable();
+            assert_eq!(reported, returned);
+        }
+
+        // Selections use the caller's indexes even though the filter shifts
+        // the pool the solver works on.
+        let selected = events
+            .iter()
+            .filter_map(|event| match event {
+                SolverEvent::SelectionMade { car, lap_time, .. } => Some((*car, *lap_time)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(selected.len() >= 6);
+        assert!(selected
+            .iter()
+            .all(|&(index, lap_time)| cars[index].lap_time == lap_time && index >= 2));
+        assert!(matches!(
+            events.first(),
+            Some(SolverEvent::RunStarted { run: 1, .. })
+        ));
+    }
+
     #[test]
     fn infinite_timeout_is_rejected() {
         let cars = vec![car("exact", 100)];
-        assert!(perform_multiple_runs(&cars, 100, 1, 1, f64::INFINITY, 0.0).is_err());
+        assert!(perform_multiple_runs(&cars, &run_config(100, 1, 1, f64::INFINITY, 0.0)).is_err());
     }
 }
 
+pub mod analysis;
+pub mod cancel;
+pub mod compression;
+pub mod curve;
+pub mod dataset;
+pub mod diagnostics;
+pub mod difficulty;
+pub mod evaluation;
+pub mod events;
+mod exhaustive;
+pub mod export;
+pub mod generators;
+pub mod history;
+pub mod iracing;
+pub mod metrics;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native_worker;
+mod rebalance;
+pub mod trace;
 pub mod worker_agent;
+#[cfg(feature = "wasm-threads")]
+mod worker_threads;
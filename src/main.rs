@@ -2,15 +2,23 @@
 //! Wires UI components, state hooks, and side-effect logic.
 
 use futures::future::AbortHandle;
-use gloo_timers::callback::Timeout;
+use gloo_events::EventListener;
+use gloo_timers::callback::{Interval, Timeout};
 use random_karma::{
-    format_ms_to_minsecms, get_target_range_for_subset, read_cars_from_csv_string,
-    worker_agent::{KarmaArgs, RequestMetadata},
-    Car, SolverStrategy,
+    dataset_hash,
+    export::KarmaExport,
+    export_named_results_csv, format_ms_to_minsecms, format_subset_markdown,
+    get_target_range_for_players, quality_score, read_cars_from_bytes, tolerance_probability,
+    validate_request,
+    worker_agent::{KarmaArgs, RequestMetadata, WorkerError},
+    Car, DifficultyProfile, QualityWeights, Severity, SolverStrategy, SubsetCalculationConfig,
+    TargetSpec,
 };
 use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
 use std::rc::Rc;
-use web_sys::HtmlInputElement;
+use wasm_bindgen::JsCast;
+use web_sys::{EventTarget, HtmlInputElement, HtmlTextAreaElement};
 use yew::prelude::*;
 
 mod cache;
@@ -18,24 +26,57 @@ mod chart;
 mod components;
 mod config; // Add this line
 mod controllers;
+mod datasets;
+mod download;
+mod hooks;
 mod state {
+    pub mod car_pool;
+    pub mod chart;
+    pub mod history;
+    pub mod link;
     pub mod request;
+    pub mod roster;
+    pub mod run_reports;
 }
 mod utils;
 
-use cache::{CacheValue, CACHE_STORE};
-use chart::init_similarity_chart;
-use components::ResultsWrapper;
+use cache::{CacheSnapshot, CacheValue, CACHE_STORE, MAX_CACHE_ENTRIES};
+use chart::{
+    clear_chart_view_listener, init_similarity_chart, pin_similarity_curve,
+    set_chart_view_listener, unpin_similarity_curve,
+};
+use components::{
+    CarPoolEditor, ComparedResult, ResultComparison, ResultDiff, ResultsWrapper, StatColumn,
+};
 use config::*; // This will bring SLIDER_MAX_INDEX and other config constants into scope
 use controllers::calculation::{
-    apply_result, cache_key, cached_result, run_worker, CalculationOutcome,
+    apply_result, cache_key, cached_result, cached_targets, reroll_subset, reroll_unlocked,
+    run_worker, CalculationOutcome,
 };
 use controllers::chart::{initialize_and_replay, ChartCacheFilter};
 use controllers::precache::{
-    run as run_precache, PrecacheConfig, PrecacheExecutionContext, PrecacheJob,
+    run as run_precache, PrecacheConfig, PrecacheExecutionContext, PrecacheJob, PrecacheProgress,
+};
+use controllers::worker_count::{
+    detected_worker_count, detected_worker_limit, parse_worker_count, store_worker_count,
+    stored_worker_count,
 };
+use datasets::{builtin_index, BUILTIN_DATASETS};
+use download::download;
+use hooks::{use_validated_input, InputRules};
+use state::car_pool::{apply_edit, CarEdit};
+use state::chart::{pin_label, MAX_PINNED_CURVES};
+use state::history::{ParameterHistory, Parameters};
+use state::link::LinkState;
 use state::request::RequestState;
-use utils::{base_target_range, base_target_step, calc_target_from_idx, parse_time_to_ms};
+use state::roster::{parse_roster, subset_label, Draw};
+use state::run_reports::ShownRunReports;
+use utils::{
+    base_target_range, base_target_step, calc_idx_from_target, calc_target_from_idx,
+    difficulty_gradient, format_optional_lap_time, parse_decimal, parse_target_input,
+    validate_lap_count, validate_lap_time_filter, validate_optional_lap_time,
+    validate_player_count,
+};
 
 // ──────────────────────────────────────────────────────────────────────────────
 // Helper functions
@@ -60,6 +101,20 @@ fn debounce_callback<T: 'static>(
     timer_handle.set(Some(handle));
 }
 
+/// Whether key presses on `target` edit text, which handles its own undo.
+fn is_text_entry(target: Option<EventTarget>) -> bool {
+    let Some(target) = target else {
+        return false;
+    };
+    target.dyn_ref::<HtmlTextAreaElement>().is_some()
+        || target.dyn_ref::<HtmlInputElement>().is_some_and(|input| {
+            !matches!(
+                input.type_().as_str(),
+                "range" | "checkbox" | "radio" | "button"
+            )
+        })
+}
+
 /// Helper to update cache version and trigger UI re-render
 fn update_cache_version(cache_version: &UseStateHandle<usize>) {
     cache_version.set(cache_version.wrapping_add(1));
@@ -68,27 +123,44 @@ fn update_cache_version(cache_version: &UseStateHandle<usize>) {
 /// Primary application component wiring state, effects, and UI elements.
 #[function_component(Main)]
 fn main_component() -> Html {
-    let csv_data = include_str!("cars.csv");
+    // Parameters of a shared link start in place of the defaults
+    let link = use_memo((), |_| LinkState::from_location());
     let cars = use_state(Vec::<Car>::new);
     let target = use_state(|| DEFAULT_TARGET_MS);
-    let lap_count = use_state(|| DEFAULT_LAP_COUNT);
-    let player_count = use_state(|| DEFAULT_PLAYER_COUNT);
-    let timeout_seconds = use_state(|| DEFAULT_TIMEOUT_SEC);
-    let tolerance_percent = use_state(|| DEFAULT_TOLERANCE_PCT);
+    let lap_count = use_state(|| link.lap_count.unwrap_or(DEFAULT_LAP_COUNT));
+    let player_count = use_state(|| link.player_count.unwrap_or(DEFAULT_PLAYER_COUNT));
+    let timeout_seconds = use_state(|| link.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SEC));
+    let tolerance_percent = use_state(|| link.tolerance_percent.unwrap_or(DEFAULT_TOLERANCE_PCT));
+    // Seed of every calculation; `None` solves with fresh randomness
+    let seed = use_state(|| link.seed);
+    // The linked target waits for the cars, which bound it
+    let link_target = use_mut_ref(|| link.target);
     let solver_strategy = use_state(|| SolverStrategy::Bounded);
-
-    // Text states for input fields
-    let lap_count_text = use_state(|| DEFAULT_LAP_COUNT.to_string());
-    let player_count_text = use_state(|| DEFAULT_PLAYER_COUNT.to_string());
-    let target_text = use_state(|| format_ms_to_minsecms(DEFAULT_TARGET_MS));
-    let timeout_seconds_text = use_state(|| DEFAULT_TIMEOUT_SEC.to_string());
-    let tolerance_percent_text = use_state(|| DEFAULT_TOLERANCE_PCT.to_string());
+    let min_lap_time = use_state(|| None::<u32>);
+    let max_lap_time = use_state(|| None::<u32>);
 
     let results = use_state(|| None::<CacheValue>);
+    // Solver reports of the last result a worker found.
+    let run_reports = use_state(|| None::<Rc<ShownRunReports>>);
     let is_calculating = use_state(|| false);
+    // Finished and total runs reported by the worker of the active calculation.
+    let calculation_progress = use_state(|| None::<(usize, usize)>);
+    // Seconds the active calculation has been running.
+    let calculation_elapsed = use_state(|| 0.0_f64);
     let error_message = use_state(|| None::<String>);
     // Cache version state triggers UI re-render when global cache changes
     let cache_version = use_state(|| 0usize);
+    // Entries the cache has evicted to stay within its capacity.
+    let cache_evictions = use_mut_ref(|| 0usize);
+    {
+        let cache_evictions = cache_evictions.clone();
+        use_effect_with((), move |_| {
+            CACHE_STORE.with(|c| {
+                c.borrow_mut()
+                    .set_eviction_hook(move |_, _| *cache_evictions.borrow_mut() += 1)
+            });
+        });
+    }
     let precache_enabled = use_state(|| true);
     let last_from_cache = use_state(|| false);
     // Debounce timer handle - simplified to use UseStateHandle
@@ -107,65 +179,43 @@ fn main_component() -> Html {
     let precache_failed_targets = use_state(|| Rc::new(Vec::<u32>::new()));
     // Trigger to manually restart pre-cache (incremented to trigger effect)
     let precache_trigger = use_state(|| 0usize);
+    // Targets of the current pre-cache run, and whether its workers wait.
+    let precache_progress = use_state(PrecacheProgress::default);
+    let precache_paused = use_state(|| false);
+    let precache_pause = use_state(|| Rc::new(Cell::new(false)));
+    // Slider position pre-caching works outward from.
+    let precache_focus = use_state(|| Rc::new(Cell::new(0usize)));
+    // Lap and player counts of the curves pinned on the chart.
+    let pinned_curves = use_state(Vec::<(u32, u32)>::new);
+    // Range the chart shows, reported by the chart after zooming or panning.
+    let chart_view = use_state_eq(|| None::<(u32, u32)>);
+    {
+        let chart_view = chart_view.clone();
+        use_effect_with((), move |_| {
+            set_chart_view_listener(move |min, max| chart_view.set(Some((min, max))));
+            clear_chart_view_listener
+        });
+    }
     // State to control cache settings visibility
     let cache_settings_visible = use_state(|| false);
 
-    // Remove worker_count state - use constant instead
+    // Pre-cache worker count chosen in the settings; `None` uses the detected one
+    let worker_count_override = use_state(stored_worker_count);
+    let detected_workers = use_state(detected_worker_count);
+    let worker_limit = use_state(detected_worker_limit);
+    let worker_count =
+        worker_count_override.map_or(*detected_workers, |count| count.min(*worker_limit));
     // slider index state (0..SLIDER_MAX_INDEX)
     let slider_idx = use_state(|| 0);
     let clipboard_feedback = use_state(|| None::<String>);
     let copy_feedback = use_state(|| None::<String>);
-
-    // Text input validation states
-    let lap_count_error = use_state(|| None::<String>);
-    let player_count_error = use_state(|| None::<String>);
-    let target_error = use_state(|| None::<String>);
-    let timeout_error = use_state(|| None::<String>);
-    let tolerance_error = use_state(|| None::<String>);
-
-    // --- OnInput Handlers for Text States ---
-    let lap_count_text_oninput = {
-        let lap_count_text_setter = lap_count_text.clone();
-        Callback::from(move |e: InputEvent| {
-            let input: HtmlInputElement = e.target_unchecked_into();
-            lap_count_text_setter.set(input.value());
-        })
-    };
-    let player_count_text_oninput = {
-        let player_count_text_setter = player_count_text.clone();
-        Callback::from(move |e: InputEvent| {
-            let input: HtmlInputElement = e.target_unchecked_into();
-            player_count_text_setter.set(input.value());
-        })
-    };
-    let target_text_oninput = {
-        let target_text_setter = target_text.clone();
-        Callback::from(move |e: InputEvent| {
-            let input: HtmlInputElement = e.target_unchecked_into();
-            target_text_setter.set(input.value());
-        })
-    };
-    let timeout_seconds_text_oninput = {
-        let timeout_seconds_text_setter = timeout_seconds_text.clone();
-        Callback::from(move |e: InputEvent| {
-            let input: HtmlInputElement = e.target_unchecked_into();
-            timeout_seconds_text_setter.set(input.value());
-        })
-    };
-    let tolerance_percent_text_oninput = {
-        let tolerance_percent_text_setter = tolerance_percent_text.clone();
-        Callback::from(move |e: InputEvent| {
-            let input: HtmlInputElement = e.target_unchecked_into();
-            tolerance_percent_text_setter.set(input.value());
-        })
-    };
+    let cache_feedback = use_state(|| None::<String>);
 
     // Load cars from CSV on mount
     {
         let cars = cars.clone();
         use_effect_with((), move |_| {
-            let loaded = read_cars_from_csv_string(csv_data).unwrap_or_default();
-            cars.set(loaded);
+            cars.set(BUILTIN_DATASETS[0].cars());
         });
     }
 
@@ -179,10 +229,15 @@ fn main_component() -> Html {
         let timeout_state = timeout_seconds.clone();
         let tolerance_state = tolerance_percent.clone();
         let strategy_state = solver_strategy.clone();
+        let min_lap_time_state = min_lap_time.clone();
+        let max_lap_time_state = max_lap_time.clone();
+        let seed_state = seed.clone();
         let last_from_cache = last_from_cache.clone();
         let results = results.clone();
+        let run_reports = run_reports.clone();
         let error_message = error_message.clone();
         let is_calculating = is_calculating.clone();
+        let calculation_progress = calculation_progress.clone();
         let request_state = request_state.clone();
         let active_calculation = active_calculation.clone();
         let cache_version = cache_version.clone();
@@ -191,15 +246,37 @@ fn main_component() -> Html {
                 handle.abort();
             }
 
-            let metadata = request_state.borrow_mut().begin(
-                target_override.unwrap_or(*target_state),
-                *lap_count_state,
-                *player_count_state,
-                *timeout_state * 1000.0,
-                *tolerance_state,
-                *strategy_state,
-            );
-            if let Some(cached) = cached_result(&metadata) {
+            let metadata = request_state.borrow_mut().begin(RequestMetadata {
+                request_id: 0,
+                dataset_generation: 0,
+                dataset_hash: dataset_hash(&cars_state),
+                target: target_override.unwrap_or(*target_state),
+                lap_count: *lap_count_state,
+                player_count: *player_count_state,
+                timeout_ms: *timeout_state * 1000.0,
+                tolerance_percent: *tolerance_state,
+                strategy: *strategy_state,
+                min_lap_time: *min_lap_time_state,
+                max_lap_time: *max_lap_time_state,
+            });
+            // Requests that cannot succeed never reach a worker.
+            if let Some(blocking) = validate_request(&cars_state, &metadata.config())
+                .into_iter()
+                .find(|diagnostic| diagnostic.is_error())
+            {
+                request_state.borrow_mut().finish(&metadata);
+                results.set(None);
+                error_message.set(Some(blocking.message));
+                is_calculating.set(false);
+                return;
+            }
+            // Cached results were solved without the seed, so seeded
+            // calculations always reach a worker.
+            if let Some(cached) = seed_state
+                .is_none()
+                .then(|| cached_result(&metadata))
+                .flatten()
+            {
                 request_state.borrow_mut().finish(&metadata);
                 last_from_cache.set(true);
                 results.set(Some(cached));
@@ -209,32 +286,39 @@ fn main_component() -> Html {
             }
 
             is_calculating.set(true);
-            let args = KarmaArgs {
-                cars: (*cars_state).clone(),
-                metadata: metadata.clone(),
-            };
+            calculation_progress.set(None);
+            let cars = (*cars_state).clone();
+            let mut args = KarmaArgs::new(metadata.clone());
+            args.options.seed = *seed_state;
+            let options = args.options.clone();
             let (abort_handle, abort_registration) = AbortHandle::new_pair();
             *active_calculation.borrow_mut() = Some(abort_handle);
             let request_state = request_state.clone();
             let active_calculation = active_calculation.clone();
             let last_from_cache = last_from_cache.clone();
             let results = results.clone();
+            let run_reports = run_reports.clone();
             let error_message = error_message.clone();
             let is_calculating = is_calculating.clone();
             let cache_version = cache_version.clone();
+            let calculation_progress = calculation_progress.clone();
 
             wasm_bindgen_futures::spawn_local(async move {
-                let Some(response) = run_worker(args, abort_registration).await else {
+                let on_progress = |run, total| calculation_progress.set(Some((run, total)));
+                let Some(response) = run_worker(&cars, args, abort_registration, on_progress).await
+                else {
                     return;
                 };
                 if !request_state.borrow_mut().finish(&metadata) {
                     return;
                 }
                 *active_calculation.borrow_mut() = None;
-                match apply_result(response) {
-                    CalculationOutcome::Success(value) => {
+                match apply_result(&cars, response, &options) {
+                    CalculationOutcome::Success(value, runs) => {
                         update_cache_version(&cache_version);
                         last_from_cache.set(false);
+                        run_reports
+                            .set(Some(Rc::new(ShownRunReports::new(value.2, &value.0, runs))));
                         results.set(Some(value));
                         error_message.set(None);
                     }
@@ -262,7 +346,11 @@ fn main_component() -> Html {
                 *timeout_seconds,
                 *tolerance_percent,
                 *solver_strategy,
+                *min_lap_time,
+                *max_lap_time,
+                *seed,
                 cars_len,
+                dataset_generation.get(),
             ),
             move |_| {
                 let timer =
@@ -272,32 +360,93 @@ fn main_component() -> Html {
         );
     }
 
+    // Counts the seconds of a running calculation, between the worker's
+    // progress messages too.
+    {
+        let calculation_elapsed = calculation_elapsed.clone();
+        use_effect_with(*is_calculating, move |&calculating| {
+            calculation_elapsed.set(0.0);
+            let ticker = calculating.then(|| {
+                let started = js_sys::Date::now();
+                Interval::new(100, move || {
+                    calculation_elapsed.set((js_sys::Date::now() - started) / 1000.0)
+                })
+            });
+            move || drop(ticker)
+        });
+    }
+
+    // Stops the active calculation; the worker cancels the target it is
+    // solving once its current run ends, and then closes.
+    let handle_cancel_calculation = {
+        let request_state = request_state.clone();
+        let active_calculation = active_calculation.clone();
+        let results = results.clone();
+        let error_message = error_message.clone();
+        let is_calculating = is_calculating.clone();
+        let calculation_progress = calculation_progress.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(handle) = active_calculation.borrow_mut().take() {
+                handle.abort();
+            }
+            request_state.borrow_mut().cancel();
+            results.set(None);
+            error_message.set(Some(WorkerError::Cancelled.to_string()));
+            is_calculating.set(false);
+            calculation_progress.set(None);
+        })
+    };
+
     // keep slider_idx and target in sync when range changes
     {
         let slider_idx = slider_idx.clone();
         let target = target.clone();
         let cars = cars.clone();
         let lap_count = lap_count.clone();
-        use_effect_with((*lap_count, *player_count), move |_| {
-            let (min, max) = base_target_range(&cars, *lap_count);
-            let clamped = calc_target_from_idx(min, max, *slider_idx);
-            target.set(clamped);
-            || ()
-        });
+        let player_count = player_count.clone();
+        let solver_strategy = solver_strategy.clone();
+        let min_lap_time = min_lap_time.clone();
+        let max_lap_time = max_lap_time.clone();
+        use_effect_with(
+            (
+                *lap_count,
+                *player_count,
+                *solver_strategy,
+                *min_lap_time,
+                *max_lap_time,
+                dataset_generation.get(),
+            ),
+            move |_| {
+                let (min, max) = base_target_range(
+                    &cars,
+                    *lap_count,
+                    *player_count,
+                    *solver_strategy,
+                    (*min_lap_time, *max_lap_time),
+                );
+                let clamped = calc_target_from_idx(min, max, *slider_idx);
+                target.set(clamped);
+                || ()
+            },
+        );
     }
 
-    // Automatically clamp target when cars are loaded or lap_count changes
+    // Automatically clamp target when cars are loaded or the reachable range changes
     {
         let target = target.clone();
         let cars_state = (*cars).clone();
         use_effect_with(
-            (cars_state.len(), *lap_count),
-            move |&(cars_len, subset)| {
-                let (min, max) = if cars_len > 0 {
-                    get_target_range_for_subset(&cars_state, subset)
-                } else {
-                    (0, 0)
-                };
+            (
+                cars_state.len(),
+                dataset_generation.get(),
+                *lap_count,
+                *player_count,
+                *solver_strategy,
+                (*min_lap_time, *max_lap_time),
+            ),
+            move |&(_, _, subset, players, strategy, lap_time_filter)| {
+                let (min, max) =
+                    base_target_range(&cars_state, subset, players, strategy, lap_time_filter);
                 let val = *target;
                 if val < min {
                     target.set(min);
@@ -309,6 +458,184 @@ fn main_component() -> Html {
         );
     }
 
+    // Apply a linked target once the cars are loaded, after the effects above
+    // have moved the target into the new range, and keep the slider on it.
+    {
+        let link_target = link_target.clone();
+        let target = target.clone();
+        let slider_idx = slider_idx.clone();
+        let cars = cars.clone();
+        let lap_count = *lap_count;
+        let player_count = *player_count;
+        let strategy = *solver_strategy;
+        let lap_time_filter = (*min_lap_time, *max_lap_time);
+        use_effect_with(cars.len(), move |&cars_len| {
+            if cars_len > 0 {
+                if let Some(linked) = link_target.borrow_mut().take() {
+                    let (min, max) = base_target_range(
+                        &cars,
+                        lap_count,
+                        player_count,
+                        strategy,
+                        lap_time_filter,
+                    );
+                    let linked = linked.clamp(min, max.max(min));
+                    target.set(linked);
+                    slider_idx.set(calc_idx_from_target(min, max, linked));
+                }
+            }
+            || ()
+        });
+    }
+
+    // Undo and redo restore the target after the range effects above have
+    // moved it with the slider for the restored lap and player counts.
+    let restored_target = use_mut_ref(|| None::<u32>);
+    {
+        let restored_target = restored_target.clone();
+        let target = target.clone();
+        use_effect_with((*lap_count, *player_count, *solver_strategy), move |_| {
+            if let Some(restored) = restored_target.borrow_mut().take() {
+                target.set(restored);
+            }
+            || ()
+        });
+    }
+
+    // Keep the address a link to the current parameters.
+    {
+        let link = LinkState {
+            target: Some(*target),
+            lap_count: Some(*lap_count),
+            player_count: Some(*player_count),
+            tolerance_percent: Some(*tolerance_percent),
+            timeout_seconds: Some(*timeout_seconds),
+            seed: *seed,
+        };
+        let ready = !cars.is_empty() && link_target.borrow().is_none();
+        use_effect_with((link, ready), |(link, ready)| {
+            if *ready {
+                link.replace_location();
+            }
+            || ()
+        });
+    }
+
+    // Remember parameter states for undo, once the page has settled on the
+    // cars and linked target.
+    let parameter_history = use_mut_ref(ParameterHistory::default);
+    // Whether there is a change to undo and one to redo.
+    let history_steps = use_state_eq(|| (false, false));
+    {
+        let parameters = Parameters {
+            target: *target,
+            lap_count: *lap_count,
+            player_count: *player_count,
+            tolerance_percent: *tolerance_percent,
+            timeout_seconds: *timeout_seconds,
+        };
+        let parameter_history = parameter_history.clone();
+        let history_steps = history_steps.clone();
+        let ready = !cars.is_empty() && link_target.borrow().is_none();
+        use_effect_with((parameters, ready), move |(parameters, ready)| {
+            if *ready {
+                let mut history = parameter_history.borrow_mut();
+                history.record(*parameters, js_sys::Date::now());
+                history_steps.set((history.can_undo(), history.can_redo()));
+            }
+            || ()
+        });
+    }
+
+    let restore_parameters = {
+        let target = target.clone();
+        let lap_count = lap_count.clone();
+        let player_count = player_count.clone();
+        let tolerance_percent = tolerance_percent.clone();
+        let timeout_seconds = timeout_seconds.clone();
+        let slider_idx = slider_idx.clone();
+        let cars = cars.clone();
+        let strategy = *solver_strategy;
+        let lap_time_filter = (*min_lap_time, *max_lap_time);
+        let restored_target = restored_target.clone();
+        Callback::from(move |parameters: Parameters| {
+            let (min, max) = base_target_range(
+                &cars,
+                parameters.lap_count,
+                parameters.player_count,
+                strategy,
+                lap_time_filter,
+            );
+            let restored = parameters.target.clamp(min, max.max(min));
+            if (parameters.lap_count, parameters.player_count) != (*lap_count, *player_count) {
+                *restored_target.borrow_mut() = Some(restored);
+            }
+            lap_count.set(parameters.lap_count);
+            player_count.set(parameters.player_count);
+            tolerance_percent.set(parameters.tolerance_percent);
+            timeout_seconds.set(parameters.timeout_seconds);
+            target.set(restored);
+            slider_idx.set(calc_idx_from_target(min, max, restored));
+        })
+    };
+    let handle_history_step = |undo: bool| {
+        let parameter_history = parameter_history.clone();
+        let history_steps = history_steps.clone();
+        let restore_parameters = restore_parameters.clone();
+        Callback::from(move |_: ()| {
+            let step = {
+                let mut history = parameter_history.borrow_mut();
+                let parameters = if undo { history.undo() } else { history.redo() };
+                history_steps.set((history.can_undo(), history.can_redo()));
+                parameters
+            };
+            if let Some(parameters) = step {
+                restore_parameters.emit(parameters);
+            }
+        })
+    };
+    let handle_undo = handle_history_step(true);
+    let handle_redo = handle_history_step(false);
+
+    // Ctrl+Z undoes and Ctrl+Shift+Z or Ctrl+Y redoes parameter changes
+    // (⌘ on macOS), except while typing into a text field.
+    {
+        let shortcuts = (handle_undo.clone(), handle_redo.clone());
+        use_effect_with(shortcuts, |(undo, redo)| {
+            let (undo, redo) = (undo.clone(), redo.clone());
+            let listener = web_sys::window()
+                .and_then(|window| window.document())
+                .map(|document| {
+                    EventListener::new(&document, "keydown", move |event| {
+                        let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+                            return;
+                        };
+                        if !(event.ctrl_key() || event.meta_key())
+                            || event.alt_key()
+                            || is_text_entry(event.target())
+                        {
+                            return;
+                        }
+                        match (event.key().to_lowercase().as_str(), event.shift_key()) {
+                            ("z", false) => undo.emit(()),
+                            ("z", true) | ("y", false) => redo.emit(()),
+                            _ => return,
+                        }
+                        event.prevent_default();
+                    })
+                });
+            move || drop(listener)
+        });
+    }
+
+    {
+        let precache_focus = precache_focus.clone();
+        use_effect_with(*slider_idx, move |&index| {
+            (*precache_focus).set(index);
+            || ()
+        });
+    }
+
     // A change to every pre-cache input, including enabled state and dataset,
     // invalidates both queued and in-flight work through the live token.
     use_effect_with(
@@ -319,14 +646,20 @@ fn main_component() -> Html {
             *timeout_seconds,
             *tolerance_percent,
             *solver_strategy,
+            *min_lap_time,
+            *max_lap_time,
             *precache_enabled,
-            *precache_trigger,
+            (*precache_trigger, *chart_view),
+            worker_count,
             dataset_generation.get(),
         ),
         {
             let cars = cars.clone();
             let precache_error_count = precache_error_count.clone();
             let precache_failed_targets = precache_failed_targets.clone();
+            let precache_progress = precache_progress.clone();
+            let precache_pause = precache_pause.clone();
+            let precache_focus = precache_focus.clone();
             let cache_version = cache_version.clone();
             let precache_generation = precache_generation.clone();
             let dataset_generation = dataset_generation.clone();
@@ -341,8 +674,11 @@ fn main_component() -> Html {
                 timeout_secs,
                 tolerance_val,
                 strategy,
+                min_lap_ms,
+                max_lap_ms,
                 enabled,
-                _trigger,
+                (_trigger, zoom),
+                workers,
                 dataset_id,
             )|
                   -> Box<dyn FnOnce()> {
@@ -354,6 +690,7 @@ fn main_component() -> Html {
                 (*precache_generation).set(generation);
 
                 if !enabled || car_count == 0 {
+                    precache_progress.set(PrecacheProgress::default());
                     return Box::new(|| ());
                 }
 
@@ -368,11 +705,18 @@ fn main_component() -> Html {
                             timeout_secs,
                             tolerance_percent: tolerance_val,
                             strategy,
+                            min_lap_time: min_lap_ms,
+                            max_lap_time: max_lap_ms,
+                            worker_count: workers,
+                            zoom,
                         },
                         context: PrecacheExecutionContext {
                             cache_version,
                             error_count: precache_error_count,
                             failed_targets: precache_failed_targets,
+                            progress: precache_progress,
+                            paused: (*precache_pause).clone(),
+                            focus: (*precache_focus).clone(),
                             dataset_generation: (*dataset_generation).clone(),
                             expected_dataset_generation: dataset_id,
                             precache_generation: (*precache_generation).clone(),
@@ -401,9 +745,16 @@ fn main_component() -> Html {
         let cars_vec = (*cars_for_count).clone();
         let ss = *lap_count;
         let nr = *player_count;
-        let (min, max) = base_target_range(&cars_vec, ss);
+        let (min, max) = base_target_range(
+            &cars_vec,
+            ss,
+            nr,
+            *solver_strategy,
+            (*min_lap_time, *max_lap_time),
+        );
         let step = base_target_step(min, max);
         let dataset_id = dataset_generation.get();
+        let cars_hash = dataset_hash(&cars_vec);
         let timeout_ms = *timeout_seconds * 1000.0;
         CACHE_STORE.with(|c| {
             (0..=SLIDER_MAX_INDEX)
@@ -411,12 +762,15 @@ fn main_component() -> Html {
                     let metadata = RequestMetadata {
                         request_id: 0,
                         dataset_generation: dataset_id,
+                        dataset_hash: cars_hash,
                         target: (min + step * *idx as u32).min(max),
                         lap_count: ss,
                         player_count: nr,
                         timeout_ms,
                         tolerance_percent: *tolerance_percent,
                         strategy: *solver_strategy,
+                        min_lap_time: *min_lap_time,
+                        max_lap_time: *max_lap_time,
                     };
                     c.borrow().contains_key(&cache_key(&metadata))
                 })
@@ -424,6 +778,88 @@ fn main_component() -> Html {
         })
     };
 
+    let handle_toggle_precache_pause = {
+        let precache_paused = precache_paused.clone();
+        let precache_pause = precache_pause.clone();
+        Callback::from(move |_: MouseEvent| {
+            let paused = !*precache_paused;
+            (*precache_pause).set(paused);
+            precache_paused.set(paused);
+        })
+    };
+
+    let handle_pin_curve = {
+        let pinned_curves = pinned_curves.clone();
+        Callback::from(move |_: MouseEvent| pinned_curves.set(pin_similarity_curve()))
+    };
+    let current_curve = (*lap_count as u32, *player_count as u32);
+    let current_curve_pinned = pinned_curves.contains(&current_curve);
+
+    // Findings about the current parameters, shown before any calculation runs.
+    let request_diagnostics = if cars.is_empty() {
+        Vec::new()
+    } else {
+        let metadata = RequestMetadata {
+            request_id: 0,
+            dataset_generation: 0,
+            dataset_hash: 0,
+            target: *target,
+            lap_count: *lap_count,
+            player_count: *player_count,
+            timeout_ms: *timeout_seconds * 1000.0,
+            tolerance_percent: *tolerance_percent,
+            strategy: *solver_strategy,
+            min_lap_time: *min_lap_time,
+            max_lap_time: *max_lap_time,
+        };
+        validate_request(&cars, &metadata.config())
+    };
+
+    // The difficulty profile depends only on the pool and the runs, so a
+    // tolerance or target change just reads it again.
+    let difficulty_profile = {
+        let cars = cars.clone();
+        use_memo(
+            (
+                dataset_generation.get(),
+                cars.len(),
+                *lap_count,
+                *player_count,
+                *solver_strategy,
+                *min_lap_time,
+                *max_lap_time,
+            ),
+            move |&(_, _, lap_count, player_count, strategy, min_lap_time, max_lap_time)| {
+                let config = SubsetCalculationConfig {
+                    lap_count,
+                    player_count,
+                    strategy,
+                    min_lap_time,
+                    max_lap_time,
+                    ..SubsetCalculationConfig::default()
+                };
+                DifficultyProfile::for_config(&cars, &config)
+            },
+        )
+    };
+    let target_range = base_target_range(
+        &cars,
+        *lap_count,
+        *player_count,
+        *solver_strategy,
+        (*min_lap_time, *max_lap_time),
+    );
+    let difficulty_track = if cars.is_empty() {
+        None
+    } else {
+        let (min, max) = target_range;
+        Some(format!(
+            "--difficulty-track: {}",
+            difficulty_gradient(&difficulty_profile, min, max, *tolerance_percent)
+        ))
+    };
+    let target_success = difficulty_profile.success_probability(*target, *tolerance_percent);
+
     // (re-)initialise the chart on lap_count or player_count changes, and replay cache
     {
         let lap_handle = lap_count.clone();
@@ -433,6 +869,8 @@ fn main_component() -> Html {
         let timeout_seconds = timeout_seconds.clone();
         let tolerance_percent = tolerance_percent.clone();
         let chart_strategy = solver_strategy.clone();
+        let chart_min_lap_time = min_lap_time.clone();
+        let chart_max_lap_time = max_lap_time.clone();
         use_effect_with(
             (
                 *lap_count,
@@ -442,20 +880,30 @@ fn main_component() -> Html {
                 *timeout_seconds,
                 *tolerance_percent,
                 *solver_strategy,
+                *min_lap_time,
+                *max_lap_time,
                 *cache_version,
             ),
             move |_| {
-                let (min, max) = base_target_range(&cars, *lap_handle);
+                let (min, max) = base_target_range(
+                    &cars,
+                    *lap_handle,
+                    *player_handle,
+                    *chart_strategy,
+                    (*chart_min_lap_time, *chart_max_lap_time),
+                );
                 initialize_and_replay(
                     min,
                     max,
                     ChartCacheFilter {
-                        dataset_generation: dataset_generation.get(),
+                        dataset_hash: dataset_hash(&cars),
                         lap_count: *lap_handle,
                         player_count: *player_handle,
                         timeout_ms: *timeout_seconds * 1000.0,
                         tolerance_percent: *tolerance_percent,
                         strategy: *chart_strategy,
+                        min_lap_time: *chart_min_lap_time,
+                        max_lap_time: *chart_max_lap_time,
                     },
                 );
                 || ()
@@ -463,284 +911,121 @@ fn main_component() -> Html {
         );
     }
 
-    // Simplified input handlers - no text state management
-    let handle_lap_count_input = {
-        let lap_count_text_handle = lap_count_text.clone();
-        let lap_count_num_handle = lap_count.clone();
-        let lap_count_err_handle = lap_count_error.clone();
+    // Numeric fields. Committing the lap count, player count, or target
+    // recalculates after the debounce; the timeout and tolerance recalculate
+    // through the parameter effect.
+    let lap_count_input = {
         let cars_len = cars.len();
         let calculate = calculate.clone();
         let debounce_timer = debounce_timer.clone();
-
-        Callback::from(move |_: ()| {
-            let text_val = (*lap_count_text_handle).clone();
-            match crate::utils::validate_lap_count(&text_val, cars_len) {
-                Ok(valid_num) => {
-                    lap_count_err_handle.set(None);
-                    lap_count_num_handle.set(valid_num);
-                    lap_count_text_handle.set(valid_num.to_string());
-                    debounce_callback(&debounce_timer, calculate.clone(), None, DEBOUNCE_MS);
-                }
-                Err(e) => {
-                    lap_count_err_handle.set(Some(e));
-                }
-            }
-        })
+        use_validated_input(
+            lap_count.clone(),
+            InputRules::new(move |text| validate_lap_count(text, cars_len)).on_commit(move |_| {
+                debounce_callback(&debounce_timer, calculate.clone(), None, DEBOUNCE_MS)
+            }),
+        )
     };
-
-    let handle_player_count_input = {
-        let player_count_text_handle = player_count_text.clone();
-        let player_count_num_handle = player_count.clone();
-        let player_count_err_handle = player_count_error.clone();
+    let player_count_input = {
         let calculate = calculate.clone();
         let debounce_timer = debounce_timer.clone();
-
-        Callback::from(move |_: ()| {
-            let text_val = (*player_count_text_handle).clone();
-            match crate::utils::validate_player_count(&text_val) {
-                Ok(valid_num) => {
-                    player_count_err_handle.set(None);
-                    player_count_num_handle.set(valid_num);
-                    player_count_text_handle.set(valid_num.to_string());
-                    debounce_callback(&debounce_timer, calculate.clone(), None, DEBOUNCE_MS);
-                }
-                Err(e) => {
-                    player_count_err_handle.set(Some(e));
-                }
-            }
-        })
+        use_validated_input(
+            player_count.clone(),
+            InputRules::new(validate_player_count).on_commit(move |_| {
+                debounce_callback(&debounce_timer, calculate.clone(), None, DEBOUNCE_MS)
+            }),
+        )
     };
-
-    let handle_target_input = {
-        let target_text_handle = target_text.clone();
-        let target_num_handle = target.clone();
-        let target_err_handle = target_error.clone();
-        let slider_idx_handle = slider_idx.clone();
+    let target_input = {
+        let (min, max) = base_target_range(
+            &cars,
+            *lap_count,
+            *player_count,
+            *solver_strategy,
+            (*min_lap_time, *max_lap_time),
+        );
+        let laps = *lap_count;
+        let slider_idx = slider_idx.clone();
         let calculate = calculate.clone();
         let debounce_timer = debounce_timer.clone();
-        let cars_handle = cars.clone();
-        let lap_count_handle = lap_count.clone();
-
-        Callback::from(move |_: ()| {
-            let text_val = (*target_text_handle).clone();
-            if text_val.trim().is_empty() {
-                target_err_handle.set(None); // Allow empty commit to clear errors, but don't change target
-                return;
-            }
-            match parse_time_to_ms(&text_val) {
-                Ok(ms) => {
-                    let (min, max) = base_target_range(&cars_handle, *lap_count_handle);
-                    if ms < min || ms > max {
-                        target_err_handle.set(Some(format!(
-                            "Target must be between {} and {}",
-                            format_ms_to_minsecms(min),
-                            format_ms_to_minsecms(max)
-                        )));
-                    } else {
-                        target_err_handle.set(None);
-                        target_num_handle.set(ms);
-                        target_text_handle.set(format_ms_to_minsecms(ms));
-
-                        let range = max - min;
-                        let pos = if range > 0 {
-                            ((ms - min) as f64 / range as f64 * SLIDER_MAX_INDEX as f64).round()
-                                as usize
-                        } else {
-                            0
-                        };
-                        slider_idx_handle.set(pos.min(SLIDER_MAX_INDEX));
-                        debounce_callback(
-                            &debounce_timer,
-                            calculate.clone(),
-                            Some(ms),
-                            DEBOUNCE_MS,
-                        );
-                    }
-                }
-                Err(error) => {
-                    target_err_handle.set(Some(error));
-                }
-            }
-        })
-    };
-
-    let handle_timeout_input = {
-        let timeout_text_handle = timeout_seconds_text.clone();
-        let timeout_num_handle = timeout_seconds.clone();
-        let timeout_err_handle = timeout_error.clone();
-
-        Callback::from(move |_: ()| {
-            let text_val = (*timeout_text_handle).clone();
-            if text_val.trim().is_empty() {
-                // Allow empty commit to clear errors
-                timeout_err_handle.set(None);
-                return;
-            }
-            match text_val.parse::<f64>() {
-                Ok(v) => {
-                    if (MIN_TIMEOUT_SEC..=MAX_TIMEOUT_SEC).contains(&v) {
-                        timeout_err_handle.set(None);
-                        timeout_num_handle.set(v);
-                        timeout_text_handle.set(v.to_string());
-                    } else {
-                        timeout_err_handle.set(Some(format!(
-                            "Timeout must be between {} and {} seconds",
-                            MIN_TIMEOUT_SEC, MAX_TIMEOUT_SEC
-                        )));
-                    }
-                }
-                Err(_) => {
-                    timeout_err_handle.set(Some("Invalid number".to_string()));
-                }
-            }
-        })
-    };
-
-    let handle_tolerance_input = {
-        let tolerance_text_handle = tolerance_percent_text.clone();
-        let tolerance_num_handle = tolerance_percent.clone();
-        let tolerance_err_handle = tolerance_error.clone();
-
-        Callback::from(move |_: ()| {
-            let text_val = (*tolerance_text_handle).clone();
-            if text_val.trim().is_empty() {
-                // Allow empty commit to clear errors
-                tolerance_err_handle.set(None);
-                return;
-            }
-            match text_val.parse::<f64>() {
-                Ok(v) => {
-                    if (MIN_TOLERANCE_PCT..=MAX_TOLERANCE_PCT).contains(&v) {
-                        tolerance_err_handle.set(None);
-                        tolerance_num_handle.set(v);
-                        tolerance_text_handle.set(v.to_string());
-                    } else {
-                        tolerance_err_handle.set(Some(format!(
-                            "Tolerance must be between {} and {}%",
-                            MIN_TOLERANCE_PCT, MAX_TOLERANCE_PCT
-                        )));
-                    }
-                }
-                Err(_) => {
-                    tolerance_err_handle.set(Some("Invalid number".to_string()));
-                }
-            }
-        })
+        use_validated_input(
+            target.clone(),
+            InputRules::new(move |text| {
+                parse_target_input(text, laps).map(|spec| spec.total(laps))
+            })
+            .format(|&ms| format_ms_to_minsecms(ms))
+            .range(min, max, |min, max| {
+                format!("Target must be between {min} and {max}")
+            })
+            .allow_blank()
+            .on_commit(move |ms| {
+                slider_idx.set(calc_idx_from_target(min, max, ms));
+                debounce_callback(&debounce_timer, calculate.clone(), Some(ms), DEBOUNCE_MS);
+            }),
+        )
     };
+    let timeout_input = use_validated_input(
+        timeout_seconds.clone(),
+        InputRules::new(parse_decimal)
+            .range(MIN_TIMEOUT_SEC, MAX_TIMEOUT_SEC, |min, max| {
+                format!("Timeout must be between {min} and {max} seconds")
+            })
+            .allow_blank(),
+    );
+    let tolerance_input = use_validated_input(
+        tolerance_percent.clone(),
+        InputRules::new(parse_decimal)
+            .range(MIN_TOLERANCE_PCT, MAX_TOLERANCE_PCT, |min, max| {
+                format!("Tolerance must be between {min} and {max}%")
+            })
+            .allow_blank(),
+    );
 
-    // --- KeyDown Handlers for Enter Key ---
-    let lap_count_onkeydown = {
-        let commit_handler = handle_lap_count_input.clone();
-        Callback::from(move |e: KeyboardEvent| {
-            if e.key() == "Enter" {
-                commit_handler.emit(());
-            }
-        })
-    };
-    let player_count_onkeydown = {
-        let commit_handler = handle_player_count_input.clone();
-        Callback::from(move |e: KeyboardEvent| {
-            if e.key() == "Enter" {
-                commit_handler.emit(());
-            }
-        })
-    };
-    let target_onkeydown = {
-        let commit_handler = handle_target_input.clone();
-        Callback::from(move |e: KeyboardEvent| {
-            if e.key() == "Enter" {
-                commit_handler.emit(());
-            }
-        })
-    };
-    let timeout_onkeydown = {
-        let commit_handler = handle_timeout_input.clone();
-        Callback::from(move |e: KeyboardEvent| {
-            if e.key() == "Enter" {
-                commit_handler.emit(());
-            }
-        })
+    // An empty seed returns to fresh randomness for every calculation.
+    let seed_input = use_validated_input(
+        seed.clone(),
+        InputRules::with_format(
+            |text| match text.trim() {
+                "" => Ok(None),
+                text => text
+                    .parse::<u64>()
+                    .map(Some)
+                    .map_err(|_| "Seed must be a whole number".to_string()),
+            },
+            |seed| seed.map(|seed| seed.to_string()).unwrap_or_default(),
+        ),
+    );
+    // An empty bound clears it. Each bound is checked against the other so
+    // an inverted range never reaches a request.
+    let min_lap_time_input = {
+        let max = *max_lap_time;
+        use_validated_input(
+            min_lap_time.clone(),
+            InputRules::with_format(
+                move |text| {
+                    let min = validate_optional_lap_time(text)?;
+                    validate_lap_time_filter(min, max).map(|()| min)
+                },
+                format_optional_lap_time,
+            ),
+        )
     };
-    let tolerance_onkeydown = {
-        let commit_handler = handle_tolerance_input.clone();
-        Callback::from(move |e: KeyboardEvent| {
-            if e.key() == "Enter" {
-                commit_handler.emit(());
-            }
-        })
+    let max_lap_time_input = {
+        let min = *min_lap_time;
+        use_validated_input(
+            max_lap_time.clone(),
+            InputRules::with_format(
+                move |text| {
+                    let max = validate_optional_lap_time(text)?;
+                    validate_lap_time_filter(min, max).map(|()| max)
+                },
+                format_optional_lap_time,
+            ),
+        )
     };
-
-    // --- Synchronization Effects (Numeric State -> Text State) ---
-    {
-        // Sync lap_count -> lap_count_text
-        let num_val = *lap_count;
-        let text_setter = lap_count_text.clone();
-        let error_setter = lap_count_error.clone();
-        use_effect_with(num_val, move |&current_num_val| {
-            let num_as_string = current_num_val.to_string();
-            if *text_setter != num_as_string {
-                text_setter.set(num_as_string);
-                error_setter.set(None); // If synced from num, assume valid or error handled elsewhere
-            }
-            || ()
-        });
-    }
-    {
-        // Sync player_count -> player_count_text
-        let num_val = *player_count;
-        let text_setter = player_count_text.clone();
-        let error_setter = player_count_error.clone();
-        use_effect_with(num_val, move |&current_num_val| {
-            let num_as_string = current_num_val.to_string();
-            if *text_setter != num_as_string {
-                text_setter.set(num_as_string);
-                error_setter.set(None);
-            }
-            || ()
-        });
-    }
-    {
-        // Sync target -> target_text
-        let num_val = *target;
-        let text_setter = target_text.clone();
-        let error_setter = target_error.clone();
-        use_effect_with(num_val, move |&current_num_val| {
-            let num_as_string = format_ms_to_minsecms(current_num_val);
-            if *text_setter != num_as_string {
-                text_setter.set(num_as_string);
-                error_setter.set(None);
-            }
-            || ()
-        });
-    }
-    {
-        // Sync timeout_seconds -> timeout_seconds_text
-        let num_val = *timeout_seconds;
-        let text_setter = timeout_seconds_text.clone();
-        let error_setter = timeout_error.clone();
-        use_effect_with(num_val, move |&current_num_val| {
-            let num_as_string = current_num_val.to_string();
-            if *text_setter != num_as_string {
-                text_setter.set(num_as_string);
-                error_setter.set(None);
-            }
-            || ()
-        });
-    }
-    {
-        // Sync tolerance_percent -> tolerance_percent_text
-        let num_val = *tolerance_percent;
-        let text_setter = tolerance_percent_text.clone();
-        let error_setter = tolerance_error.clone();
-        use_effect_with(num_val, move |&current_num_val| {
-            let num_as_string = current_num_val.to_string();
-            if *text_setter != num_as_string {
-                text_setter.set(num_as_string);
-                error_setter.set(None);
-            }
-            || ()
-        });
-    }
+    let lap_time_filter_error = min_lap_time_input
+        .error
+        .clone()
+        .or_else(|| max_lap_time_input.error.clone());
 
     let handle_paste_from_clipboard = {
         let cars_setter = cars.clone();
@@ -778,16 +1063,17 @@ fn main_component() -> Html {
                                 feedback_setter.set(Some("Clipboard is empty.".to_string()));
                                 return;
                             }
-                            match read_cars_from_csv_string(&text_str) {
-                                Ok(new_cars) => {
+                            match read_cars_from_bytes(text_str.as_bytes()) {
+                                Ok((new_cars, layout)) => {
                                     if new_cars.is_empty() {
-                                        feedback_setter.set(Some(
-                                            "No valid car data found in clipboard content."
-                                                .to_string(),
-                                        ));
+                                        feedback_setter.set(Some(format!(
+                                            "No valid car data found in clipboard content (detected: {}).",
+                                            layout
+                                        )));
                                     } else {
                                         let car_count = new_cars.len();
-                                        // New rows invalidate every old index and all in-flight work.
+                                        // New rows invalidate all in-flight work; cached results
+                                        // stay keyed to the cars they were calculated on.
                                         let generation =
                                             request_state.borrow_mut().replace_dataset();
                                         (*dataset_generation).set(generation);
@@ -797,15 +1083,14 @@ fn main_component() -> Html {
                                         }
                                         (*precache_generation)
                                             .set(precache_generation.get().wrapping_add(1));
-                                        CACHE_STORE.with(|c| c.borrow_mut().clear());
                                         update_cache_version(&cache_version);
                                         results.set(None);
                                         error_message.set(None);
                                         is_calculating.set(false);
                                         cars_setter.set(new_cars);
                                         feedback_setter.set(Some(format!(
-                                            "Successfully loaded {} cars from clipboard.",
-                                            car_count
+                                            "Successfully loaded {} cars from clipboard (detected: {}).",
+                                            car_count, layout
                                         )));
                                     }
                                 }
@@ -830,39 +1115,215 @@ fn main_component() -> Html {
         })
     };
 
+    // Replaces the loaded cars like pasting new rows: in-flight work is
+    // invalidated, and cached results stay keyed to the cars they were
+    // calculated on.
+    let replace_cars = {
+        let cars = cars.clone();
+        let results = results.clone();
+        let error_message = error_message.clone();
+        let is_calculating = is_calculating.clone();
+        let request_state = request_state.clone();
+        let active_calculation = active_calculation.clone();
+        let dataset_generation = dataset_generation.clone();
+        let precache_generation = precache_generation.clone();
+        let cache_version = cache_version.clone();
+
+        Callback::from(move |new_cars: Vec<Car>| {
+            let generation = request_state.borrow_mut().replace_dataset();
+            (*dataset_generation).set(generation);
+            if let Some(handle) = active_calculation.borrow_mut().take() {
+                handle.abort();
+            }
+            (*precache_generation).set(precache_generation.get().wrapping_add(1));
+            update_cache_version(&cache_version);
+            results.set(None);
+            error_message.set(None);
+            is_calculating.set(false);
+            cars.set(new_cars);
+        })
+    };
+
+    let car_pool_error = use_state(|| None::<String>);
+
+    // Player names pasted one per line, which name the subsets in order.
+    let roster_text = use_state(String::new);
+    let roster = use_memo((*roster_text).clone(), |text| parse_roster(text));
+    let roster_text_oninput = {
+        let roster_text = roster_text.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
+            roster_text.set(input.value());
+        })
+    };
+
+    // Parameters the shown results were calculated for; any change to them
+    // clears the results.
+    let results_config = results
+        .as_ref()
+        .map(|(_, _, target, _)| SubsetCalculationConfig {
+            target: *target,
+            lap_count: *lap_count,
+            player_count: *player_count,
+            timeout_ms: *timeout_seconds * 1000.0,
+            tolerance_percent: *tolerance_percent,
+            strategy: *solver_strategy,
+            min_lap_time: *min_lap_time,
+            max_lap_time: *max_lap_time,
+            seed: *seed,
+            ..SubsetCalculationConfig::default()
+        });
+    // A draw deals the shown subsets to the roster, and locked rows are
+    // kept when the others are recomputed. Results for other parameters call
+    // for new ones, and new names for a new draw; re-rolled rows keep both.
+    let draw = use_state(|| None::<Draw>);
+    let locked_rows = use_state(|| Rc::new(BTreeSet::<usize>::new()));
+    // Rows recomputed and rows to recompute of a running recomputation.
+    let recompute_progress = use_state(|| None::<(usize, usize)>);
+    // Raised with every new set of results, which discards a running
+    // recomputation of the previous ones.
+    let results_epoch = use_mut_ref(|| 0u64);
+    {
+        let draw = draw.clone();
+        let subset_count = results.as_ref().map(|(sets, ..)| sets.len());
+        use_effect_with(
+            (roster.clone(), results_config.clone(), subset_count),
+            move |_| {
+                draw.set(None);
+            },
+        );
+    }
+    {
+        let locked_rows = locked_rows.clone();
+        let recompute_progress = recompute_progress.clone();
+        let results_epoch = results_epoch.clone();
+        let subset_count = results.as_ref().map(|(sets, ..)| sets.len());
+        use_effect_with((results_config.clone(), subset_count), move |_| {
+            *results_epoch.borrow_mut() += 1;
+            locked_rows.set(Rc::new(BTreeSet::new()));
+            recompute_progress.set(None);
+        });
+    }
+    // Names of the result rows: the drawn players once revealed, otherwise
+    // the roster in order.
+    let subset_names = match (&*draw, &*results) {
+        (Some(draw), Some((sets, ..))) => Rc::new(draw.subset_names(&roster, sets.len())),
+        _ => roster.clone(),
+    };
+    let handle_draw = {
+        let draw = draw.clone();
+        let roster = roster.clone();
+        let results = results.clone();
+        let seed = seed.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some((sets, ..)) = &*results {
+                let seed = seed.unwrap_or_else(rand::random);
+                draw.set(Some(Draw::new(roster.len(), sets.len(), seed)));
+            }
+        })
+    };
+    let handle_reveal_next = {
+        let draw = draw.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(mut next) = (*draw).clone() {
+                next.reveal_next();
+                draw.set(Some(next));
+            }
+        })
+    };
+    let handle_toggle_lock = {
+        let locked_rows = locked_rows.clone();
+        Callback::from(move |index: usize| {
+            let mut locked = (**locked_rows).clone();
+            if !locked.remove(&index) {
+                locked.insert(index);
+            }
+            locked_rows.set(Rc::new(locked));
+        })
+    };
+    // Statistics columns of the results table, kept across calculations.
+    let stat_columns = use_state(|| Rc::new(BTreeSet::<StatColumn>::new()));
+    let handle_toggle_stat_column = {
+        let stat_columns = stat_columns.clone();
+        Callback::from(move |column: StatColumn| {
+            let mut columns = (**stat_columns).clone();
+            if !columns.remove(&column) {
+                columns.insert(column);
+            }
+            stat_columns.set(Rc::new(columns));
+        })
+    };
+    let handle_car_edit = {
+        let cars = cars.clone();
+        let car_pool_error = car_pool_error.clone();
+        let replace_cars = replace_cars.clone();
+        Callback::from(move |edit: CarEdit| {
+            let mut edited = (*cars).clone();
+            if let Err(error) = apply_edit(&mut edited, edit) {
+                car_pool_error.set(Some(error));
+                return;
+            }
+            car_pool_error.set(None);
+            if edited != *cars {
+                replace_cars.emit(edited);
+            }
+        })
+    };
+
+    // The bundled pool that is loaded, if the cars are one unchanged
+    let selected_dataset = use_memo((dataset_generation.get(), cars.len()), {
+        let cars = cars.clone();
+        move |_| builtin_index(&cars)
+    });
+    let handle_dataset_select = {
+        let replace_cars = replace_cars.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            if let Some(dataset) = select
+                .value()
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| BUILTIN_DATASETS.get(index))
+            {
+                replace_cars.emit(dataset.cars());
+            }
+        })
+    };
+
     let handle_copy_results_to_clipboard = {
         let cars = cars.clone();
         let results = results.clone();
+        let subset_names = subset_names.clone();
         let feedback_setter = copy_feedback.clone();
 
         Callback::from(move |_: MouseEvent| {
             let feedback_setter = feedback_setter.clone();
             let cars = cars.clone();
             let results = results.clone();
+            let subset_names = subset_names.clone();
 
             wasm_bindgen_futures::spawn_local(async move {
-                if let Some((result_sets, _, _)) = results.as_ref() {
+                if let Some((result_sets, _, calculated_target, _)) = results.as_ref() {
                     if result_sets.is_empty() {
                         feedback_setter.set(Some("No results to copy.".to_string()));
                         return;
                     }
 
-                    let csv_content = result_sets
+                    if result_sets
                         .iter()
-                        .filter_map(|car_indices| {
-                            let row = car_indices
-                                .iter()
-                                .filter_map(|&index| cars.get(index).map(|car| car.id.clone()))
-                                .collect::<Vec<String>>();
-                            (!row.is_empty()).then(|| row.join(","))
-                        })
-                        .collect::<Vec<String>>()
-                        .join("\n");
-                    if csv_content.is_empty() {
+                        .flatten()
+                        .all(|&index| index >= cars.len())
+                    {
                         feedback_setter
                             .set(Some("Results contain no valid cars to copy.".to_string()));
                         return;
                     }
+                    let csv_content = export_named_results_csv(
+                        &cars,
+                        result_sets,
+                        *calculated_target,
+                        &subset_names,
+                    );
 
                     let window = web_sys::window().expect("no global `window` exists");
                     let navigator = window.navigator();
@@ -886,10 +1347,357 @@ fn main_component() -> Html {
         })
     };
 
+    let handle_recompute_unlocked = {
+        let cars = cars.clone();
+        let results = results.clone();
+        let results_config = results_config.clone();
+        let locked_rows = locked_rows.clone();
+        let recompute_progress = recompute_progress.clone();
+        let results_epoch = results_epoch.clone();
+        let subset_names = subset_names.clone();
+        let feedback_setter = copy_feedback.clone();
+        Callback::from(move |_: MouseEvent| {
+            let (Some(value), Some(config)) = ((*results).clone(), results_config.clone()) else {
+                return;
+            };
+            let cars = cars.clone();
+            let results = results.clone();
+            let locked = locked_rows.clone();
+            let recompute_progress = recompute_progress.clone();
+            let results_epoch = results_epoch.clone();
+            let epoch = *results_epoch.borrow();
+            let subset_names = subset_names.clone();
+            let feedback_setter = feedback_setter.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let current = || *results_epoch.borrow() == epoch;
+                let outcome = reroll_unlocked(&cars, value, &locked, &config, |done, total| {
+                    if current() {
+                        recompute_progress.set(Some((done, total)));
+                    }
+                })
+                .await;
+                if !current() {
+                    return;
+                }
+                recompute_progress.set(None);
+                match outcome {
+                    Ok(recomputed) => {
+                        results.set(Some(recomputed));
+                        feedback_setter
+                            .set(Some("Recomputed the unlocked selections.".to_string()));
+                    }
+                    Err((index, error)) => feedback_setter.set(Some(format!(
+                        "Could not recompute selection {}: {error}",
+                        subset_label(&subset_names, index)
+                    ))),
+                }
+            });
+        })
+    };
+    // The shown results with their parameters and dataset.
+    let results_export = {
+        let cars = cars.clone();
+        let results = results.clone();
+        let results_config = results_config.clone();
+        let subset_names = subset_names.clone();
+        move || {
+            let (sets, ..) = results.as_ref()?;
+            let export =
+                KarmaExport::new(&cars, results_config.as_ref()?, sets).with_players(&subset_names);
+            Some((export, sets.clone()))
+        }
+    };
+    let handle_reroll_row = {
+        let cars = cars.clone();
+        let results = results.clone();
+        let subset_names = subset_names.clone();
+        let feedback_setter = copy_feedback.clone();
+        Callback::from(move |index: usize| {
+            let (Some(value), Some(config)) = (results.as_ref(), &results_config) else {
+                return;
+            };
+            let label = subset_label(&subset_names, index);
+            match reroll_subset(&cars, value, index, config) {
+                Ok(rerolled) => {
+                    results.set(Some(rerolled));
+                    feedback_setter.set(Some(format!("Re-rolled selection {label}.")));
+                }
+                Err(error) => feedback_setter.set(Some(format!(
+                    "Could not re-roll selection {label}: {error}"
+                ))),
+            }
+        })
+    };
+    let handle_download_csv = {
+        let results_export = results_export.clone();
+        let cars = cars.clone();
+        let subset_names = subset_names.clone();
+        let feedback_setter = copy_feedback.clone();
+        Callback::from(move |_: MouseEvent| {
+            let Some((export, sets)) = results_export() else {
+                return;
+            };
+            let csv = export_named_results_csv(&cars, &sets, export.config.target, &subset_names);
+            if download(&export.file_name("csv"), "text/csv", &csv).is_err() {
+                feedback_setter.set(Some("Failed to start the download.".to_string()));
+            }
+        })
+    };
+    let handle_download_json = {
+        let results_export = results_export.clone();
+        let feedback_setter = copy_feedback.clone();
+        Callback::from(move |_: MouseEvent| {
+            let Some((export, _)) = results_export() else {
+                return;
+            };
+            if download(
+                &export.file_name("json"),
+                "application/json",
+                &export.to_json(),
+            )
+            .is_err()
+            {
+                feedback_setter.set(Some("Failed to start the download.".to_string()));
+            }
+        })
+    };
+    let handle_copy_text = {
+        let feedback_setter = copy_feedback.clone();
+        Callback::from(move |_: MouseEvent| {
+            let Some((export, _)) = results_export() else {
+                return;
+            };
+            let feedback_setter = feedback_setter.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let window = web_sys::window().expect("no global `window` exists");
+                match wasm_bindgen_futures::JsFuture::from(
+                    window.navigator().clipboard().write_text(&export.to_text()),
+                )
+                .await
+                {
+                    Ok(_) => feedback_setter.set(Some("Results copied as text!".to_string())),
+                    Err(_) => {
+                        feedback_setter.set(Some("Failed to copy. Check permissions.".to_string()))
+                    }
+                }
+            });
+        })
+    };
+
+    let handle_copy_row = {
+        let cars = cars.clone();
+        let results = results.clone();
+        let subset_names = subset_names.clone();
+        let feedback_setter = copy_feedback.clone();
+        Callback::from(move |index: usize| {
+            let Some((sets, _, target, _)) = results.as_ref() else {
+                return;
+            };
+            let Some(set) = sets.get(index) else {
+                return;
+            };
+            let label = subset_label(&subset_names, index);
+            let text = format_subset_markdown(&cars, set, *target, &label);
+            let feedback_setter = feedback_setter.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let window = web_sys::window().expect("no global `window` exists");
+                match wasm_bindgen_futures::JsFuture::from(
+                    window.navigator().clipboard().write_text(&text),
+                )
+                .await
+                {
+                    Ok(_) => feedback_setter.set(Some(format!("Copied selection {label}!"))),
+                    Err(_) => {
+                        feedback_setter.set(Some("Failed to copy. Check permissions.".to_string()))
+                    }
+                }
+            });
+        })
+    };
+
+    // A result kept to compare later results against.
+    let pinned_result = use_state(|| None::<Rc<ComparedResult>>);
+    let current_result = results.as_ref().map(|(sets, _, calc_target, _)| {
+        Rc::new(ComparedResult {
+            cars: Rc::new((*cars).clone()),
+            results: Rc::new(sets.clone()),
+            target: *calc_target,
+            lap_count: *lap_count,
+            player_count: *player_count,
+            tolerance_percent: *tolerance_percent,
+        })
+    });
+    let handle_pin_result = {
+        let pinned_result = pinned_result.clone();
+        let current_result = current_result.clone();
+        Callback::from(move |_: MouseEvent| pinned_result.set(current_result.clone()))
+    };
+    let handle_unpin_result = {
+        let pinned_result = pinned_result.clone();
+        Callback::from(move |_: ()| pinned_result.set(None))
+    };
+
+    // Another cached target for the same parameters, to list what changed
+    // from its result to the shown one.
+    let diff_target = use_state(|| None::<u32>);
+    let diff_metadata = results
+        .as_ref()
+        .map(|(_, _, calc_target, _)| RequestMetadata {
+            request_id: 0,
+            dataset_generation: dataset_generation.get(),
+            dataset_hash: dataset_hash(&cars),
+            target: *calc_target,
+            lap_count: *lap_count,
+            player_count: *player_count,
+            timeout_ms: *timeout_seconds * 1000.0,
+            tolerance_percent: *tolerance_percent,
+            strategy: *solver_strategy,
+            min_lap_time: *min_lap_time,
+            max_lap_time: *max_lap_time,
+        });
+    let diff_targets = diff_metadata.as_ref().map_or_else(Vec::new, |metadata| {
+        let mut targets = cached_targets(metadata);
+        targets.retain(|&target| target != metadata.target);
+        targets
+    });
+    let diff_source = (*diff_target)
+        .filter(|target| diff_targets.contains(target))
+        .zip(diff_metadata.as_ref())
+        .and_then(|(target, metadata)| {
+            let (sets, ..) = cached_result(&RequestMetadata {
+                target,
+                ..metadata.clone()
+            })?;
+            Some((target, Rc::new(sets)))
+        });
+    let handle_diff_select = {
+        let diff_target = diff_target.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            diff_target.set(select.value().parse::<u32>().ok());
+        })
+    };
+    let handle_close_diff = {
+        let diff_target = diff_target.clone();
+        Callback::from(move |_: ()| diff_target.set(None))
+    };
+
+    // The address already carries the parameters; see `LinkState`.
+    let handle_copy_link = {
+        let feedback_setter = copy_feedback.clone();
+        Callback::from(move |_: MouseEvent| {
+            let feedback_setter = feedback_setter.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let window = web_sys::window().expect("no global `window` exists");
+                let Ok(href) = window.location().href() else {
+                    feedback_setter.set(Some("Failed to read the page address.".to_string()));
+                    return;
+                };
+                match wasm_bindgen_futures::JsFuture::from(
+                    window.navigator().clipboard().write_text(&href),
+                )
+                .await
+                {
+                    Ok(_) => feedback_setter.set(Some("Link copied to clipboard!".to_string())),
+                    Err(_) => {
+                        feedback_setter.set(Some("Failed to copy. Check permissions.".to_string()))
+                    }
+                }
+            });
+        })
+    };
+
+    // Cached results travel between sessions as a versioned snapshot.
+    let copy_cache = {
+        let feedback_setter = cache_feedback.clone();
+        Callback::from(move |_: MouseEvent| {
+            let feedback_setter = feedback_setter.clone();
+            let snapshot = CACHE_STORE.with(|c| c.borrow().snapshot());
+            wasm_bindgen_futures::spawn_local(async move {
+                if snapshot.entries.is_empty() {
+                    feedback_setter.set(Some("The cache is empty.".to_string()));
+                    return;
+                }
+                let window = web_sys::window().expect("no global `window` exists");
+                match wasm_bindgen_futures::JsFuture::from(
+                    window
+                        .navigator()
+                        .clipboard()
+                        .write_text(&snapshot.to_json()),
+                )
+                .await
+                {
+                    Ok(_) => feedback_setter.set(Some(format!(
+                        "Copied {} cached results.",
+                        snapshot.entries.len()
+                    ))),
+                    Err(_) => {
+                        feedback_setter.set(Some("Failed to copy. Check permissions.".to_string()))
+                    }
+                }
+            });
+        })
+    };
+
+    let paste_cache = {
+        let feedback_setter = cache_feedback.clone();
+        let cache_version = cache_version.clone();
+        Callback::from(move |_: MouseEvent| {
+            let feedback_setter = feedback_setter.clone();
+            let cache_version = cache_version.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let window = web_sys::window().expect("no global `window` exists");
+                let text = wasm_bindgen_futures::JsFuture::from(
+                    window.navigator().clipboard().read_text(),
+                )
+                .await
+                .ok()
+                .and_then(|text| text.as_string());
+                let Some(text) = text else {
+                    feedback_setter.set(Some(
+                        "Failed to read from clipboard. Check permissions.".to_string(),
+                    ));
+                    return;
+                };
+                match CacheSnapshot::from_json(&text) {
+                    Ok(snapshot) => {
+                        let restored = snapshot.entries.len();
+                        let skipped = snapshot.skipped;
+                        CACHE_STORE.with(|c| c.borrow_mut().restore(snapshot));
+                        update_cache_version(&cache_version);
+                        feedback_setter.set(Some(if skipped > 0 {
+                            format!(
+                                "Restored {restored} cached results; {skipped} were unreadable."
+                            )
+                        } else {
+                            format!("Restored {restored} cached results.")
+                        }));
+                    }
+                    Err(error) => feedback_setter.set(Some(error)),
+                }
+            });
+        })
+    };
+
     html! {
         <div class="container">
             <h1>{ "Random Karma Configuration" }</h1>
 
+            <div class="history-controls">
+                <button class="btn-secondary small"
+                    onclick={handle_undo.reform(|_: MouseEvent| ())}
+                    disabled={!history_steps.0}
+                    title="Undo the last parameter change (Ctrl+Z)">
+                    { "Undo" }
+                </button>
+                <button class="btn-secondary small"
+                    onclick={handle_redo.reform(|_: MouseEvent| ())}
+                    disabled={!history_steps.1}
+                    title="Redo the parameter change undone (Ctrl+Shift+Z)">
+                    { "Redo" }
+                </button>
+            </div>
+
             <div class="top-controls">
                 <div class="form-group">
                     <label for="lap_count_text_input">{ "Lap Count:" }</label>
@@ -923,13 +1731,13 @@ fn main_component() -> Html {
                             id="lap_count_text_input"
                             min="1"
                             max={cars.len().to_string()}
-                            value={(*lap_count_text).clone()}
-                            class={if (*lap_count_error).is_some() { "invalid" } else { "" }}
-                            oninput={lap_count_text_oninput}
-                            onchange={handle_lap_count_input.reform(|_|())}
-                            onkeydown={lap_count_onkeydown}
+                            value={lap_count_input.text.clone()}
+                            class={if lap_count_input.error.is_some() { "invalid" } else { "" }}
+                            oninput={lap_count_input.on_text_input.clone()}
+                            onchange={lap_count_input.on_commit.reform(|_|())}
+                            onkeydown={lap_count_input.on_keydown.clone()}
                         />
-                        if let Some(ref err) = *lap_count_error {
+                        if let Some(ref err) = lap_count_input.error {
                             <div class="input-error">{ err }</div>
                         }
                     </div>
@@ -968,13 +1776,13 @@ fn main_component() -> Html {
                             id="player_count_text_input"
                             min="0"
                             max={MAX_PLAYER_COUNT.to_string()}
-                            value={(*player_count_text).clone()}
-                            class={if (*player_count_error).is_some() { "invalid" } else { "" }}
-                            oninput={player_count_text_oninput}
-                            onchange={handle_player_count_input.reform(|_|())}
-                            onkeydown={player_count_onkeydown}
+                            value={player_count_input.text.clone()}
+                            class={if player_count_input.error.is_some() { "invalid" } else { "" }}
+                            oninput={player_count_input.on_text_input.clone()}
+                            onchange={player_count_input.on_commit.reform(|_|())}
+                            onkeydown={player_count_input.on_keydown.clone()}
                         />
-                        if let Some(ref err) = *player_count_error {
+                        if let Some(ref err) = player_count_input.error {
                             <div class="input-error">{ err }</div>
                         }
                     </div>
@@ -984,6 +1792,31 @@ fn main_component() -> Html {
             // Chart section (full width)
             <div class="chart-section">
                 <canvas id="similarityChart"></canvas>
+                <div class="chart-pins">
+                    <button class="btn-secondary small"
+                        onclick={handle_pin_curve}
+                        disabled={!current_curve_pinned && pinned_curves.len() >= MAX_PINNED_CURVES}
+                        title="Keep this curve on the chart to compare it with other lap and player counts">
+                        { if current_curve_pinned { "Update pinned curve" } else { "Pin curve" } }
+                    </button>
+                    { for pinned_curves.iter().enumerate().map(|(index, &(laps, players))| {
+                        let label = pin_label(laps, players);
+                        let onclick = {
+                            let pinned_curves = pinned_curves.clone();
+                            Callback::from(move |_: MouseEvent| {
+                                pinned_curves.set(unpin_similarity_curve(laps, players))
+                            })
+                        };
+                        html! {
+                            <span class="pinned-curve" style={format!("--pin-color: var(--pin-{})", index + 1)}>
+                                { label.clone() }
+                                <button class="pinned-curve-remove" aria-label={format!("Unpin {label}")} {onclick}>
+                                    { "×" }
+                                </button>
+                            </span>
+                        }
+                    }) }
+                </div>
             </div>
 
             // Target Time slider (full width, aligned with chart)
@@ -993,15 +1826,20 @@ fn main_component() -> Html {
                         <label for="target_text_input">{ "Target Time:" }</label>
                         <div class="slider-with-value">
                             <input type="range"
-                                min={base_target_range(&cars, *lap_count).0.to_string()}
-                                max={base_target_range(&cars, *lap_count).1.to_string()}
+                                min={target_range.0.to_string()}
+                                max={target_range.1.to_string()}
                                 value={target.to_string()}
                                 class="target-slider"
+                                style={difficulty_track}
+                                title={(!cars.is_empty()).then(|| format!("Estimated chance of success: {:.0}%", target_success * 100.0))}
                                 oninput={
                                     let target_setter = target.clone();
                                     let slider_idx_setter = slider_idx.clone();
                                     let cars_clone = cars.clone();
                                     let lap_count_clone = lap_count.clone();
+                                    let player_count_clone = player_count.clone();
+                                    let strategy_clone = solver_strategy.clone();
+                                    let lap_time_filter = (*min_lap_time, *max_lap_time);
                                     let calculate_cb = calculate.clone();
                                     let debounce_timer_cb = debounce_timer.clone();
                                     let request_state = request_state.clone();
@@ -1016,12 +1854,8 @@ fn main_component() -> Html {
                                             results.set(None);
                                             error_message.set(None);
                                             // Update slider_idx based on new target value
-                                            let (min_target, max_target) = base_target_range(&cars_clone, *lap_count_clone);
-                                            let range = max_target - min_target;
-                                            let pos = if range > 0 {
-                                                ((val - min_target) as f64 / range as f64 * SLIDER_MAX_INDEX as f64).round() as usize
-                                            } else { 0 };
-                                            slider_idx_setter.set(pos.min(SLIDER_MAX_INDEX));
+                                            let (min_target, max_target) = base_target_range(&cars_clone, *lap_count_clone, *player_count_clone, *strategy_clone, lap_time_filter);
+                                            slider_idx_setter.set(calc_idx_from_target(min_target, max_target, val));
                                             debounce_callback(&debounce_timer_cb, calculate_cb.clone(), Some(val), DEBOUNCE_MS);
                                         }
                                     })
@@ -1031,17 +1865,29 @@ fn main_component() -> Html {
                             <input
                                 type="text"
                                 id="target_text_input"
-                                value={(*target_text).clone()}
-                                class={format!("slider-value {}", if (*target_error).is_some() { "invalid" } else { "" })}
-                                placeholder="MM:SS.mmm"
-                                oninput={target_text_oninput}
-                                onchange={handle_target_input.reform(|_|())}
-                                onkeydown={target_onkeydown}
+                                value={target_input.text.clone()}
+                                class={format!("slider-value {}", if target_input.error.is_some() { "invalid" } else { "" })}
+                                placeholder="MM:SS.mmm or M:SS.mmm/lap"
+                                oninput={target_input.on_text_input.clone()}
+                                onchange={target_input.on_commit.reform(|_|())}
+                                onkeydown={target_input.on_keydown.clone()}
                             />
                         </div>
-                        if let Some(ref error) = *target_error {
+                        if let Some(ref error) = target_input.error {
                             <div class="input-error">{ error }</div>
                         }
+                        if !request_diagnostics.is_empty() {
+                            <ul class="request-diagnostics">
+                                { for request_diagnostics.iter().map(|diagnostic| html! {
+                                    <li class={classes!("diagnostic", match diagnostic.severity {
+                                        Severity::Error => "error",
+                                        Severity::Warning => "warning",
+                                    })}>
+                                        { &diagnostic.message }
+                                    </li>
+                                }) }
+                            </ul>
+                        }
                     </div>
                 </div>
             </div>
@@ -1086,6 +1932,40 @@ fn main_component() -> Html {
                                 <div class="clipboard-feedback">{ feedback }</div>
                             }
                         </div>
+                        <div class="form-group dataset-select">
+                            <label for="dataset_select">{ "Dataset:" }</label>
+                            <select id="dataset_select" onchange={handle_dataset_select}>
+                                if selected_dataset.is_none() {
+                                    <option value="" selected=true disabled=true>{ "Custom pool" }</option>
+                                }
+                                { for BUILTIN_DATASETS.iter().enumerate().map(|(index, dataset)| html! {
+                                    <option value={index.to_string()} selected={*selected_dataset == Some(index)}>
+                                        { dataset.name }
+                                    </option>
+                                }) }
+                            </select>
+                        </div>
+                        <CarPoolEditor
+                            cars={Rc::new((*cars).clone())}
+                            on_edit={handle_car_edit}
+                            error={(*car_pool_error).clone()}
+                        />
+                        <div class="form-group">
+                            <label for="roster_text_input">{ "Player Names (one per line):" }</label>
+                            <textarea
+                                id="roster_text_input"
+                                class="roster-input"
+                                rows="4"
+                                value={(*roster_text).clone()}
+                                placeholder="Results are numbered without names"
+                                oninput={roster_text_oninput}
+                            />
+                            if !roster.is_empty() && roster.len() != *player_count {
+                                <div class="input-hint">
+                                    { format!("{} names for {} players; subsets without a name stay numbered", roster.len(), *player_count) }
+                                </div>
+                            }
+                        </div>
                         <div class="form-group checkbox-group">
                             <label>
                                 <input type="checkbox"
@@ -1101,6 +1981,75 @@ fn main_component() -> Html {
                                 { "Enable Pre-caching" }
                             </label>
                         </div>
+                        if *precache_enabled && precache_progress.planned > 0 {
+                            <div class="precache-panel" role="status">
+                                <progress
+                                    max={precache_progress.planned.to_string()}
+                                    value={precache_progress.done.to_string()}
+                                />
+                                <span class="precache-counts">
+                                    if *precache_paused {
+                                        <strong>{ "Paused · " }</strong>
+                                    }
+                                    { format!(
+                                        "{} queued · {} running · {}/{} done",
+                                        precache_progress.queued(),
+                                        precache_progress.running,
+                                        precache_progress.done,
+                                        precache_progress.planned
+                                    ) }
+                                    if precache_progress.failed > 0 {
+                                        { format!(" · {} failed", precache_progress.failed) }
+                                    }
+                                </span>
+                                if precache_progress.done < precache_progress.planned {
+                                    <button class="btn-secondary small" onclick={handle_toggle_precache_pause}>
+                                        { if *precache_paused { "Resume" } else { "Pause" } }
+                                    </button>
+                                }
+                            </div>
+                        }
+                        <div class="form-group">
+                            <label for="worker_count_input">
+                                { format!("Background Workers: {worker_count}") }
+                                if worker_count_override.is_none() {
+                                    { " (auto)" }
+                                }
+                            </label>
+                            <div class="worker-count-setting">
+                                <input
+                                    type="range"
+                                    id="worker_count_input"
+                                    min="1"
+                                    max={worker_limit.to_string()}
+                                    step="1"
+                                    value={worker_count.to_string()}
+                                    onchange={
+                                        let worker_count_override = worker_count_override.clone();
+                                        Callback::from(move |e: Event| {
+                                            let input: HtmlInputElement = e.target_unchecked_into();
+                                            let count = parse_worker_count(&input.value());
+                                            store_worker_count(count);
+                                            worker_count_override.set(count);
+                                        })
+                                    }
+                                />
+                                if worker_count_override.is_some() {
+                                    <button
+                                        class="btn-secondary small"
+                                        onclick={
+                                            let worker_count_override = worker_count_override.clone();
+                                            Callback::from(move |_: MouseEvent| {
+                                                store_worker_count(None);
+                                                worker_count_override.set(None);
+                                            })
+                                        }
+                                    >
+                                        { format!("Auto ({})", *detected_workers) }
+                                    </button>
+                                }
+                            </div>
+                        </div>
 
                         <fieldset class="strategy-setting">
                             <legend>{ "Solver strategy" }</legend>
@@ -1172,14 +2121,14 @@ fn main_component() -> Html {
                                     step="0.1"
                                     min={MIN_TIMEOUT_SEC.to_string()}
                                     max={MAX_TIMEOUT_SEC.to_string()}
-                                    value={(*timeout_seconds_text).clone()}
-                                    class={if (*timeout_error).is_some() { "invalid" } else { "" }}
+                                    value={timeout_input.text.clone()}
+                                    class={if timeout_input.error.is_some() { "invalid" } else { "" }}
                                     placeholder={DEFAULT_TIMEOUT_SEC.to_string()}
-                                    oninput={timeout_seconds_text_oninput}
-                                    onchange={handle_timeout_input.reform(|_|())}
-                                    onkeydown={timeout_onkeydown}
+                                    oninput={timeout_input.on_text_input.clone()}
+                                    onchange={timeout_input.on_commit.reform(|_|())}
+                                    onkeydown={timeout_input.on_keydown.clone()}
                                 />
-                                if let Some(ref err) = *timeout_error {
+                                if let Some(ref err) = timeout_input.error {
                                     <div class="input-error">{ err }</div>
                                 }
                             </div>
@@ -1193,33 +2142,119 @@ fn main_component() -> Html {
                                     step="0.1"
                                     min={MIN_TOLERANCE_PCT.to_string()}
                                     max={MAX_TOLERANCE_PCT.to_string()}
-                                    value={(*tolerance_percent_text).clone()}
-                                    class={if (*tolerance_error).is_some() { "invalid" } else { "" }}
+                                    value={tolerance_input.text.clone()}
+                                    class={if tolerance_input.error.is_some() { "invalid" } else { "" }}
                                     placeholder={DEFAULT_TOLERANCE_PCT.to_string()}
-                                    oninput={tolerance_percent_text_oninput}
-                                    onchange={handle_tolerance_input.reform(|_|())}
-                                    onkeydown={tolerance_onkeydown}
+                                    oninput={tolerance_input.on_text_input.clone()}
+                                    onchange={tolerance_input.on_commit.reform(|_|())}
+                                    onkeydown={tolerance_input.on_keydown.clone()}
+                                />
+                                if let Some(ref err) = tolerance_input.error {
+                                    <div class="input-error">{ err }</div>
+                                }
+                            </div>
+
+                            <div class="form-group">
+                                <label for="seed_text_input">{ "Seed:" }</label>
+                                <input
+                                    type="text"
+                                    id="seed_text_input"
+                                    inputmode="numeric"
+                                    value={seed_input.text.clone()}
+                                    class={if seed_input.error.is_some() { "invalid" } else { "" }}
+                                    placeholder="Random"
+                                    title="Repeats the same subsets for the same parameters"
+                                    oninput={seed_input.on_text_input.clone()}
+                                    onchange={seed_input.on_commit.reform(|_|())}
+                                    onkeydown={seed_input.on_keydown.clone()}
                                 />
-                                if let Some(ref err) = *tolerance_error {
+                                if let Some(ref err) = seed_input.error {
                                     <div class="input-error">{ err }</div>
                                 }
                             </div>
                         </div>
 
+                        <div class="form-row">
+                            <div class="form-group">
+                                <label for="min_lap_time_text_input">{ "Min Lap Time:" }</label>
+                                <input
+                                    type="text"
+                                    id="min_lap_time_text_input"
+                                    value={min_lap_time_input.text.clone()}
+                                    class={if min_lap_time_input.error.is_some() { "invalid" } else { "" }}
+                                    placeholder="None"
+                                    oninput={min_lap_time_input.on_text_input.clone()}
+                                    onchange={min_lap_time_input.on_commit.reform(|_|())}
+                                    onkeydown={min_lap_time_input.on_keydown.clone()}
+                                />
+                            </div>
+
+                            <div class="form-group">
+                                <label for="max_lap_time_text_input">{ "Max Lap Time:" }</label>
+                                <input
+                                    type="text"
+                                    id="max_lap_time_text_input"
+                                    value={max_lap_time_input.text.clone()}
+                                    class={if max_lap_time_input.error.is_some() { "invalid" } else { "" }}
+                                    placeholder="None"
+                                    oninput={max_lap_time_input.on_text_input.clone()}
+                                    onchange={max_lap_time_input.on_commit.reform(|_|())}
+                                    onkeydown={max_lap_time_input.on_keydown.clone()}
+                                />
+                            </div>
+                        </div>
+                        if let Some(ref err) = lap_time_filter_error {
+                            <div class="input-error">{ err }</div>
+                        }
+
                         <div class="cache-stats">
                     <div class="cache-status compact">
                         { format!("Cache: {}/{} calculations", cached_count, SLIDER_MAX_INDEX + 1) }
                     </div>
 
                     <div class="cache-status-global compact">
-                        { format!("Total entries: {}", CACHE_STORE.with(|c| c.borrow().len())) }
+                        { CACHE_STORE.with(|c| {
+                            let cache = c.borrow();
+                            format!("Total entries: {}/{}", cache.len(), cache.capacity())
+                        }) }
+                        if *cache_evictions.borrow() > 0 {
+                            { format!(" · {} evicted", cache_evictions.borrow()) }
+                        }
                     </div>
 
+                    <label class="cache-capacity compact">
+                        { "Max entries: " }
+                        <input
+                            type="number"
+                            min={MIN_CACHE_CAPACITY.to_string()}
+                            max={MAX_CACHE_CAPACITY.to_string()}
+                            step="1"
+                            value={CACHE_STORE.with(|c| c.borrow().capacity()).to_string()}
+                            onchange={
+                                let cache_version = cache_version.clone();
+                                Callback::from(move |e: Event| {
+                                    let input: HtmlInputElement = e.target_unchecked_into();
+                                    let capacity = input
+                                        .value()
+                                        .trim()
+                                        .parse::<usize>()
+                                        .unwrap_or(MAX_CACHE_ENTRIES)
+                                        .clamp(MIN_CACHE_CAPACITY, MAX_CACHE_CAPACITY);
+                                    input.set_value(&capacity.to_string());
+                                    CACHE_STORE.with(|c| c.borrow_mut().set_capacity(capacity));
+                                    update_cache_version(&cache_version);
+                                })
+                            }
+                        />
+                    </label>
+
                     <button class="btn-secondary small"
                         onclick={
                             let cache_version = cache_version.clone();
                             let lap_count = *lap_count; // Capture value, not state handle
                             let player_count = *player_count; // Capture value, not state handle
+                            let strategy = *solver_strategy; // Capture value, not state handle
+                            let lap_time_filter = (*min_lap_time, *max_lap_time); // Capture values
                             let cars = cars.clone(); // Clone the handle
                             let precache_enabled = *precache_enabled; // Capture value
                             let precache_trigger = precache_trigger.clone();
@@ -1228,7 +2263,7 @@ fn main_component() -> Html {
                                 update_cache_version(&cache_version);
 
                                 // Re-initialize the chart to clear any cached data points
-                                let (min, max) = base_target_range(&cars, lap_count);
+                                let (min, max) = base_target_range(&cars, lap_count, player_count, strategy, lap_time_filter);
                                 if max > min {
                                     init_similarity_chart(min, max, lap_count as u32, player_count as u32);
                                 }
@@ -1242,6 +2277,15 @@ fn main_component() -> Html {
                     >
                         { "Clear Cache" }
                     </button>
+                    <button class="btn-secondary small" onclick={copy_cache}>
+                        { "Copy Cache" }
+                    </button>
+                    <button class="btn-secondary small" onclick={paste_cache}>
+                        { "Paste Cache" }
+                    </button>
+                    if let Some(feedback) = &*cache_feedback {
+                        <div class="cache-feedback compact">{ feedback }</div>
+                    }
                 </div>
 
                         if let Some(err) = &*error_message {
@@ -1256,23 +2300,130 @@ fn main_component() -> Html {
             // Results section
             <div class="results-section">
                 if *is_calculating {
-                    <div class="loading-indicator">{ "Calculating..." }</div>
+                    <div class="loading-indicator" role="status">
+                        if let Some((run, total)) = *calculation_progress {
+                            { format!("Calculating... run {run}/{total}, {:.1}s elapsed", *calculation_elapsed) }
+                            <progress max={total.to_string()} value={run.to_string()} />
+                        } else {
+                            { format!("Calculating... {:.1}s elapsed", *calculation_elapsed) }
+                            <progress />
+                        }
+                        <button class="btn-secondary small" onclick={handle_cancel_calculation}>
+                            { "Cancel" }
+                        </button>
+                    </div>
                 } else if let Some(ref error) = *error_message {
                     <div class="error-message">{ error }</div>
-                } else if let Some((sets, sim, calc_target)) = &*results {
+                } else if let Some((sets, sim, calc_target, weighted_sim)) = &*results {
                     <div class="results-header">
                         <button onclick={handle_copy_results_to_clipboard} class="button-secondary">
                             { "Copy Results as CSV" }
                         </button>
+                        <button onclick={handle_copy_link} class="button-secondary">
+                            { "Copy Link" }
+                        </button>
+                        <button
+                            onclick={handle_pin_result}
+                            class="button-secondary"
+                            title="Keep this result to compare other targets and parameters against"
+                        >
+                            { if pinned_result.is_some() { "Pin This Instead" } else { "Pin for Comparison" } }
+                        </button>
+                        if !locked_rows.is_empty() && locked_rows.len() < sets.len() {
+                            <button
+                                onclick={handle_recompute_unlocked}
+                                class="button-primary"
+                                disabled={recompute_progress.is_some()}
+                            >
+                                { match *recompute_progress {
+                                    Some((done, total)) => format!("Recomputing {done}/{total}…"),
+                                    None => format!("Recompute {} Unlocked", sets.len() - locked_rows.len()),
+                                } }
+                            </button>
+                        }
+                        <button onclick={handle_copy_text} class="button-secondary">
+                            { "Copy as Text" }
+                        </button>
+                        <button onclick={handle_download_csv} class="button-secondary">
+                            { "Download CSV" }
+                        </button>
+                        <button onclick={handle_download_json} class="button-secondary">
+                            { "Download JSON" }
+                        </button>
                         if let Some(feedback) = &*copy_feedback {
                             <div class="copy-feedback">{ feedback }</div>
                         }
                     </div>
+                    if !roster.is_empty() && !sets.is_empty() {
+                        <div class="draw-panel">
+                            <button onclick={handle_draw} class="button-secondary">
+                                { if draw.is_some() { "Redraw" } else { "Draw Subsets" } }
+                            </button>
+                            if let Some(draw) = &*draw {
+                                <button onclick={handle_reveal_next} class="button-primary" disabled={draw.is_complete()}>
+                                    { "Reveal Next" }
+                                </button>
+                                <span class="draw-seed" title="Set this seed under Settings to repeat the draw">
+                                    { format!("Seed {}", draw.seed) }
+                                </span>
+                                <ol class="draw-reveals" aria-live="polite">
+                                    { for draw.revealed().map(|(player, subset)| html! {
+                                        <li><strong>{ &roster[player] }</strong>{ format!(" → Set #{}", subset + 1) }</li>
+                                    }) }
+                                </ol>
+                            }
+                        </div>
+                    }
+                    if !diff_targets.is_empty() {
+                        <div class="diff-picker">
+                            <label for="diff_target_select">{ "Show changes from target:" }</label>
+                            <select id="diff_target_select" onchange={handle_diff_select}>
+                                <option value="" selected={diff_source.is_none()}>{ "None" }</option>
+                                { for diff_targets.iter().map(|&target| html! {
+                                    <option
+                                        value={target.to_string()}
+                                        selected={diff_source.as_ref().is_some_and(|(from, _)| *from == target)}
+                                    >
+                                        { format_ms_to_minsecms(target) }
+                                    </option>
+                                }) }
+                            </select>
+                        </div>
+                    }
+                    if let Some((from_target, from)) = &diff_source {
+                        <ResultDiff
+                            cars={Rc::new((*cars).clone())}
+                            from={from.clone()}
+                            from_target={*from_target}
+                            to={Rc::new(sets.clone())}
+                            to_target={*calc_target}
+                            players={subset_names.clone()}
+                            on_close={handle_close_diff}
+                        />
+                    }
+                    if let (Some(pinned), Some(current)) = (&*pinned_result, &current_result) {
+                        <ResultComparison
+                            pinned={pinned.clone()}
+                            current={current.clone()}
+                            on_unpin={handle_unpin_result}
+                        />
+                    }
                     <ResultsWrapper
                         cars={Rc::new((*cars).clone())}
                         all_results={Rc::new(sets.clone())}
                         similarity={*sim}
+                        weighted_similarity={*weighted_sim}
+                        quality={quality_score(&cars, sets, *calc_target, *tolerance_percent, QualityWeights::default()).score}
                         calculated_target={*calc_target}
+                        tolerance_percent={*tolerance_percent}
+                        players={subset_names.clone()}
+                        on_copy={handle_copy_row}
+                        on_reroll={handle_reroll_row}
+                        locked={(*locked_rows).clone()}
+                        on_toggle_lock={handle_toggle_lock}
+                        stat_columns={(*stat_columns).clone()}
+                        on_toggle_stat_column={handle_toggle_stat_column}
+                        run_reports={Rc::new(run_reports.as_ref().map_or_else(Vec::new, |reports| reports.for_rows(*calc_target, sets)))}
                     />
                 } else {
                     <div class="no-results-placeholder">{ "Select parameters and find karma" }</div>
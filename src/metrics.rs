@@ -0,0 +1,316 @@
+//! Solver performance metrics.
+//!
+//! [`MetricsRecorder`] is an [`EventSink`] that aggregates solver events into
+//! a serializable [`MetricsSnapshot`] for diagnostics panels and CLI output,
+//! with a [`RunReport`] for every completed run.
+
+use crate::events::{EventSink, LogSink, SolverEvent};
+use crate::{
+    perform_multiple_runs_with_events, Car, CarIndex, SubsetCalculationConfig, SubsetError,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Summary statistics over a set of samples.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Distribution {
+    /// Summarizes `samples`, or returns `None` when there are none.
+    pub fn of(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let count = samples.len();
+        let mean = samples.iter().sum::<f64>() / count as f64;
+        let variance = samples
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+        Some(Self {
+            count,
+            min: samples.iter().copied().fold(f64::INFINITY, f64::min),
+            max: samples.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            std_dev: variance.sqrt(),
+        })
+    }
+}
+
+/// How one completed run, i.e. one player's subset, was found.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub run: usize,
+    /// Solver attempts made for the run, including rejected ones.
+    pub attempts: usize,
+    /// Attempts that missed the tolerance or shared too many cars.
+    pub backtracks: usize,
+    /// Wall-clock time spent on the run.
+    pub elapsed_ms: f64,
+    /// Sum as a percentage of the target.
+    pub accuracy: f64,
+    pub exact: bool,
+    /// The run used up its budget and has no subset.
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+/// Aggregated solver metrics for one or more calculations.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    /// Runs that produced a subset.
+    pub runs_completed: usize,
+    /// Completed runs whose sum equals the target exactly.
+    #[serde(default)]
+    pub exact_hits: usize,
+    /// Solver attempts across all runs, including rejected ones.
+    pub attempts: usize,
+    /// Attempts that finished outside the tolerance and restarted their run.
+    pub backtracks: usize,
+    /// Selections where no car fit the valid range.
+    pub fallbacks: usize,
+    /// Cars chosen one at a time by the legacy heuristic.
+    pub selections: usize,
+    /// Wall-clock time of each completed run, in run order.
+    pub run_times_ms: Vec<f64>,
+    /// Sum as a percentage of the target for each completed run.
+    pub accuracy_percent: Vec<f64>,
+    /// Every completed or skipped run, in run order.
+    #[serde(default)]
+    pub runs: Vec<RunReport>,
+}
+
+impl MetricsSnapshot {
+    pub fn run_time_distribution(&self) -> Option<Distribution> {
+        Distribution::of(&self.run_times_ms)
+    }
+
+    pub fn accuracy_distribution(&self) -> Option<Distribution> {
+        Distribution::of(&self.accuracy_percent)
+    }
+
+    pub fn total_time_ms(&self) -> f64 {
+        self.run_times_ms.iter().sum()
+    }
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Runs completed: {} ({} exact)",
+            self.runs_completed, self.exact_hits
+        )?;
+        writeln!(
+            f,
+            "Attempts: {} ({} backtracks, {} fallbacks)",
+            self.attempts, self.backtracks, self.fallbacks
+        )?;
+        writeln!(f, "Total time: {:.1} ms", self.total_time_ms())?;
+        if let Some(times) = self.run_time_distribution() {
+            writeln!(
+                f,
+                "Time per run: mean {:.1} ms, min {:.1} ms, max {:.1} ms",
+                times.mean, times.min, times.max
+            )?;
+        }
+        if let Some(accuracy) = self.accuracy_distribution() {
+            writeln!(
+                f,
+                "Accuracy: mean {:.3}%, min {:.3}%, max {:.3}%, std dev {:.3}",
+                accuracy.mean, accuracy.min, accuracy.max, accuracy.std_dev
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Event sink that accumulates a [`MetricsSnapshot`].
+///
+/// A recorder may observe several calculations; its snapshot then covers all
+/// of them until [`MetricsRecorder::reset`] is called.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRecorder {
+    snapshot: MetricsSnapshot,
+    /// Backtracks of the run in progress.
+    run_backtracks: usize,
+}
+
+impl MetricsRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.snapshot.clone()
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl EventSink for MetricsRecorder {
+    fn emit(&mut self, event: SolverEvent) {
+        let snapshot = &mut self.snapshot;
+        match event {
+            SolverEvent::SelectionMade { .. } => snapshot.selections += 1,
+            SolverEvent::FallbackUsed { .. } => snapshot.fallbacks += 1,
+            SolverEvent::AttemptRejected { .. } | SolverEvent::SharedCarsRejected { .. } => {
+                snapshot.backtracks += 1;
+                self.run_backtracks += 1;
+            }
+            SolverEvent::RunStarted { .. } => self.run_backtracks = 0,
+            SolverEvent::RunCompleted {
+                run,
+                accuracy,
+                exact,
+                attempts,
+                elapsed_ms,
+                ..
+            } => {
+                snapshot.runs_completed += 1;
+                snapshot.exact_hits += usize::from(exact);
+                snapshot.attempts += attempts;
+                snapshot.run_times_ms.push(elapsed_ms);
+                snapshot.accuracy_percent.push(accuracy);
+                snapshot.runs.push(RunReport {
+                    run,
+                    attempts,
+                    backtracks: std::mem::take(&mut self.run_backtracks),
+                    elapsed_ms,
+                    accuracy,
+                    exact,
+                    skipped: false,
+                });
+            }
+            SolverEvent::RunSkipped {
+                run,
+                attempts,
+                elapsed_ms,
+                ..
+            } => {
+                snapshot.attempts += attempts;
+                snapshot.runs.push(RunReport {
+                    run,
+                    attempts,
+                    backtracks: std::mem::take(&mut self.run_backtracks),
+                    elapsed_ms,
+                    skipped: true,
+                    ..RunReport::default()
+                });
+            }
+            SolverEvent::Rebalanced { .. }
+            | SolverEvent::SwapPassCompleted { .. }
+            | SolverEvent::ToleranceTightened { .. } => {}
+        }
+    }
+}
+
+/// Runs [`perform_multiple_runs`](crate::perform_multiple_runs) and returns
+/// its metrics alongside the result. Events are still logged.
+///
+/// Attempts made by a run that never completes are not counted.
+pub fn measure_multiple_runs(
+    global_cars: &[Car],
+    config: &SubsetCalculationConfig,
+) -> (Result<Vec<Vec<CarIndex>>, SubsetError>, MetricsSnapshot) {
+    let mut recorder = MetricsRecorder::new();
+    let result =
+        perform_multiple_runs_with_events(global_cars, config, &mut (LogSink, &mut recorder));
+    (result, recorder.snapshot())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completed(accuracy: f64, attempts: usize, elapsed_ms: f64) -> SolverEvent {
+        SolverEvent::RunCompleted {
+            run: 1,
+            player_count: 1,
+            subset: vec![0],
+            sum: 100,
+            accuracy,
+            exact: accuracy == 100.0,
+            attempts,
+            elapsed_ms,
+        }
+    }
+
+    #[test]
+    fn recorder_aggregates_events_into_a_snapshot() {
+        let mut recorder = MetricsRecorder::new();
+        recorder.emit(SolverEvent::FallbackUsed {
+            car: 0,
+            remaining_needed: 2,
+        });
+        recorder.emit(SolverEvent::AttemptRejected {
+            run: 1,
+            accuracy: 90.0,
+            tolerance_percent: 1.0,
+        });
+        recorder.emit(completed(99.0, 2, 4.0));
+        recorder.emit(completed(101.0, 1, 2.0));
+
+        let snapshot = recorder.snapshot();
+        assert_eq!(snapshot.runs_completed, 2);
+        assert_eq!(snapshot.attempts, 3);
+        assert_eq!(snapshot.backtracks, 1);
+        assert_eq!(snapshot.fallbacks, 1);
+        assert_eq!(snapshot.total_time_ms(), 6.0);
+
+        let accuracy = snapshot.accuracy_distribution().unwrap();
+        assert_eq!(
+            (accuracy.min, accuracy.max, accuracy.mean),
+            (99.0, 101.0, 100.0)
+        );
+        assert!((accuracy.std_dev - 1.0).abs() < 1e-9);
+        assert_eq!(
+            snapshot
+                .runs
+                .iter()
+                .map(|run| (run.attempts, run.backtracks))
+                .collect::<Vec<_>>(),
+            [(2, 1), (1, 0)]
+        );
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            serde_json::from_str::<MetricsSnapshot>(&json).unwrap(),
+            snapshot
+        );
+
+        recorder.reset();
+        assert_eq!(recorder.snapshot(), MetricsSnapshot::default());
+    }
+
+    #[test]
+    fn measured_runs_report_one_sample_per_run() {
+        let cars = (0..8)
+            .map(|index| Car::new(format!("car-{index}"), 1_000 + index * 5))
+            .collect::<Vec<_>>();
+        let config = SubsetCalculationConfig::builder(2_010, 2, 3)
+            .tolerance_percent(5.0)
+            .build()
+            .unwrap();
+
+        let (result, snapshot) = measure_multiple_runs(&cars, &config);
+        assert_eq!(result.unwrap().len(), 3);
+        assert_eq!(snapshot.runs_completed, 3);
+        assert_eq!(snapshot.run_times_ms.len(), 3);
+        assert_eq!(
+            snapshot.runs.iter().map(|run| run.run).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert!(snapshot.attempts >= 3);
+        assert!(snapshot.to_string().contains("Runs completed: 3"));
+    }
+}
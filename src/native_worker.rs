@@ -0,0 +1,258 @@
+//! The karma task on native threads, for builds without Web Workers.
+//!
+//! A [`NativeBridge`] speaks the protocol of
+//! [`KarmaTask`](crate::worker_agent::KarmaTask), [`WorkerInput`] in and
+//! [`WorkerOutput`] out, over std channels. One thread receives the inputs
+//! while another works through the queued calculations, so a
+//! [`WorkerInput::Cancel`] also stops the running calculation through its
+//! [`CancellationToken`]. [`spawn_task`] starts the same task answering
+//! through a callback instead, for callers that await its messages.
+
+use crate::worker_agent::{run_task, KarmaArgs, TaskState, WorkerInput, WorkerOutput};
+use crate::CancellationToken;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<TaskState>,
+    /// Notified when a calculation is queued or the bridge is dropped.
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, TaskState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A karma task on two threads of its own. Both end once the bridge is
+/// dropped and the running calculation, which is cancelled, returns.
+pub struct NativeBridge {
+    input: Option<Sender<WorkerInput>>,
+    output: Receiver<WorkerOutput>,
+}
+
+impl NativeBridge {
+    pub fn spawn() -> Self {
+        let (outputs, output) = mpsc::channel();
+        Self {
+            input: Some(spawn_task(move |answer| outputs.send(answer).is_ok())),
+            output,
+        }
+    }
+
+    pub fn send(&self, input: WorkerInput) {
+        if let Some(sender) = &self.input {
+            let _ = sender.send(input);
+        }
+    }
+
+    /// Waits for the next message, or `None` once the task has stopped.
+    pub fn recv(&self) -> Option<WorkerOutput> {
+        self.output.recv().ok()
+    }
+
+    /// [`NativeBridge::recv`] giving up after `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<WorkerOutput> {
+        self.output.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for NativeBridge {
+    fn drop(&mut self) {
+        // Closing the input channel ends the receiving thread, which
+        // cancels the running calculation.
+        self.input.take();
+    }
+}
+
+/// Starts a karma task answering through `send`, which returns `false`
+/// once nobody listens any more. The task ends once the returned sender is
+/// dropped and the running calculation, which is cancelled, returns.
+pub fn spawn_task(
+    send: impl Fn(WorkerOutput) -> bool + Clone + Send + 'static,
+) -> Sender<WorkerInput> {
+    let (input, inputs) = mpsc::channel();
+    let shared = Arc::new(Shared::default());
+
+    let receiving = shared.clone();
+    let answers = send.clone();
+    thread::spawn(move || {
+        for input in inputs {
+            for answer in receiving.lock().receive(input) {
+                answers(answer);
+            }
+            receiving.changed.notify_one();
+        }
+        receiving.lock().close();
+        receiving.changed.notify_one();
+    });
+
+    thread::spawn(move || loop {
+        let (args, cars, token) = {
+            let mut state = shared.lock();
+            let args = loop {
+                if state.closed {
+                    return;
+                }
+                if let Some(args) = KarmaArgs::next_in(&mut state.queue) {
+                    break args;
+                }
+                state = shared
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+            };
+            if let Some(finished) = state.results.answer(&args) {
+                send(finished);
+                continue;
+            }
+            let cars = state.datasets.get(&args.metadata.dataset_hash).cloned();
+            let token = CancellationToken::new();
+            state.running = Some((args.metadata.request_id, token.clone()));
+            (args, cars, token)
+        };
+        let options = args.options.clone();
+        let finished = run_task(cars.as_deref(), args, Some(token), |progress| {
+            send(progress);
+        });
+        let mut state = shared.lock();
+        state.running = None;
+        state.results.remember(&options, &finished);
+        drop(state);
+        if !send(finished) {
+            return;
+        }
+    });
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker_agent::{RequestMetadata, WorkerError};
+    use crate::{dataset_hash, Car};
+
+    fn cars() -> Vec<Car> {
+        (0..9)
+            .map(|index| Car::new(format!("car-{index}"), 100_000 + 2 * index))
+            .collect()
+    }
+
+    fn calculate(cars: &[Car], request_id: u64, targets: &[u32]) -> WorkerInput {
+        WorkerInput::Calculate(KarmaArgs {
+            batch_targets: targets[1..].to_vec(),
+            ..KarmaArgs::new(RequestMetadata {
+                request_id,
+                dataset_generation: 0,
+                dataset_hash: dataset_hash(cars),
+                target: targets[0],
+                lap_count: 3,
+                player_count: 3,
+                timeout_ms: 60_000.0,
+                tolerance_percent: 1.0,
+                strategy: crate::DEFAULT_SOLVER_STRATEGY,
+                min_lap_time: None,
+                max_lap_time: None,
+            })
+        })
+    }
+
+    /// Request id and target of a finished message.
+    fn answered(output: &WorkerOutput) -> Option<(u64, u32)> {
+        let metadata = match output {
+            WorkerOutput::Registered { .. } | WorkerOutput::Progress { .. } => return None,
+            WorkerOutput::Finished(Ok(success)) => &success.metadata,
+            WorkerOutput::Finished(Err(failure)) => &failure.metadata,
+        };
+        Some((metadata.request_id, metadata.target))
+    }
+
+    fn finished(bridge: &NativeBridge) -> Result<(u64, u32), WorkerError> {
+        loop {
+            match bridge.recv_timeout(Duration::from_secs(10)) {
+                Some(WorkerOutput::Registered { .. } | WorkerOutput::Progress { .. }) => {}
+                Some(output @ WorkerOutput::Finished(Ok(_))) => {
+                    return Ok(answered(&output).unwrap())
+                }
+                Some(WorkerOutput::Finished(Err(failure))) => return Err(failure.error),
+                None => panic!("no answer"),
+            }
+        }
+    }
+
+    #[test]
+    fn calculations_and_their_batches_are_answered_in_order() {
+        let cars = cars();
+        let bridge = NativeBridge::spawn();
+        bridge.send(calculate(&cars, 1, &[300_000]));
+        assert!(matches!(
+            finished(&bridge),
+            Err(WorkerError::UnknownDataset { .. })
+        ));
+
+        bridge.send(WorkerInput::RegisterDataset { cars: cars.clone() });
+        bridge.send(calculate(&cars, 2, &[300_000, 300_010, 300_020]));
+        bridge.send(calculate(&cars, 3, &[300_000]));
+        // Native calculations run one at a time, without a pool.
+        assert!(matches!(
+            bridge.recv_timeout(Duration::from_secs(10)),
+            Some(WorkerOutput::Registered { dataset_hash: hash, threaded: false })
+                if hash == dataset_hash(&cars)
+        ));
+        match bridge.recv_timeout(Duration::from_secs(10)) {
+            Some(WorkerOutput::Progress { run: 1, total, .. }) => assert_eq!(total, 3),
+            _ => panic!("expected progress first"),
+        }
+        assert_eq!(finished(&bridge), Ok((2, 300_000)));
+        assert_eq!(finished(&bridge), Ok((2, 300_010)));
+        assert_eq!(finished(&bridge), Ok((2, 300_020)));
+        assert_eq!(finished(&bridge), Ok((3, 300_000)));
+    }
+
+    #[test]
+    fn cancels_reach_queued_and_running_calculations() {
+        let cars = cars();
+        let mut state = TaskState::default();
+        let registered = WorkerInput::RegisterDataset { cars: cars.clone() };
+        assert!(matches!(
+            state.receive(registered)[..],
+            [WorkerOutput::Registered {
+                threaded: false,
+                ..
+            }]
+        ));
+        assert!(state
+            .receive(calculate(&cars, 1, &[300_000, 300_010, 300_020]))
+            .is_empty());
+        assert!(state.receive(calculate(&cars, 2, &[300_000])).is_empty());
+        let running = KarmaArgs::next_in(&mut state.queue).unwrap();
+        assert_eq!(running.metadata.target, 300_000);
+        let token = CancellationToken::new();
+        state.running = Some((1, token.clone()));
+
+        let answers = state.receive(WorkerInput::Cancel { request_id: 2 });
+        assert_eq!(
+            answers.iter().map(answered).collect::<Vec<_>>(),
+            [Some((2, 300_000))]
+        );
+        assert!(!token.is_cancelled());
+
+        // The running target stops through its token; the rest of its batch
+        // is answered at once.
+        let answers = state.receive(WorkerInput::Cancel { request_id: 1 });
+        assert!(token.is_cancelled());
+        assert_eq!(
+            answers.iter().map(answered).collect::<Vec<_>>(),
+            [Some((1, 300_010)), Some((1, 300_020))]
+        );
+        assert!(matches!(
+            &answers[0],
+            WorkerOutput::Finished(Err(failure)) if failure.error == WorkerError::Cancelled
+        ));
+        assert!(state.queue.is_empty());
+    }
+}
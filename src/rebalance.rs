@@ -0,0 +1,450 @@
+//! Post-passes that exchange cars between finished subsets.
+//!
+//! Runs choose their subsets one after another, so the last run gets
+//! whatever the earlier ones left. A pass here revisits all subsets together
+//! and moves cars between them, or in from the rest of the pool, while every
+//! constraint of the request keeps holding.
+
+use crate::{calculate_subset_sum_u64, Car, CarIndex};
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
+
+/// Most moves one pass makes, per subset.
+const MOVES_PER_SUBSET: usize = 64;
+/// Candidate moves a swap pass evaluates between checks of its deadline.
+const EVALUATIONS_PER_CHECK: usize = 1024;
+
+/// Constraints that every move must preserve.
+pub(crate) struct Constraints<'a> {
+    /// Cars each subset may use; empty lets every subset use every car.
+    pub player_pools: &'a [Vec<bool>],
+    pub disjoint: bool,
+    pub max_shared: Option<usize>,
+    /// Every car in the pool must stay in some subset.
+    pub full_coverage: bool,
+    pub quantiles: Option<(&'a [usize], usize)>,
+}
+
+impl Constraints<'_> {
+    fn allows(&self, subset: usize, car: CarIndex) -> bool {
+        self.player_pools
+            .get(subset)
+            .is_none_or(|allowed| allowed[car])
+    }
+
+    fn spreads(&self, subset: &[CarIndex]) -> bool {
+        self.quantiles.is_none_or(|(bins, bin_count)| {
+            (0..bin_count).all(|bin| subset.iter().any(|&car| bins[car] == bin))
+        })
+    }
+}
+
+/// Replacing `outgoing` in subset `subset` with `incoming`, which moves the
+/// other way when it comes from subset `from`.
+#[derive(Clone, Copy)]
+struct Move {
+    subset: usize,
+    position: usize,
+    incoming: CarIndex,
+    from: Option<(usize, usize)>,
+}
+
+/// Repeatedly improves the subset farthest from `target` by replacing one of
+/// its cars with an unused car or swapping one with another subset, as long
+/// as that lowers the largest deviation of any subset. Returns the number of
+/// moves made.
+pub(crate) fn minimize_worst_deviation<F: FnMut() -> bool>(
+    cars: &[Car],
+    subsets: &mut [Vec<CarIndex>],
+    target: u32,
+    constraints: &Constraints<'_>,
+    mut deadline_exceeded: F,
+) -> usize {
+    let target = u64::from(target);
+    let lap = |car: CarIndex| u64::from(cars[car].lap_time);
+    let mut sums = subsets
+        .iter()
+        .map(|subset| calculate_subset_sum_u64(cars, subset))
+        .collect::<Vec<_>>();
+    let mut usage = vec![0_usize; cars.len()];
+    for &car in subsets.iter().flatten() {
+        usage[car] += 1;
+    }
+
+    let mut moves = 0;
+    while moves < subsets.len().saturating_mul(MOVES_PER_SUBSET) && !deadline_exceeded() {
+        let Some(worst) = (0..subsets.len()).max_by_key(|&index| sums[index].abs_diff(target))
+        else {
+            break;
+        };
+        let current = sums[worst].abs_diff(target);
+        if current == 0 {
+            break;
+        }
+
+        let mut holders = vec![Vec::new(); cars.len()];
+        for (index, subset) in subsets.iter().enumerate() {
+            for &car in subset {
+                holders[car].push(index);
+            }
+        }
+        let mut candidates = Vec::new();
+        for (position, &outgoing) in subsets[worst].iter().enumerate() {
+            let base = sums[worst] - lap(outgoing);
+            for incoming in 0..cars.len() {
+                let improved = (base + lap(incoming)).abs_diff(target);
+                if improved >= current || holders[incoming].contains(&worst) {
+                    continue;
+                }
+                let replace = Move {
+                    subset: worst,
+                    position,
+                    incoming,
+                    from: None,
+                };
+                // Ties favor moves that leave cars less shared.
+                candidates.push(((improved, usage[incoming]), replace));
+                for &other in &holders[incoming] {
+                    if holders[outgoing].contains(&other) {
+                        continue;
+                    }
+                    let other_position = subsets[other]
+                        .iter()
+                        .position(|&car| car == incoming)
+                        .expect("holders lists the subsets containing each car");
+                    let other_sum = sums[other] - lap(incoming) + lap(outgoing);
+                    let score = improved.max(other_sum.abs_diff(target));
+                    if score < current {
+                        candidates.push((
+                            (score, 0),
+                            Move {
+                                from: Some((other, other_position)),
+                                ..replace
+                            },
+                        ));
+                    }
+                }
+            }
+        }
+        candidates.sort_by_key(|&(score, _)| score);
+        let Some(&(_, chosen)) = candidates
+            .iter()
+            .find(|(_, candidate)| keeps_constraints(subsets, &usage, candidate, constraints))
+        else {
+            break;
+        };
+
+        let outgoing = subsets[worst][chosen.position];
+        subsets[worst][chosen.position] = chosen.incoming;
+        sums[worst] = sums[worst] - lap(outgoing) + lap(chosen.incoming);
+        match chosen.from {
+            Some((other, other_position)) => {
+                subsets[other][other_position] = outgoing;
+                sums[other] = sums[other] - lap(chosen.incoming) + lap(outgoing);
+            }
+            None => {
+                usage[outgoing] -= 1;
+                usage[chosen.incoming] += 1;
+            }
+        }
+        moves += 1;
+    }
+    moves
+}
+
+/// Exchanges cars between subsets, or replaces them with other cars of the
+/// pool, as long as a move makes neither the total deviation from `target`
+/// nor the pairwise Jaccard similarity worse and improves at least one. Every
+/// changed subset stays within `accepted`. Moves are tried in random order
+/// and the first improving one is made. Returns the number of moves made.
+pub(crate) fn improve_by_swaps<R: rand::Rng, F: FnMut() -> bool>(
+    cars: &[Car],
+    subsets: &mut [Vec<CarIndex>],
+    target: u32,
+    accepted: (u64, u64),
+    constraints: &Constraints<'_>,
+    rng: &mut R,
+    mut deadline_exceeded: F,
+) -> usize {
+    let target = u64::from(target);
+    let lap = |car: CarIndex| u64::from(cars[car].lap_time);
+    let lap_count = subsets.first().map_or(0, Vec::len);
+    // Subsets have equal sizes, so `i` shared cars are a Jaccard similarity
+    // of `i / (2k - i)`.
+    let similarity = |shared: usize| shared as f64 / (2 * lap_count - shared).max(1) as f64;
+    let deviation = |sum: u64| sum.abs_diff(target) as i64;
+    let mut sums = subsets
+        .iter()
+        .map(|subset| calculate_subset_sum_u64(cars, subset))
+        .collect::<Vec<_>>();
+    let mut usage = vec![0_usize; cars.len()];
+    for &car in subsets.iter().flatten() {
+        usage[car] += 1;
+    }
+    let mut order = (0..cars.len()).collect::<Vec<_>>();
+    let mut subset_order = (0..subsets.len()).collect::<Vec<_>>();
+
+    let mut moves = 0;
+    let mut evaluations = 0_usize;
+    'pass: while moves < subsets.len().saturating_mul(MOVES_PER_SUBSET) {
+        let mut holders = vec![Vec::new(); cars.len()];
+        for (index, subset) in subsets.iter().enumerate() {
+            for &car in subset {
+                holders[car].push(index);
+            }
+        }
+        let shared = |left: usize, right: usize| {
+            subsets[left]
+                .iter()
+                .filter(|&&car| holders[car].contains(&right))
+                .count()
+        };
+        // Change in similarity when subset `index` loses `outgoing` and gains
+        // `incoming`, ignoring its pair with `except`.
+        let similarity_change =
+            |index: usize, outgoing: CarIndex, incoming: CarIndex, except: Option<usize>| {
+                (0..subsets.len())
+                    .filter(|&other| other != index && Some(other) != except)
+                    .map(|other| {
+                        let before = shared(index, other);
+                        let after = before + usize::from(holders[incoming].contains(&other))
+                            - usize::from(holders[outgoing].contains(&other));
+                        similarity(after) - similarity(before)
+                    })
+                    .sum::<f64>()
+            };
+        let improves = |deviation_change: i64, similarity_change: f64| {
+            deviation_change <= 0
+                && similarity_change <= f64::EPSILON
+                && (deviation_change < 0 || similarity_change < -f64::EPSILON)
+        };
+
+        subset_order.shuffle(rng);
+        order.shuffle(rng);
+        for &index in &subset_order {
+            for position in 0..subsets[index].len() {
+                let outgoing = subsets[index][position];
+                let base = sums[index] - lap(outgoing);
+                for &incoming in &order {
+                    if holders[incoming].contains(&index) {
+                        continue;
+                    }
+                    evaluations += 1;
+                    if evaluations.is_multiple_of(EVALUATIONS_PER_CHECK) && deadline_exceeded() {
+                        break 'pass;
+                    }
+                    let sum = base + lap(incoming);
+                    if !(accepted.0..=accepted.1).contains(&sum) {
+                        continue;
+                    }
+                    let change = deviation(sum) - deviation(sums[index]);
+                    let replace = Move {
+                        subset: index,
+                        position,
+                        incoming,
+                        from: None,
+                    };
+                    let mut candidates = vec![(
+                        improves(change, similarity_change(index, outgoing, incoming, None)),
+                        replace,
+                    )];
+                    for &other in &holders[incoming] {
+                        if holders[outgoing].contains(&other) {
+                            continue;
+                        }
+                        let other_sum = sums[other] - lap(incoming) + lap(outgoing);
+                        if !(accepted.0..=accepted.1).contains(&other_sum) {
+                            continue;
+                        }
+                        // The pair of the two subsets shares as many cars
+                        // after the exchange as before.
+                        let other_change = deviation(other_sum) - deviation(sums[other]);
+                        let similarity = similarity_change(index, outgoing, incoming, Some(other))
+                            + similarity_change(other, incoming, outgoing, Some(index));
+                        let position = subsets[other]
+                            .iter()
+                            .position(|&car| car == incoming)
+                            .expect("holders lists the subsets containing each car");
+                        candidates.push((
+                            improves(change + other_change, similarity),
+                            Move {
+                                from: Some((other, position)),
+                                ..replace
+                            },
+                        ));
+                    }
+                    let Some(&(_, chosen)) = candidates.iter().find(|(improves, candidate)| {
+                        *improves && keeps_constraints(subsets, &usage, candidate, constraints)
+                    }) else {
+                        continue;
+                    };
+
+                    subsets[index][position] = incoming;
+                    sums[index] = sum;
+                    match chosen.from {
+                        Some((other, other_position)) => {
+                            subsets[other][other_position] = outgoing;
+                            sums[other] = sums[other] - lap(incoming) + lap(outgoing);
+                        }
+                        None => {
+                            usage[outgoing] -= 1;
+                            usage[incoming] += 1;
+                        }
+                    }
+                    moves += 1;
+                    continue 'pass;
+                }
+            }
+        }
+        break;
+    }
+    moves
+}
+
+fn keeps_constraints(
+    subsets: &[Vec<CarIndex>],
+    usage: &[usize],
+    candidate: &Move,
+    constraints: &Constraints<'_>,
+) -> bool {
+    let outgoing = subsets[candidate.subset][candidate.position];
+    if !constraints.allows(candidate.subset, candidate.incoming) {
+        return false;
+    }
+    if candidate.from.is_none() {
+        if constraints.disjoint && usage[candidate.incoming] > 0 {
+            return false;
+        }
+        if constraints.full_coverage && usage[outgoing] < 2 {
+            return false;
+        }
+    }
+
+    let mut changed = vec![(candidate.subset, subsets[candidate.subset].clone())];
+    changed[0].1[candidate.position] = candidate.incoming;
+    if let Some((other, other_position)) = candidate.from {
+        if !constraints.allows(other, outgoing) {
+            return false;
+        }
+        let mut subset = subsets[other].clone();
+        subset[other_position] = outgoing;
+        changed.push((other, subset));
+    }
+    if !changed
+        .iter()
+        .all(|(_, subset)| constraints.spreads(subset))
+    {
+        return false;
+    }
+
+    let Some(max_shared) = constraints.max_shared else {
+        return true;
+    };
+    changed.iter().all(|(index, subset)| {
+        let members = subset.iter().copied().collect::<HashSet<_>>();
+        (0..subsets.len())
+            .filter(|other| other != index)
+            .all(|other| {
+                let other_subset = changed
+                    .iter()
+                    .find(|(changed_index, _)| *changed_index == other)
+                    .map_or(&subsets[other], |(_, subset)| subset);
+                other_subset
+                    .iter()
+                    .filter(|car| members.contains(car))
+                    .count()
+                    <= max_shared
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cars(lap_times: &[u32]) -> Vec<Car> {
+        lap_times
+            .iter()
+            .enumerate()
+            .map(|(index, &lap_time)| Car::new(format!("car-{index}"), lap_time))
+            .collect()
+    }
+
+    const UNCONSTRAINED: Constraints<'static> = Constraints {
+        player_pools: &[],
+        disjoint: false,
+        max_shared: None,
+        full_coverage: false,
+        quantiles: None,
+    };
+
+    #[test]
+    fn swaps_and_replacements_lower_the_worst_deviation() {
+        // 0+1 = 1_980 and 2+3 = 2_020 both miss 2_000 by 20; trading one
+        // car between them makes both exact without sharing a car.
+        let pool = cars(&[1_000, 980, 1_000, 1_020]);
+        let mut subsets = vec![vec![0, 1], vec![2, 3]];
+        let moves = minimize_worst_deviation(&pool, &mut subsets, 2_000, &UNCONSTRAINED, || false);
+        assert!(moves >= 1);
+        for subset in &subsets {
+            assert_eq!(calculate_subset_sum_u64(&pool, subset), 2_000);
+        }
+        let mut used = subsets.concat();
+        used.sort_unstable();
+        assert_eq!(used, [0, 1, 2, 3]);
+
+        // A disjoint request cannot swap in a car another subset holds.
+        let pool = cars(&[1_000, 980, 1_030, 1_020]);
+        let mut subsets = vec![vec![0, 1], vec![2, 3]];
+        let disjoint = Constraints {
+            disjoint: true,
+            ..UNCONSTRAINED
+        };
+        minimize_worst_deviation(&pool, &mut subsets, 2_000, &disjoint, || false);
+        let mut used = subsets.concat();
+        used.sort_unstable();
+        assert_eq!(used, [0, 1, 2, 3]);
+        let worst = subsets
+            .iter()
+            .map(|subset| calculate_subset_sum_u64(&pool, subset).abs_diff(2_000))
+            .max();
+        assert_eq!(worst, Some(20));
+    }
+
+    #[test]
+    fn swap_passes_improve_accuracy_or_overlap_without_worsening_either() {
+        let pool = cars(&[1_000, 980, 1_000, 1_020]);
+        let mut subsets = vec![vec![0, 1], vec![2, 3]];
+        let moves = improve_by_swaps(
+            &pool,
+            &mut subsets,
+            2_000,
+            (1_950, 2_050),
+            &UNCONSTRAINED,
+            &mut rand::rng(),
+            || false,
+        );
+        assert!(moves >= 1);
+        for subset in &subsets {
+            assert_eq!(calculate_subset_sum_u64(&pool, subset), 2_000);
+        }
+
+        // Equal lap times leave accuracy alone, so only the shared car moves
+        // out to the unused one.
+        let pool = cars(&[1_000; 4]);
+        let mut subsets = vec![vec![0, 1], vec![0, 2]];
+        let moves = improve_by_swaps(
+            &pool,
+            &mut subsets,
+            2_000,
+            (2_000, 2_000),
+            &UNCONSTRAINED,
+            &mut rand::rng(),
+            || false,
+        );
+        assert_eq!(moves, 1);
+        let mut used = subsets.concat();
+        used.sort_unstable();
+        assert_eq!(used, [0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,118 @@
+use random_karma::{parse_lap_time, Car};
+
+/// Lap time of a row added to an empty pool.
+const NEW_CAR_LAP_TIME_MS: u32 = 60_000;
+
+/// A change made in the car pool editor.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CarEdit {
+    /// Renames the car at an index; ids stay unique and non-empty.
+    SetId(usize, String),
+    /// Parses a lap time typed for the car at an index.
+    SetLapTime(usize, String),
+    SetEnabled(usize, bool),
+    Remove(usize),
+    /// Appends a car named after the next free `car-N`, copying the lap
+    /// time of the last row.
+    Add,
+    SetAllEnabled(bool),
+    RemoveDisabled,
+    SortByLapTime,
+}
+
+/// Applies `edit` to `cars`, leaving them unchanged when it is rejected.
+pub fn apply_edit(cars: &mut Vec<Car>, edit: CarEdit) -> Result<(), String> {
+    match edit {
+        CarEdit::SetId(index, id) => {
+            let id = id.trim();
+            if id.is_empty() {
+                return Err("Car IDs cannot be empty".to_string());
+            }
+            if cars
+                .iter()
+                .enumerate()
+                .any(|(other, car)| other != index && car.id == id)
+            {
+                return Err(format!("Another car already has the ID '{id}'"));
+            }
+            car_at(cars, index)?.id = id.to_string();
+        }
+        CarEdit::SetLapTime(index, text) => {
+            let lap_time = parse_lap_time(text.trim())?;
+            car_at(cars, index)?.lap_time = lap_time;
+        }
+        CarEdit::SetEnabled(index, enabled) => car_at(cars, index)?.enabled = enabled,
+        CarEdit::Remove(index) => {
+            car_at(cars, index)?;
+            cars.remove(index);
+        }
+        CarEdit::Add => {
+            let id = (cars.len() + 1..)
+                .map(|number| format!("car-{number}"))
+                .find(|id| cars.iter().all(|car| &car.id != id))
+                .expect("some id is free");
+            let lap_time = cars.last().map_or(NEW_CAR_LAP_TIME_MS, |car| car.lap_time);
+            cars.push(Car::new(id, lap_time));
+        }
+        CarEdit::SetAllEnabled(enabled) => {
+            for car in cars.iter_mut() {
+                car.enabled = enabled;
+            }
+        }
+        CarEdit::RemoveDisabled => cars.retain(|car| car.enabled),
+        CarEdit::SortByLapTime => cars.sort_by_key(|car| car.lap_time),
+    }
+    Ok(())
+}
+
+fn car_at(cars: &mut [Car], index: usize) -> Result<&mut Car, String> {
+    cars.get_mut(index)
+        .ok_or_else(|| format!("No car at row {}", index + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cars() -> Vec<Car> {
+        ["b", "a"]
+            .iter()
+            .zip([61_000, 60_000])
+            .map(|(&id, lap_time)| Car::new(id, lap_time))
+            .collect()
+    }
+
+    #[test]
+    fn edits_keep_ids_unique_and_lap_times_valid() {
+        let mut cars = cars();
+        assert!(apply_edit(&mut cars, CarEdit::SetId(0, " a ".to_string())).is_err());
+        assert!(apply_edit(&mut cars, CarEdit::SetId(0, "  ".to_string())).is_err());
+        apply_edit(&mut cars, CarEdit::SetId(0, " c ".to_string())).unwrap();
+        assert_eq!(cars[0].id, "c");
+
+        assert!(apply_edit(&mut cars, CarEdit::SetLapTime(1, "fast".to_string())).is_err());
+        apply_edit(&mut cars, CarEdit::SetLapTime(1, "1:02.500".to_string())).unwrap();
+        assert_eq!(cars[1].lap_time, 62_500);
+        assert!(apply_edit(&mut cars, CarEdit::SetEnabled(5, false)).is_err());
+        assert_eq!(cars.len(), 2);
+    }
+
+    #[test]
+    fn rows_are_added_removed_and_edited_in_bulk() {
+        let mut cars = cars();
+        apply_edit(&mut cars, CarEdit::Add).unwrap();
+        assert_eq!((cars[2].id.as_str(), cars[2].lap_time), ("car-3", 60_000));
+        apply_edit(&mut cars, CarEdit::SortByLapTime).unwrap();
+        let ids = |cars: &[Car]| cars.iter().map(|car| car.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&cars), ["a", "car-3", "b"]);
+
+        apply_edit(&mut cars, CarEdit::SetAllEnabled(false)).unwrap();
+        apply_edit(&mut cars, CarEdit::SetEnabled(2, true)).unwrap();
+        apply_edit(&mut cars, CarEdit::RemoveDisabled).unwrap();
+        assert_eq!(ids(&cars), ["b"]);
+        apply_edit(&mut cars, CarEdit::Remove(0)).unwrap();
+        assert!(cars.is_empty());
+        apply_edit(&mut cars, CarEdit::Add).unwrap();
+        assert_eq!((cars[0].id.as_str(), cars[0].lap_time), ("car-1", 60_000));
+    }
+}
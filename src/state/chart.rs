@@ -0,0 +1,435 @@
+//! Points and visible range of the similarity chart, apart from drawing.
+//!
+//! [`ChartState`] keeps each series sorted by target and maps between
+//! targets and the pixels of a [`PlotArea`], so zooming, panning and hit
+//! testing are plain arithmetic.
+
+/// Narrowest view, as a fraction of the full range.
+const MIN_VIEW_FRACTION: f64 = 0.01;
+/// Most labelled ticks on the target axis.
+const MAX_TARGET_TICKS: usize = 7;
+/// Tick steps of the target axis, in milliseconds.
+const TARGET_TICK_STEPS: [u32; 13] = [
+    1_000, 2_000, 5_000, 10_000, 15_000, 30_000, 60_000, 120_000, 300_000, 600_000, 900_000,
+    1_800_000, 3_600_000,
+];
+/// Most curves pinned at once.
+pub const MAX_PINNED_CURVES: usize = 4;
+/// Labelled values of the similarity axis, in percent.
+pub const VALUE_TICKS: [f64; 5] = [0.0, 25.0, 50.0, 75.0, 100.0];
+
+/// Room above the plot for the section's title and the legend.
+const PLOT_TOP: f64 = 56.0;
+/// Room left of the plot for the similarity labels.
+const PLOT_LEFT: f64 = 44.0;
+const PLOT_RIGHT: f64 = 10.0;
+/// Room below the plot for the target labels.
+const PLOT_BOTTOM: f64 = 28.0;
+
+/// One target of a series, with its value in percent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChartPoint {
+    pub target: u32,
+    pub value: f64,
+}
+
+/// A target that could not be calculated, with the lines of its tooltip.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FailedTarget {
+    pub target: u32,
+    pub reason: Vec<String>,
+}
+
+/// A similarity curve kept for comparison after the lap or player count
+/// changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PinnedCurve {
+    pub lap_count: u32,
+    pub player_count: u32,
+    pub points: Vec<ChartPoint>,
+}
+
+impl PinnedCurve {
+    /// The similarity at `target`, interpolated between its nearest points;
+    /// `None` beyond the curve's ends.
+    pub fn value_at(&self, target: u32) -> Option<f64> {
+        let index = self.points.partition_point(|point| point.target < target);
+        let after = self.points.get(index)?;
+        if after.target == target {
+            return Some(after.value);
+        }
+        let before = self.points.get(index.checked_sub(1)?)?;
+        let share = f64::from(target - before.target) / f64::from(after.target - before.target);
+        Some(before.value + (after.value - before.value) * share)
+    }
+}
+
+/// Where the plot sits on the canvas, in CSS pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlotArea {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl PlotArea {
+    /// The plot of a canvas `width` by `height` CSS pixels.
+    pub fn for_canvas(width: f64, height: f64) -> Self {
+        Self {
+            left: PLOT_LEFT,
+            top: PLOT_TOP,
+            width: (width - PLOT_LEFT - PLOT_RIGHT).max(0.0),
+            height: (height - PLOT_TOP - PLOT_BOTTOM).max(0.0),
+        }
+    }
+
+    pub fn right(&self) -> f64 {
+        self.left + self.width
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.top + self.height
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        (self.left..=self.right()).contains(&x) && (self.top..=self.bottom()).contains(&y)
+    }
+
+    /// Vertical position of a value from 0 to 100 percent.
+    pub fn y_px(&self, value: f64) -> f64 {
+        self.top + self.height * (1.0 - value / 100.0)
+    }
+}
+
+/// The target nearest a position on the plot and what is shown there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChartHit {
+    pub target: u32,
+    pub similarity: Option<f64>,
+    pub weighted: Option<f64>,
+    pub failure: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChartState {
+    /// Targets the slider reaches.
+    pub full: (u32, u32),
+    /// Targets shown, within `full`.
+    pub view: (f64, f64),
+    pub lap_count: u32,
+    pub player_count: u32,
+    pub similarity: Vec<ChartPoint>,
+    pub weighted: Vec<ChartPoint>,
+    pub failed: Vec<FailedTarget>,
+    /// Curves pinned for comparison, kept when the chart is reset.
+    pub pinned: Vec<PinnedCurve>,
+}
+
+impl ChartState {
+    pub fn new(min: u32, max: u32, lap_count: u32, player_count: u32) -> Self {
+        Self {
+            full: (min, max),
+            view: (f64::from(min), f64::from(max)),
+            lap_count,
+            player_count,
+            similarity: Vec::new(),
+            weighted: Vec::new(),
+            failed: Vec::new(),
+            pinned: Vec::new(),
+        }
+    }
+
+    /// Starts over for another range or lap and player count, dropping the
+    /// points but not the pinned curves. The view survives when they are
+    /// unchanged; returns whether it was reset instead.
+    pub fn reset(&mut self, min: u32, max: u32, lap_count: u32, player_count: u32) -> bool {
+        let view = self.view;
+        let same = self.full == (min, max) && self.shows(lap_count, player_count);
+        let pinned = std::mem::take(&mut self.pinned);
+        *self = Self::new(min, max, lap_count, player_count);
+        self.pinned = pinned;
+        if same {
+            self.view = view;
+        }
+        !same
+    }
+
+    /// Whether points for `lap_count` and `player_count` belong here.
+    pub fn shows(&self, lap_count: u32, player_count: u32) -> bool {
+        (self.lap_count, self.player_count) == (lap_count, player_count)
+    }
+
+    pub fn add_similarity(&mut self, target: u32, similarity: f64, weighted: f64) {
+        upsert(&mut self.similarity, target, similarity);
+        upsert(&mut self.weighted, target, weighted);
+    }
+
+    pub fn add_failed(&mut self, target: u32, reason: Vec<String>) {
+        match self
+            .failed
+            .iter_mut()
+            .find(|failed| failed.target == target)
+        {
+            Some(failed) => failed.reason = reason,
+            None => self.failed.push(FailedTarget { target, reason }),
+        }
+    }
+
+    /// Pins a copy of the similarity curve shown, replacing an earlier pin
+    /// of the same lap and player count. Returns `false`, pinning nothing,
+    /// when [`MAX_PINNED_CURVES`] others are pinned.
+    pub fn pin(&mut self) -> bool {
+        let curve = PinnedCurve {
+            lap_count: self.lap_count,
+            player_count: self.player_count,
+            points: self.similarity.clone(),
+        };
+        let same = |pinned: &PinnedCurve| {
+            (pinned.lap_count, pinned.player_count) == (curve.lap_count, curve.player_count)
+        };
+        match self.pinned.iter().position(same) {
+            Some(index) => self.pinned[index] = curve,
+            None if self.pinned.len() < MAX_PINNED_CURVES => self.pinned.push(curve),
+            None => return false,
+        }
+        true
+    }
+
+    pub fn unpin(&mut self, lap_count: u32, player_count: u32) {
+        self.pinned
+            .retain(|pinned| (pinned.lap_count, pinned.player_count) != (lap_count, player_count));
+    }
+
+    /// Shows `min..max`, widened about its middle to the narrowest view
+    /// and moved inside the full range.
+    pub fn set_view(&mut self, min: f64, max: f64) {
+        let (low, high) = (f64::from(self.full.0), f64::from(self.full.1));
+        let full_span = high - low;
+        let span = (max - min)
+            .max(full_span * MIN_VIEW_FRACTION)
+            .min(full_span);
+        let start = ((min + max - span) / 2.0).min(high - span).max(low);
+        self.view = (start, start + span);
+    }
+
+    /// Scales the view by `factor` around the target `anchor`; factors
+    /// below one zoom in.
+    pub fn zoom_at(&mut self, anchor: f64, factor: f64) {
+        let (min, max) = self.view;
+        self.set_view(
+            anchor - (anchor - min) * factor,
+            anchor + (max - anchor) * factor,
+        );
+    }
+
+    pub fn x_px(&self, plot: &PlotArea, target: f64) -> f64 {
+        let (min, max) = self.view;
+        plot.left + plot.width * (target - min) / (max - min).max(1.0)
+    }
+
+    pub fn target_at(&self, plot: &PlotArea, x: f64) -> f64 {
+        let (min, max) = self.view;
+        min + (x - plot.left) / plot.width.max(1.0) * (max - min)
+    }
+
+    /// Labelled targets of the view, on a round step.
+    pub fn target_ticks(&self) -> Vec<u32> {
+        let (min, max) = self.view;
+        let span = max - min;
+        let step = TARGET_TICK_STEPS
+            .into_iter()
+            .find(|&step| span / f64::from(step) < MAX_TARGET_TICKS as f64)
+            .unwrap_or(TARGET_TICK_STEPS[TARGET_TICK_STEPS.len() - 1]);
+        let first = (min / f64::from(step)).ceil() as u32;
+        (first..)
+            .map(|tick| tick * step)
+            .take_while(|&tick| f64::from(tick) <= max)
+            .collect()
+    }
+
+    /// The target shown nearest the horizontal position `x`, if any is in
+    /// view.
+    pub fn hit(&self, plot: &PlotArea, x: f64) -> Option<ChartHit> {
+        let (min, max) = self.view;
+        let target = self
+            .similarity
+            .iter()
+            .map(|point| point.target)
+            .chain(self.failed.iter().map(|failed| failed.target))
+            .filter(|&target| (min..=max).contains(&f64::from(target)))
+            .min_by(|&a, &b| {
+                let distance = |target: u32| (self.x_px(plot, f64::from(target)) - x).abs();
+                distance(a).total_cmp(&distance(b))
+            })?;
+        let value = |series: &[ChartPoint]| {
+            series
+                .iter()
+                .find(|point| point.target == target)
+                .map(|point| point.value)
+        };
+        Some(ChartHit {
+            target,
+            similarity: value(&self.similarity),
+            weighted: value(&self.weighted),
+            failure: self
+                .failed
+                .iter()
+                .find(|failed| failed.target == target)
+                .map(|failed| failed.reason.clone()),
+        })
+    }
+}
+
+/// Names a pinned curve, such as `3 laps · 4 players`.
+pub fn pin_label(lap_count: u32, player_count: u32) -> String {
+    let plural = |count: u32| if count == 1 { "" } else { "s" };
+    format!(
+        "{lap_count} lap{} · {player_count} player{}",
+        plural(lap_count),
+        plural(player_count)
+    )
+}
+
+/// A target axis label such as `46:40`.
+pub fn target_tick_label(target: u32) -> String {
+    let seconds = target / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// A tooltip title such as `46m 40s`.
+pub fn target_title(target: u32) -> String {
+    let seconds = target / 1000;
+    format!("{}m {}s", seconds / 60, seconds % 60)
+}
+
+fn upsert(series: &mut Vec<ChartPoint>, target: u32, value: f64) {
+    match series.binary_search_by_key(&target, |point| point.target) {
+        Ok(index) => series[index].value = value,
+        Err(index) => series.insert(index, ChartPoint { target, value }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLOT: PlotArea = PlotArea {
+        left: 40.0,
+        top: 30.0,
+        width: 1_000.0,
+        height: 200.0,
+    };
+
+    #[test]
+    fn points_stay_sorted_and_views_survive_the_same_range() {
+        let mut chart = ChartState::new(100_000, 200_000, 3, 4);
+        chart.add_similarity(150_000, 40.0, 30.0);
+        chart.add_similarity(120_000, 10.0, 5.0);
+        chart.add_similarity(150_000, 45.0, 35.0);
+        let targets = chart.similarity.iter().map(|point| point.target);
+        assert_eq!(targets.collect::<Vec<_>>(), [120_000, 150_000]);
+        assert_eq!(chart.weighted[1].value, 35.0);
+
+        chart.add_failed(180_000, vec!["Unreachable".to_string()]);
+        chart.add_failed(180_000, vec!["Timed out".to_string()]);
+        assert_eq!(chart.failed.len(), 1);
+
+        chart.zoom_at(150_000.0, 0.5);
+        assert_eq!(chart.view, (125_000.0, 175_000.0));
+        assert!(!chart.reset(100_000, 200_000, 3, 4));
+        assert!(chart.similarity.is_empty() && chart.failed.is_empty());
+        assert_eq!(chart.view, (125_000.0, 175_000.0));
+        assert!(chart.reset(100_000, 200_000, 3, 5));
+        assert_eq!(chart.view, (100_000.0, 200_000.0));
+    }
+
+    #[test]
+    fn pinned_curves_survive_resets_and_interpolate() {
+        let mut chart = ChartState::new(100_000, 200_000, 3, 4);
+        chart.add_similarity(120_000, 10.0, 5.0);
+        chart.add_similarity(140_000, 30.0, 25.0);
+        assert!(chart.pin());
+        chart.reset(150_000, 250_000, 5, 4);
+        assert_eq!(chart.pinned.len(), 1);
+        assert!(chart.similarity.is_empty());
+
+        let pinned = &chart.pinned[0];
+        assert_eq!(
+            pin_label(pinned.lap_count, pinned.player_count),
+            "3 laps · 4 players"
+        );
+        assert_eq!(pinned.value_at(130_000), Some(20.0));
+        assert_eq!(pinned.value_at(140_000), Some(30.0));
+        assert_eq!(pinned.value_at(110_000), None);
+        assert_eq!(pinned.value_at(150_000), None);
+
+        chart.add_similarity(200_000, 50.0, 45.0);
+        assert!(chart.pin());
+        assert!(chart.pin());
+        assert_eq!(chart.pinned.len(), 2);
+        for players in 1..=3 {
+            chart.reset(150_000, 250_000, 1, players);
+            assert_eq!(chart.pin(), players < 3);
+        }
+        assert_eq!(pin_label(1, 1), "1 lap · 1 player");
+        chart.unpin(3, 4);
+        assert_eq!(chart.pinned.len(), 3);
+        assert!(chart.pin());
+    }
+
+    #[test]
+    fn views_stay_inside_the_range_and_above_the_narrowest() {
+        let mut chart = ChartState::new(100_000, 200_000, 3, 4);
+        chart.set_view(50_000.0, 150_000.0);
+        assert_eq!(chart.view, (100_000.0, 200_000.0));
+        chart.zoom_at(150_000.0, 0.0001);
+        assert_eq!(chart.view, (149_500.0, 150_500.0));
+        chart.set_view(249_500.0, 250_500.0);
+        assert_eq!(chart.view, (199_000.0, 200_000.0));
+        chart.zoom_at(199_500.0, 1_000.0);
+        assert_eq!(chart.view, (100_000.0, 200_000.0));
+    }
+
+    #[test]
+    fn pixels_ticks_and_hits_follow_the_view() {
+        let mut chart = ChartState::new(100_000, 200_000, 3, 4);
+        assert_eq!(chart.x_px(&PLOT, 150_000.0), 540.0);
+        assert_eq!(chart.target_at(&PLOT, 540.0), 150_000.0);
+        assert_eq!(PLOT.y_px(25.0), 180.0);
+        assert_eq!(
+            PlotArea::for_canvas(1_054.0, 284.0),
+            PlotArea {
+                left: 44.0,
+                top: 56.0,
+                width: 1_000.0,
+                height: 200.0
+            }
+        );
+        assert_eq!(target_tick_label(2_800_500), "46:40");
+        assert_eq!(target_title(65_000), "1m 5s");
+        assert_eq!(
+            chart.target_ticks(),
+            [105_000, 120_000, 135_000, 150_000, 165_000, 180_000, 195_000]
+        );
+
+        chart.add_similarity(120_000, 10.0, 5.0);
+        chart.add_similarity(150_000, 40.0, 30.0);
+        chart.add_failed(190_000, vec!["Unreachable".to_string()]);
+        let hit = chart.hit(&PLOT, 520.0).unwrap();
+        assert_eq!(
+            (hit.target, hit.similarity, hit.weighted),
+            (150_000, Some(40.0), Some(30.0))
+        );
+        let failed = chart.hit(&PLOT, 1_000.0).unwrap();
+        assert_eq!(failed.failure, Some(vec!["Unreachable".to_string()]));
+        assert_eq!(failed.similarity, None);
+
+        chart.set_view(160_000.0, 180_000.0);
+        assert_eq!(
+            chart.target_ticks(),
+            [160_000, 165_000, 170_000, 175_000, 180_000]
+        );
+        assert_eq!(chart.hit(&PLOT, 40.0), None);
+    }
+}
@@ -0,0 +1,122 @@
+//! Earlier parameter states, for undo and redo.
+//!
+//! Changes closer together than [`PARAMETER_HISTORY_COALESCE_MS`] replace
+//! one another, so dragging the target slider is undone in one step.
+
+use crate::config::{PARAMETER_HISTORY_COALESCE_MS, PARAMETER_HISTORY_LIMIT};
+use std::collections::VecDeque;
+
+/// The parameters undo and redo restore.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Parameters {
+    pub target: u32,
+    pub lap_count: usize,
+    pub player_count: usize,
+    pub tolerance_percent: f64,
+    pub timeout_seconds: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ParameterHistory {
+    entries: VecDeque<Parameters>,
+    /// Index of the entry shown.
+    current: usize,
+    /// When the entry shown was recorded, unless it was restored since.
+    recorded_at: Option<f64>,
+}
+
+impl ParameterHistory {
+    /// Records `parameters` as changed at `now` milliseconds, dropping the
+    /// states undone before. A change soon after the last one replaces it,
+    /// and the oldest entries go beyond [`PARAMETER_HISTORY_LIMIT`].
+    pub fn record(&mut self, parameters: Parameters, now: f64) {
+        if self.entries.get(self.current) == Some(&parameters) {
+            return;
+        }
+        self.entries.truncate(self.current + 1);
+        let recent = self
+            .recorded_at
+            .is_some_and(|recorded_at| now - recorded_at < PARAMETER_HISTORY_COALESCE_MS);
+        match self.entries.back_mut() {
+            Some(last) if recent => *last = parameters,
+            _ => self.entries.push_back(parameters),
+        }
+        if self.entries.len() > PARAMETER_HISTORY_LIMIT {
+            self.entries.pop_front();
+        }
+        self.current = self.entries.len() - 1;
+        self.recorded_at = Some(now);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.current > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.current + 1 < self.entries.len()
+    }
+
+    /// Steps back, returning the parameters to restore.
+    pub fn undo(&mut self) -> Option<Parameters> {
+        self.can_undo().then(|| self.restore(self.current - 1))
+    }
+
+    /// Steps forward again after [`ParameterHistory::undo`].
+    pub fn redo(&mut self) -> Option<Parameters> {
+        self.can_redo().then(|| self.restore(self.current + 1))
+    }
+
+    fn restore(&mut self, index: usize) -> Parameters {
+        self.current = index;
+        self.recorded_at = None;
+        self.entries[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(target: u32) -> Parameters {
+        Parameters {
+            target,
+            lap_count: 25,
+            player_count: 32,
+            tolerance_percent: 0.5,
+            timeout_seconds: 5.0,
+        }
+    }
+
+    #[test]
+    fn quick_changes_coalesce_and_undo_restores_the_one_before() {
+        let mut history = ParameterHistory::default();
+        history.record(at(1), 0.0);
+        history.record(at(2), 10_000.0);
+        history.record(at(3), 10_100.0);
+        history.record(at(4), 10_200.0);
+        assert_eq!(history.undo(), Some(at(1)));
+        assert_eq!(history.undo(), None);
+        history.record(at(1), 10_300.0);
+        assert_eq!(history.redo(), Some(at(4)));
+        assert!(!history.can_redo());
+
+        assert_eq!(history.undo(), Some(at(1)));
+        history.record(at(5), 10_400.0);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(), Some(at(1)));
+        assert_eq!(history.redo(), Some(at(5)));
+    }
+
+    #[test]
+    fn history_keeps_the_latest_entries() {
+        let mut history = ParameterHistory::default();
+        for target in 0..PARAMETER_HISTORY_LIMIT as u32 + 10 {
+            history.record(at(target), f64::from(target) * 1_000_000.0);
+        }
+        let mut oldest = None;
+        while let Some(parameters) = history.undo() {
+            oldest = Some(parameters);
+        }
+        assert_eq!(oldest, Some(at(10)));
+    }
+}
@@ -0,0 +1,147 @@
+//! Calculation parameters carried in the page's query string, so a
+//! configuration can be shared as a link.
+
+use crate::config::{
+    MAX_PLAYER_COUNT, MAX_TIMEOUT_SEC, MAX_TOLERANCE_PCT, MIN_TIMEOUT_SEC, MIN_TOLERANCE_PCT,
+};
+
+/// Parameters read from or written to a link. Missing, unreadable and
+/// out-of-range values are `None` and keep the page's defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkState {
+    pub target: Option<u32>,
+    pub lap_count: Option<usize>,
+    pub player_count: Option<usize>,
+    pub tolerance_percent: Option<f64>,
+    pub timeout_seconds: Option<f64>,
+    pub seed: Option<u64>,
+}
+
+impl LinkState {
+    /// Reads a query string such as `location.search`, with or without its
+    /// leading `?`. Unknown keys are ignored.
+    pub fn from_query(query: &str) -> Self {
+        let mut link = Self::default();
+        let query = query.strip_prefix('?').unwrap_or(query);
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match key {
+                "target" => link.target = value.parse().ok(),
+                "laps" => link.lap_count = value.parse().ok().filter(|&laps| laps > 0),
+                "players" => {
+                    link.player_count = value
+                        .parse()
+                        .ok()
+                        .filter(|&players| players <= MAX_PLAYER_COUNT)
+                }
+                "tolerance" => {
+                    link.tolerance_percent = value.parse().ok().filter(|tolerance| {
+                        (MIN_TOLERANCE_PCT..=MAX_TOLERANCE_PCT).contains(tolerance)
+                    })
+                }
+                "timeout" => {
+                    link.timeout_seconds = value
+                        .parse()
+                        .ok()
+                        .filter(|timeout| (MIN_TIMEOUT_SEC..=MAX_TIMEOUT_SEC).contains(timeout))
+                }
+                "seed" => link.seed = value.parse().ok(),
+                _ => {}
+            }
+        }
+        link
+    }
+
+    /// The query string for these parameters, starting with `?`, or empty
+    /// when none are set.
+    pub fn to_query(&self) -> String {
+        let pairs = [
+            ("target", self.target.map(|target| target.to_string())),
+            ("laps", self.lap_count.map(|laps| laps.to_string())),
+            (
+                "players",
+                self.player_count.map(|players| players.to_string()),
+            ),
+            (
+                "tolerance",
+                self.tolerance_percent
+                    .map(|tolerance| tolerance.to_string()),
+            ),
+            (
+                "timeout",
+                self.timeout_seconds.map(|timeout| timeout.to_string()),
+            ),
+            ("seed", self.seed.map(|seed| seed.to_string())),
+        ];
+        let query = pairs
+            .iter()
+            .filter_map(|(key, value)| value.as_ref().map(|value| format!("{key}={value}")))
+            .collect::<Vec<_>>()
+            .join("&");
+        if query.is_empty() {
+            query
+        } else {
+            format!("?{query}")
+        }
+    }
+
+    /// The parameters in the current page's address.
+    pub fn from_location() -> Self {
+        web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .map(|query| Self::from_query(&query))
+            .unwrap_or_default()
+    }
+
+    /// Replaces the current page's address with one carrying these
+    /// parameters, without adding a history entry.
+    pub fn replace_location(&self) {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let Ok(path) = window.location().pathname() else {
+            return;
+        };
+        if let Ok(history) = window.history() {
+            let url = format!("{path}{}", self.to_query());
+            let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&url));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_round_trip_and_ignore_bad_values() {
+        let link = LinkState {
+            target: Some(2_800_000),
+            lap_count: Some(25),
+            player_count: Some(32),
+            tolerance_percent: Some(0.5),
+            timeout_seconds: Some(7.5),
+            seed: Some(42),
+        };
+        let query = link.to_query();
+        assert_eq!(
+            query,
+            "?target=2800000&laps=25&players=32&tolerance=0.5&timeout=7.5&seed=42"
+        );
+        assert_eq!(LinkState::from_query(&query), link);
+
+        let unseeded = LinkState {
+            seed: None,
+            ..link.clone()
+        };
+        assert!(!unseeded.to_query().contains("seed"));
+        assert_eq!(LinkState::default().to_query(), "");
+
+        let bad =
+            LinkState::from_query("laps=0&players=999&tolerance=9&timeout=0.5&target=soon&x=1");
+        assert_eq!(bad, LinkState::default());
+        assert_eq!(
+            LinkState::from_query("seed=7&players=4").player_count,
+            Some(4)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use random_karma::{worker_agent::RequestMetadata, SolverStrategy};
+use random_karma::worker_agent::RequestMetadata;
 
 /// Monotonic identities used to correlate worker responses and invalidate datasets.
 #[derive(Debug, Default)]
@@ -9,25 +9,14 @@ pub struct RequestState {
 }
 
 impl RequestState {
-    pub fn begin(
-        &mut self,
-        target: u32,
-        lap_count: usize,
-        player_count: usize,
-        timeout_ms: f64,
-        tolerance_percent: f64,
-        strategy: SolverStrategy,
-    ) -> RequestMetadata {
+    /// Starts a request for `parameters`, assigning it a fresh request id and
+    /// the current dataset generation.
+    pub fn begin(&mut self, parameters: RequestMetadata) -> RequestMetadata {
         self.next_request_id = self.next_request_id.wrapping_add(1);
         let metadata = RequestMetadata {
             request_id: self.next_request_id,
             dataset_generation: self.dataset_generation,
-            target,
-            lap_count,
-            player_count,
-            timeout_ms,
-            tolerance_percent,
-            strategy,
+            ..parameters
         };
         self.active = Some(metadata.clone());
         metadata
@@ -61,9 +50,22 @@ impl RequestState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use random_karma::SolverStrategy;
 
     fn begin(state: &mut RequestState, target: u32) -> RequestMetadata {
-        state.begin(target, 2, 3, 1_000.0, 0.5, SolverStrategy::Bounded)
+        state.begin(RequestMetadata {
+            request_id: 0,
+            dataset_generation: 0,
+            dataset_hash: 0,
+            target,
+            lap_count: 2,
+            player_count: 3,
+            timeout_ms: 1_000.0,
+            tolerance_percent: 0.5,
+            strategy: SolverStrategy::Bounded,
+            min_lap_time: None,
+            max_lap_time: None,
+        })
     }
 
     #[test]
@@ -0,0 +1,123 @@
+//! Names of the players the subsets of a calculation go to.
+
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// Reads a pasted roster with one name per line, trimmed, skipping blank
+/// lines.
+pub fn parse_roster(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Label of the subset at `index`: the name of its player in `names`, or
+/// its number for subsets beyond them or named `""`.
+pub fn subset_label(names: &[String], index: usize) -> String {
+    names
+        .get(index)
+        .filter(|name| !name.is_empty())
+        .cloned()
+        .unwrap_or_else(|| (index + 1).to_string())
+}
+
+/// A random assignment of subsets to the players of a roster, revealed one
+/// player at a time in roster order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Draw {
+    pub seed: u64,
+    /// Subset of every player who gets one.
+    subsets: Vec<usize>,
+    revealed: usize,
+}
+
+impl Draw {
+    /// Shuffles `subset_count` subsets with `seed` and deals them to
+    /// `player_count` players. Players beyond the subsets get none.
+    pub fn new(player_count: usize, subset_count: usize, seed: u64) -> Self {
+        let mut subsets = (0..subset_count).collect::<Vec<_>>();
+        subsets.shuffle(&mut ChaCha20Rng::seed_from_u64(seed));
+        subsets.truncate(player_count);
+        Self {
+            seed,
+            subsets,
+            revealed: 0,
+        }
+    }
+
+    /// Reveals the next player's subset, returning `false` once all are.
+    pub fn reveal_next(&mut self) -> bool {
+        let more = !self.is_complete();
+        self.revealed = (self.revealed + 1).min(self.subsets.len());
+        more
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.revealed == self.subsets.len()
+    }
+
+    /// Players revealed so far with their subsets, in roster order.
+    pub fn revealed(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.subsets[..self.revealed].iter().copied().enumerate()
+    }
+
+    /// The names of [`subset_label`] for `subset_count` subsets: each
+    /// revealed player's name at their subset, `""` elsewhere.
+    pub fn subset_names(&self, roster: &[String], subset_count: usize) -> Vec<String> {
+        let mut names = vec![String::new(); subset_count];
+        for (player, subset) in self.revealed() {
+            if let (Some(name), Some(slot)) = (roster.get(player), names.get_mut(subset)) {
+                name.clone_into(slot);
+            }
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rosters_name_subsets_in_order() {
+        let roster = parse_roster("  Ana \n\n\tBo\r\nCy\n");
+        assert_eq!(roster, ["Ana", "Bo", "Cy"]);
+        assert_eq!(subset_label(&roster, 1), "Bo");
+        assert_eq!(subset_label(&roster, 3), "4");
+        assert!(parse_roster(" \n ").is_empty());
+        assert_eq!(subset_label(&["".to_string()], 0), "1");
+    }
+
+    #[test]
+    fn draws_deal_every_subset_once_and_reveal_in_turn() {
+        let roster = parse_roster("Ana\nBo\nCy");
+        let mut draw = Draw::new(3, 5, 7);
+        assert_eq!(draw, Draw::new(3, 5, 7));
+        assert_eq!(draw.subset_names(&roster, 5), vec![String::new(); 5]);
+
+        assert!(draw.reveal_next());
+        let (player, first) = draw.revealed().next().unwrap();
+        assert_eq!(player, 0);
+        assert_eq!(draw.subset_names(&roster, 5)[first], "Ana");
+        assert!(draw.reveal_next() && draw.reveal_next());
+        assert!(draw.is_complete());
+        assert!(!draw.reveal_next());
+
+        let mut dealt = draw
+            .revealed()
+            .map(|(_, subset)| subset)
+            .collect::<Vec<_>>();
+        dealt.sort_unstable();
+        dealt.dedup();
+        assert_eq!(dealt.len(), 3);
+        assert!(dealt.iter().all(|&subset| subset < 5));
+        let names = draw.subset_names(&roster, 5);
+        assert_eq!(names.iter().filter(|name| name.is_empty()).count(), 2);
+
+        let short = Draw::new(4, 2, 7);
+        assert_eq!(short.subsets.len(), 2);
+    }
+}
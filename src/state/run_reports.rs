@@ -0,0 +1,58 @@
+//! Solver reports of the runs behind the results shown.
+
+use random_karma::RunReport;
+
+/// The runs of a worker calculation, with the subsets they found, so rows
+/// re-rolled or read from the cache since are told apart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShownRunReports {
+    target: u32,
+    sets: Vec<Vec<usize>>,
+    runs: Vec<RunReport>,
+}
+
+impl ShownRunReports {
+    pub fn new(target: u32, sets: &[Vec<usize>], runs: Vec<RunReport>) -> Self {
+        Self {
+            target,
+            sets: sets.to_vec(),
+            runs,
+        }
+    }
+
+    /// The report of every row of `sets`, solved for `target`: none for a
+    /// row that is not the subset its run found.
+    pub fn for_rows(&self, target: u32, sets: &[Vec<usize>]) -> Vec<Option<RunReport>> {
+        sets.iter()
+            .enumerate()
+            .map(|(index, set)| {
+                if target != self.target || self.sets.get(index) != Some(set) {
+                    return None;
+                }
+                self.runs.iter().find(|run| run.run == index + 1).copied()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_rows_lose_their_reports() {
+        let run = |run| RunReport {
+            run,
+            attempts: run,
+            ..RunReport::default()
+        };
+        let sets = vec![vec![0, 1], vec![2, 3]];
+        let reports = ShownRunReports::new(100, &sets, vec![run(1), run(2)]);
+        assert_eq!(reports.for_rows(100, &sets), [Some(run(1)), Some(run(2))]);
+        assert_eq!(
+            reports.for_rows(100, &[vec![0, 1], vec![2, 4]]),
+            [Some(run(1)), None]
+        );
+        assert_eq!(reports.for_rows(101, &sets), [None, None]);
+    }
+}
@@ -0,0 +1,294 @@
+//! Step-by-step record of how each subset converged toward its target.
+//!
+//! [`TraceRecorder`] is an [`EventSink`] that turns solver events into a
+//! serializable [`ConvergenceTrace`]: the running sum and accuracy after every
+//! car added to a subset, and how that car was chosen. Frontends can replay it
+//! to animate a calculation.
+
+use crate::events::{EventSink, LogSink, SolverEvent};
+use crate::{
+    accuracy_percent, perform_multiple_runs_with_events, Car, CarIndex, SubsetCalculationConfig,
+    SubsetError,
+};
+use serde::{Deserialize, Serialize};
+
+/// How the car of a [`TraceStep`] was chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// The legacy heuristic drew the car from those keeping the target
+    /// reachable.
+    Selected,
+    /// No car kept the target reachable, so the car closest to the needed
+    /// average was taken.
+    Fallback,
+    /// The bounded search chose the subset as a whole; its cars are listed
+    /// in subset order.
+    Search,
+}
+
+/// One car added to a subset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub car: CarIndex,
+    pub lap_time: u32,
+    /// Sum of the subset's lap times after this car, in milliseconds.
+    pub running_sum: u32,
+    /// `running_sum` as a percentage of the target.
+    pub accuracy: f64,
+    pub decision: Decision,
+}
+
+/// The steps of one run, i.e. one player's subset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunTrace {
+    pub run: usize,
+    /// Steps of the attempt that produced the subset, or of the attempt in
+    /// progress when the calculation stopped.
+    pub steps: Vec<TraceStep>,
+    /// Attempts that missed the tolerance and were discarded.
+    pub rejected_attempts: usize,
+    pub completed: bool,
+}
+
+/// Convergence of every run of a calculation, in run order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConvergenceTrace {
+    pub target: u32,
+    pub runs: Vec<RunTrace>,
+}
+
+/// Event sink that records a [`ConvergenceTrace`] for a calculation on
+/// `cars`.
+///
+/// Every calculation it observes, such as each pass of
+/// [`SubsetCalculationConfig::tighten_tolerance`], is traced separately, and [`TraceRecorder::trace`] returns the last one that
+/// completed, which is the one whose subsets are returned.
+#[derive(Debug, Clone)]
+pub struct TraceRecorder<'a> {
+    cars: &'a [Car],
+    target: u32,
+    passes: Vec<Vec<RunTrace>>,
+    pending_fallback: Option<CarIndex>,
+}
+
+impl<'a> TraceRecorder<'a> {
+    pub fn new(cars: &'a [Car], target: u32) -> Self {
+        Self {
+            cars,
+            target,
+            passes: Vec::new(),
+            pending_fallback: None,
+        }
+    }
+
+    pub fn trace(&self) -> ConvergenceTrace {
+        let runs = self
+            .passes
+            .iter()
+            .rev()
+            .find(|runs| !runs.is_empty() && runs.iter().all(|run| run.completed))
+            .or(self.passes.last())
+            .cloned()
+            .unwrap_or_default();
+        ConvergenceTrace {
+            target: self.target,
+            runs,
+        }
+    }
+
+    fn current_run(&mut self) -> Option<&mut RunTrace> {
+        self.passes.last_mut().and_then(|runs| runs.last_mut())
+    }
+}
+
+impl EventSink for TraceRecorder<'_> {
+    fn emit(&mut self, event: SolverEvent) {
+        let target = self.target;
+        match event {
+            SolverEvent::RunStarted { run, .. } => {
+                if run == 1 || self.passes.is_empty() {
+                    self.passes.push(Vec::new());
+                }
+                self.pending_fallback = None;
+                if let Some(runs) = self.passes.last_mut() {
+                    runs.push(RunTrace {
+                        run,
+                        ..RunTrace::default()
+                    });
+                }
+            }
+            SolverEvent::FallbackUsed { car, .. } => self.pending_fallback = Some(car),
+            SolverEvent::SelectionMade {
+                car,
+                lap_time,
+                current_sum,
+                ..
+            } => {
+                let decision = if self.pending_fallback.take() == Some(car) {
+                    Decision::Fallback
+                } else {
+                    Decision::Selected
+                };
+                if let Some(run) = self.current_run() {
+                    run.steps.push(TraceStep {
+                        car,
+                        lap_time,
+                        running_sum: current_sum,
+                        accuracy: accuracy_percent(current_sum, target),
+                        decision,
+                    });
+                }
+            }
+            SolverEvent::AttemptRejected { .. } | SolverEvent::SharedCarsRejected { .. } => {
+                if let Some(run) = self.current_run() {
+                    run.steps.clear();
+                    run.rejected_attempts += 1;
+                }
+            }
+            SolverEvent::RunCompleted { subset, .. } => {
+                let cars = self.cars;
+                let Some(run) = self.current_run() else {
+                    return;
+                };
+                if run.steps.is_empty() {
+                    // The bounded search reports only the finished subset.
+                    let mut running_sum = 0_u32;
+                    run.steps = subset
+                        .into_iter()
+                        .map(|car| {
+                            let lap_time = cars[car].lap_time;
+                            running_sum = running_sum.saturating_add(lap_time);
+                            TraceStep {
+                                car,
+                                lap_time,
+                                running_sum,
+                                accuracy: accuracy_percent(running_sum, target),
+                                decision: Decision::Search,
+                            }
+                        })
+                        .collect();
+                }
+                run.completed = true;
+            }
+            // A skipped run stays incomplete.
+            SolverEvent::RunSkipped { .. } => {}
+            // Steps record selection only; later trades between subsets
+            // are summarized by the event itself.
+            SolverEvent::Rebalanced { .. }
+            | SolverEvent::SwapPassCompleted { .. }
+            | SolverEvent::ToleranceTightened { .. } => {}
+        }
+    }
+}
+
+/// Runs [`perform_multiple_runs`](crate::perform_multiple_runs) and returns
+/// the convergence of its runs alongside the result. Events are still
+/// logged.
+///
+/// Steps are in selection order; the returned subsets are afterwards
+/// arranged by [`SubsetCalculationConfig::lap_order`].
+pub fn trace_multiple_runs(
+    global_cars: &[Car],
+    config: &SubsetCalculationConfig,
+) -> (Result<Vec<Vec<CarIndex>>, SubsetError>, ConvergenceTrace) {
+    let mut recorder = TraceRecorder::new(global_cars, config.target);
+    let result =
+        perform_multiple_runs_with_events(global_cars, config, &mut (LogSink, &mut recorder));
+    (result, recorder.trace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolverStrategy;
+
+    fn cars() -> Vec<Car> {
+        (0..12)
+            .map(|index| Car::new(format!("car-{index}"), 1_000 + index * 10))
+            .collect()
+    }
+
+    #[test]
+    fn traces_end_at_each_returned_subset() {
+        let cars = cars();
+        for strategy in [SolverStrategy::Legacy, SolverStrategy::Bounded] {
+            let config = SubsetCalculationConfig::builder(3_100, 3, 2)
+                .tolerance_percent(5.0)
+                .strategy(strategy)
+                .build()
+                .unwrap();
+            let (result, trace) = trace_multiple_runs(&cars, &config);
+            let subsets = result.unwrap();
+
+            assert_eq!(trace.target, 3_100);
+            assert_eq!(trace.runs.len(), subsets.len());
+            for (run, subset) in trace.runs.iter().zip(&subsets) {
+                assert!(run.completed);
+                let last = run.steps.last().unwrap();
+                assert_eq!(last.running_sum, crate::calculate_subset_sum(&cars, subset));
+                assert_eq!(last.accuracy, accuracy_percent(last.running_sum, 3_100));
+
+                let mut traced = run.steps.iter().map(|step| step.car).collect::<Vec<_>>();
+                let mut subset = subset.clone();
+                traced.sort_unstable();
+                subset.sort_unstable();
+                assert_eq!(traced, subset);
+                assert!(run
+                    .steps
+                    .windows(2)
+                    .all(|pair| pair[0].running_sum < pair[1].running_sum));
+            }
+            let searched = trace
+                .runs
+                .iter()
+                .flat_map(|run| &run.steps)
+                .all(|step| step.decision == Decision::Search);
+            assert_eq!(searched, strategy == SolverStrategy::Bounded);
+        }
+    }
+
+    #[test]
+    fn fallbacks_and_rejected_attempts_are_recorded() {
+        let cars = cars();
+        let mut recorder = TraceRecorder::new(&cars, 2_000);
+        recorder.emit(SolverEvent::RunStarted {
+            run: 1,
+            player_count: 1,
+            available: 12,
+        });
+        let selection = |car: CarIndex, current_sum: u32| SolverEvent::SelectionMade {
+            car,
+            lap_time: cars[car].lap_time,
+            current_sum,
+            target: 2_000,
+            selected: 1,
+            lap_count: 2,
+        };
+        recorder.emit(selection(0, 1_000));
+        recorder.emit(SolverEvent::AttemptRejected {
+            run: 1,
+            accuracy: 110.0,
+            tolerance_percent: 1.0,
+        });
+        recorder.emit(SolverEvent::FallbackUsed {
+            car: 1,
+            remaining_needed: 2,
+        });
+        recorder.emit(selection(1, 1_010));
+        recorder.emit(selection(0, 2_010));
+
+        let trace = recorder.trace();
+        let run = &trace.runs[0];
+        assert_eq!(run.rejected_attempts, 1);
+        assert!(!run.completed);
+        assert_eq!(
+            run.steps
+                .iter()
+                .map(|step| step.decision)
+                .collect::<Vec<_>>(),
+            [Decision::Fallback, Decision::Selected]
+        );
+        assert!((run.steps[1].accuracy - 100.5).abs() < 1e-9);
+    }
+}
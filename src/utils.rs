@@ -1,60 +1,55 @@
 use crate::config::SLIDER_MAX_INDEX;
-use crate::get_target_range_for_subset;
-use crate::Car;
+use crate::get_target_range_for_players;
+use crate::{Car, DifficultyProfile, SolverStrategy, TargetSpec};
 use once_cell::sync::Lazy;
+use random_karma::{filter_by_lap_time, format_ms_to_minsecms, parse_lap_time};
 use regex::Regex;
-use std::collections::VecDeque;
-
-// Compiled regexes for time parsing
-static TIME_MIN_SEC_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+)m\s*(\d+)s$").unwrap());
-static TIME_COLON_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+):(\d+)$").unwrap());
-static TIME_SEC_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+)s$").unwrap());
-static TIME_COLON_MSEC_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^(\d+):(\d{2})\.(\d{1,3})$").unwrap());
-
-/// Return indices `0..n` in a “spread-out” order (0, n-1, mid, …).
-pub fn spread_indices(n: usize) -> Vec<usize> {
-    if n == 0 {
-        return Vec::new();
-    }
-
-    let mut out = Vec::with_capacity(n);
-    let mut seen = vec![false; n];
-    let mut q: VecDeque<(usize, usize)> = VecDeque::new();
-
-    out.push(0);
-    seen[0] = true;
-    if n > 1 {
-        out.push(n - 1);
-        seen[n - 1] = true;
-    }
-    q.push_back((0, n - 1));
-
-    while out.len() < n {
-        let (lo, hi) = q.pop_front().unwrap();
-        if hi - lo <= 1 {
-            continue;
-        }
-        let mid = (lo + hi) / 2;
-        if !seen[mid] {
-            out.push(mid);
-            seen[mid] = true;
-        }
-        q.push_back((lo, mid));
-        q.push_back((mid, hi));
-    }
-    out
-}
 
-/// Return the (min, max) possible total lap times for the given subset size.
-pub fn base_target_range(cars: &[Car], subset_size: usize) -> (u32, u32) {
-    if cars.is_empty() {
+/// Return the (min, max) total lap time every player's subset can reach
+/// from the cars within the lap-time filter.
+pub fn base_target_range(
+    cars: &[Car],
+    subset_size: usize,
+    player_count: usize,
+    strategy: SolverStrategy,
+    (min_lap_time, max_lap_time): (Option<u32>, Option<u32>),
+) -> (u32, u32) {
+    let pool = filter_by_lap_time(cars, min_lap_time, max_lap_time)
+        .into_iter()
+        .map(|index| cars[index].clone())
+        .collect::<Vec<_>>();
+    if pool.is_empty() {
         (0, 0)
     } else {
-        get_target_range_for_subset(cars, subset_size)
+        get_target_range_for_players(&pool, subset_size, strategy.disjoint_runs(player_count))
     }
 }
 
+/// CSS gradient coloring the target slider's track by the estimated
+/// difficulty of each stretch of `min..=max`.
+pub fn difficulty_gradient(
+    profile: &DifficultyProfile,
+    min: u32,
+    max: u32,
+    tolerance_percent: f64,
+) -> String {
+    const SEGMENTS: usize = 40;
+    let span = f64::from(max.saturating_sub(min));
+    let stops = (0..SEGMENTS)
+        .map(|segment| {
+            let middle = (segment as f64 + 0.5) / SEGMENTS as f64;
+            let target = min + (span * middle).round() as u32;
+            format!(
+                "var(--difficulty-{}) {:.1}% {:.1}%",
+                profile.level(target, tolerance_percent).as_str(),
+                segment as f64 * 100.0 / SEGMENTS as f64,
+                (segment + 1) as f64 * 100.0 / SEGMENTS as f64
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("linear-gradient(90deg, {})", stops.join(", "))
+}
+
 /// Compute the step size between indices given a target range.
 pub fn base_target_step(min: u32, max: u32) -> u32 {
     if max > min {
@@ -70,38 +65,55 @@ pub fn calc_target_from_idx(min: u32, max: u32, idx: usize) -> u32 {
     (min + step * idx as u32).min(max)
 }
 
-/// Time parsing error types for better error handling
-#[derive(Debug)]
-pub enum TimeParseError {
-    EmptyInput,
-    InvalidFormat(String),
-    InvalidMinutes,
-    InvalidSeconds(u32),
-    InvalidMilliseconds,
+/// The slider index nearest to a target within [min, max].
+pub fn calc_idx_from_target(min: u32, max: u32, target: u32) -> usize {
+    let range = max.saturating_sub(min);
+    let pos = if range > 0 {
+        (target.saturating_sub(min) as f64 / range as f64 * SLIDER_MAX_INDEX as f64).round()
+            as usize
+    } else {
+        0
+    };
+    pos.min(SLIDER_MAX_INDEX)
 }
 
-impl std::fmt::Display for TimeParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            TimeParseError::EmptyInput => write!(f, "Time cannot be empty"),
-            TimeParseError::InvalidFormat(hint) => write!(f, "Invalid time format. {}", hint),
-            TimeParseError::InvalidMinutes => write!(f, "Invalid minutes value"),
-            TimeParseError::InvalidSeconds(s) => write!(f, "Invalid seconds: {} (must be 0-59)", s),
-            TimeParseError::InvalidMilliseconds => write!(f, "Invalid milliseconds value"),
+static TARGET_MULTIPLIER_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.+?)\s*[×xX*]\s*(\d+)$").unwrap());
+
+/// Parse the target text input. A plain time as accepted by
+/// [`parse_time_to_ms`] is the total; an average lap is written
+/// "1:52.000/lap" or "1:52.000 × 25", where the multiplier must be the lap
+/// count.
+pub fn parse_target_input(input: &str, lap_count: usize) -> Result<TargetSpec, String> {
+    let trimmed = input.trim();
+    if let Some(average) = trimmed
+        .strip_suffix("/lap")
+        .or_else(|| trimmed.strip_suffix(" per lap"))
+    {
+        return parse_time_to_ms(average).map(TargetSpec::AverageLap);
+    }
+    if let Some(captures) = TARGET_MULTIPLIER_REGEX.captures(trimmed) {
+        let laps: usize = captures[2].parse().map_err(|_| "Invalid lap count")?;
+        if laps != lap_count {
+            return Err(format!(
+                "Multiplier {} does not match the lap count {}",
+                laps, lap_count
+            ));
         }
+        return parse_time_to_ms(&captures[1]).map(TargetSpec::AverageLap);
     }
+    parse_time_to_ms(trimmed).map(TargetSpec::Total)
 }
 
-impl std::error::Error for TimeParseError {}
-
-/// Parse a time string in various formats to milliseconds.
+/// Parse a time string to milliseconds with the lap-time parser the CSV
+/// import uses, so both accept the same formats.
 ///
 /// Supported formats:
 /// - Pure number: "150000" (interpreted as milliseconds)
-/// - Minutes:seconds.milliseconds: "2:30.500"
+/// - Minutes:seconds with optional milliseconds: "2:30" or "2:30.500"
+/// - Hours:minutes:seconds: "1:02:30.500"
 /// - Minutes and seconds: "2m 30s" or "2m30s"
-/// - Colon format: "2:30" (minutes:seconds)
-/// - Seconds only: "150s"
+/// - Seconds only: "150s" or "150.5"
 ///
 /// # Examples
 /// ```
@@ -111,68 +123,10 @@ impl std::error::Error for TimeParseError {}
 /// assert_eq!(parse_time_to_ms("150000"), Ok(150_000));
 /// ```
 pub fn parse_time_to_ms(input: &str) -> Result<u32, String> {
-    let trimmed = input.trim();
-    if trimmed.is_empty() {
-        return Err(TimeParseError::EmptyInput.to_string());
-    }
-
-    // Try parsing as pure number (assume milliseconds)
-    if let Ok(ms) = trimmed.parse::<u32>() {
-        return Ok(ms);
-    }
-
-    // Try parsing mm:ss.SSS format
-    if let Some(captures) = TIME_COLON_MSEC_REGEX.captures(trimmed) {
-        let minutes: u32 = captures[1]
-            .parse()
-            .map_err(|_| TimeParseError::InvalidMinutes.to_string())?;
-        let seconds: u32 = captures[2]
-            .parse()
-            .map_err(|_| TimeParseError::InvalidSeconds(0).to_string())?;
-        let mut milliseconds: u32 = captures[3]
-            .parse()
-            .map_err(|_| TimeParseError::InvalidMilliseconds.to_string())?;
-
-        if seconds > 59 {
-            return Err(TimeParseError::InvalidSeconds(seconds).to_string());
-        }
-
-        // Normalize ms: 1 digit = x100, 2 digits = x10, 3 digits = as is
-        match captures[3].len() {
-            1 => milliseconds *= 100,
-            2 => milliseconds *= 10,
-            _ => {}
-        }
-        return Ok(minutes * 60_000 + seconds * 1_000 + milliseconds);
-    }
-
-    // Try parsing "XmYs" format
-    if let Some(captures) = TIME_MIN_SEC_REGEX.captures(trimmed) {
-        let minutes: u32 = captures[1].parse().map_err(|_| "Invalid minutes")?;
-        let seconds: u32 = captures[2].parse().map_err(|_| "Invalid seconds")?;
-        if seconds > 59 {
-            return Err("Invalid time format: seconds must be 0-59".to_string());
-        }
-        return Ok(minutes * 60_000 + seconds * 1_000);
-    }
-
-    // Try parsing "X:Y" format (minutes:seconds)
-    if let Some(captures) = TIME_COLON_REGEX.captures(trimmed) {
-        let minutes: u32 = captures[1].parse().map_err(|_| "Invalid minutes")?;
-        let seconds: u32 = captures[2].parse().map_err(|_| "Invalid seconds")?;
-        if seconds > 59 {
-            return Err("Invalid time format: seconds must be 0-59".to_string());
-        }
-        return Ok(minutes * 60_000 + seconds * 1_000);
-    }
-
-    // Try parsing "Xs" format (seconds)
-    if let Some(captures) = TIME_SEC_REGEX.captures(trimmed) {
-        let seconds: u32 = captures[1].parse().map_err(|_| "Invalid seconds")?;
-        return Ok(seconds * 1_000);
+    if input.trim().is_empty() {
+        return Err("Time cannot be empty".to_string());
     }
-
-    Err(TimeParseError::InvalidFormat("Use: 2:30, 2m30s, 150s, or 150000".to_string()).to_string())
+    parse_lap_time(input)
 }
 
 /// Generic numeric input validation
@@ -217,3 +171,58 @@ pub fn validate_lap_count(input: &str, max_cars: usize) -> Result<usize, String>
 pub fn validate_player_count(input: &str) -> Result<usize, String> {
     validate_numeric_input(input, Some(0), Some(250), "Player count")
 }
+
+/// Reads a decimal number such as a timeout or tolerance.
+pub fn parse_decimal(input: &str) -> Result<f64, String> {
+    input.parse().map_err(|_| "Invalid number".to_string())
+}
+
+/// Validate an optional lap-time filter bound; empty input clears the bound.
+pub fn validate_optional_lap_time(input: &str) -> Result<Option<u32>, String> {
+    if input.trim().is_empty() {
+        return Ok(None);
+    }
+    parse_time_to_ms(input).map(Some)
+}
+
+/// Reject lap-time filter bounds where the minimum exceeds the maximum.
+pub fn validate_lap_time_filter(min: Option<u32>, max: Option<u32>) -> Result<(), String> {
+    match (min, max) {
+        (Some(min), Some(max)) if min > max => {
+            Err("Minimum lap time cannot exceed maximum lap time".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Show an optional lap-time filter bound; no bound shows as empty text.
+pub fn format_optional_lap_time(bound: &Option<u32>) -> String {
+    bound.map(format_ms_to_minsecms).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_ranges_only_count_cars_within_the_lap_time_filter() {
+        let cars = [50_000, 60_000, 70_000, 200_000]
+            .into_iter()
+            .enumerate()
+            .map(|(index, lap_time)| Car::new(format!("car-{index}"), lap_time))
+            .collect::<Vec<_>>();
+        let strategy = SolverStrategy::Bounded;
+        assert_eq!(
+            base_target_range(&cars, 2, 1, strategy, (None, None)),
+            (110_000, 270_000)
+        );
+        assert_eq!(
+            base_target_range(&cars, 2, 1, strategy, (None, Some(100_000))),
+            (110_000, 130_000)
+        );
+        assert_eq!(
+            base_target_range(&cars, 2, 1, strategy, (Some(300_000), None)),
+            (0, 0)
+        );
+    }
+}
@@ -1,10 +1,146 @@
 //! Web Worker agent for offloading karma calculations to background threads.
+//!
+//! A [`KarmaTask`] keeps the datasets sent with
+//! [`WorkerInput::RegisterDataset`], so each [`WorkerInput::Calculate`]
+//! names its cars by [`dataset_hash`] instead of carrying them. It queues
+//! the calculations it receives and works through them in order, those of
+//! the interactive [`Priority`] lane first. A
+//! [`WorkerInput::Cancel`] drops a queued request before it starts, and
+//! stops the running one through the [`CancellationToken`] it was given in
+//! [`SubsetCalculationConfig::cancellation`]: the worker applies the
+//! messages received after every run of the calculation. A
+//! [`ResultCache`] answers a request the worker already solved without
+//! solving it again.
+//!
+//! Messages cross the worker boundary as [`WorkerCodec`] bytes, so both the
+//! registrar and the spawner must use it.
 
-use crate::{compute_jaccard_similarity, perform_multiple_runs_with_strategy, Car, SolverStrategy};
+use crate::events::{EventSink, LogSink, SolverEvent};
+use crate::{
+    compute_jaccard_similarity, compute_weighted_jaccard_similarity, dataset_hash, exact_hit_count,
+    perform_multiple_runs_with_events, quality_score, AttemptScoreWeights, CancellationToken, Car,
+    LapOrder, MetricsRecorder, Objective, QualityWeights, RunReport, SolverStrategy,
+    SubsetCalculationConfig, SubsetError,
+};
 use futures::sink::SinkExt;
-use futures::StreamExt;
+use futures::{FutureExt, StreamExt};
+use gloo_timers::future::TimeoutFuture;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use wasm_bindgen::JsValue;
 use yew_agent::reactor::{reactor, ReactorScope};
+use yew_agent::Codec;
+
+/// Encodes worker messages with postcard in a `Uint8Array`.
+///
+/// Car indexes and counts take a byte or two as varints, fields go without
+/// their names, and car pools travel as arrays of fields. Postcard does not
+/// describe the data, so both sides must be built from the same message
+/// types, as the worker and the page it serves are.
+pub struct WorkerCodec;
+
+impl WorkerCodec {
+    pub fn to_bytes(message: &impl Serialize) -> Vec<u8> {
+        postcard::to_stdvec(message).expect("worker messages always serialize")
+    }
+
+    pub fn from_bytes<O: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result<O, String> {
+        postcard::from_bytes(bytes).map_err(|error| error.to_string())
+    }
+}
+
+impl Codec for WorkerCodec {
+    fn encode<I: Serialize>(input: I) -> JsValue {
+        js_sys::Uint8Array::from(Self::to_bytes(&input).as_slice()).into()
+    }
+
+    /// Panics on malformed bytes, which stops the worker; the main thread
+    /// then reports [`WorkerError::Deserialization`].
+    fn decode<O: for<'de> Deserialize<'de>>(input: JsValue) -> O {
+        let bytes = js_sys::Uint8Array::from(input).to_vec();
+        Self::from_bytes(&bytes).unwrap_or_else(|error| panic!("Invalid worker message: {error}"))
+    }
+}
+
+/// Serializes cars as arrays of every field, which spares the field names
+/// of [`Car`] in each of the pool's entries.
+mod car_fields {
+    use crate::Car;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    type Fields = (
+        String,
+        u32,
+        Option<u32>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        bool,
+    );
+
+    pub fn serialize<S: Serializer>(cars: &[Car], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(cars.iter().map(|car| {
+            (
+                &car.id,
+                car.lap_time,
+                car.uncertainty_ms,
+                &car.name,
+                &car.class,
+                &car.track,
+                car.enabled,
+            )
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Car>, D::Error> {
+        let fields = Vec::<Fields>::deserialize(deserializer)?;
+        Ok(fields
+            .into_iter()
+            .map(
+                |(id, lap_time, uncertainty_ms, name, class, track, enabled)| Car {
+                    id,
+                    lap_time,
+                    uncertainty_ms,
+                    name,
+                    class,
+                    track,
+                    enabled,
+                },
+            )
+            .collect())
+    }
+}
+
+/// Serializes a [`WorkerError`] as its JSON text to formats such as the
+/// [`WorkerCodec`]'s that are not human-readable: they cannot read the
+/// adjacently tagged errors back, and failures are too rare for the text to
+/// cost much. Human-readable formats keep the tagged form.
+mod tagged_error {
+    use super::WorkerError;
+    use serde::de::Error as _;
+    use serde::ser::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(error: &WorkerError, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return error.serialize(serializer);
+        }
+        serde_json::to_string(error)
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<WorkerError, D::Error> {
+        if deserializer.is_human_readable() {
+            return WorkerError::deserialize(deserializer);
+        }
+        let json = String::deserialize(deserializer)?;
+        serde_json::from_str(&json).map_err(D::Error::custom)
+    }
+}
 
 /// Complete identity of a worker request. Echoed for both success and failure
 /// so callers can reject responses from superseded requests or datasets.
@@ -12,19 +148,202 @@ use yew_agent::reactor::{reactor, ReactorScope};
 pub struct RequestMetadata {
     pub request_id: u64,
     pub dataset_generation: u64,
+    /// [`dataset_hash`](crate::dataset_hash) of the cars the request runs on.
+    pub dataset_hash: u64,
+    /// Total of a subset's lap times; see [`TargetSpec`](crate::TargetSpec)
+    /// for targets given as an average lap.
     pub target: u32,
     pub lap_count: usize,
     pub player_count: usize,
     pub timeout_ms: f64,
     pub tolerance_percent: f64,
     pub strategy: SolverStrategy,
+    pub min_lap_time: Option<u32>,
+    pub max_lap_time: Option<u32>,
+}
+
+impl RequestMetadata {
+    /// Solver configuration described by this request.
+    pub fn config(&self) -> SubsetCalculationConfig {
+        SubsetCalculationConfig {
+            target: self.target,
+            lap_count: self.lap_count,
+            player_count: self.player_count,
+            timeout_ms: self.timeout_ms,
+            tolerance_percent: self.tolerance_percent,
+            strategy: self.strategy,
+            min_lap_time: self.min_lap_time,
+            max_lap_time: self.max_lap_time,
+            ..SubsetCalculationConfig::default()
+        }
+    }
 }
 
-/// Arguments for karma calculation tasks sent to workers.
+/// Solver settings beyond those of [`RequestMetadata`], each as in
+/// [`SubsetCalculationConfig`]. The defaults are the solver's own, and
+/// fields missing from a message take them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SolverOptions {
+    pub seed: Option<u64>,
+    pub run_timeout_ms: Option<f64>,
+    pub full_coverage: bool,
+    pub quantile_bins: usize,
+    pub lap_order: LapOrder,
+    pub attempts_per_run: usize,
+    pub attempt_weights: AttemptScoreWeights,
+    pub tighten_tolerance: bool,
+    pub exhaustive_limit: u64,
+    pub disjoint: bool,
+    pub max_shared_cars: Option<usize>,
+    pub objective: Objective,
+    pub swap_pass: bool,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        let config = SubsetCalculationConfig::default();
+        Self {
+            seed: config.seed,
+            run_timeout_ms: config.run_timeout_ms,
+            full_coverage: config.full_coverage,
+            quantile_bins: config.quantile_bins,
+            lap_order: config.lap_order,
+            attempts_per_run: config.attempts_per_run,
+            attempt_weights: config.attempt_weights,
+            tighten_tolerance: config.tighten_tolerance,
+            exhaustive_limit: config.exhaustive_limit,
+            disjoint: config.disjoint,
+            max_shared_cars: config.max_shared_cars,
+            objective: config.objective,
+            swap_pass: config.swap_pass,
+        }
+    }
+}
+
+impl SolverOptions {
+    /// `config` with these options in place of its own.
+    pub fn apply(&self, config: SubsetCalculationConfig) -> SubsetCalculationConfig {
+        SubsetCalculationConfig {
+            seed: self.seed,
+            run_timeout_ms: self.run_timeout_ms,
+            full_coverage: self.full_coverage,
+            quantile_bins: self.quantile_bins,
+            lap_order: self.lap_order,
+            attempts_per_run: self.attempts_per_run,
+            attempt_weights: self.attempt_weights,
+            tighten_tolerance: self.tighten_tolerance,
+            exhaustive_limit: self.exhaustive_limit,
+            disjoint: self.disjoint,
+            max_shared_cars: self.max_shared_cars,
+            objective: self.objective,
+            swap_pass: self.swap_pass,
+            ..config
+        }
+    }
+}
+
+/// Which queue lane a calculation waits in. A worker starts every queued
+/// [`Priority::Interactive`] target before any [`Priority::Background`] one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Priority {
+    /// A result the user is waiting for.
+    #[default]
+    Interactive,
+    /// Work ahead of the user, such as pre-caching.
+    Background,
+}
+
+/// Arguments for karma calculation tasks sent to workers. The cars are
+/// the registered dataset [`RequestMetadata::dataset_hash`] names.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct KarmaArgs {
-    pub cars: Vec<Car>,
     pub metadata: RequestMetadata,
+    /// Further targets solved in order after `metadata.target`, the request
+    /// otherwise unchanged. Each is answered on its own, with metadata
+    /// naming its target; a cancel drops those not started yet.
+    #[serde(default)]
+    pub batch_targets: Vec<u32>,
+    /// Applied to every target. Answers do not repeat them, so a caller
+    /// caching results by [`RequestMetadata`] should only send the defaults.
+    #[serde(default)]
+    pub options: SolverOptions,
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+impl KarmaArgs {
+    pub fn new(metadata: RequestMetadata) -> Self {
+        Self {
+            metadata,
+            batch_targets: Vec::new(),
+            options: SolverOptions::default(),
+            priority: Priority::default(),
+        }
+    }
+
+    /// Solver configuration for the first target.
+    pub fn config(&self) -> SubsetCalculationConfig {
+        self.options.apply(self.metadata.config())
+    }
+
+    /// The request of every target, in the order they are answered.
+    pub fn requests(&self) -> impl Iterator<Item = RequestMetadata> + '_ {
+        let first = std::iter::once(self.metadata.clone());
+        first.chain(self.batch_targets.iter().map(|&target| RequestMetadata {
+            target,
+            ..self.metadata.clone()
+        }))
+    }
+
+    /// Splits off the first target, returning the rest of the batch too.
+    fn split_first(self) -> (KarmaArgs, Option<KarmaArgs>) {
+        let mut targets = self.batch_targets.into_iter();
+        let rest = targets.next().map(|target| KarmaArgs {
+            metadata: RequestMetadata {
+                target,
+                ..self.metadata.clone()
+            },
+            batch_targets: targets.collect(),
+            options: self.options.clone(),
+            priority: self.priority,
+        });
+        let first = KarmaArgs {
+            batch_targets: Vec::new(),
+            ..self
+        };
+        (first, rest)
+    }
+
+    /// Takes the next target off `queue`, leaving the rest of its batch
+    /// first in its lane.
+    pub(crate) fn next_in(queue: &mut VecDeque<KarmaArgs>) -> Option<KarmaArgs> {
+        let (args, rest) = queue.pop_front()?.split_first();
+        if let Some(rest) = rest {
+            match rest.priority {
+                Priority::Interactive => queue.push_front(rest),
+                Priority::Background => queue.insert(background_start(queue), rest),
+            }
+        }
+        Some(args)
+    }
+}
+
+/// A message to [`KarmaTask`].
+#[derive(Serialize, Deserialize, Clone)]
+pub enum WorkerInput {
+    /// Keeps `cars` for the calculations naming their [`dataset_hash`].
+    RegisterDataset {
+        #[serde(with = "car_fields")]
+        cars: Vec<Car>,
+    },
+    /// Queues a calculation at the end of its [`Priority`] lane, answered
+    /// with [`WorkerOutput`] messages.
+    Calculate(KarmaArgs),
+    /// Drops the queued targets of `request_id`, answering each with
+    /// [`WorkerError::Cancelled`], and cancels the target running, which
+    /// answers once it stops.
+    Cancel { request_id: u64 },
 }
 
 /// A successful worker calculation with its complete request identity.
@@ -33,51 +352,974 @@ pub struct KarmaSuccess {
     pub metadata: RequestMetadata,
     pub sets: Vec<Vec<usize>>,
     pub similarity: f64,
+    /// Lap-time-weighted similarity from
+    /// [`compute_weighted_jaccard_similarity`](crate::compute_weighted_jaccard_similarity).
+    #[serde(default)]
+    pub weighted_similarity: f64,
     pub calculated_target: u32,
+    /// Subsets whose sum equals the target exactly.
+    #[serde(default)]
+    pub exact_hits: usize,
+    /// [`quality_score`](crate::quality_score) with the default weights, for
+    /// ranking alternative results for the same target.
+    #[serde(default)]
+    pub quality: f64,
+    /// How every run went, in the order of `sets`.
+    #[serde(default)]
+    pub runs: Vec<RunReport>,
+}
+
+/// Why a worker calculation failed.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, thiserror::Error)]
+#[serde(tag = "kind", content = "error", rename_all = "snake_case")]
+pub enum WorkerError {
+    /// The solver ran out of time before every subset was found; a longer
+    /// timeout or a wider tolerance may succeed.
+    #[error("{0}")]
+    Timeout(SubsetError),
+    /// The request cannot be met as configured.
+    #[error("{0}")]
+    Infeasible(SubsetError),
+    /// A message across the worker boundary could not be decoded. The
+    /// worker stops at such a message, so the main thread reports this when
+    /// a bridge closes without answering.
+    #[error("The calculation worker stopped: {0}")]
+    Deserialization(String),
+    /// The request was cancelled before it finished.
+    #[error("The calculation was cancelled")]
+    Cancelled,
+    /// No dataset with the request's hash was registered with the worker.
+    #[error("The calculation worker has no dataset {dataset_hash:016x}")]
+    UnknownDataset { dataset_hash: u64 },
+}
+
+impl WorkerError {
+    pub fn subset_error(&self) -> Option<&SubsetError> {
+        match self {
+            WorkerError::Timeout(error) | WorkerError::Infeasible(error) => Some(error),
+            WorkerError::Deserialization(_)
+            | WorkerError::Cancelled
+            | WorkerError::UnknownDataset { .. } => None,
+        }
+    }
+
+    /// See [`SubsetError::suggestions`].
+    pub fn suggestions(&self) -> Vec<String> {
+        match self {
+            WorkerError::Deserialization(_) | WorkerError::UnknownDataset { .. } => {
+                vec!["Reload the page".to_string()]
+            }
+            _ => self
+                .subset_error()
+                .map(SubsetError::suggestions)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<SubsetError> for WorkerError {
+    fn from(error: SubsetError) -> Self {
+        match error {
+            SubsetError::NotEnoughSuccessfulRuns { .. } | SubsetError::RunsSkipped { .. } => {
+                WorkerError::Timeout(error)
+            }
+            SubsetError::Cancelled => WorkerError::Cancelled,
+            _ => WorkerError::Infeasible(error),
+        }
+    }
 }
 
 /// A failed worker calculation with its complete request identity.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct KarmaFailure {
     pub metadata: RequestMetadata,
-    pub error: String,
+    #[serde(with = "tagged_error")]
+    pub error: WorkerError,
 }
 
 /// Worker responses always include full request metadata, including errors.
-pub type KarmaResult = Result<KarmaSuccess, KarmaFailure>;
+pub type WorkerResult = Result<KarmaSuccess, KarmaFailure>;
+
+/// A message from [`KarmaTask`]: a [`WorkerOutput::Progress`] update per
+/// finished run, then the [`WorkerOutput::Finished`] result.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum WorkerOutput {
+    /// Answers [`WorkerInput::RegisterDataset`]. `threaded` tells whether
+    /// the worker's thread pool started, so it solves the targets of a
+    /// request together rather than one at a time.
+    Registered {
+        dataset_hash: u64,
+        threaded: bool,
+    },
+    /// `run` of `total` runs are done, after `elapsed_ms` spent in them.
+    Progress {
+        run: usize,
+        total: usize,
+        elapsed_ms: f64,
+    },
+    Finished(WorkerResult),
+}
+
+/// Turns the solver's completed and skipped runs into
+/// [`WorkerOutput::Progress`] messages for `send`.
+struct ProgressSink<F> {
+    send: F,
+    elapsed_ms: f64,
+}
+
+impl<F: FnMut(WorkerOutput)> EventSink for ProgressSink<F> {
+    fn emit(&mut self, event: SolverEvent) {
+        if let SolverEvent::RunCompleted {
+            run,
+            player_count,
+            elapsed_ms,
+            ..
+        }
+        | SolverEvent::RunSkipped {
+            run,
+            player_count,
+            elapsed_ms,
+            ..
+        } = event
+        {
+            self.elapsed_ms += elapsed_ms;
+            (self.send)(WorkerOutput::Progress {
+                run,
+                total: player_count,
+                elapsed_ms: self.elapsed_ms,
+            });
+        }
+    }
+}
+
+/// Datasets registered with a worker, by [`dataset_hash`].
+pub(crate) type Datasets = HashMap<u64, Arc<[Car]>>;
+
+/// Number of results a [`ResultCache`] keeps.
+pub const WORKER_CACHE_ENTRIES: usize = 64;
+
+/// Every setting of a request that can affect its result, as in the main
+/// thread's cache key, and its [`SolverOptions`]; the ids are left out.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ResultKey {
+    dataset_hash: u64,
+    target: u32,
+    lap_count: usize,
+    player_count: usize,
+    timeout_ms_bits: u64,
+    tolerance_percent_bits: u64,
+    strategy: SolverStrategy,
+    min_lap_time: Option<u32>,
+    max_lap_time: Option<u32>,
+    /// The options as encoded, so they compare exactly.
+    options: Vec<u8>,
+}
+
+impl ResultKey {
+    fn new(metadata: &RequestMetadata, options: &SolverOptions) -> Self {
+        Self {
+            dataset_hash: metadata.dataset_hash,
+            target: metadata.target,
+            lap_count: metadata.lap_count,
+            player_count: metadata.player_count,
+            timeout_ms_bits: metadata.timeout_ms.to_bits(),
+            tolerance_percent_bits: metadata.tolerance_percent.to_bits(),
+            strategy: metadata.strategy,
+            min_lap_time: metadata.min_lap_time,
+            max_lap_time: metadata.max_lap_time,
+            options: WorkerCodec::to_bytes(options),
+        }
+    }
+}
+
+/// The successful results a worker computed, so a request repeated after
+/// the main thread dropped its result is answered at once. Holds at most
+/// [`WORKER_CACHE_ENTRIES`], evicting the least recently used.
+#[derive(Default)]
+pub(crate) struct ResultCache {
+    /// Each result with the tick of its last use.
+    results: HashMap<ResultKey, (KarmaSuccess, u64)>,
+    clock: u64,
+}
+
+impl ResultCache {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// The finished message for `args` from an earlier result, if any.
+    pub(crate) fn answer(&mut self, args: &KarmaArgs) -> Option<WorkerOutput> {
+        let tick = self.tick();
+        let key = ResultKey::new(&args.metadata, &args.options);
+        let (success, last_used) = self.results.get_mut(&key)?;
+        *last_used = tick;
+        Some(WorkerOutput::Finished(Ok(KarmaSuccess {
+            metadata: args.metadata.clone(),
+            ..success.clone()
+        })))
+    }
+
+    /// Keeps the result of a successful finished message, which answered a
+    /// request with `options`.
+    pub(crate) fn remember(&mut self, options: &SolverOptions, output: &WorkerOutput) {
+        let WorkerOutput::Finished(Ok(success)) = output else {
+            return;
+        };
+        let key = ResultKey::new(&success.metadata, options);
+        if !self.results.contains_key(&key) && self.results.len() >= WORKER_CACHE_ENTRIES {
+            let oldest = self
+                .results
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.results.remove(&oldest);
+            }
+        }
+        let tick = self.tick();
+        self.results.insert(key, (success.clone(), tick));
+    }
+}
+
+/// Position of the first background calculation in `queue`, where the
+/// interactive lane ends.
+fn background_start(queue: &VecDeque<KarmaArgs>) -> usize {
+    queue
+        .iter()
+        .position(|args| args.priority == Priority::Background)
+        .unwrap_or(queue.len())
+}
+
+/// Applies `input` to the registered `datasets` and the `queue` of
+/// calculations not started yet, returning the calculation a cancel removed.
+pub(crate) fn receive(
+    datasets: &mut Datasets,
+    queue: &mut VecDeque<KarmaArgs>,
+    input: WorkerInput,
+) -> Option<KarmaArgs> {
+    match input {
+        WorkerInput::RegisterDataset { cars } => {
+            datasets.insert(dataset_hash(&cars), cars.into());
+            None
+        }
+        WorkerInput::Calculate(args) => {
+            match args.priority {
+                Priority::Interactive => queue.insert(background_start(queue), args),
+                Priority::Background => queue.push_back(args),
+            }
+            None
+        }
+        WorkerInput::Cancel { request_id } => {
+            let position = queue
+                .iter()
+                .position(|args| args.metadata.request_id == request_id)?;
+            queue.remove(position)
+        }
+    }
+}
+
+/// The datasets, queue, and cache of a karma task, and the calculation it
+/// is running.
+#[derive(Default)]
+pub(crate) struct TaskState {
+    pub(crate) datasets: Datasets,
+    pub(crate) queue: VecDeque<KarmaArgs>,
+    pub(crate) results: ResultCache,
+    /// Request id and token of the running calculation.
+    pub(crate) running: Option<(u64, CancellationToken)>,
+    /// Set once every bridge is dropped.
+    pub(crate) closed: bool,
+    /// Whether calculations are solved on a thread pool.
+    pub(crate) threaded: bool,
+}
+
+impl TaskState {
+    /// Applies `input`, returning the answers to the targets it cancelled
+    /// before they started, or to the dataset it registered. Cancelling the
+    /// running calculation also cancels its token; it answers once it stops.
+    pub(crate) fn receive(&mut self, input: WorkerInput) -> Vec<WorkerOutput> {
+        let registered = match &input {
+            WorkerInput::RegisterDataset { cars } => Some(dataset_hash(cars)),
+            WorkerInput::Cancel { request_id } => {
+                if let Some((_, token)) = self
+                    .running
+                    .as_ref()
+                    .filter(|(running, _)| running == request_id)
+                {
+                    token.cancel();
+                }
+                None
+            }
+            WorkerInput::Calculate(_) => None,
+        };
+        let mut answers = receive(&mut self.datasets, &mut self.queue, input)
+            .map(cancelled)
+            .unwrap_or_default();
+        answers.extend(registered.map(|dataset_hash| WorkerOutput::Registered {
+            dataset_hash,
+            threaded: self.threaded,
+        }));
+        answers
+    }
+
+    /// Marks the task closed, cancelling the running calculation.
+    pub(crate) fn close(&mut self) {
+        self.closed = true;
+        if let Some((_, token)) = &self.running {
+            token.cancel();
+        }
+    }
+
+    /// Answers `args` from the cache or solves it, passing its progress to
+    /// `send`. After every run, the inputs `poll` has ready are applied, so
+    /// a cancel among them stops the calculation; the answers to the
+    /// targets they cancel go to `send` too. `poll` gives `Some(None)` once
+    /// every bridge is dropped.
+    pub(crate) fn solve(
+        &mut self,
+        args: KarmaArgs,
+        mut poll: impl FnMut() -> Option<Option<WorkerInput>>,
+        mut send: impl FnMut(WorkerOutput),
+    ) -> WorkerOutput {
+        if let Some(finished) = self.results.answer(&args) {
+            return finished;
+        }
+        let cars = self.datasets.get(&args.metadata.dataset_hash).cloned();
+        let options = args.options.clone();
+        let token = CancellationToken::new();
+        self.running = Some((args.metadata.request_id, token.clone()));
+        let finished = run_task(cars.as_deref(), args, Some(token), |progress| {
+            send(progress);
+            while let Some(input) = poll() {
+                let Some(input) = input else {
+                    self.close();
+                    break;
+                };
+                for answer in self.receive(input) {
+                    send(answer);
+                }
+            }
+        });
+        self.running = None;
+        self.results.remember(&options, &finished);
+        finished
+    }
+}
+
+/// The answers to the targets of a calculation cancelled before they
+/// started.
+pub(crate) fn cancelled(args: KarmaArgs) -> Vec<WorkerOutput> {
+    args.requests()
+        .map(|metadata| {
+            WorkerOutput::Finished(Err(KarmaFailure {
+                metadata,
+                error: WorkerError::Cancelled,
+            }))
+        })
+        .collect()
+}
+
+/// Runs the calculation `args` ask for on `cars`, their registered dataset,
+/// passing its progress to `send`, and returns the finished message.
+pub(crate) fn run_task(
+    cars: Option<&[Car]>,
+    args: KarmaArgs,
+    cancellation: Option<CancellationToken>,
+    send: impl FnMut(WorkerOutput),
+) -> WorkerOutput {
+    let config = SubsetCalculationConfig {
+        cancellation,
+        ..args.config()
+    };
+    let metadata = args.metadata;
+    let Some(cars) = cars else {
+        return WorkerOutput::Finished(Err(KarmaFailure {
+            error: WorkerError::UnknownDataset {
+                dataset_hash: metadata.dataset_hash,
+            },
+            metadata,
+        }));
+    };
+    let result = calculate(cars, &metadata, &config, send);
+    WorkerOutput::Finished(result.map_err(|error| KarmaFailure {
+        metadata,
+        error: error.into(),
+    }))
+}
+
+/// Runs one calculation on `cars`, passing its progress to `send`.
+fn calculate(
+    cars: &[Car],
+    metadata: &RequestMetadata,
+    config: &SubsetCalculationConfig,
+    send: impl FnMut(WorkerOutput),
+) -> Result<KarmaSuccess, SubsetError> {
+    let mut recorder = MetricsRecorder::new();
+    let mut events = (
+        LogSink,
+        (
+            ProgressSink {
+                send,
+                elapsed_ms: 0.0,
+            },
+            &mut recorder,
+        ),
+    );
+    let sets = perform_multiple_runs_with_events(cars, config, &mut events)?;
+    let similarity = compute_jaccard_similarity(&sets).unwrap_or(0.0);
+    let weighted_similarity = compute_weighted_jaccard_similarity(cars, &sets).unwrap_or(0.0);
+    let exact_hits = exact_hit_count(cars, &sets, metadata.target);
+    let quality = quality_score(
+        cars,
+        &sets,
+        metadata.target,
+        metadata.tolerance_percent,
+        QualityWeights::default(),
+    )
+    .score;
+    Ok(KarmaSuccess {
+        metadata: metadata.clone(),
+        sets,
+        similarity,
+        weighted_similarity,
+        calculated_target: metadata.target,
+        exact_hits,
+        quality,
+        runs: recorder.snapshot().runs,
+    })
+}
+
+/// Whether the page or worker is cross-origin isolated, which the
+/// `SharedArrayBuffer` memory of a thread pool needs.
+pub fn cross_origin_isolated() -> bool {
+    js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("crossOriginIsolated"))
+        .ok()
+        .and_then(|isolated| isolated.as_bool())
+        .unwrap_or(false)
+}
 
 /// Worker reactor that processes karma calculation requests.
 #[reactor]
-pub async fn KarmaTask(mut scope: ReactorScope<KarmaArgs, KarmaResult>) {
-    while let Some(args) = scope.next().await {
-        let metadata = args.metadata.clone();
-        let res = (|| {
-            let sets = perform_multiple_runs_with_strategy(
-                metadata.strategy,
-                &args.cars,
-                metadata.target,
-                metadata.lap_count,
-                metadata.player_count,
-                metadata.timeout_ms,
-                metadata.tolerance_percent,
-            )
-            .map_err(|e| KarmaFailure {
-                metadata: metadata.clone(),
-                error: e.to_string(),
-            })?;
+pub async fn KarmaTask(scope: ReactorScope<WorkerInput, WorkerOutput>) {
+    let (mut outputs, mut inputs) = scope.split();
+    let mut state = TaskState::default();
+    // Registrations report whether the pool started, and the page spreads
+    // its targets over the workers accordingly.
+    #[cfg(feature = "wasm-threads")]
+    {
+        state.threaded = crate::worker_threads::start_thread_pool().await;
+    }
+    'tasks: loop {
+        if state.queue.is_empty() {
+            let Some(input) = inputs.next().await else {
+                break;
+            };
+            for answer in state.receive(input) {
+                if outputs.send(answer).await.is_err() {
+                    break 'tasks;
+                }
+            }
+        }
+        // Let the browser deliver the messages posted during the last
+        // calculation, so cancels among them reach the queue first.
+        TimeoutFuture::new(0).await;
+        let mut dropped = Vec::new();
+        while let Some(input) = inputs.next().now_or_never() {
+            // All bridges dropped.
+            let Some(input) = input else {
+                break 'tasks;
+            };
+            dropped.extend(state.receive(input));
+        }
+        for answer in dropped {
+            if outputs.send(answer).await.is_err() {
+                break 'tasks;
+            }
+        }
 
-            let similarity = compute_jaccard_similarity(&sets).unwrap_or(0.0);
-            Ok(KarmaSuccess {
-                metadata,
-                sets,
-                similarity,
-                calculated_target: args.metadata.target,
-            })
-        })();
+        #[cfg(feature = "wasm-threads")]
+        if state.threaded {
+            let Some(args) = state.queue.pop_front() else {
+                continue;
+            };
+            let sent =
+                crate::worker_threads::solve_on_pool(&mut state, args, &mut inputs, &mut outputs)
+                    .await;
+            if state.closed || sent.is_err() {
+                break;
+            }
+            continue;
+        }
 
+        // Batches are solved one target at a time, so a cancel arriving
+        // meanwhile drops the targets left.
+        let Some(args) = KarmaArgs::next_in(&mut state.queue) else {
+            continue;
+        };
+        // The scope's channel is unbounded, so progress is sent at once and
+        // the solver does not have to yield for it.
+        let finished = state.solve(
+            args,
+            || inputs.next().now_or_never(),
+            |output| {
+                let _ = outputs.send(output).now_or_never();
+            },
+        );
         // Abort loop if all bridges dropped.
-        if scope.send(res).await.is_err() {
+        if state.closed || outputs.send(finished).await.is_err() {
             break;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn solver_errors_are_told_apart_and_survive_serialization() {
+        let timeout = WorkerError::from(SubsetError::NotEnoughSuccessfulRuns {
+            required: 4,
+            found: 2,
+        });
+        assert!(matches!(timeout, WorkerError::Timeout(_)));
+        assert!(timeout
+            .suggestions()
+            .contains(&"Allow a longer timeout".to_string()));
+
+        let infeasible = WorkerError::from(SubsetError::ImpossibleCount {
+            requested: 5,
+            available: 3,
+        });
+        assert!(matches!(infeasible, WorkerError::Infeasible(_)));
+        assert_eq!(
+            infeasible.to_string(),
+            "Cannot select 5 unique cars from 3 cars"
+        );
+
+        for error in [
+            timeout,
+            infeasible,
+            WorkerError::Deserialization("closed".into()),
+            WorkerError::from(SubsetError::Cancelled),
+            WorkerError::UnknownDataset { dataset_hash: 7 },
+        ] {
+            let json = serde_json::to_string(&error).unwrap();
+            assert_eq!(serde_json::from_str::<WorkerError>(&json).unwrap(), error);
+        }
+    }
+
+    #[test]
+    fn datasets_are_registered_and_cancels_drop_queued_requests_only() {
+        let cars = vec![Car::new("a", 100_000)];
+        let mut datasets = HashMap::new();
+        let mut queue = VecDeque::new();
+        let registered = WorkerInput::RegisterDataset { cars: cars.clone() };
+        assert!(receive(&mut datasets, &mut queue, registered).is_none());
+        assert_eq!(datasets[&dataset_hash(&cars)][..], cars[..]);
+        assert!(queue.is_empty());
+
+        let args = |request_id| {
+            KarmaArgs::new(RequestMetadata {
+                request_id,
+                dataset_generation: 0,
+                dataset_hash: 0,
+                target: 300_000,
+                lap_count: 3,
+                player_count: 2,
+                timeout_ms: 1_000.0,
+                tolerance_percent: 1.0,
+                strategy: crate::DEFAULT_SOLVER_STRATEGY,
+                min_lap_time: None,
+                max_lap_time: None,
+            })
+        };
+        for request_id in 1..=3 {
+            let input = WorkerInput::Calculate(args(request_id));
+            assert!(receive(&mut datasets, &mut queue, input).is_none());
+        }
+
+        let cancel = |request_id| WorkerInput::Cancel { request_id };
+        let cancelled = receive(&mut datasets, &mut queue, cancel(2)).unwrap();
+        assert_eq!(cancelled.metadata.request_id, 2);
+        assert!(receive(&mut datasets, &mut queue, cancel(2)).is_none());
+        assert!(receive(&mut datasets, &mut queue, cancel(9)).is_none());
+        let remaining = queue
+            .iter()
+            .map(|args| args.metadata.request_id)
+            .collect::<Vec<_>>();
+        assert_eq!(remaining, [1, 3]);
+
+        let batch = KarmaArgs {
+            batch_targets: vec![300_010, 300_020],
+            options: SolverOptions {
+                seed: Some(7),
+                ..SolverOptions::default()
+            },
+            ..args(4)
+        };
+        let targets = |args: &KarmaArgs| {
+            args.requests()
+                .map(|metadata| (metadata.request_id, metadata.target))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(targets(&batch), [(4, 300_000), (4, 300_010), (4, 300_020)]);
+        let mut queue = VecDeque::from([batch, args(5)]);
+        let next = KarmaArgs::next_in(&mut queue).unwrap();
+        assert_eq!(targets(&next), [(4, 300_000)]);
+        assert_eq!(next.config().seed, Some(7));
+        let cancelled = receive(&mut datasets, &mut queue, cancel(4)).unwrap();
+        assert_eq!(targets(&cancelled), [(4, 300_010), (4, 300_020)]);
+        assert_eq!(cancelled.options.seed, Some(7));
+        assert_eq!(super::cancelled(cancelled).len(), 2);
+        assert_eq!(
+            targets(&KarmaArgs::next_in(&mut queue).unwrap()),
+            [(5, 300_000)]
+        );
+        assert!(KarmaArgs::next_in(&mut queue).is_none());
+    }
+
+    #[test]
+    fn interactive_calculations_overtake_background_batches() {
+        let args = |request_id, priority| KarmaArgs {
+            priority,
+            ..KarmaArgs::new(RequestMetadata {
+                request_id,
+                dataset_generation: 0,
+                dataset_hash: 0,
+                target: 300_000,
+                lap_count: 3,
+                player_count: 2,
+                timeout_ms: 1_000.0,
+                tolerance_percent: 1.0,
+                strategy: crate::DEFAULT_SOLVER_STRATEGY,
+                min_lap_time: None,
+                max_lap_time: None,
+            })
+        };
+        let mut datasets = HashMap::new();
+        let mut queue = VecDeque::new();
+        let mut send = |args| {
+            assert!(receive(&mut datasets, &mut queue, WorkerInput::Calculate(args)).is_none());
+        };
+        send(KarmaArgs {
+            batch_targets: vec![300_010],
+            ..args(1, Priority::Background)
+        });
+        send(args(2, Priority::Background));
+        send(args(3, Priority::Interactive));
+        send(args(4, Priority::Interactive));
+        let mut started = Vec::new();
+        while let Some(args) = KarmaArgs::next_in(&mut queue) {
+            started.push((args.metadata.request_id, args.metadata.target));
+            if started.len() == 3 {
+                let input = WorkerInput::Calculate(KarmaArgs::new(RequestMetadata {
+                    request_id: 5,
+                    ..args.metadata
+                }));
+                assert!(receive(&mut datasets, &mut queue, input).is_none());
+            }
+        }
+        assert_eq!(
+            started,
+            [
+                (3, 300_000),
+                (4, 300_000),
+                (1, 300_000),
+                (5, 300_000),
+                (1, 300_010),
+                (2, 300_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_requests_are_answered_from_the_result_cache() {
+        let args = |request_id, target| {
+            KarmaArgs::new(RequestMetadata {
+                request_id,
+                dataset_generation: 0,
+                dataset_hash: 7,
+                target,
+                lap_count: 3,
+                player_count: 2,
+                timeout_ms: 1_000.0,
+                tolerance_percent: 1.0,
+                strategy: crate::DEFAULT_SOLVER_STRATEGY,
+                min_lap_time: None,
+                max_lap_time: None,
+            })
+        };
+        let finished = |args: &KarmaArgs| {
+            WorkerOutput::Finished(Ok(KarmaSuccess {
+                metadata: args.metadata.clone(),
+                sets: vec![vec![0, 1, 2]],
+                similarity: 0.5,
+                weighted_similarity: 0.5,
+                calculated_target: args.metadata.target,
+                exact_hits: 1,
+                quality: 0.9,
+                runs: Vec::new(),
+            }))
+        };
+        let no_options = SolverOptions::default();
+        let mut results = ResultCache::default();
+        assert!(results.answer(&args(1, 300_000)).is_none());
+        results.remember(&no_options, &finished(&args(1, 300_000)));
+
+        // A later request for the same settings is answered under its own id.
+        match results.answer(&args(2, 300_000)) {
+            Some(WorkerOutput::Finished(Ok(success))) => {
+                assert_eq!(success.metadata.request_id, 2);
+                assert_eq!(success.sets, [vec![0, 1, 2]]);
+            }
+            _ => panic!("expected a cached result"),
+        }
+        let mut wider = args(3, 300_000);
+        wider.metadata.tolerance_percent = 2.0;
+        assert!(results.answer(&wider).is_none());
+        let mut seeded = args(3, 300_000);
+        seeded.options.seed = Some(7);
+        assert!(results.answer(&seeded).is_none());
+
+        // Failures are solved again.
+        results.remember(
+            &no_options,
+            &WorkerOutput::Finished(Err(KarmaFailure {
+                metadata: wider.metadata.clone(),
+                error: WorkerError::Cancelled,
+            })),
+        );
+        assert!(results.answer(&wider).is_none());
+
+        for target in 1..WORKER_CACHE_ENTRIES as u32 {
+            results.remember(&no_options, &finished(&args(4, target)));
+        }
+        assert!(results.answer(&args(5, 300_000)).is_some());
+        results.remember(&no_options, &finished(&args(4, 0)));
+        assert!(results.answer(&args(5, 1)).is_none());
+        assert!(results.answer(&args(5, 300_000)).is_some());
+    }
+
+    #[test]
+    fn options_reach_the_solver() {
+        let cars = (0..9)
+            .map(|index| Car::new(format!("car-{index}"), 100_000 + 2 * index))
+            .collect::<Vec<_>>();
+        let mut args = KarmaArgs::new(RequestMetadata {
+            request_id: 1,
+            dataset_generation: 0,
+            dataset_hash: dataset_hash(&cars),
+            target: 300_000,
+            lap_count: 3,
+            player_count: 3,
+            timeout_ms: 60_000.0,
+            tolerance_percent: 1.0,
+            strategy: crate::DEFAULT_SOLVER_STRATEGY,
+            min_lap_time: None,
+            max_lap_time: None,
+        });
+        args.options.seed = Some(7);
+        args.options.disjoint = true;
+        let solve = |args: &KarmaArgs| match run_task(Some(&cars), args.clone(), None, |_| {}) {
+            WorkerOutput::Finished(Ok(success)) => success,
+            _ => panic!("expected a result"),
+        };
+        let seeded = solve(&args);
+        assert_eq!(solve(&args).sets, seeded.sets);
+        assert_eq!(
+            seeded.runs.iter().map(|run| run.run).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        let mut used = seeded.sets.concat();
+        used.sort_unstable();
+        used.dedup();
+        assert_eq!(used.len(), 9);
+
+        // More cars than the pool holds cannot be disjoint.
+        args.metadata.player_count = 4;
+        assert!(matches!(
+            run_task(Some(&cars), args, None, |_| {}),
+            WorkerOutput::Finished(Err(KarmaFailure {
+                error: WorkerError::Infeasible(SubsetError::DisjointInfeasible { .. }),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn cancels_received_between_runs_stop_the_running_task() {
+        let cars = (0..9)
+            .map(|index| Car::new(format!("car-{index}"), 100_000 + 2 * index))
+            .collect::<Vec<_>>();
+        let args = |request_id| KarmaArgs {
+            batch_targets: vec![300_010],
+            ..KarmaArgs::new(RequestMetadata {
+                request_id,
+                dataset_generation: 0,
+                dataset_hash: dataset_hash(&cars),
+                target: 300_000,
+                lap_count: 3,
+                player_count: 3,
+                timeout_ms: 60_000.0,
+                tolerance_percent: 1.0,
+                strategy: crate::DEFAULT_SOLVER_STRATEGY,
+                min_lap_time: None,
+                max_lap_time: None,
+            })
+        };
+        let mut state = TaskState::default();
+        state.receive(WorkerInput::RegisterDataset { cars: cars.clone() });
+        state.receive(WorkerInput::Calculate(args(1)));
+        let running = KarmaArgs::next_in(&mut state.queue).unwrap();
+
+        // The cancel arrives while the first run is being solved.
+        let mut inputs = VecDeque::from([WorkerInput::Cancel { request_id: 1 }]);
+        let mut sent = Vec::new();
+        let started = Instant::now();
+        let finished = state.solve(
+            running,
+            || inputs.pop_front().map(Some),
+            |output| sent.push(output),
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(matches!(
+            finished,
+            WorkerOutput::Finished(Err(KarmaFailure {
+                error: WorkerError::Cancelled,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            sent[..],
+            [
+                WorkerOutput::Progress {
+                    run: 1,
+                    total: 3,
+                    ..
+                },
+                WorkerOutput::Finished(Err(KarmaFailure {
+                    metadata: RequestMetadata {
+                        target: 300_010,
+                        ..
+                    },
+                    error: WorkerError::Cancelled,
+                })),
+            ]
+        ));
+        assert!(state.running.is_none() && state.queue.is_empty());
+
+        // Dropping every bridge stops it too, and closes the task.
+        state.receive(WorkerInput::Calculate(args(2)));
+        let running = KarmaArgs::next_in(&mut state.queue).unwrap();
+        let mut closed = false;
+        let finished = state.solve(
+            running,
+            || (!std::mem::replace(&mut closed, true)).then_some(None),
+            |_| {},
+        );
+        assert!(matches!(
+            finished,
+            WorkerOutput::Finished(Err(KarmaFailure {
+                error: WorkerError::Cancelled,
+                ..
+            }))
+        ));
+        assert!(state.closed);
+
+        // A calculation nobody cancels is solved and cached.
+        let mut state = TaskState {
+            datasets: state.datasets,
+            ..TaskState::default()
+        };
+        let finished = state.solve(args(3), || None, |_| {});
+        assert!(matches!(finished, WorkerOutput::Finished(Ok(_))));
+        assert!(state.results.answer(&args(4)).is_some());
+    }
+
+    #[test]
+    fn codec_round_trips_messages_more_compactly_than_json() {
+        let as_json = |output: &WorkerOutput| serde_json::to_value(output).unwrap();
+        let cars = (0..100)
+            .map(|index| Car {
+                class: Some("GT3".to_string()),
+                enabled: index % 10 != 0,
+                ..Car::new(format!("car-{index}"), 100_000 + index)
+            })
+            .collect::<Vec<_>>();
+        let register = WorkerInput::RegisterDataset { cars: cars.clone() };
+        let bytes = WorkerCodec::to_bytes(&register);
+        assert!(bytes.len() * 3 < serde_json::to_vec(&register).unwrap().len() * 2);
+        match WorkerCodec::from_bytes::<WorkerInput>(&bytes).unwrap() {
+            WorkerInput::RegisterDataset { cars: decoded } => assert_eq!(decoded, cars),
+            _ => panic!("decoded another message"),
+        }
+
+        let metadata = RequestMetadata {
+            request_id: 3,
+            dataset_generation: 1,
+            dataset_hash: dataset_hash(&cars),
+            target: 300_000,
+            lap_count: 3,
+            player_count: 2,
+            timeout_ms: 1_000.0,
+            tolerance_percent: 1.0,
+            strategy: crate::DEFAULT_SOLVER_STRATEGY,
+            min_lap_time: None,
+            max_lap_time: Some(200_000),
+        };
+        // A result as the solver answers it, with the report of every run.
+        let mut args = KarmaArgs::new(RequestMetadata {
+            player_count: 8,
+            timeout_ms: 60_000.0,
+            ..metadata.clone()
+        });
+        args.options.seed = Some(3);
+        let success = run_task(Some(&cars), args, None, |_| {});
+        assert!(matches!(&success, WorkerOutput::Finished(Ok(success)) if success.runs.len() == 8));
+        let bytes = WorkerCodec::to_bytes(&success);
+        assert!(bytes.len() * 2 < serde_json::to_vec(&success).unwrap().len());
+        let decoded = WorkerCodec::from_bytes::<WorkerOutput>(&bytes).unwrap();
+        assert_eq!(as_json(&decoded), as_json(&success));
+
+        for error in [
+            WorkerError::Cancelled,
+            WorkerError::from(SubsetError::NoValidSubset),
+            WorkerError::from(SubsetError::SharedCarsInfeasible {
+                player_count: 3,
+                lap_count: 3,
+                max_shared: 1,
+                required: 6,
+                cars: 4,
+            }),
+            WorkerError::from(SubsetError::AttemptPanicked {
+                message: "index out of bounds".to_string(),
+            }),
+            WorkerError::UnknownDataset { dataset_hash: 7 },
+        ] {
+            let failure = WorkerOutput::Finished(Err(KarmaFailure {
+                metadata: metadata.clone(),
+                error: error.clone(),
+            }));
+            match WorkerCodec::from_bytes::<WorkerOutput>(&WorkerCodec::to_bytes(&failure)) {
+                Ok(WorkerOutput::Finished(Err(decoded))) => assert_eq!(decoded.error, error),
+                other => panic!("{error:?} did not round-trip: {:?}", other.err()),
+            }
+            // Human-readable formats keep the tagged form.
+            assert_eq!(
+                as_json(&failure)["Finished"]["Err"]["error"],
+                serde_json::to_value(&error).unwrap()
+            );
+        }
+        let progress = WorkerOutput::Progress {
+            run: 2,
+            total: 8,
+            elapsed_ms: 12.5,
+        };
+        let decoded = WorkerCodec::from_bytes(&WorkerCodec::to_bytes(&progress)).unwrap();
+        assert_eq!(as_json(&decoded), as_json(&progress));
+        assert!(WorkerCodec::from_bytes::<WorkerOutput>(&[0xff]).is_err());
+    }
+}
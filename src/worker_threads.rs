@@ -0,0 +1,316 @@
+//! Solving the targets of a request together on a thread pool.
+//!
+//! With the `wasm-threads` feature, a [`KarmaTask`] on a cross-origin
+//! isolated page starts a rayon pool on `SharedArrayBuffer` memory and
+//! solves every target of a calculation on it at once, one
+//! [`perform_multiple_runs`](crate::perform_multiple_runs) per thread. The
+//! worker itself only waits meanwhile, so it goes on receiving messages and
+//! a [`WorkerInput::Cancel`] stops the targets through their shared
+//! [`CancellationToken`]. Pages that are not isolated keep one solve per
+//! worker and spread the targets over several workers instead.
+//!
+//! [`KarmaTask`]: crate::worker_agent::KarmaTask
+//! [`WorkerInput::Cancel`]: crate::worker_agent::WorkerInput::Cancel
+
+use crate::worker_agent::{
+    cancelled, run_task, KarmaArgs, RequestMetadata, SolverOptions, TaskState, WorkerInput,
+    WorkerOutput,
+};
+use crate::{CancellationToken, Car};
+use futures::channel::{mpsc, oneshot};
+use futures::{FutureExt, Sink, SinkExt, Stream, StreamExt};
+use gloo_timers::future::TimeoutFuture;
+use rayon::prelude::*;
+use std::sync::Arc;
+
+/// How often the worker checks on the pool while it solves.
+const POOL_POLL_MS: u32 = 10;
+
+/// Starts the worker's thread pool with a thread per logical core, if the
+/// page is cross-origin isolated; returns whether the pool runs.
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn start_thread_pool() -> bool {
+    use wasm_bindgen::JsValue;
+    if !crate::worker_agent::cross_origin_isolated() {
+        return false;
+    }
+    let threads = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("navigator"))
+        .and_then(|navigator| {
+            js_sys::Reflect::get(&navigator, &JsValue::from_str("hardwareConcurrency"))
+        })
+        .ok()
+        .and_then(|cores| cores.as_f64())
+        .map_or(1, |cores| cores.max(1.0) as usize);
+    wasm_bindgen_futures::JsFuture::from(wasm_bindgen_rayon::init_thread_pool(threads))
+        .await
+        .is_ok()
+}
+
+/// Native threads need no setup; rayon starts its global pool itself.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn start_thread_pool() -> bool {
+    true
+}
+
+/// The targets of a calculation taken off the queue at once.
+pub(crate) struct ParallelBatch {
+    /// The answer of every target in order, from the cache, or none for the
+    /// targets in `unsolved`.
+    cached: Vec<Option<WorkerOutput>>,
+    /// The targets to solve, each on its own.
+    pub(crate) unsolved: Vec<KarmaArgs>,
+    /// The requests of `unsolved`, answered as cancelled should the pool
+    /// not answer them.
+    pending: Vec<RequestMetadata>,
+    pub(crate) cars: Option<Arc<[Car]>>,
+    pub(crate) token: CancellationToken,
+    options: SolverOptions,
+}
+
+impl TaskState {
+    /// Marks `args` running, answering the targets already cached.
+    pub(crate) fn start_batch(&mut self, args: KarmaArgs) -> ParallelBatch {
+        let token = CancellationToken::new();
+        self.running = Some((args.metadata.request_id, token.clone()));
+        let targets = args
+            .requests()
+            .map(|metadata| KarmaArgs {
+                metadata,
+                batch_targets: Vec::new(),
+                ..args.clone()
+            })
+            .collect::<Vec<_>>();
+        let cached = targets
+            .iter()
+            .map(|target| self.results.answer(target))
+            .collect::<Vec<_>>();
+        let unsolved = targets
+            .into_iter()
+            .zip(&cached)
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(target, _)| target)
+            .collect::<Vec<_>>();
+        ParallelBatch {
+            cached,
+            pending: unsolved
+                .iter()
+                .map(|target| target.metadata.clone())
+                .collect(),
+            unsolved,
+            cars: self.datasets.get(&args.metadata.dataset_hash).cloned(),
+            token,
+            options: args.options,
+        }
+    }
+
+    /// The answers to every target of `batch` in order, given those the
+    /// pool `solved`, which are cached.
+    pub(crate) fn finish_batch(
+        &mut self,
+        batch: ParallelBatch,
+        solved: Vec<WorkerOutput>,
+    ) -> Vec<WorkerOutput> {
+        self.running = None;
+        let mut solved = solved.into_iter();
+        let mut pending = batch.pending.into_iter();
+        batch
+            .cached
+            .into_iter()
+            .map(|cached| {
+                cached.unwrap_or_else(|| {
+                    let metadata = pending.next().expect("every uncached target is pending");
+                    let finished = solved
+                        .next()
+                        .unwrap_or_else(|| cancelled(KarmaArgs::new(metadata)).remove(0));
+                    self.results.remember(&batch.options, &finished);
+                    finished
+                })
+            })
+            .collect()
+    }
+}
+
+/// Solves `targets` on `cars` at once on the rayon pool, all stopped by
+/// `token`, passing their progress to `send`. Answers in the order of
+/// `targets`.
+pub(crate) fn solve_in_parallel(
+    cars: Option<&[Car]>,
+    targets: Vec<KarmaArgs>,
+    token: &CancellationToken,
+    send: impl Fn(WorkerOutput) + Sync,
+) -> Vec<WorkerOutput> {
+    targets
+        .into_par_iter()
+        .map(|target| run_task(cars, target, Some(token.clone()), &send))
+        .collect()
+}
+
+/// Solves `args` on the pool and sends its answers to `outputs`, applying
+/// the `inputs` received meanwhile. Fails once `outputs` closes.
+pub(crate) async fn solve_on_pool<I, O>(
+    state: &mut TaskState,
+    args: KarmaArgs,
+    inputs: &mut I,
+    outputs: &mut O,
+) -> Result<(), O::Error>
+where
+    I: Stream<Item = WorkerInput> + Unpin,
+    O: Sink<WorkerOutput> + Unpin,
+{
+    let mut batch = state.start_batch(args);
+    let (done, mut solved) = oneshot::channel();
+    let (progress, mut progressed) = mpsc::unbounded();
+    let unsolved = std::mem::take(&mut batch.unsolved);
+    let cars = batch.cars.clone();
+    let token = batch.token.clone();
+    rayon::spawn(move || {
+        let answers = solve_in_parallel(cars.as_deref(), unsolved, &token, |output| {
+            let _ = progress.unbounded_send(output);
+        });
+        let _ = done.send(answers);
+    });
+
+    let solved = loop {
+        // Lets the browser deliver the messages posted meanwhile.
+        TimeoutFuture::new(POOL_POLL_MS).await;
+        while let Ok(output) = progressed.try_recv() {
+            outputs.send(output).await?;
+        }
+        while let Some(input) = inputs.next().now_or_never() {
+            // All bridges dropped.
+            let Some(input) = input else {
+                state.close();
+                break;
+            };
+            for answer in state.receive(input) {
+                outputs.send(answer).await?;
+            }
+        }
+        match solved.try_recv() {
+            Ok(Some(solved)) => break solved,
+            Ok(None) => {}
+            // The pool dropped the batch.
+            Err(_) => break Vec::new(),
+        }
+    };
+    // Progress sent after the last check still precedes the answers.
+    while let Ok(output) = progressed.try_recv() {
+        outputs.send(output).await?;
+    }
+    for answer in state.finish_batch(batch, solved) {
+        outputs.send(answer).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset_hash;
+    use crate::worker_agent::{KarmaFailure, WorkerError};
+    use std::sync::Mutex;
+
+    fn cars() -> Vec<Car> {
+        (0..9)
+            .map(|index| Car::new(format!("car-{index}"), 100_000 + 2 * index))
+            .collect()
+    }
+
+    fn args(cars: &[Car], targets: &[u32]) -> KarmaArgs {
+        KarmaArgs {
+            batch_targets: targets[1..].to_vec(),
+            ..KarmaArgs::new(RequestMetadata {
+                request_id: 1,
+                dataset_generation: 0,
+                dataset_hash: dataset_hash(cars),
+                target: targets[0],
+                lap_count: 3,
+                player_count: 3,
+                timeout_ms: 60_000.0,
+                tolerance_percent: 1.0,
+                strategy: crate::DEFAULT_SOLVER_STRATEGY,
+                min_lap_time: None,
+                max_lap_time: None,
+            })
+        }
+    }
+
+    fn target(output: &WorkerOutput) -> Option<Result<u32, WorkerError>> {
+        match output {
+            WorkerOutput::Registered { .. } | WorkerOutput::Progress { .. } => None,
+            WorkerOutput::Finished(Ok(success)) => Some(Ok(success.metadata.target)),
+            WorkerOutput::Finished(Err(KarmaFailure { error, .. })) => Some(Err(error.clone())),
+        }
+    }
+
+    #[test]
+    fn batches_are_solved_together_and_answered_in_order() {
+        let cars = cars();
+        let mut state = TaskState {
+            threaded: true,
+            ..TaskState::default()
+        };
+        // The page learns from the registration that the pool runs.
+        assert!(matches!(
+            state.receive(WorkerInput::RegisterDataset { cars: cars.clone() })[..],
+            [WorkerOutput::Registered { threaded: true, .. }]
+        ));
+        let solve = |state: &mut TaskState, targets: &[u32]| {
+            let mut batch = state.start_batch(args(&cars, targets));
+            let unsolved = std::mem::take(&mut batch.unsolved);
+            let progress = Mutex::new(0);
+            let solved = solve_in_parallel(
+                batch.cars.as_deref(),
+                unsolved,
+                &batch.token.clone(),
+                |_| {
+                    *progress.lock().unwrap() += 1;
+                },
+            );
+            let answers = state.finish_batch(batch, solved);
+            (answers, progress.into_inner().unwrap())
+        };
+
+        let (answers, progress) = solve(&mut state, &[300_000, 300_010]);
+        assert_eq!(
+            answers.iter().map(target).collect::<Vec<_>>(),
+            [Some(Ok(300_000)), Some(Ok(300_010))]
+        );
+        assert_eq!(progress, 6);
+        assert!(state.running.is_none());
+
+        // Cached targets are answered without solving them again.
+        let (answers, progress) = solve(&mut state, &[300_010, 300_020, 300_000]);
+        assert_eq!(
+            answers.iter().map(target).collect::<Vec<_>>(),
+            [Some(Ok(300_010)), Some(Ok(300_020)), Some(Ok(300_000))]
+        );
+        assert_eq!(progress, 3);
+    }
+
+    #[test]
+    fn cancels_reach_every_target_on_the_pool() {
+        let cars = cars();
+        let mut state = TaskState::default();
+        state.receive(WorkerInput::RegisterDataset { cars: cars.clone() });
+        let mut batch = state.start_batch(args(&cars, &[300_000, 300_010, 300_020]));
+        assert!(state
+            .receive(WorkerInput::Cancel { request_id: 1 })
+            .is_empty());
+        assert!(batch.token.is_cancelled());
+        let unsolved = std::mem::take(&mut batch.unsolved);
+        let solved = solve_in_parallel(batch.cars.as_deref(), unsolved, &batch.token, |_| {});
+        let answers = state.finish_batch(batch, solved);
+        assert!(answers
+            .iter()
+            .all(|answer| target(answer) == Some(Err(WorkerError::Cancelled))));
+
+        // Targets the pool drops are answered as cancelled too.
+        let batch = state.start_batch(args(&cars, &[300_030]));
+        let answers = state.finish_batch(batch, Vec::new());
+        assert_eq!(
+            answers.iter().map(target).collect::<Vec<_>>(),
+            [Some(Err(WorkerError::Cancelled))]
+        );
+    }
+}
@@ -45,6 +45,179 @@ test('applies parameter changes and reaches a completed calculation state', asyn
   await expect(page.locator('.big-car-table th')).toContainText(['Set #', 'Total Time', '% Off Target', 'Car 1', 'Car 2', 'Car 3']);
 });
 
+test('restores parameters from a shared link and keeps the address current', async ({ page }) => {
+  await page.goto('./?laps=3&players=2&tolerance=1&timeout=2&seed=42');
+  await disablePrecaching(page);
+
+  await expect(page.getByLabel('Lap Count:')).toHaveValue('3');
+  await expect(page.getByLabel('Player Count:')).toHaveValue('2');
+  await expect(page.getByLabel('Calculation Timeout (seconds):')).toHaveValue('2');
+  await expect(page.getByLabel('Tolerance Threshold (%):')).toHaveValue('1');
+  await expect(page.getByLabel('Seed:')).toHaveValue('42');
+
+  await page.getByLabel('Player Count:').fill('4');
+  await page.getByLabel('Player Count:').press('Enter');
+  await expect(page).toHaveURL(/players=4/);
+  await expect(page).toHaveURL(/seed=42/);
+});
+
+test('names result rows after a pasted roster', async ({ page }) => {
+  await page.goto('./?laps=3&players=2');
+  await disablePrecaching(page);
+
+  await page.getByLabel('Player Names (one per line):').fill('Ana\nBo');
+  await expect(page.locator('.big-car-table th').first()).toHaveText('Player', { timeout: 15_000 });
+  await expect(page.locator('.big-car-table td.sticky-col')).toHaveText(['Ana', 'Bo']);
+});
+
+test('draws subsets for the roster and reveals them in turn', async ({ page }) => {
+  await page.goto('./?laps=3&players=2&seed=5');
+  await disablePrecaching(page);
+
+  await page.getByLabel('Player Names (one per line):').fill('Ana\nBo');
+  await page.getByRole('button', { name: 'Draw Subsets' }).click({ timeout: 15_000 });
+  await expect(page.locator('.draw-seed')).toHaveText('Seed 5');
+  await page.getByRole('button', { name: 'Reveal Next' }).click();
+  await expect(page.locator('.draw-reveals li')).toHaveCount(1);
+  await expect(page.locator('.draw-reveals li').first()).toContainText('Ana');
+  await page.getByRole('button', { name: 'Reveal Next' }).click();
+  await expect(page.getByRole('button', { name: 'Reveal Next' })).toBeDisabled();
+});
+
+test('re-rolls one selection and keeps the others', async ({ page }) => {
+  await page.goto('./?laps=3&players=3');
+  await disablePrecaching(page);
+
+  await expect(page.locator('.results-count')).toHaveText('3 selections', { timeout: 15_000 });
+  const rows = page.locator('.big-car-table tbody tr:not(.table-spacer)');
+  const kept = await rows.nth(1).innerText();
+  await page.getByRole('button', { name: 'Re-roll selection 1' }).click();
+  await expect(page.locator('.copy-feedback')).toHaveText(/re-roll/i);
+  await expect(rows.nth(1)).toHaveText(kept);
+  await expect(page.locator('.results-count')).toHaveText('3 selections');
+});
+
+test('recomputes the unlocked selections only', async ({ page }) => {
+  await page.goto('./?laps=3&players=3');
+  await disablePrecaching(page);
+
+  await expect(page.locator('.results-count')).toHaveText('3 selections', { timeout: 15_000 });
+  const rows = page.locator('.big-car-table tbody tr:not(.table-spacer)');
+  await page.getByRole('button', { name: 'Lock selection 1' }).click();
+  await page.getByRole('button', { name: 'Lock selection 2' }).click();
+  const first = await rows.nth(0).innerText();
+  const second = await rows.nth(1).innerText();
+  await expect(page.getByRole('button', { name: 'Re-roll selection 1' })).toBeDisabled();
+  await page.getByRole('button', { name: 'Recompute 1 Unlocked' }).click();
+  await expect(page.locator('.copy-feedback')).toHaveText(/recompute/i);
+  await expect(rows.nth(0)).toHaveText(first);
+  await expect(rows.nth(1)).toHaveText(second);
+});
+
+test('finds the selections containing a car', async ({ page }) => {
+  await page.goto('./?laps=3&players=3');
+  await disablePrecaching(page);
+
+  await expect(page.locator('.results-count')).toHaveText('3 selections', { timeout: 15_000 });
+  const title = await page.locator('.big-car-table tbody td[title]').first().getAttribute('title');
+  const id = title!.split(/ · |\n/)[0];
+  await page.getByLabel('Find selections with a car').fill(id);
+  await expect(page.locator('.big-car-table td.car-match').first()).toBeVisible();
+  await expect(page.locator('.filter-count')).toHaveText(/^[1-3] of 3 selections$/);
+  await page.getByLabel('Only matching rows').check();
+  const shown = await page.locator('.big-car-table tbody tr:not(.table-spacer)').count();
+  await expect(page.locator('.filter-count')).toHaveText(`${shown} of 3 selections`);
+});
+
+test('marks cars shared between selections', async ({ page }) => {
+  await page.goto('./?laps=3&players=6');
+  await disablePrecaching(page);
+
+  await expect(page.locator('.results-count')).toHaveText('6 selections', { timeout: 15_000 });
+  const shared = page.locator('.big-car-table td.shared-car');
+  if ((await shared.count()) > 0) {
+    await expect(shared.first().locator('.share-badge')).toHaveText(/^×\d+$/);
+    await expect(shared.first()).toHaveAttribute('title', /Also in \d+: /);
+  }
+});
+
+test('opens the car usage heatmap', async ({ page }) => {
+  await page.goto('./?laps=3&players=3');
+  await disablePrecaching(page);
+
+  await expect(page.locator('.results-count')).toHaveText('3 selections', { timeout: 15_000 });
+  await page.locator('.car-usage summary').click();
+  await expect(page.locator('.usage-cell').first()).toBeVisible();
+  await expect(page.locator('.usage-cell').first()).toHaveAttribute('title', /in \d+ of 3 selections/);
+});
+
+test('sets the background worker count', async ({ page }) => {
+  await page.goto('./');
+  await openSettings(page);
+
+  const slider = page.getByRole('slider', { name: /Background Workers/ });
+  await slider.fill('1');
+  await expect(page.getByText('Background Workers: 1', { exact: true })).toBeVisible();
+  await page.reload();
+  await openSettings(page);
+  await expect(slider).toHaveValue('1');
+  await page.getByRole('button', { name: /^Auto/ }).click();
+  await expect(page.getByText(/Background Workers: \d+ \(auto\)/)).toBeVisible();
+});
+
+test('pauses and resumes pre-caching', async ({ page }) => {
+  await page.goto('./');
+  await openSettings(page);
+
+  const panel = page.locator('.precache-panel');
+  await expect(panel).toContainText('done');
+  await panel.getByRole('button', { name: 'Pause' }).click();
+  await expect(panel).toContainText('Paused');
+  await panel.getByRole('button', { name: 'Resume' }).click();
+  await expect(panel).not.toContainText('Paused');
+});
+
+test('shows calculation progress and cancels it', async ({ page }) => {
+  await page.goto('./?laps=25&players=250&timeout=30');
+  await disablePrecaching(page);
+
+  const indicator = page.locator('.loading-indicator');
+  await expect(indicator).toContainText('s elapsed');
+  await indicator.getByRole('button', { name: 'Cancel' }).click();
+  await expect(indicator).toHaveCount(0);
+  await expect(page.locator('.error-message')).toHaveText('The calculation was cancelled');
+});
+
+test('charts every selection against the tolerance band', async ({ page }) => {
+  await page.goto('./?laps=3&players=3');
+  await disablePrecaching(page);
+
+  await expect(page.locator('.results-count')).toHaveText('3 selections', { timeout: 15_000 });
+  await expect(page.locator('.accuracy-bar')).toHaveCount(3);
+  await expect(page.locator('.accuracy-worst')).toContainText('furthest off:');
+  await expect(page.locator('.accuracy-note')).toContainText('of 3 outside');
+});
+
+test('compares selected lap times with the pool', async ({ page }) => {
+  await page.goto('./?laps=3&players=3');
+  await disablePrecaching(page);
+
+  await expect(page.locator('.results-count')).toHaveText('3 selections', { timeout: 15_000 });
+  await page.locator('.lap-histogram summary').click();
+  await expect(page.locator('.histogram-bin')).toHaveCount(12);
+  await expect(page.locator('.legend-selected')).toHaveText('Selected (9 picks)');
+});
+
+test('downloads results with their parameters', async ({ page }) => {
+  await page.goto('./?laps=3&players=2');
+  await disablePrecaching(page);
+
+  await expect(page.locator('.results-count')).toHaveText('2 selections', { timeout: 15_000 });
+  const download = page.waitForEvent('download');
+  await page.getByRole('button', { name: 'Download JSON' }).click();
+  expect((await download).suggestedFilename()).toMatch(/^random-karma-[0-9a-f]{8}-3-laps-2-players-\d+ms\.json$/);
+});
+
 test('opens settings, changes a solver setting, and clears the cache', async ({ page }) => {
   await page.goto('./');
   await openSettings(page);
@@ -88,7 +261,129 @@ test('replaces the dataset from mocked clipboard text', async ({ page, context }
 
   await page.getByRole('button', { name: 'Paste Car Data from Clipboard' }).click();
   await expect(page.locator('.clipboard-feedback')).toHaveText(
-    'Successfully loaded 3 cars from clipboard.',
+    'Successfully loaded 3 cars from clipboard (detected: comma-separated, data starts at line 1).',
   );
   await expect(page.locator('.slider-info')).toHaveText('Max: 3');
 });
+
+test('edits the car pool in place', async ({ page }) => {
+  await page.goto('./');
+  await disablePrecaching(page);
+
+  const editor = page.locator('.car-pool-editor');
+  await editor.locator('summary').click();
+  const rows = editor.locator('tbody tr');
+  const loaded = await rows.count();
+  await expect(page.locator('.slider-info')).toHaveText(`Max: ${loaded}`);
+
+  await page.getByRole('button', { name: 'Remove Hauler Custom' }).click();
+  await expect(rows).toHaveCount(loaded - 1);
+  await expect(page.locator('.slider-info')).toHaveText(`Max: ${loaded - 1}`);
+
+  await page.getByRole('button', { name: 'Disable All' }).click();
+  await expect(editor.locator('summary')).toHaveText(`Car pool · 0 of ${loaded - 1} cars in use`);
+  await page.getByRole('checkbox', { name: 'Use Phantom Custom' }).check();
+  await page.getByRole('button', { name: 'Remove Disabled' }).click();
+  await expect(editor.locator('summary')).toHaveText('Car pool · 1 of 1 cars in use');
+
+  const lapTime = page.getByLabel('Lap time of Phantom Custom');
+  await lapTime.fill('not a time');
+  await lapTime.blur();
+  await expect(editor.locator('.input-error')).toBeVisible();
+});
+
+test('pins similarity curves across lap counts', async ({ page }) => {
+  await page.goto('./');
+  await disablePrecaching(page);
+
+  await page.getByRole('button', { name: 'Pin curve' }).click();
+  await expect(page.locator('.pinned-curve')).toHaveText(['25 laps · 32 players×']);
+  await expect(page.getByRole('button', { name: 'Update pinned curve' })).toBeVisible();
+
+  await page.getByLabel('Lap Count:').fill('3');
+  await page.getByLabel('Lap Count:').press('Enter');
+  await page.getByRole('button', { name: 'Pin curve' }).click();
+  await expect(page.locator('.pinned-curve')).toHaveCount(2);
+
+  await page.getByRole('button', { name: 'Unpin 25 laps · 32 players' }).click();
+  await expect(page.locator('.pinned-curve')).toHaveText(['3 laps · 32 players×']);
+});
+
+test('compares a pinned result with the current one', async ({ page }) => {
+  await page.goto('./');
+  await disablePrecaching(page);
+
+  await page.getByRole('button', { name: 'Pin for Comparison' }).click();
+  await expect(page.locator('.result-comparison')).toBeVisible();
+  await expect(page.getByRole('button', { name: 'Pin This Instead' })).toBeVisible();
+
+  await page.getByLabel('Lap Count:').fill('3');
+  await page.getByLabel('Lap Count:').press('Enter');
+  const laps = page.locator('.comparison-table tr', { hasText: 'Laps · players' });
+  await expect(laps).toContainText('25 · 32');
+  await expect(laps).toContainText('3 · 32');
+
+  await page.locator('.result-comparison').getByRole('button', { name: 'Unpin' }).click();
+  await expect(page.locator('.result-comparison')).toBeHidden();
+});
+
+test('lists the changes from another cached target', async ({ page }) => {
+  await page.goto('./');
+  await disablePrecaching(page);
+
+  const picker = page.getByLabel('Show changes from target:');
+  await expect(picker.locator('option')).not.toHaveCount(1, { timeout: 15_000 });
+  await picker.selectOption({ index: 1 });
+  const diff = page.locator('.result-diff');
+  await expect(diff.locator('.diff-table tbody tr')).toHaveCount(32);
+  await expect(diff.locator('.comparison-overlap')).toContainText('of 32 selections unchanged');
+
+  await diff.getByRole('button', { name: 'Close' }).click();
+  await expect(diff).toBeHidden();
+  await expect(picker).toHaveValue('');
+});
+
+test('adds per-selection statistics columns', async ({ page }) => {
+  await page.goto('./');
+  await disablePrecaching(page);
+
+  const headers = page.locator('.big-car-table th');
+  await expect(headers).not.toContainText(['Spread']);
+  await page.locator('.column-chooser summary').click();
+  await page.getByRole('checkbox', { name: 'Fastest Car' }).check();
+  await page.getByRole('checkbox', { name: 'Spread' }).check();
+  await expect(headers).toContainText(['Total Time', '% Off Target', 'Fastest Car', 'Spread', 'Car 1']);
+  await expect(page.locator('.big-car-table tbody tr').first()).toContainText(/\d+\.\d{3}s/);
+});
+
+test('expands solver diagnostics under a result row', async ({ page }) => {
+  await page.goto('./');
+  await disablePrecaching(page);
+
+  const toggle = page.getByRole('button', { name: 'Solver diagnostics for selection 1', exact: true });
+  await expect(toggle).toHaveAttribute('aria-expanded', 'false', { timeout: 15_000 });
+  await toggle.click();
+  await expect(toggle).toHaveAttribute('aria-expanded', 'true');
+  await expect(page.locator('.diagnostics-row')).toHaveText(/^Run 1 of 32 · \d+ attempts? · \d+ backtracks? · [\d.]+ ms/);
+  await toggle.click();
+  await expect(page.locator('.diagnostics-row')).toHaveCount(0);
+});
+
+test('undoes and redoes parameter changes', async ({ page }) => {
+  await page.goto('./');
+  await disablePrecaching(page);
+  const undo = page.getByRole('button', { name: 'Undo' });
+  const redo = page.getByRole('button', { name: 'Redo' });
+  await expect(undo).toBeDisabled();
+
+  await page.getByLabel('Lap Count:').fill('3');
+  await page.getByLabel('Lap Count:').press('Enter');
+  await expect(undo).toBeEnabled();
+  await undo.click();
+  await expect(page.getByLabel('Lap Count:')).toHaveValue('25');
+  await expect(redo).toBeEnabled();
+
+  await page.locator('body').press('ControlOrMeta+Shift+Z');
+  await expect(page.getByLabel('Lap Count:')).toHaveValue('3');
+  await expect(redo).toBeDisabled();
+});